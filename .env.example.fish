@@ -4,6 +4,11 @@
 set -x DISCORD_TOKEN XXXXXX
 set -x DISCORD_APP_ID XXXXXX
 
+#
+# [Optional] Tokens of more Discord apps, comma separated. Each plays music in
+# its own voice channel, so a server can have more than one music session.
+set -x DISCORD_HELPER_TOKENS XXXXXX,XXXXXX
+
 #
 # [REQUIRED] Postgres database URL for the bot to use.
 #
@@ -142,6 +142,9 @@ fn load_bot_config() -> Result<BotConfig, Error> {
     let spotify_client_secret = load_key("SPOTIFY_CLIENT_SECRET".to_string()).ok();
     let openai_api_key = load_key("OPENAI_API_KEY".to_string()).ok();
     let virustotal_api_key = load_key("VIRUSTOTAL_API_KEY".to_string()).ok();
+    let helper_tokens = load_key("DISCORD_HELPER_TOKENS".to_string())
+        .map(|tokens| parse_helper_tokens(&tokens))
+        .unwrap_or_default();
 
     let config_res = BotConfig::from_config_file("./cracktunes.toml");
     let mut config = match config_res {
@@ -158,11 +161,22 @@ fn load_bot_config() -> Result<BotConfig, Error> {
         spotify_client_secret,
         openai_api_key,
         virustotal_api_key,
+        helper_tokens,
     });
 
     Ok(config_with_creds.clone())
 }
 
+/// Split a comma separated list of helper bot tokens.
+fn parse_helper_tokens(tokens: &str) -> Vec<String> {
+    tokens
+        .split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 /// Combine the stdout and debug log layers
 #[cfg(feature = "crack-tracing")]
 fn combine_log_layers(
@@ -335,6 +349,12 @@ mod test {
         let _layer = combine_log_layers(stdout_log, debug_log);
     }
 
+    #[test]
+    fn test_parse_helper_tokens() {
+        assert_eq!(parse_helper_tokens(" a, b,,c "), vec!["a", "b", "c"]);
+        assert!(parse_helper_tokens("").is_empty());
+    }
+
     #[test]
     fn test_load_key() {
         let key = "DISCORD_TOKEN".to_string();
@@ -0,0 +1,164 @@
+use crate::CrackedError;
+use chrono::{DateTime, Utc};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+/// Source of time for the background schedulers, so they can run on a fake clock in tests.
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> DateTime<Utc>;
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+/// The real clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
+
+/// A clock that only moves when told to. Sleeping advances it instantly.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += chrono::Duration::from_std(duration).unwrap_or_default();
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Work a [`Poller`] checks for on every tick, e.g. due jobs or expired cases.
+pub trait PollJob: Send + Sync + 'static {
+    /// What to call the job when a tick fails.
+    const NAME: &'static str;
+    /// How often to tick unless told otherwise.
+    const POLL_INTERVAL: Duration;
+
+    /// Handle everything that is due at `now`. Returns the number of items handled.
+    fn tick(&self, now: DateTime<Utc>) -> impl Future<Output = Result<usize, CrackedError>> + Send;
+}
+
+/// Runs a [`PollJob`] every poll interval on a [`Clock`].
+pub struct Poller<C: Clock, J: PollJob> {
+    clock: C,
+    job: J,
+    poll_interval: Duration,
+}
+
+impl<C: Clock, J: PollJob> Poller<C, J> {
+    pub fn new(clock: C, job: J) -> Self {
+        Self {
+            clock,
+            job,
+            poll_interval: J::POLL_INTERVAL,
+        }
+    }
+
+    /// Set how often to tick.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Run the job once, as of the clock's current time.
+    pub async fn tick(&self) -> Result<usize, CrackedError> {
+        self.job.tick(self.clock.now()).await
+    }
+
+    /// Tick forever.
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.tick().await {
+                tracing::error!("{} tick failed: {}", J::NAME, e);
+            }
+            self.clock.sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Run the poller in the background.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_clock_sleep_advances() {
+        let start = DateTime::parse_from_rfc3339("2024-01-05T19:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let clock = MockClock::new(start);
+        clock.sleep(Duration::from_secs(90)).await;
+        assert_eq!(clock.now(), start + chrono::Duration::seconds(90));
+    }
+
+    /// Remembers when it was ticked.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        ticks: Arc<Mutex<Vec<DateTime<Utc>>>>,
+    }
+
+    impl PollJob for Recorder {
+        const NAME: &'static str = "Recorder";
+        const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+        async fn tick(&self, now: DateTime<Utc>) -> Result<usize, CrackedError> {
+            self.ticks.lock().unwrap().push(now);
+            Ok(1)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poller_ticks_at_clock_time() {
+        let start = DateTime::parse_from_rfc3339("2024-01-05T19:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let clock = MockClock::new(start);
+        let job = Recorder::default();
+        let poller = Poller::new(clock.clone(), job.clone());
+        assert_eq!(poller.poll_interval, Recorder::POLL_INTERVAL);
+
+        poller.tick().await.unwrap();
+        clock.sleep(poller.poll_interval).await;
+        poller.tick().await.unwrap();
+        assert_eq!(
+            *job.ticks.lock().unwrap(),
+            vec![start, start + chrono::Duration::seconds(30)]
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use crate::duration::{format_duration, HumanDuration};
+use crate::errors::CrackedError;
+use crate::guild::cam_rules::CamPolicy;
+use crate::messaging::message::CrackedMessage;
+use crate::messaging::messages::{
+    CAM_POLICIES, CAM_POLICIES_NONE, CAM_POLICY_EXEMPT, CAM_POLICY_NOT_EXEMPT,
+    CAM_POLICY_NOT_FOUND, CAM_POLICY_NOT_VOICE, CAM_POLICY_REMOVED, CAM_POLICY_SET,
+};
+use crate::poise_ext::ContextExt;
+use crate::utils::send_reply;
+use crate::{Context, Error};
+use poise::CreateReply;
+use serenity::all::{
+    Channel, ChannelId, ChannelType, CreateEmbed, GuildId, Mention, Mentionable, Role, RoleId,
+};
+use std::time::Duration;
+
+/// The grace period and step interval when they're not given.
+const DEFAULT_STEP: Duration = Duration::from_secs(60);
+/// The longest grace period or step interval.
+const MAX_STEP: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Camera policies for voice channels. Members with their camera off get a
+/// warning, then a server mute, a move and a disconnect. Lists the policies.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands(
+        "set_cam_policy",
+        "remove_cam_policy",
+        "exempt_cam_policy",
+        "unexempt_cam_policy",
+        "list_cam_policies"
+    ),
+    required_permissions = "MANAGE_CHANNELS",
+    ephemeral
+)]
+pub async fn campolicy(ctx: Context<'_>) -> Result<(), Error> {
+    list_cam_policies_internal(ctx).await
+}
+
+/// Require cameras in a voice channel, or change its policy.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS",
+    rename = "set",
+    ephemeral
+)]
+pub async fn set_cam_policy(
+    ctx: Context<'_>,
+    #[description = "Voice channel to police."] channel: Channel,
+    #[description = "Camera off this long before a warning, e.g. `2m`. 1m by default."]
+    grace: Option<HumanDuration>,
+    #[description = "Time between steps of the ladder, e.g. `30s`. 1m by default."] step: Option<
+        HumanDuration,
+    >,
+    #[description = "Voice channel to move members to, none by default."] move_to: Option<Channel>,
+    #[rest]
+    #[description = "Warning to DM members, a default one by default."]
+    warn_message: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    if !is_voice_in(&channel, guild_id)
+        || move_to.as_ref().is_some_and(|c| !is_voice_in(c, guild_id))
+    {
+        return reply(ctx, CAM_POLICY_NOT_VOICE.to_string()).await;
+    }
+    let policy = CamPolicy::set(
+        &pool,
+        guild_id.get() as i64,
+        channel.id().get() as i64,
+        seconds(grace),
+        seconds(step),
+        move_to.map(|c| c.id().get() as i64),
+        warn_message,
+    )
+    .await?;
+    let msg = format!("{} {}", CAM_POLICY_SET, format_policy(&policy));
+    reply(ctx, msg).await
+}
+
+/// Stop policing a voice channel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS",
+    rename = "remove",
+    ephemeral
+)]
+pub async fn remove_cam_policy(
+    ctx: Context<'_>,
+    #[description = "Voice channel to stop policing."] channel: Channel,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    if !CamPolicy::delete(&pool, guild_id.get() as i64, channel.id().get() as i64).await? {
+        return reply(ctx, CAM_POLICY_NOT_FOUND.to_string()).await;
+    }
+    let msg = format!("{} {}", CAM_POLICY_REMOVED, channel.id().mention());
+    reply(ctx, msg).await
+}
+
+/// Let a role keep their camera off in a voice channel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS",
+    rename = "exempt",
+    ephemeral
+)]
+pub async fn exempt_cam_policy(
+    ctx: Context<'_>,
+    #[description = "Policed voice channel."] channel: Channel,
+    #[description = "Role to exempt."] role: Role,
+) -> Result<(), Error> {
+    set_exempt_internal(ctx, channel, role, true).await
+}
+
+/// Stop exempting a role in a voice channel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS",
+    rename = "unexempt",
+    ephemeral
+)]
+pub async fn unexempt_cam_policy(
+    ctx: Context<'_>,
+    #[description = "Policed voice channel."] channel: Channel,
+    #[description = "Role to stop exempting."] role: Role,
+) -> Result<(), Error> {
+    set_exempt_internal(ctx, channel, role, false).await
+}
+
+/// List the server's camera policies.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS",
+    rename = "list",
+    ephemeral
+)]
+pub async fn list_cam_policies(ctx: Context<'_>) -> Result<(), Error> {
+    list_cam_policies_internal(ctx).await
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn set_exempt_internal(
+    ctx: Context<'_>,
+    channel: Channel,
+    role: Role,
+    exempt: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let channel_id = channel.id();
+    let Some(_) = CamPolicy::set_exempt(
+        &pool,
+        guild_id.get() as i64,
+        channel_id.get() as i64,
+        role.id.get() as i64,
+        exempt,
+    )
+    .await?
+    else {
+        return reply(ctx, CAM_POLICY_NOT_FOUND.to_string()).await;
+    };
+    let status = if exempt {
+        CAM_POLICY_EXEMPT
+    } else {
+        CAM_POLICY_NOT_EXEMPT
+    };
+    let msg = format!("{} {}: {}", status, channel_id.mention(), role.id.mention());
+    reply(ctx, msg).await
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn list_cam_policies_internal(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let policies = CamPolicy::list(&pool, guild_id.get() as i64).await?;
+    if policies.is_empty() {
+        return reply(ctx, CAM_POLICIES_NONE.to_string()).await;
+    }
+    let description = policies
+        .iter()
+        .map(format_policy)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let embed = CreateEmbed::default()
+        .title(CAM_POLICIES)
+        .description(description);
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// A duration argument in seconds, a minute by default and capped at a day.
+fn seconds(duration: Option<HumanDuration>) -> i32 {
+    duration
+        .map_or(DEFAULT_STEP, |d| d.0)
+        .min(MAX_STEP)
+        .as_secs() as i32
+}
+
+/// Whether `channel` is one of the guild's voice channels.
+fn is_voice_in(channel: &Channel, guild_id: GuildId) -> bool {
+    channel
+        .clone()
+        .guild()
+        .is_some_and(|channel| channel.kind == ChannelType::Voice && channel.guild_id == guild_id)
+}
+
+/// One line for a policy: its channel, timings, move channel and exempt roles.
+fn format_policy(policy: &CamPolicy) -> String {
+    let format = |secs: i32| format_duration(Duration::from_secs(secs.max(0) as u64));
+    let mut line = format!(
+        "{}: warn after {}, then every {}",
+        Mention::Channel(ChannelId::new(policy.channel_id as u64)),
+        format(policy.grace_seconds),
+        format(policy.step_seconds),
+    );
+    if let Some(channel_id) = policy.move_channel() {
+        line.push_str(&format!(", move to {}", channel_id.mention()));
+    }
+    if !policy.exempt_roles.is_empty() {
+        let roles = policy
+            .exempt_roles
+            .iter()
+            .map(|id| RoleId::new(*id as u64).mention().to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        line.push_str(&format!(", exempt {}", roles));
+    }
+    line
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn reply(ctx: Context<'_>, msg: String) -> Result<(), Error> {
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_policy() {
+        let mut policy = CamPolicy {
+            channel_id: 1,
+            guild_id: 2,
+            grace_seconds: 90,
+            step_seconds: 60,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_policy(&policy),
+            "<#1>: warn after 1m 30s, then every 1m"
+        );
+        policy.move_channel_id = Some(3);
+        policy.exempt_roles = vec![4, 5];
+        assert_eq!(
+            format_policy(&policy),
+            "<#1>: warn after 1m 30s, then every 1m, move to <#3>, exempt <@&4> <@&5>"
+        );
+    }
+
+    #[test]
+    fn test_seconds() {
+        assert_eq!(seconds(None), 60);
+        assert_eq!(seconds(Some(HumanDuration(Duration::from_secs(90)))), 90);
+        assert_eq!(
+            seconds(Some(HumanDuration(Duration::from_secs(1 << 40)))),
+            86_400
+        );
+    }
+}
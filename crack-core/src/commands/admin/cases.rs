@@ -0,0 +1,150 @@
+use crate::db::{CaseAction, ModerationCase};
+use crate::errors::CrackedError;
+use crate::messaging::message::CrackedMessage;
+use crate::messaging::messages::{CASES_NONE, CASES_TITLE};
+use crate::moderation::{
+    case_embed, case_list_line, parse_evidence, record_case, refresh_case_log,
+};
+use crate::poise_ext::ContextExt;
+use crate::utils::send_reply;
+use crate::{Context, Error};
+use poise::CreateReply;
+use serenity::all::{CreateEmbed, Mentionable, User};
+use std::time::Duration;
+
+/// Discord embeds allow at most this many characters in the description.
+const MAX_CASE_LIST_LEN: usize = 4000;
+
+/// Show a moderation case.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MODERATE_MEMBERS",
+    ephemeral
+)]
+pub async fn case(
+    ctx: Context<'_>,
+    #[description = "Case number."] number: i64,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let case = ModerationCase::get(&pool, guild_id.get() as i64, number)
+        .await?
+        .ok_or(CrackedError::CaseNotFound(number))?;
+    ctx.send(CreateReply::default().embed(case_embed(&case)))
+        .await?;
+    Ok(())
+}
+
+/// List a user's moderation cases.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MODERATE_MEMBERS",
+    ephemeral
+)]
+pub async fn cases(
+    ctx: Context<'_>,
+    #[description = "User to list cases for."] user: User,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let cases =
+        ModerationCase::get_for_target(&pool, guild_id.get() as i64, user.id.get() as i64).await?;
+    if cases.is_empty() {
+        send_reply(
+            &ctx,
+            CrackedMessage::Other(format!("{} {}", CASES_NONE, user.mention())),
+            true,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut description = String::new();
+    for case in cases.iter() {
+        let line = case_list_line(case);
+        if description.len() + line.len() + 1 > MAX_CASE_LIST_LEN {
+            break;
+        }
+        description.push_str(&line);
+        description.push('\n');
+    }
+    let embed = CreateEmbed::default()
+        .title(format!("{} {} ({})", CASES_TITLE, user.name, cases.len()))
+        .description(description);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Change the reason of a moderation case and add evidence to it.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MODERATE_MEMBERS",
+    ephemeral
+)]
+pub async fn reason(
+    ctx: Context<'_>,
+    #[description = "Case number."] number: i64,
+    #[description = "New reason."] reason: String,
+    #[description = "Evidence links to add, separated by spaces."] evidence: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let evidence = parse_evidence(evidence.as_deref())?;
+    let pool = ctx.get_db_pool()?;
+    let case =
+        ModerationCase::update_reason(&pool, guild_id.get() as i64, number, &reason, &evidence)
+            .await?
+            .ok_or(CrackedError::CaseNotFound(number))?;
+    if let Err(e) = refresh_case_log(ctx.http(), &case).await {
+        tracing::warn!("Failed to update the mod log for case {}: {}", case.id, e);
+    }
+    send_reply(&ctx, CrackedMessage::CaseReasonUpdated(number), true).await?;
+    Ok(())
+}
+
+/// Record a moderation action taken by the command's author as a new case and post
+/// it to the mod log. The action has already happened by now, so failures are only
+/// logged, and without a database nothing is recorded.
+#[cfg(not(tarpaulin_include))]
+pub async fn log_case(
+    ctx: Context<'_>,
+    action: CaseAction,
+    target: &User,
+    reason: Option<String>,
+    duration: Option<Duration>,
+    evidence: Vec<String>,
+) -> Option<ModerationCase> {
+    let guild_id = ctx.guild_id()?;
+    if ctx.data().database_pool.is_none() {
+        tracing::warn!("No database, not recording {} of {}", action, target.id);
+        return None;
+    }
+    let case = ModerationCase::new(
+        guild_id.get() as i64,
+        action,
+        ctx.author().id.get() as i64,
+        target.id.get() as i64,
+        reason,
+        duration,
+        chrono::Utc::now(),
+    )
+    .with_evidence(evidence);
+    match record_case(ctx.http(), &ctx.data(), case).await {
+        Ok(case) => Some(case),
+        Err(e) => {
+            tracing::error!("Failed to record {} of {}: {}", action, target.id, e);
+            None
+        },
+    }
+}
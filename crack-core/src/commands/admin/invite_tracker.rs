@@ -1,58 +1,111 @@
-use crate::Context;
-use crate::Error;
-use serenity::all::Guild;
-use std::collections::BTreeMap;
-use std::fmt::Write;
-
-#[poise::command(prefix_command, owners_only, ephemeral)]
-pub async fn track_invites(
-    ctx: Context<'_>,
-    #[description = "Guild to track invites for."] guild: Guild,
-) -> Result<(), Error> {
-    // Get all the invites for the guild
-    let invites = guild.invites(&ctx).await?;
-    let _inviter_by_code = invites
-        .iter()
-        .map(|invite| (invite.code.clone(), invite.inviter.clone()))
-        .collect::<BTreeMap<_, _>>();
+use crate::db::InviteJoin;
+use crate::errors::CrackedError;
+use crate::messaging::message::CrackedMessage;
+use crate::messaging::messages::{
+    INVITES_COUNT, INVITES_INVITED_BY, INVITES_LEADERBOARD, INVITES_NONE, INVITES_UNKNOWN,
+};
+use crate::poise_ext::ContextExt;
+use crate::utils::send_reply;
+use crate::{Context, Error};
+use poise::CreateReply;
+use serenity::all::{CreateEmbed, Mention, Mentionable, User, UserId};
+
+/// How many inviters the leaderboard shows.
+const LEADERBOARD_LEN: i64 = 10;
+
+/// Invite tracking commands.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("leaderboard", "who"),
+    required_permissions = "MANAGE_GUILD",
+    ephemeral
+)]
+pub async fn invites(ctx: Context<'_>) -> Result<(), Error> {
+    leaderboard_internal(ctx).await
+}
+
+/// Show who invited the most members.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    ephemeral
+)]
+pub async fn leaderboard(ctx: Context<'_>) -> Result<(), Error> {
+    leaderboard_internal(ctx).await
+}
 
-    let invites_by_user = invites
+#[cfg(not(tarpaulin_include))]
+async fn leaderboard_internal(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let top = InviteJoin::leaderboard(&pool, guild_id.get() as i64, LEADERBOARD_LEN).await?;
+    if top.is_empty() {
+        send_reply(&ctx, CrackedMessage::Other(INVITES_NONE.to_string()), true).await?;
+        return Ok(());
+    }
+
+    let description = top
         .iter()
-        .map(|invite| {
-            (
-                invite.inviter.as_ref().map(|x| x.id).unwrap_or_default(),
-                invite,
-            )
+        .enumerate()
+        .map(|(i, row)| {
+            let inviter = Mention::User(UserId::new(row.inviter_id as u64));
+            format!("{}. {} {} {}", i + 1, inviter, INVITES_COUNT, row.joins)
         })
-        .collect::<BTreeMap<_, _>>();
-
-    let mut invites_by_user_string = "{ ".to_string();
-    invites_by_user.iter().for_each(|(key, &value)| {
-        let _ = writeln!(
-            &mut invites_by_user_string,
-            "{}: {} {}/{}, ",
-            key, value.code, value.uses, value.max_uses
-        );
-    });
-    invites_by_user_string.pop(); // Remove the last comma.
-    invites_by_user_string.pop(); // Remove the last space.
-    invites_by_user_string.push_str(" }");
-    tracing::warn!("invites_by_user: {}", invites_by_user_string);
-
-    ctx.say(invites_by_user_string).await?;
-
+        .collect::<Vec<_>>()
+        .join("\n");
+    let embed = CreateEmbed::default()
+        .title(INVITES_LEADERBOARD)
+        .description(description);
+    ctx.send(CreateReply::default().embed(embed)).await?;
     Ok(())
 }
 
-pub fn kv_iter_to_string(
-    iter: impl Iterator<Item = (impl std::fmt::Display, impl std::fmt::Display)>,
-) -> String {
-    let mut string = "{ ".to_string();
-    iter.for_each(|(key, value)| {
-        let _ = writeln!(&mut string, "{}: {}, ", key, value);
-    });
-    string.pop(); // Remove the last comma.
-    string.pop(); // Remove the last space.
-    string.push_str(" }");
-    string
+/// Show who invited a member, and how many members they invited.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    ephemeral
+)]
+pub async fn who(
+    ctx: Context<'_>,
+    #[description = "Member to look up."] user: User,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let guild_id = guild_id.get() as i64;
+    let user_id = user.id.get() as i64;
+
+    let join = InviteJoin::get_latest(&pool, guild_id, user_id).await?;
+    let mut msg = match join.as_ref().and_then(|join| join.inviter_id) {
+        Some(inviter_id) => format!(
+            "{} {} {} (`{}`)",
+            user.mention(),
+            INVITES_INVITED_BY,
+            Mention::User(UserId::new(inviter_id as u64)),
+            join.and_then(|join| join.invite_code).unwrap_or_default(),
+        ),
+        None => format!("{} {}", INVITES_UNKNOWN, user.mention()),
+    };
+    let invited = InviteJoin::count_for_inviter(&pool, guild_id, user_id).await?;
+    msg.push_str(&format!(
+        "\n{} {} {}",
+        user.mention(),
+        INVITES_COUNT,
+        invited
+    ));
+
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
 }
@@ -1,7 +1,10 @@
 //#![feature(const_random)] // This is a nightly feature
+use crate::commands::admin::log_case;
+use crate::db::CaseAction;
 use crate::errors::CrackedError;
 use crate::guild::operations::GuildSettingsOperations;
 use crate::messaging::message::CrackedMessage;
+use crate::moderation::parse_evidence;
 use crate::utils::send_reply;
 use crate::Context;
 use crate::Error;
@@ -22,9 +25,12 @@ use std::time::Duration;
 pub async fn kick(
     ctx: Context<'_>,
     #[description = "User to kick."] user: User,
+    #[description = "Reason for the kick."] reason: Option<String>,
+    #[description = "Evidence links, separated by spaces."] evidence: Option<String>,
 ) -> Result<(), Error> {
     let mention = user.mention();
     let id = user.id;
+    let evidence = parse_evidence(evidence.as_deref())?;
     let guild_id = ctx.guild_id().ok_or(CrackedError::GuildOnly)?;
     let as_embed = ctx.data().get_reply_with_embed(guild_id).await;
     let guild = guild_id.to_partial_guild(&ctx).await?;
@@ -36,6 +42,7 @@ pub async fn kick(
         )
         .await?;
     } else {
+        log_case(ctx, CaseAction::Kick, &user, reason, None, evidence).await;
         // Send success message
         send_reply(&ctx, CrackedMessage::UserKicked { id, mention }, as_embed).await?;
     }
@@ -0,0 +1,101 @@
+use crate::db::Lockdown;
+use crate::errors::CrackedError;
+use crate::messaging::message::CrackedMessage;
+use crate::messaging::messages::{
+    LOCKDOWN_ACTIVE, LOCKDOWN_ALREADY, LOCKDOWN_ENDED, LOCKDOWN_NOT_ACTIVE, LOCKDOWN_NO_REASON,
+    LOCKDOWN_STARTED,
+};
+use crate::moderation::{end_lockdown, start_lockdown};
+use crate::poise_ext::ContextExt;
+use crate::utils::send_reply;
+use crate::{Context, Error};
+use serenity::all::Mentionable;
+
+/// Lockdown commands. Shows whether the server is locked down.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("lockdown_start", "lockdown_end"),
+    required_permissions = "MANAGE_GUILD",
+    ephemeral
+)]
+pub async fn lockdown(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let msg = match Lockdown::get_active(&pool, guild_id.get() as i64).await? {
+        Some(lockdown) => format!(
+            "{} <t:{}:R>: {}",
+            LOCKDOWN_ACTIVE,
+            lockdown.created_at.and_utc().timestamp(),
+            lockdown.reason
+        ),
+        None => LOCKDOWN_NOT_ACTIVE.to_string(),
+    };
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
+
+/// Lock the server down: raise the verification level, stop @everyone sending
+/// messages and pause invites.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "MANAGE_GUILD|MANAGE_ROLES",
+    rename = "start",
+    ephemeral
+)]
+pub async fn lockdown_start(
+    ctx: Context<'_>,
+    #[rest]
+    #[description = "Why the server is locked down."]
+    reason: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let reason = reason.unwrap_or_else(|| LOCKDOWN_NO_REASON.to_string());
+    let reason = format!("{} ({})", reason, ctx.author().name);
+    let started = start_lockdown(
+        ctx.serenity_context(),
+        ctx.data(),
+        guild_id,
+        reason,
+        Some(ctx.author().id),
+    )
+    .await?;
+    let msg = match started {
+        Some(_) => LOCKDOWN_STARTED,
+        None => LOCKDOWN_ALREADY,
+    };
+    send_reply(&ctx, CrackedMessage::Other(msg.to_string()), true).await?;
+    Ok(())
+}
+
+/// End the lockdown, putting back what it changed.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "MANAGE_GUILD|MANAGE_ROLES",
+    rename = "end",
+    ephemeral
+)]
+pub async fn lockdown_end(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let author = ctx.author().id;
+    let ended = end_lockdown(ctx.serenity_context(), ctx.data(), guild_id, author).await?;
+    let msg = match ended {
+        Some(_) => format!("{} {}", LOCKDOWN_ENDED, author.mention()),
+        None => LOCKDOWN_NOT_ACTIVE.to_string(),
+    };
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
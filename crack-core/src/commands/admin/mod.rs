@@ -1,6 +1,8 @@
 // pub mod audit_logs;
 // pub mod authorize;
 // pub mod broadcast_voice;
+pub mod cam_policy;
+pub mod cases;
 // pub mod create_text_channel;
 // pub mod create_voice_channel;
 // pub mod deauthorize;
@@ -8,12 +10,14 @@
 // pub mod defend;
 // pub mod delete_channel;
 // pub mod get_active;
-// pub mod invite_tracker;
+pub mod invite_tracker;
+pub mod lockdown;
 // pub mod kick;
 // pub mod message_cache;
 // pub mod move_users;
 pub mod mute;
 pub mod random_mute_lol;
+pub mod role_menu;
 // pub mod role;
 // pub mod set_vc_size;
 // pub mod timeout;
@@ -23,6 +27,8 @@ use crate::{Context, Error};
 // pub use audit_logs::*;
 // pub use authorize::*;
 // pub use broadcast_voice::*;
+pub use cam_policy::*;
+pub use cases::*;
 // pub use create_text_channel::*;
 // pub use create_voice_channel::*;
 // pub use deauthorize::*;
@@ -30,13 +36,15 @@ use crate::{Context, Error};
 // pub use defend::*;
 // pub use delete_channel::*;
 // pub use get_active::*;
-// pub use invite_tracker::track_invites;
+pub use invite_tracker::*;
+pub use lockdown::*;
 // pub use kick::changenicks;
 // pub use kick::*;
 // pub use message_cache::*;
 // pub use move_users::*;
 pub use mute::*;
 pub use random_mute_lol::*;
+pub use role_menu::*;
 // pub use role::*;
 // pub use set_vc_size::*;
 // pub use timeout::*;
@@ -55,6 +63,8 @@ pub use user::*;
         // "audit_logs",
         // "authorize",
         // "broadcast_voice",
+        "case",
+        "cases",
         // "create_text_channel",
         // "create_voice_channel",
         "deafen",
@@ -70,6 +80,7 @@ pub use user::*;
         "undeafen",
         "unmute",
         "random_mute",
+        "reason",
         // "get_active_vcs",
         // "set_vc_size",
         // "timeout",
@@ -91,6 +102,13 @@ pub async fn admin(ctx: Context<'_>) -> Result<(), Error> {
 pub fn commands() -> Vec<crate::Command> {
     vec![
         admin(),
+        campolicy(),
+        case(),
+        cases(),
+        invites(),
+        lockdown(),
+        reason(),
+        role_menu(),
         // user(),
         // role(),
         // kick(),
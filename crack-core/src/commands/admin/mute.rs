@@ -1,6 +1,10 @@
+use crate::commands::admin::log_case;
 use crate::connection::get_voice_channel_for_user;
+use crate::db::{CaseAction, ModerationCase};
+use crate::duration::HumanDuration;
 use crate::errors::CrackedError;
 use crate::messaging::message::CrackedMessage;
+use crate::moderation::parse_evidence;
 use crate::utils::send_reply;
 use crate::{Context, Error};
 
@@ -9,6 +13,7 @@ use poise::serenity_prelude as serenity;
 use serenity::model::id::{ChannelId, GuildId, UserId};
 use serenity::model::{user::User, voice::VoiceState};
 use serenity::{CacheHttp, EditMember, Mentionable};
+use std::time::Duration;
 
 /// Mute a user.
 #[poise::command(
@@ -21,9 +26,21 @@ use serenity::{CacheHttp, EditMember, Mentionable};
 pub async fn mute(
     ctx: Context<'_>,
     #[description = "User to mute"] user: User,
+    #[description = "Reason for the mute."] reason: Option<String>,
+    #[description = "Unmute after this long, e.g. `30m`."] duration: Option<HumanDuration>,
+    #[description = "Evidence links, separated by spaces."] evidence: Option<String>,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    let crack_msg = mute_internal(&ctx, user, guild_id, true).await?;
+    let duration = duration.map(Duration::from);
+    let evidence = parse_evidence(evidence.as_deref())?;
+    let crack_msg = mute_internal(&ctx, user.clone(), guild_id, true).await?;
+    if matches!(crack_msg, CrackedMessage::UserMuted { .. }) {
+        let action = match duration {
+            Some(_) => CaseAction::TempMute,
+            None => CaseAction::Mute,
+        };
+        log_case(ctx, action, &user, reason, duration, evidence).await;
+    }
     send_reply(&ctx, crack_msg, true)
         .await
         .map(|_| ())
@@ -158,6 +175,21 @@ pub async fn unmute_impl(ctx: Context<'_>, user: User) -> Result<(), Error> {
         )
         .await
     } else {
+        // A manual unmute ends any pending tempmute.
+        if let Some(pool) = ctx.data().database_pool.as_ref() {
+            if let Err(e) = ModerationCase::mark_reversed_for_target(
+                pool,
+                guild_id.get() as i64,
+                id.get() as i64,
+                CaseAction::TempMute,
+                chrono::Utc::now().naive_utc(),
+            )
+            .await
+            {
+                tracing::warn!("Failed to close tempmute cases of {}: {}", id, e);
+            }
+        }
+        log_case(ctx, CaseAction::Unmute, &user, None, None, Vec::new()).await;
         // Send success message
         send_reply(&ctx, CrackedMessage::UserUnmuted { id, mention }, true).await
     }?;
@@ -0,0 +1,371 @@
+use crate::db::{RoleMenu, RoleMenuOption};
+use crate::errors::CrackedError;
+use crate::guild::role_menu::{
+    parse_emoji, role_menu_message, role_menu_role_problem, RoleMenuMode, RoleMenuStyle,
+    MAX_ROLE_MENU_OPTIONS,
+};
+use crate::messaging::message::CrackedMessage;
+use crate::messaging::messages::{
+    ROLE_MENUS, ROLE_MENUS_NONE, ROLE_MENU_CREATED, ROLE_MENU_DELETED, ROLE_MENU_EMPTY,
+    ROLE_MENU_EVERYONE, ROLE_MENU_FULL, ROLE_MENU_INVALID_EMOJI, ROLE_MENU_NOT_FOUND,
+    ROLE_MENU_OPTION_ADDED, ROLE_MENU_OPTION_NOT_FOUND, ROLE_MENU_OPTION_REMOVED, ROLE_MENU_POSTED,
+};
+use crate::poise_ext::ContextExt;
+use crate::utils::send_reply;
+use crate::{Context, Error};
+use poise::CreateReply;
+use serenity::all::{
+    Channel, ChannelId, CreateEmbed, CreateMessage, EditMessage, GuildId, Member, Mention,
+    MessageId, Role,
+};
+use sqlx::PgPool;
+
+/// Role menus members use to pick their own roles. Lists the server's menus.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands(
+        "create_role_menu",
+        "add_role_menu_role",
+        "remove_role_menu_role",
+        "post_role_menu",
+        "list_role_menus",
+        "delete_role_menu"
+    ),
+    required_permissions = "MANAGE_ROLES",
+    ephemeral
+)]
+pub async fn role_menu(ctx: Context<'_>) -> Result<(), Error> {
+    list_role_menus_internal(ctx).await
+}
+
+/// Create a role menu. Add roles to it, then post it.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES",
+    rename = "create",
+    ephemeral
+)]
+pub async fn create_role_menu(
+    ctx: Context<'_>,
+    #[description = "Channel to post the menu in."] channel: Channel,
+    #[description = "How picks combine, toggle by default."] mode: Option<RoleMenuMode>,
+    #[description = "Buttons or a select menu, buttons by default."] style: Option<RoleMenuStyle>,
+    #[description = "Most roles a member can pick in max mode."] max_roles: Option<u32>,
+    #[rest]
+    #[description = "Title of the menu."]
+    title: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let mode = mode.unwrap_or_default();
+    if mode == RoleMenuMode::Max && max_roles.unwrap_or_default() == 0 {
+        return Err(CrackedError::Other("Max mode needs a maximum.").into());
+    }
+    let menu = RoleMenu::create(
+        &pool,
+        guild_id.get() as i64,
+        channel.id().get() as i64,
+        title,
+        mode,
+        style.unwrap_or_default(),
+        max_roles.unwrap_or_default() as i32,
+    )
+    .await?;
+    let msg = format!("{} #{}: {}", ROLE_MENU_CREATED, menu.id, menu.title);
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
+
+/// Add a role to a role menu, or change its label or emoji.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES",
+    rename = "add",
+    ephemeral
+)]
+pub async fn add_role_menu_role(
+    ctx: Context<'_>,
+    #[description = "Id of the role menu."] menu: i64,
+    #[description = "Role to add."] role: Role,
+    #[description = "Emoji for the role."] emoji: Option<String>,
+    #[rest]
+    #[description = "Label for the role, its name by default."]
+    label: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(menu) = RoleMenu::get(&pool, guild_id.get() as i64, menu).await? else {
+        return reply(ctx, ROLE_MENU_NOT_FOUND.to_string()).await;
+    };
+    if role.id.get() == guild_id.get() {
+        return reply(ctx, ROLE_MENU_EVERYONE.to_string()).await;
+    }
+    if let Some(problem) = role_problem(ctx, guild_id, &role).await? {
+        return reply(ctx, problem.to_string()).await;
+    }
+    if emoji
+        .as_deref()
+        .is_some_and(|emoji| parse_emoji(emoji).is_none())
+    {
+        return reply(ctx, ROLE_MENU_INVALID_EMOJI.to_string()).await;
+    }
+    let options = menu.options(&pool).await?;
+    let role_id = role.id.get() as i64;
+    if options.len() >= MAX_ROLE_MENU_OPTIONS && !options.iter().any(|o| o.role_id == role_id) {
+        return reply(ctx, ROLE_MENU_FULL.to_string()).await;
+    }
+
+    let label = label.unwrap_or_else(|| role.name.to_string());
+    RoleMenuOption::add(&pool, menu.id, role_id, label, emoji).await?;
+    refresh_posted(ctx, &pool, &menu).await?;
+    let msg = format!(
+        "{} #{}: {}",
+        ROLE_MENU_OPTION_ADDED,
+        menu.id,
+        Mention::Role(role.id)
+    );
+    reply(ctx, msg).await
+}
+
+/// Why the invoker can't put `role` on a menu, if they can't.
+#[cfg(not(tarpaulin_include))]
+async fn role_problem(
+    ctx: Context<'_>,
+    guild_id: GuildId,
+    role: &Role,
+) -> Result<Option<&'static str>, Error> {
+    let author = ctx
+        .author_member()
+        .await
+        .ok_or(CrackedError::AuthorNotFound)?
+        .into_owned();
+    let bot_id = ctx.cache().current_user().id;
+    let cached_bot = ctx
+        .guild()
+        .and_then(|guild| guild.members.get(&bot_id).cloned());
+    let bot = match cached_bot {
+        Some(bot) => bot,
+        None => guild_id.member(ctx.http(), bot_id).await?,
+    };
+    let guild = ctx.guild().ok_or(CrackedError::NoGuildCached)?;
+    let top = |member: &Member| {
+        member
+            .roles
+            .iter()
+            .filter_map(|id| guild.roles.get(id))
+            .map(|role| role.position)
+            .max()
+            .unwrap_or_default()
+    };
+    let invoker_top = (guild.owner_id != author.user.id).then(|| top(&author));
+    Ok(role_menu_role_problem(
+        role.position,
+        role.permissions,
+        invoker_top,
+        top(&bot),
+    ))
+}
+
+/// Take a role off a role menu.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES",
+    rename = "remove",
+    ephemeral
+)]
+pub async fn remove_role_menu_role(
+    ctx: Context<'_>,
+    #[description = "Id of the role menu."] menu: i64,
+    #[description = "Role to take off."] role: Role,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(menu) = RoleMenu::get(&pool, guild_id.get() as i64, menu).await? else {
+        return reply(ctx, ROLE_MENU_NOT_FOUND.to_string()).await;
+    };
+    if !RoleMenuOption::remove(&pool, menu.id, role.id.get() as i64).await? {
+        return reply(ctx, ROLE_MENU_OPTION_NOT_FOUND.to_string()).await;
+    }
+    refresh_posted(ctx, &pool, &menu).await?;
+    let msg = format!(
+        "{} #{}: {}",
+        ROLE_MENU_OPTION_REMOVED,
+        menu.id,
+        Mention::Role(role.id)
+    );
+    reply(ctx, msg).await
+}
+
+/// Post a role menu in its channel, or update it if it's already posted.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES",
+    required_bot_permissions = "SEND_MESSAGES|MANAGE_ROLES",
+    rename = "post",
+    ephemeral
+)]
+pub async fn post_role_menu(
+    ctx: Context<'_>,
+    #[description = "Id of the role menu."] menu: i64,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(menu) = RoleMenu::get(&pool, guild_id.get() as i64, menu).await? else {
+        return reply(ctx, ROLE_MENU_NOT_FOUND.to_string()).await;
+    };
+    let options = menu.options(&pool).await?;
+    if options.is_empty() {
+        return reply(ctx, ROLE_MENU_EMPTY.to_string()).await;
+    }
+
+    let channel_id = ChannelId::new(menu.channel_id as u64);
+    if !refresh_posted(ctx, &pool, &menu).await? {
+        let (embed, components) = role_menu_message(&menu, &options);
+        let message = channel_id
+            .send_message(
+                ctx.http(),
+                CreateMessage::new().embed(embed).components(components),
+            )
+            .await?;
+        RoleMenu::set_message(&pool, menu.id, message.id.get() as i64).await?;
+    }
+    let msg = format!(
+        "{} #{} in {}",
+        ROLE_MENU_POSTED,
+        menu.id,
+        Mention::Channel(channel_id)
+    );
+    reply(ctx, msg).await
+}
+
+/// List the server's role menus.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES",
+    rename = "list",
+    ephemeral
+)]
+pub async fn list_role_menus(ctx: Context<'_>) -> Result<(), Error> {
+    list_role_menus_internal(ctx).await
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn list_role_menus_internal(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let menus = RoleMenu::list(&pool, guild_id.get() as i64).await?;
+    if menus.is_empty() {
+        return reply(ctx, ROLE_MENUS_NONE.to_string()).await;
+    }
+
+    let mut lines = Vec::with_capacity(menus.len());
+    for menu in &menus {
+        let roles = menu.options(&pool).await?.len();
+        lines.push(format!(
+            "**#{}** {} ({}, {}, {} roles) in {}",
+            menu.id,
+            menu.title,
+            menu.mode().as_str(),
+            menu.style().as_str(),
+            roles,
+            Mention::Channel(ChannelId::new(menu.channel_id as u64)),
+        ));
+    }
+    let embed = CreateEmbed::default()
+        .title(ROLE_MENUS)
+        .description(lines.join("\n"));
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Delete a role menu and its message.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Admin",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES",
+    rename = "delete",
+    ephemeral
+)]
+pub async fn delete_role_menu(
+    ctx: Context<'_>,
+    #[description = "Id of the role menu."] menu: i64,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(menu) = RoleMenu::get(&pool, guild_id.get() as i64, menu).await? else {
+        return reply(ctx, ROLE_MENU_NOT_FOUND.to_string()).await;
+    };
+    if let Some(message_id) = menu.message_id {
+        let channel_id = ChannelId::new(menu.channel_id as u64);
+        if let Err(e) = channel_id
+            .delete_message(ctx.http(), MessageId::new(message_id as u64), None)
+            .await
+        {
+            tracing::warn!("Failed to delete role menu {} message: {}", menu.id, e);
+        }
+    }
+    RoleMenu::delete(&pool, menu.guild_id, menu.id).await?;
+    reply(ctx, format!("{} #{}", ROLE_MENU_DELETED, menu.id)).await
+}
+
+/// Update a posted menu's message. Returns whether it's posted and still there.
+#[cfg(not(tarpaulin_include))]
+async fn refresh_posted(ctx: Context<'_>, pool: &PgPool, menu: &RoleMenu) -> Result<bool, Error> {
+    let Some(message_id) = menu.message_id else {
+        return Ok(false);
+    };
+    let options = menu.options(pool).await?;
+    let (embed, components) = role_menu_message(menu, &options);
+    let edited = ChannelId::new(menu.channel_id as u64)
+        .edit_message(
+            ctx.http(),
+            MessageId::new(message_id as u64),
+            EditMessage::new().embed(embed).components(components),
+        )
+        .await;
+    match edited {
+        Ok(_) => Ok(true),
+        Err(e) => {
+            tracing::info!(
+                "Role menu {} message in guild {} is gone: {}",
+                menu.id,
+                GuildId::new(menu.guild_id as u64),
+                e
+            );
+            Ok(false)
+        },
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn reply(ctx: Context<'_>, msg: String) -> Result<(), Error> {
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
@@ -1,15 +1,16 @@
+use crate::commands::admin::log_case;
+use crate::db::CaseAction;
+use crate::duration::HumanDuration;
 use crate::errors::CrackedError;
 use crate::messaging::message::CrackedMessage;
 use crate::utils::send_reply;
 use crate::Context;
 use crate::Error;
 use poise::serenity_prelude::Mentionable;
-use regex::Regex;
 use serenity::all::User;
 use serenity::builder::EditMember;
-use std::time::Duration;
 
-/// Timeout a user from the server. Broken.
+/// Timeout a user from the server, for at most 28 days.
 #[cfg(not(tarpaulin_include))]
 #[poise::command(
     category = "Admin",
@@ -22,7 +23,8 @@ use std::time::Duration;
 pub async fn timeout(
     ctx: Context<'_>,
     #[description = "User to timout."] user: User,
-    #[description = "Amount of time"] duration: String,
+    #[description = "How long, e.g. `1h30m` or `until 18:00`."] duration: HumanDuration,
+    #[description = "Reason for the timeout."] reason: Option<String>,
     #[flag]
     #[description = "Show the help menu."]
     help: bool,
@@ -36,7 +38,7 @@ pub async fn timeout(
 
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
 
-    let timeout_duration = parse_duration(&duration)?;
+    let timeout_duration = duration.clamp_to_timeout();
     tracing::info!("Timeout duration: {:?}", timeout_duration);
 
     let now = chrono::Utc::now();
@@ -67,6 +69,15 @@ pub async fn timeout(
         )
         .await?;
     } else {
+        log_case(
+            ctx,
+            CaseAction::Timeout,
+            &user,
+            reason,
+            Some(timeout_duration),
+            Vec::new(),
+        )
+        .await;
         // Send success message
         let msg = CrackedMessage::UserTimeout {
             id,
@@ -78,58 +89,3 @@ pub async fn timeout(
     }
     Ok(())
 }
-
-/// Parse the input string for a duration
-fn parse_duration(input: &str) -> Result<Duration, CrackedError> {
-    let mut parts = Vec::new();
-    let re = Regex::new(r"((\d+)([smhd]))").unwrap();
-    for (_, [_, d, u]) in re.captures_iter(input).map(|c| c.extract()) {
-        parts.push((d, u));
-    }
-
-    if parts.is_empty() {
-        return Err(CrackedError::Other("Invalid format"));
-    }
-    let mut total = Duration::from_secs(0);
-
-    for (d, u) in parts {
-        let d = match d.parse::<u64>() {
-            Ok(n) => n,
-            Err(_) => {
-                return Err(CrackedError::DurationParseError(
-                    d.to_string(),
-                    u.to_string(),
-                ));
-            },
-        };
-        match u {
-            "s" => total += Duration::from_secs(d),
-            "m" => total += Duration::from_secs(d * 60),
-            "h" => total += Duration::from_secs(d * 60 * 60),
-            "d" => total += Duration::from_secs(d * 24 * 60 * 60),
-            _ => return Err(CrackedError::Other("Invalid time unit")),
-        }
-    }
-    Ok(total)
-}
-
-#[cfg(test)]
-mod test {
-
-    use super::*;
-
-    #[test]
-    fn test_parse_duration() {
-        assert_eq!(parse_duration("1s").unwrap(), Duration::from_secs(1));
-        assert_eq!(parse_duration("1m").unwrap(), Duration::from_secs(60));
-        assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(60 * 60));
-        assert_eq!(
-            parse_duration("1d").unwrap(),
-            Duration::from_secs(24 * 60 * 60)
-        );
-        assert_eq!(
-            parse_duration("1d1h1m1s").unwrap(),
-            Duration::from_secs(24 * 60 * 60 + 60 * 60 + 60 + 1)
-        );
-    }
-}
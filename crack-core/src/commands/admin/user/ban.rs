@@ -1,10 +1,15 @@
+use crate::commands::admin::log_case;
+use crate::db::CaseAction;
+use crate::duration::HumanDuration;
 use crate::errors::CrackedError;
 use crate::messaging::message::CrackedMessage;
+use crate::moderation::parse_evidence;
 use crate::utils::send_reply;
 use crate::Context;
 use crate::Error;
 use poise::serenity_prelude::Mentionable;
 use serenity::all::User;
+use std::time::Duration;
 
 /// Ban a user from the server.
 // There really doesn't seem to be a good way to restructure commands like this
@@ -29,13 +34,20 @@ pub async fn ban(
     #[description = "User to ban."] user: User,
     #[description = "Number of day to delete messages of the user."] dmd: Option<u8>,
     #[description = "Reason for the ban."] reason: Option<String>,
+    #[description = "Lift the ban after this long, e.g. `7d`."] duration: Option<HumanDuration>,
+    #[description = "Evidence links, separated by spaces."] evidence: Option<String>,
 ) -> Result<(), Error> {
     let mention = user.mention();
     let id = user.id;
     let dmd = dmd.unwrap_or(0);
-    let reason = reason.unwrap_or("No reason provided".to_string());
+    let duration = duration.map(Duration::from);
+    let evidence = parse_evidence(evidence.as_deref())?;
+    let audit_reason = reason.clone().unwrap_or("No reason provided".to_string());
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    if let Err(e) = guild_id.ban(ctx.http(), user.id, dmd, Some(&reason)).await {
+    if let Err(e) = guild_id
+        .ban(ctx.http(), user.id, dmd, Some(&audit_reason))
+        .await
+    {
         // Handle error, send error message
         send_reply(
             &ctx,
@@ -44,6 +56,11 @@ pub async fn ban(
         )
         .await?;
     } else {
+        let action = match duration {
+            Some(_) => CaseAction::TempBan,
+            None => CaseAction::Ban,
+        };
+        log_case(ctx, action, &user, reason, duration, evidence).await;
         // Send success message
         send_reply(&ctx, CrackedMessage::UserBanned { mention, id }, true).await?;
     }
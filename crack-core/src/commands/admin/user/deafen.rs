@@ -1,3 +1,5 @@
+use crate::commands::admin::log_case;
+use crate::db::CaseAction;
 use crate::errors::CrackedError;
 use crate::messaging::message::CrackedMessage;
 use crate::utils::send_reply;
@@ -22,6 +24,9 @@ pub async fn deafen(
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::GuildOnly)?;
     let crack_msg = deafen_internal(&ctx, guild_id, user.clone(), true).await?;
+    if matches!(crack_msg, CrackedMessage::UserDeafened { .. }) {
+        log_case(ctx, CaseAction::Deafen, &user, None, None, Vec::new()).await;
+    }
     // Handle error, send error message
     let _ = send_reply(&ctx, crack_msg, true).await?;
     Ok(())
@@ -42,6 +47,9 @@ pub async fn undeafen(
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::GuildOnly)?;
     let crack_msg = deafen_internal(&ctx, guild_id, user.clone(), false).await?;
+    if matches!(crack_msg, CrackedMessage::UserUndeafened { .. }) {
+        log_case(ctx, CaseAction::Undeafen, &user, None, None, Vec::new()).await;
+    }
     // Handle error, send error message
     let _ = send_reply(&ctx, crack_msg, true).await?;
     Ok(())
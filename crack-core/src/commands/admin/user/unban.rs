@@ -1,3 +1,5 @@
+use crate::commands::admin::log_case;
+use crate::db::{CaseAction, ModerationCase};
 use crate::errors::CrackedError;
 use crate::messaging::message::CrackedMessage;
 use crate::{poise_ext::PoiseContextExt, CommandResult, Context};
@@ -49,6 +51,21 @@ pub async fn unban_internal(ctx: Context<'_>, user_id: UserId) -> CommandResult
     let msg = if let Err(e) = guild_id.unban(ctx.http(), user_id, None).await {
         CrackedMessage::Other(format!("Failed to unban user: {}", e))
     } else {
+        // A manual unban ends any pending tempban.
+        if let Some(pool) = ctx.data().database_pool.as_ref() {
+            if let Err(e) = ModerationCase::mark_reversed_for_target(
+                pool,
+                guild_id.get() as i64,
+                user_id.get() as i64,
+                CaseAction::TempBan,
+                chrono::Utc::now().naive_utc(),
+            )
+            .await
+            {
+                tracing::warn!("Failed to close tempban cases of {}: {}", user_id, e);
+            }
+        }
+        log_case(ctx, CaseAction::Unban, &user, None, None, Vec::new()).await;
         CrackedMessage::UserUnbanned {
             id: user_id,
             mention,
@@ -9,9 +9,9 @@ pub mod music_utils;
 #[cfg(feature = "crack-osint")]
 pub mod osint;
 pub mod permissions;
-//pub mod playlist;
+pub mod playlist;
 pub mod register;
-//pub mod settings;
+pub mod settings;
 pub mod utility;
 
 //pub use admin::commands;
@@ -65,9 +65,9 @@ pub fn all_commands() -> Vec<crate::Command> {
     .chain(music::music_commands())
     // .chain(music::game_commands())
     .chain(utility::utility_commands())
-    //.chain(settings::commands())
-    //.chain(admin::commands())
-    //.chain(playlist::commands())
+    .chain(settings::commands())
+    .chain(admin::commands())
+    .chain(playlist::commands())
     .collect()
 }
 
@@ -87,9 +87,9 @@ pub fn commands_to_register() -> Vec<crate::Command> {
     .chain(music::music_commands())
     // .chain(music::game_commands())
     .chain(utility::utility_commands())
-    //.chain(settings::commands())
-    //.chain(admin::commands())
-    //.chain(playlist::commands())
+    .chain(settings::commands())
+    .chain(admin::commands())
+    .chain(playlist::commands())
     .collect()
 }
 
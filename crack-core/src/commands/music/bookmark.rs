@@ -0,0 +1,195 @@
+use crate::{
+    commands::{
+        cmd_check_music, get_call_or_join_author, queue_aux_metadata_with_offsets, sub_help as help,
+    },
+    db::{aux_metadata_from_db, aux_metadata_to_db_structures, Bookmark, Metadata, MetadataAnd},
+    errors::CrackedError,
+    messaging::message::CrackedMessage,
+    poise_ext::ContextExt,
+    utils::{
+        build_bookmark_list_embed, get_track_handle_metadata, send_embed_response_poise, send_reply,
+    },
+    Context, Error,
+};
+use crack_types::NewAuxMetadata;
+
+/// Bookmark the current position in the playing track.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    prefix_command,
+    slash_command,
+    guild_only
+)]
+pub async fn bookmark(
+    ctx: Context<'_>,
+    #[rest]
+    #[description = "A note to remember the moment by"]
+    note: Option<String>,
+) -> Result<(), Error> {
+    bookmark_internal(ctx, note).await
+}
+
+/// Internal bookmark function.
+#[cfg(not(tarpaulin_include))]
+async fn bookmark_internal(ctx: Context<'_>, note: Option<String>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let call = ctx.get_call().await?;
+
+    let track = call
+        .lock()
+        .await
+        .queue()
+        .current()
+        .ok_or(CrackedError::NothingPlaying)?;
+    let position = track.get_info().await?.position;
+    let aux_metadata = get_track_handle_metadata(&track).await?;
+
+    let guild_id_i64 = guild_id.get() as i64;
+    let channel_id = ctx.channel_id().get() as i64;
+    let user_id = ctx.author().id.get() as i64;
+    let pool = ctx.get_db_pool()?;
+
+    let MetadataAnd::Track(in_metadata, _) =
+        aux_metadata_to_db_structures(&aux_metadata, guild_id_i64, channel_id)?;
+    let metadata = Metadata::get_or_create(&pool, &in_metadata).await?;
+    let title = metadata
+        .title
+        .clone()
+        .or(metadata.track.clone())
+        .ok_or(CrackedError::NoTrackName)?;
+
+    let bookmark = Bookmark::create(
+        &pool,
+        user_id,
+        Some(guild_id_i64),
+        metadata.id,
+        position,
+        note,
+    )
+    .await?;
+
+    send_reply(
+        &ctx,
+        CrackedMessage::BookmarkSaved {
+            id: bookmark.id,
+            title,
+            position,
+        },
+        true,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// List, replay, and delete your bookmarks.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    subcommands("bookmarks_list", "bookmarks_play", "bookmarks_delete", "help"),
+    subcommand_required
+)]
+pub async fn bookmarks(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// List your bookmarks.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "list"
+)]
+pub async fn bookmarks_list(
+    ctx: Context<'_>,
+    #[description = "Page of bookmarks to show"] page: Option<usize>,
+) -> Result<(), Error> {
+    let pool = ctx.get_db_pool()?;
+    let user_id = ctx.author().id.get() as i64;
+
+    let bookmarks = Bookmark::list_for_user(&pool, user_id).await?;
+    let page = page.unwrap_or(1).saturating_sub(1);
+    let embed = build_bookmark_list_embed(&bookmarks, page);
+
+    send_embed_response_poise(ctx, embed).await?;
+    Ok(())
+}
+
+/// Queue a bookmarked track, starting at the bookmarked position.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "play"
+)]
+pub async fn bookmarks_play(
+    ctx: Context<'_>,
+    #[description = "Bookmark id, as shown by `/bookmarks list`"] id: i64,
+) -> Result<(), Error> {
+    let pool = ctx.get_db_pool()?;
+    let user_id = ctx.author().id.get() as i64;
+
+    let bookmark = Bookmark::get_for_user(&pool, id, user_id)
+        .await?
+        .ok_or(CrackedError::Other("Bookmark not found."))?;
+    let metadata = bookmark.metadata(&pool).await?;
+    let aux_metadata = NewAuxMetadata(aux_metadata_from_db(&metadata)?);
+    let title = metadata
+        .title
+        .clone()
+        .or(metadata.track.clone())
+        .unwrap_or_default();
+
+    // Make sure we're in a call before queuing.
+    let _call = get_call_or_join_author(ctx).await?;
+
+    let msg = send_reply(
+        &ctx,
+        CrackedMessage::BookmarkQueued {
+            title,
+            position: bookmark.position(),
+        },
+        true,
+    )
+    .await?
+    .into_message()
+    .await?;
+
+    queue_aux_metadata_with_offsets(ctx, &[(aux_metadata, bookmark.offsets())], msg).await?;
+
+    Ok(())
+}
+
+/// Delete one of your bookmarks.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "delete"
+)]
+pub async fn bookmarks_delete(
+    ctx: Context<'_>,
+    #[description = "Bookmark id, as shown by `/bookmarks list`"] id: i64,
+) -> Result<(), Error> {
+    let pool = ctx.get_db_pool()?;
+    let user_id = ctx.author().id.get() as i64;
+
+    if !Bookmark::delete_for_user(&pool, id, user_id).await? {
+        return Err(CrackedError::Other("Bookmark not found.").into());
+    }
+
+    send_reply(&ctx, CrackedMessage::BookmarkDeleted(id), true).await?;
+    Ok(())
+}
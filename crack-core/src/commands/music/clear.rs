@@ -1,8 +1,9 @@
 use crate::{
-    commands::{cmd_check_music, help},
+    commands::{cmd_check_dj_or_vote, cmd_check_music, help},
     errors::{verify, CrackedError},
     handlers::track_end::update_queue_messages,
     messaging::message::CrackedMessage,
+    poise_ext::ContextExt,
     utils::send_reply,
     Context, Error,
 };
@@ -31,9 +32,13 @@ pub async fn clear(
 /// Clear the queue, internal.
 pub async fn clear_internal(ctx: Context<'_>) -> Result<(), Error> {
     let guild_id = ctx.guild_id().unwrap();
-    let manager = ctx.data().songbird.clone();
+    let manager = ctx.get_songbird().await?;
     let call = manager.get(guild_id).ok_or(CrackedError::NotConnected)?;
 
+    if !cmd_check_dj_or_vote(ctx, "clear").await? {
+        return Ok(());
+    }
+
     let handler = call.lock().await;
     let queue = handler.queue().current_queue();
 
@@ -1,6 +1,7 @@
 use crate::commands::{cmd_check_music, help};
+use crate::db::TrackOffsets;
 use crate::music::query::query_type_from_url;
-use crate::music::queue::{get_mode, get_msg, queue_track_back};
+use crate::music::queue::{apply_track_offsets, get_mode, get_msg, queue_track_back};
 use crate::music::NewQueryType;
 use crate::utils::{edit_embed_response2, TrackData};
 use crate::{commands::get_call_or_join_author, http_utils::SendMessageParams};
@@ -535,6 +536,7 @@ pub async fn resolve_query_to_tracks(
         let track_data = Arc::new(TrackData {
             user_id: Arc::new(RwLock::new(Some(resolved_clone.clone().user_id))),
             aux_metadata: Arc::new(RwLock::new(resolved_clone.metadata.clone())),
+            overlay: false,
         });
         let track2 = Track::new_with_data(ytdl.clone().into(), track_data);
         track_handles.push(track2);
@@ -761,15 +763,28 @@ use rusty_ytdl::search::YouTube;
 pub async fn queue_aux_metadata(
     ctx: Context<'_>,
     aux_metadata: &[NewAuxMetadata],
+    msg: Message,
+) -> CrackedResult<()> {
+    let entries = aux_metadata
+        .iter()
+        .map(|metadata| (metadata.clone(), TrackOffsets::default()))
+        .collect::<Vec<_>>();
+    queue_aux_metadata_with_offsets(ctx, &entries, msg).await
+}
+
+/// Add tracks to the queue from aux_metadata, each playing only between its offsets.
+#[cfg(not(tarpaulin_include))]
+pub async fn queue_aux_metadata_with_offsets(
+    ctx: Context<'_>,
+    entries: &[(NewAuxMetadata, TrackOffsets)],
     mut msg: Message,
 ) -> CrackedResult<()> {
     // use crate::http_utils;
 
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    let search_results = aux_metadata;
 
     let client = &ctx.data().http_client;
-    let manager = ctx.data().songbird.clone();
+    let manager = ctx.get_songbird().await?;
 
     let call = manager.get(guild_id).ok_or(CrackedError::NotConnected)?;
 
@@ -777,7 +792,7 @@ pub async fn queue_aux_metadata(
         .set_client(client.clone())
         .build();
     let rusty_ytdl = YouTube::new_with_options(&req)?;
-    for metadata in search_results {
+    for (metadata, offsets) in entries {
         let source_url = metadata.metadata().source_url.as_ref();
         let metadata_final = if source_url.is_none() || source_url.unwrap().is_empty() {
             let search_query = build_query_aux_metadata(metadata.metadata());
@@ -805,7 +820,12 @@ pub async fn queue_aux_metadata(
                 .cloned()
                 .expect("source_url does not exist"),
         );
-        let _ = queue_track_back(ctx, &call, &query_type).await?;
+        let queue = queue_track_back(ctx, &call, &query_type).await?;
+        if !offsets.is_empty() {
+            if let Some(track) = queue.last() {
+                apply_track_offsets(track, *offsets)?;
+            }
+        }
     }
 
     let queue = call.lock().await.queue().current_queue();
@@ -0,0 +1,139 @@
+use crate::{
+    commands::{cmd_check_dj_or_vote, cmd_check_music},
+    errors::CrackedError,
+    http_utils,
+    messaging::{
+        message::CrackedMessage,
+        messages::{KARAOKE_INVALID_FILE, KARAOKE_NOT_FOUND},
+    },
+    music::{
+        karaoke::{follow_track, karaoke_embed, restart_without_vocals},
+        lrc::Lrc,
+    },
+    poise_ext::ContextExt,
+    sources::lrclib,
+    utils::{send_reply, TrackData},
+    Context, Error,
+};
+use poise::CreateReply;
+use serenity::all::Attachment;
+
+/// Biggest `.lrc` upload.
+const MAX_LRC_BYTES: u32 = 64 * 1024;
+
+/// Sing along: shows the current track's lyrics, following the line being sung.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    prefix_command,
+    slash_command,
+    guild_only
+)]
+pub async fn karaoke(
+    ctx: Context<'_>,
+    #[flag]
+    #[description = "Turn the vocals down on the current track."]
+    no_vocals: bool,
+    #[description = "An .lrc file with the lyrics, if they can't be found."] file: Option<
+        Attachment,
+    >,
+) -> Result<(), Error> {
+    let call = ctx.get_call().await?;
+    let current = call
+        .lock()
+        .await
+        .queue()
+        .current()
+        .ok_or(CrackedError::NothingPlaying)?;
+    if let Some(file) = &file {
+        if !is_lrc_file(&file.filename, file.size) {
+            return reply(ctx, KARAOKE_INVALID_FILE.to_string()).await;
+        }
+    }
+    // Restarting the track affects everyone listening.
+    if no_vocals && !cmd_check_dj_or_vote(ctx, "karaoke no_vocals").await? {
+        return Ok(());
+    }
+    ctx.defer().await?;
+
+    let metadata = current
+        .data::<TrackData>()
+        .aux_metadata
+        .read()
+        .await
+        .clone()
+        .unwrap_or_default();
+    let title = metadata
+        .track
+        .clone()
+        .or(metadata.title.clone())
+        .ok_or(CrackedError::NoTrackName)?;
+    let text = match file {
+        Some(file) => {
+            let bytes = http_utils::get_client()
+                .get(file.url.as_str())
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?;
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        },
+        None => {
+            lrclib::synced_lyrics(
+                http_utils::get_client(),
+                &title,
+                metadata.artist.as_deref(),
+                metadata.duration,
+            )
+            .await?
+        },
+    };
+    let Some(lyrics) = text.as_deref().and_then(Lrc::parse) else {
+        return reply(ctx, KARAOKE_NOT_FOUND.to_string()).await;
+    };
+
+    let track = if no_vocals {
+        restart_without_vocals(ctx.data(), &call).await?
+    } else {
+        current
+    };
+    let position = track
+        .get_info()
+        .await
+        .map(|info| info.position)
+        .unwrap_or_default();
+    let embed = karaoke_embed(&title, &lyrics, lyrics.line_at(position));
+    let message = ctx
+        .send(CreateReply::default().embed(embed))
+        .await?
+        .into_message()
+        .await?;
+    let http = ctx.serenity_context().http.clone();
+    tokio::spawn(follow_track(http, message, title, lyrics, track));
+    Ok(())
+}
+
+fn is_lrc_file(filename: &str, size: u32) -> bool {
+    filename.to_lowercase().ends_with(".lrc") && size <= MAX_LRC_BYTES
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn reply(ctx: Context<'_>, msg: String) -> Result<(), Error> {
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_lrc_file() {
+        assert!(is_lrc_file("Song 2.lrc", 1024));
+        assert!(is_lrc_file("SONG2.LRC", MAX_LRC_BYTES));
+        assert!(!is_lrc_file("song2.txt", 1024));
+        assert!(!is_lrc_file("song2.lrc", MAX_LRC_BYTES + 1));
+    }
+}
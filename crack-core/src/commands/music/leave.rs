@@ -1,7 +1,9 @@
 use crate::{
     commands::{cmd_check_music, help},
     errors::CrackedError,
+    handlers::SessionKey,
     messaging::message::CrackedMessage,
+    poise_ext::ContextExt,
     utils::send_reply,
     Context, Error,
 };
@@ -33,11 +35,13 @@ pub async fn leave(
 /// Leave a voice channel. Actually impl.
 pub async fn leave_internal(ctx: Context<'_>) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    let manager = ctx.data().songbird.clone();
+    let manager = ctx.get_songbird().await?;
     // check if we're actually in a call
     let crack_msg = match manager.remove(guild_id).await {
         Ok(()) => {
             tracing::info!("Driver successfully removed.");
+            ctx.data()
+                .cancel_sleep_timer(SessionKey::new(guild_id, &manager));
             CrackedMessage::Leaving
         },
         Err(err) => {
@@ -0,0 +1,223 @@
+use crate::{
+    db::SessionLink,
+    errors::CrackedError,
+    messaging::{
+        message::CrackedMessage,
+        messages::{
+            LINK_ALREADY_FOLLOWING, LINK_ALREADY_LEADING, LINK_INVALID_SERVER, LINK_LEADER_FOLLOWS,
+            LINK_LINKS, LINK_NONE, LINK_NOT_FOUND, LINK_REMOVED, LINK_SELF, LINK_WAITING,
+        },
+    },
+    poise_ext::ContextExt,
+    utils::send_reply,
+    Context, Error,
+};
+use poise::CreateReply;
+use serenity::all::{CreateEmbed, GuildId};
+
+/// Listen along with another server. One server leads and the others play
+/// what it plays; both have to agree. Shows this server's links.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("link_lead", "link_follow", "link_stop", "link_status"),
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn link(ctx: Context<'_>) -> Result<(), Error> {
+    link_status_internal(ctx).await
+}
+
+/// Agree to lead another server, which then plays what this one does.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "lead"
+)]
+pub async fn link_lead(
+    ctx: Context<'_>,
+    #[description = "ID of the server to lead."] server: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(other) = parse_server(ctx, guild_id, &server) else {
+        return reply(ctx, LINK_INVALID_SERVER.to_string()).await;
+    };
+    if other == guild_id {
+        return reply(ctx, LINK_SELF.to_string()).await;
+    }
+    if SessionLink::leader_of(&pool, guild_id.get() as i64)
+        .await?
+        .is_some()
+    {
+        return reply(ctx, LINK_ALREADY_FOLLOWING.to_string()).await;
+    }
+    let link = SessionLink::lead(&pool, guild_id.get() as i64, other.get() as i64).await?;
+    reply(ctx, format_link(ctx, guild_id, &link)).await
+}
+
+/// Agree to follow another server, playing what it plays.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "follow"
+)]
+pub async fn link_follow(
+    ctx: Context<'_>,
+    #[description = "ID of the server to follow."] server: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(other) = parse_server(ctx, guild_id, &server) else {
+        return reply(ctx, LINK_INVALID_SERVER.to_string()).await;
+    };
+    if other == guild_id {
+        return reply(ctx, LINK_SELF.to_string()).await;
+    }
+    if !SessionLink::followers(&pool, guild_id.get() as i64)
+        .await?
+        .is_empty()
+    {
+        return reply(ctx, LINK_ALREADY_LEADING.to_string()).await;
+    }
+    if SessionLink::leader_of(&pool, other.get() as i64)
+        .await?
+        .is_some()
+    {
+        return reply(ctx, LINK_LEADER_FOLLOWS.to_string()).await;
+    }
+    let link = SessionLink::follow(&pool, other.get() as i64, guild_id.get() as i64).await?;
+    reply(ctx, format_link(ctx, guild_id, &link)).await
+}
+
+/// Stop listening along with another server, from either side.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "stop"
+)]
+pub async fn link_stop(
+    ctx: Context<'_>,
+    #[description = "ID of the linked server."] server: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(other) = server.trim().parse::<u64>().ok().filter(|id| *id != 0) else {
+        return reply(ctx, LINK_NOT_FOUND.to_string()).await;
+    };
+    if !SessionLink::delete(&pool, guild_id.get() as i64, other as i64).await? {
+        return reply(ctx, LINK_NOT_FOUND.to_string()).await;
+    }
+    let name = server_name(ctx, GuildId::new(other));
+    reply(ctx, format!("{} {}", LINK_REMOVED, name)).await
+}
+
+/// Show this server's links.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "status"
+)]
+pub async fn link_status(ctx: Context<'_>) -> Result<(), Error> {
+    link_status_internal(ctx).await
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn link_status_internal(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let links = SessionLink::by_guild(&pool, guild_id.get() as i64).await?;
+    if links.is_empty() {
+        return reply(ctx, LINK_NONE.to_string()).await;
+    }
+    let description = links
+        .iter()
+        .map(|link| format_link(ctx, guild_id, link))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let embed = CreateEmbed::default()
+        .title(LINK_LINKS)
+        .description(description);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// A server ID argument, if it's a server the bot is in.
+#[cfg(not(tarpaulin_include))]
+fn parse_server(ctx: Context<'_>, guild_id: GuildId, server: &str) -> Option<GuildId> {
+    let id = server.trim().parse::<u64>().ok().filter(|id| *id != 0)?;
+    let id = GuildId::new(id);
+    (id == guild_id || ctx.cache().guild(id).is_some()).then_some(id)
+}
+
+/// A server's name, or its ID if the bot isn't in it anymore.
+#[cfg(not(tarpaulin_include))]
+fn server_name(ctx: Context<'_>, guild_id: GuildId) -> String {
+    ctx.cache()
+        .guild(guild_id)
+        .map(|guild| guild.name.to_string())
+        .unwrap_or_else(|| guild_id.to_string())
+}
+
+/// One line for a link, from `guild_id`'s side.
+#[cfg(not(tarpaulin_include))]
+fn format_link(ctx: Context<'_>, guild_id: GuildId, link: &SessionLink) -> String {
+    let leading = link.leader_guild_id == guild_id.get() as i64;
+    let other = if leading {
+        link.follower_guild_id
+    } else {
+        link.leader_guild_id
+    };
+    link_line(
+        leading,
+        &server_name(ctx, GuildId::new(other as u64)),
+        link.is_active(),
+    )
+}
+
+fn link_line(leading: bool, other: &str, active: bool) -> String {
+    let role = if leading { "Leading" } else { "Following" };
+    if active {
+        format!("🔗 {} **{}**", role, other)
+    } else {
+        format!("🔗 {} **{}**. {}", role, other, LINK_WAITING)
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn reply(ctx: Context<'_>, msg: String) -> Result<(), Error> {
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_link_line() {
+        assert_eq!(link_line(true, "Lofi", true), "🔗 Leading **Lofi**");
+        assert_eq!(
+            link_line(false, "Lofi", false),
+            "🔗 Following **Lofi**. ⏳ Waiting for the other server to agree."
+        );
+    }
+}
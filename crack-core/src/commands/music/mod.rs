@@ -1,5 +1,6 @@
 pub mod autopause;
 pub mod autoplay;
+pub mod bookmark;
 pub mod clear;
 pub mod collector;
 pub mod doplay;
@@ -7,19 +8,26 @@ pub mod dosearch;
 pub mod gambling;
 pub mod get_metadata;
 pub mod grab;
+pub mod karaoke;
 pub mod leave;
+pub mod link;
 pub mod lyrics;
 pub mod manage_sources;
 pub mod nowplaying;
 pub mod pause;
 pub mod playlog;
 pub mod queue;
+pub mod quiz;
+pub mod record;
 pub mod remove;
 pub mod repeat;
 pub mod resume;
+pub mod schedule;
 pub mod seek;
 pub mod shuffle;
 pub mod skip;
+pub mod sleep;
+pub mod soundboard;
 pub mod stop;
 pub mod summon;
 pub mod volume;
@@ -28,25 +36,33 @@ pub mod voteskip;
 
 pub use autopause::*;
 pub use autoplay::*;
+pub use bookmark::*;
 pub use clear::*;
 pub use collector::*;
 pub use doplay::*;
 pub use gambling::*;
 pub use get_metadata::*;
 pub use grab::*;
+pub use karaoke::*;
 pub use leave::*;
+pub use link::*;
 pub use lyrics::*;
 pub use manage_sources::*;
 pub use nowplaying::*;
 pub use pause::*;
 pub use playlog::*;
 pub use queue::*;
+pub use quiz::*;
+pub use record::*;
 pub use remove::*;
 pub use repeat::*;
 pub use resume::*;
+pub use schedule::*;
 pub use seek::*;
 pub use shuffle::*;
 pub use skip::*;
+pub use sleep::*;
+pub use soundboard::*;
 pub use stop::*;
 pub use summon::*;
 pub use volume::*;
@@ -58,9 +74,13 @@ pub fn music_commands() -> Vec<crate::Command> {
         vec![
             autopause(),
             autoplay(),
+            bookmark(),
+            bookmarks(),
             clear(),
             grab(),
+            karaoke(),
             leave(),
+            link(),
             lyrics(),
             nowplaying(),
             optplay(),
@@ -71,15 +91,21 @@ pub fn music_commands() -> Vec<crate::Command> {
             playnext(),
             playytplaylist(),
             queue(),
+            quiz(),
+            record(),
             remove(),
             repeat(),
             resume(),
+            sb(),
+            schedule(),
             search(),
             seek(),
             shuffle(),
             movesong(),
             skip(),
+            sleep(),
             stop(),
+            stopafter(),
             summon::summon(),
             summonchannel(),
             volume(),
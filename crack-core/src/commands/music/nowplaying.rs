@@ -2,7 +2,12 @@ use crate::poise_ext::{ContextExt, PoiseContextExt};
 use crate::{
     commands::{cmd_check_music, help},
     errors::CrackedError,
-    messaging::interface::create_now_playing_embed,
+    handlers::SessionKey,
+    messaging::{
+        interface::create_now_playing_embed,
+        messages::{SLEEP_TIMER, SLEEP_TIMER_AFTER_TRACK, SLEEP_TIMER_LEFT},
+    },
+    utils::duration_to_string,
     Context, Error,
 };
 
@@ -30,15 +35,27 @@ pub async fn nowplaying(
 
 /// Get the currently playing track. Internal function.
 pub async fn nowplaying_internal(ctx: Context<'_>) -> Result<(), Error> {
-    let call = ctx.get_call().await?;
+    let (call, guild_id) = ctx.get_call_guild_id().await?;
 
     let handler = call.lock().await;
     let track = handler
         .queue()
         .current()
         .ok_or(CrackedError::NothingPlaying)?;
+    drop(handler);
 
-    let embed = create_now_playing_embed(track).await;
+    let mut embed = create_now_playing_embed(track).await;
+    let manager = ctx.data().songbird_for_call(guild_id, &call);
+    if let Some(timer) = ctx
+        .data()
+        .get_sleep_timer(SessionKey::new(guild_id, &manager))
+    {
+        let value = match timer.remaining() {
+            Some(remaining) => format!("{} {}", duration_to_string(remaining), SLEEP_TIMER_LEFT),
+            None => SLEEP_TIMER_AFTER_TRACK.to_string(),
+        };
+        embed = embed.field(SLEEP_TIMER, value, true);
+    }
     let _ = ctx.send_embed_response(embed).await?;
     Ok(())
 }
@@ -7,6 +7,7 @@ use crate::{
         interface::{create_nav_btns, create_queue_embed},
         messages::QUEUE_EXPIRED,
     },
+    poise_ext::ContextExt,
     utils::{calculate_num_pages, forget_queue_message},
     Context, Error,
 };
@@ -49,7 +50,7 @@ use poise::serenity_prelude::CollectComponentInteractions;
 #[cfg(not(tarpaulin_include))]
 pub async fn queue_internal(ctx: Context<'_>) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    let manager = ctx.data().songbird.clone();
+    let manager = ctx.get_songbird().await?;
     let call = manager.get(guild_id).ok_or(CrackedError::NotConnected)?;
 
     // FIXME
@@ -0,0 +1,415 @@
+use crate::{
+    commands::{cmd_check_music, get_call_or_join_author},
+    db::{PlayLog, Playlist, QuizScore},
+    errors::CrackedError,
+    http_utils,
+    messaging::{
+        message::CrackedMessage,
+        messages::{
+            QUIZ_ALREADY_RUNNING, QUIZ_FINAL_SCORES, QUIZ_IT_WAS, QUIZ_LEADERBOARD,
+            QUIZ_LEADERBOARD_EMPTY, QUIZ_NOBODY_SCORED, QUIZ_NOT_ENOUGH_TRACKS, QUIZ_NOT_RUNNING,
+            QUIZ_NO_NAME, QUIZ_ROUND, QUIZ_STARTING, QUIZ_STOPPING,
+        },
+    },
+    music::quiz::{
+        render_scores, Guess, QuizSource, QuizTrack, Round, Scoreboard, DEFAULT_ROUNDS, MAX_ROUNDS,
+        ROUND_LENGTH, SNIPPET_LENGTH,
+    },
+    poise_ext::ContextExt,
+    sources::rusty_ytdl::RustyYoutubeSearch,
+    utils::{send_reply, TrackData},
+    Context, Data, Error,
+};
+use crack_types::QueryType;
+use dashmap::mapref::entry::Entry;
+use poise::{futures_util::StreamExt, CreateReply};
+use rand::{seq::SliceRandom, Rng};
+use serenity::all::{CreateEmbed, CreateMessage, UserId};
+use songbird::{
+    input::AuxMetadata,
+    tracks::{PlayMode, Track, TrackHandle},
+    Call,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::sync::Mutex;
+
+/// Most of the server's history to pick tracks from.
+const HISTORY_TRACKS: i64 = 500;
+/// Members shown on the leaderboard.
+const LEADERBOARD_SIZE: i64 = 10;
+
+/// Guess the song! Plays snippets of tracks for everyone to name. Shows the
+/// leaderboard.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Games",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("quiz_start", "quiz_stop", "quiz_leaderboard")
+)]
+pub async fn quiz(ctx: Context<'_>) -> Result<(), Error> {
+    quiz_leaderboard_internal(ctx).await
+}
+
+/// Start a music quiz. Whatever's playing is paused until it's over.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Games",
+    check = "cmd_check_music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "start"
+)]
+pub async fn quiz_start(
+    ctx: Context<'_>,
+    #[description = "Where the songs come from."] source: Option<QuizSource>,
+    #[description = "The playlist or genre to play from."] name: Option<String>,
+    #[description = "How many songs."]
+    #[min = 1]
+    #[max = 20]
+    rounds: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let source = source.unwrap_or_default();
+    let name = name.filter(|name| !name.trim().is_empty());
+    if source != QuizSource::History && name.is_none() {
+        return reply(ctx, QUIZ_NO_NAME.to_string()).await;
+    }
+    let data = ctx.data();
+    if data.quizzes.contains_key(&guild_id) {
+        return reply(ctx, QUIZ_ALREADY_RUNNING.to_string()).await;
+    }
+    ctx.defer().await?;
+
+    let mut tracks = quiz_tracks(ctx, source, name).await?;
+    if tracks.is_empty() {
+        return reply(ctx, QUIZ_NOT_ENOUGH_TRACKS.to_string()).await;
+    }
+    tracks.truncate(rounds.unwrap_or(DEFAULT_ROUNDS).clamp(1, MAX_ROUNDS) as usize);
+    let call = get_call_or_join_author(ctx).await?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let started = match data.quizzes.entry(guild_id) {
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
+            entry.insert(stop.clone());
+            true
+        },
+    };
+    if !started {
+        return reply(ctx, QUIZ_ALREADY_RUNNING.to_string()).await;
+    }
+
+    let current = call.lock().await.queue().current();
+    let paused = match current {
+        Some(track) => {
+            matches!(
+                track.get_info().await.map(|info| info.playing),
+                Ok(PlayMode::Play)
+            ) && track.pause().is_ok()
+        },
+        None => false,
+    };
+    let result = match reply(ctx, QUIZ_STARTING.to_string()).await {
+        Ok(()) => run_quiz(ctx, &call, tracks, &stop).await,
+        Err(e) => Err(e),
+    };
+    data.quizzes.remove(&guild_id);
+    if paused {
+        let _ = call.lock().await.queue().resume();
+    }
+    let scores = result?.ranked();
+
+    // The quiz still works without a database, just without a leaderboard.
+    if let Ok(pool) = ctx.get_db_pool() {
+        let game = scores
+            .iter()
+            .map(|(user_id, points)| (user_id.get() as i64, i64::from(*points)))
+            .collect::<Vec<_>>();
+        QuizScore::record_game(&pool, guild_id.get() as i64, &game).await?;
+    }
+    let description = if scores.is_empty() {
+        QUIZ_NOBODY_SCORED.to_string()
+    } else {
+        render_scores(&scores)
+    };
+    let embed = CreateEmbed::default()
+        .title(QUIZ_FINAL_SCORES)
+        .description(description);
+    ctx.channel_id()
+        .send_message(ctx.http(), CreateMessage::new().embed(embed))
+        .await?;
+    Ok(())
+}
+
+/// Stop the music quiz after the current round.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Games",
+    check = "cmd_check_music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "stop"
+)]
+pub async fn quiz_stop(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let Some(stop) = ctx.data().quizzes.get(&guild_id).map(|stop| stop.clone()) else {
+        return reply(ctx, QUIZ_NOT_RUNNING.to_string()).await;
+    };
+    stop.store(true, Ordering::Relaxed);
+    reply(ctx, QUIZ_STOPPING.to_string()).await
+}
+
+/// Show the server's music quiz leaderboard.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Games",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "leaderboard"
+)]
+pub async fn quiz_leaderboard(ctx: Context<'_>) -> Result<(), Error> {
+    quiz_leaderboard_internal(ctx).await
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn quiz_leaderboard_internal(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let board = QuizScore::leaderboard(&pool, guild_id.get() as i64, LEADERBOARD_SIZE).await?;
+    if board.is_empty() {
+        return reply(ctx, QUIZ_LEADERBOARD_EMPTY.to_string()).await;
+    }
+    let scores = board
+        .iter()
+        .map(|score| (UserId::new(score.user_id as u64), score.points))
+        .collect::<Vec<_>>();
+    let embed = CreateEmbed::default()
+        .title(QUIZ_LEADERBOARD)
+        .description(render_scores(&scores));
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Play the rounds, returning the scores. Messages go to the channel rather
+/// than as replies, since a long quiz outlasts the interaction.
+#[cfg(not(tarpaulin_include))]
+async fn run_quiz(
+    ctx: Context<'_>,
+    call: &Arc<Mutex<Call>>,
+    tracks: Vec<QuizTrack>,
+    stop: &AtomicBool,
+) -> Result<Scoreboard, Error> {
+    let http = ctx.serenity_context().http.clone();
+    let channel_id = ctx.channel_id();
+    let rounds = tracks.len();
+    let mut scores = Scoreboard::default();
+    for (i, track) in tracks.into_iter().enumerate() {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let snippet = match play_snippet(ctx.data(), call, &track).await {
+            Ok(snippet) => snippet,
+            Err(e) => {
+                tracing::warn!("Couldn't play quiz track {}: {}", track.url, e);
+                continue;
+            },
+        };
+        let prompt = format!("**{}/{}** {}", i + 1, rounds, QUIZ_ROUND);
+        channel_id
+            .send_message(&http, CreateMessage::new().content(prompt))
+            .await?;
+
+        let mut round = Round::new(track);
+        let mut guesses = channel_id
+            .collect_messages(ctx.serenity_context().shard.clone())
+            .timeout(ROUND_LENGTH)
+            .stream();
+        while let Some(msg) = guesses.next().await {
+            if msg.author.bot() {
+                continue;
+            }
+            let Some(guess) = round.guess(msg.author.id, &msg.content) else {
+                continue;
+            };
+            scores.add(msg.author.id, guess.points());
+            channel_id
+                .send_message(
+                    &http,
+                    CreateMessage::new().content(guess_text(msg.author.id, guess)),
+                )
+                .await?;
+            if round.is_done() {
+                break;
+            }
+        }
+        let _ = snippet.stop();
+
+        let ranked = scores.ranked();
+        let description = if ranked.is_empty() {
+            QUIZ_NOBODY_SCORED.to_string()
+        } else {
+            render_scores(&ranked)
+        };
+        let embed = CreateEmbed::default()
+            .title(format!("{} {}", QUIZ_IT_WAS, answer_text(&round.track)))
+            .url(round.track.url.clone())
+            .description(description);
+        channel_id
+            .send_message(&http, CreateMessage::new().embed(embed))
+            .await?;
+    }
+    Ok(scores)
+}
+
+/// Play a snippet of `track` over the call, stopping it after
+/// [`SNIPPET_LENGTH`]. It's an overlay, so it doesn't touch the queue.
+#[cfg(not(tarpaulin_include))]
+async fn play_snippet(
+    data: &Data,
+    call: &Arc<Mutex<Call>>,
+    track: &QuizTrack,
+) -> Result<TrackHandle, CrackedError> {
+    let query = QueryType::VideoLink(track.url.clone());
+    let resolved = data.ct_client.resolve_track(query.clone()).await?;
+    let input = RustyYoutubeSearch::new_with_stuff(
+        http_utils::get_client_old().clone(),
+        query,
+        resolved.metadata,
+        resolved.video,
+    )?;
+    let start = track.snippet_start(rand::thread_rng().gen());
+    let handle = call
+        .lock()
+        .await
+        .play(Track::new_with_data(input.into(), TrackData::overlay()));
+    if !start.is_zero() {
+        let _ = handle.seek(start);
+    }
+    let snippet = handle.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(SNIPPET_LENGTH).await;
+        let _ = snippet.stop();
+    });
+    Ok(handle)
+}
+
+/// Tracks to play a quiz from, shuffled, once each.
+#[cfg(not(tarpaulin_include))]
+async fn quiz_tracks(
+    ctx: Context<'_>,
+    source: QuizSource,
+    name: Option<String>,
+) -> Result<Vec<QuizTrack>, Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let metadata: Vec<AuxMetadata> = match (source, name) {
+        (QuizSource::Playlist, Some(name)) => {
+            let pool = ctx.get_db_pool()?;
+            let playlist = Playlist::get_playlist_by_name_scoped(
+                &pool,
+                name,
+                ctx.author().id.get() as i64,
+                Some(guild_id.get() as i64),
+            )
+            .await?;
+            Playlist::get_track_metadata_for_playlist(&pool, playlist.id)
+                .await?
+                .into_iter()
+                .map(AuxMetadata::from)
+                .collect()
+        },
+        (QuizSource::Genre, Some(genre)) => {
+            let mut found = Vec::new();
+            for query in genre_queries(&genre) {
+                match ctx.data().ct_client.resolve_search(&query).await {
+                    Ok(results) => found.extend(results.into_iter().map(|track| {
+                        let mut metadata = track.get_metadata().unwrap_or_default();
+                        metadata.source_url.get_or_insert_with(|| track.get_url());
+                        metadata.title.get_or_insert_with(|| track.get_title());
+                        metadata
+                    })),
+                    Err(e) => tracing::warn!("Error searching for quiz tracks: {}", e),
+                }
+            }
+            found
+        },
+        _ => {
+            let pool = ctx.get_db_pool()?;
+            PlayLog::get_played_tracks_by_guild(&pool, guild_id.get() as i64, HISTORY_TRACKS)
+                .await?
+                .into_iter()
+                .map(AuxMetadata::from)
+                .collect()
+        },
+    };
+    let mut tracks = metadata
+        .iter()
+        .filter_map(QuizTrack::from_metadata)
+        .collect::<Vec<_>>();
+    tracks.sort_by(|a, b| a.url.cmp(&b.url));
+    tracks.dedup_by(|a, b| a.url == b.url);
+    tracks.shuffle(&mut rand::thread_rng());
+    Ok(tracks)
+}
+
+/// Searches that turn up songs in a genre. Each returns a handful.
+fn genre_queries(genre: &str) -> [String; 3] {
+    let genre = genre.trim();
+    [
+        format!("{} songs", genre),
+        format!("best {} hits", genre),
+        format!("{} music", genre),
+    ]
+}
+
+fn guess_text(user_id: UserId, guess: Guess) -> String {
+    let what = match guess {
+        Guess::Title => "title",
+        Guess::Artist => "artist",
+    };
+    format!("✅ <@{}> got the {}! +{}", user_id, what, guess.points())
+}
+
+fn answer_text(track: &QuizTrack) -> String {
+    match &track.artist {
+        Some(artist) => format!("{} by {}", track.title, artist),
+        None => track.title.clone(),
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn reply(ctx: Context<'_>, msg: String) -> Result<(), Error> {
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quiz_text() {
+        assert_eq!(
+            guess_text(UserId::new(1), Guess::Title),
+            "✅ <@1> got the title! +2"
+        );
+        assert_eq!(
+            guess_text(UserId::new(1), Guess::Artist),
+            "✅ <@1> got the artist! +1"
+        );
+        let track = QuizTrack {
+            url: String::new(),
+            title: "Song 2".to_string(),
+            artist: Some("Blur".to_string()),
+            duration: None,
+        };
+        assert_eq!(answer_text(&track), "Song 2 by Blur");
+        assert_eq!(genre_queries(" shoegaze ")[0], "shoegaze songs");
+    }
+}
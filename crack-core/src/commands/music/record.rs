@@ -0,0 +1,133 @@
+use crate::{
+    commands::cmd_check_music,
+    duration::format_duration,
+    errors::CrackedError,
+    handlers::voice::register_voice_handlers,
+    messaging::{
+        message::CrackedMessage,
+        messages::{
+            RECORD_ALREADY, RECORD_ANNOUNCE, RECORD_NOT_RECORDING, RECORD_SAVED_TO, RECORD_STOPPED,
+            RECORD_TRACKS,
+        },
+    },
+    poise_ext::ContextExt,
+    recording::{record_buttons, Recording},
+    utils::send_reply,
+    Context, Error,
+};
+use poise::CreateReply;
+use serenity::all::{CreateAttachment, Mentionable};
+use std::sync::Arc;
+
+/// Biggest recording uploaded to Discord. Longer ones are only saved.
+const UPLOAD_LIMIT: u64 = 10 * 1024 * 1024;
+
+/// Record the voice channel. Only members who opt in are recorded.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("record_start", "record_stop")
+)]
+pub async fn record(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let msg = match ctx.data().recordings.get(&guild_id) {
+        Some(recording) => format!("{} {}", RECORD_ANNOUNCE, recording.started_by.mention()),
+        None => RECORD_NOT_RECORDING.to_string(),
+    };
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
+
+/// Start recording, and announce it with buttons to opt in and out. You're
+/// opted in.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "start"
+)]
+pub async fn record_start(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let call = ctx.get_call().await?;
+    let data = ctx.data();
+    if data.recordings.contains_key(&guild_id) {
+        send_reply(
+            &ctx,
+            CrackedMessage::Other(RECORD_ALREADY.to_string()),
+            true,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let author = ctx.author().id;
+    let recording = Arc::new(Recording::start(
+        guild_id,
+        ctx.channel_id(),
+        author,
+        chrono::Utc::now(),
+    )?);
+    recording.with_recorder(|recorder| recorder.opt_in(author));
+    register_voice_handlers(recording.clone(), call).await?;
+    data.recordings.insert(guild_id, recording);
+
+    let announcement = format!("{} {}", RECORD_ANNOUNCE, author.mention());
+    ctx.send(
+        CreateReply::default()
+            .content(announcement)
+            .components(record_buttons(guild_id)),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Stop recording and upload the mix, or say where it's saved if it's too big.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "stop"
+)]
+pub async fn record_stop(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let Some((_, recording)) = ctx.data().recordings.remove(&guild_id) else {
+        send_reply(
+            &ctx,
+            CrackedMessage::Other(RECORD_NOT_RECORDING.to_string()),
+            true,
+        )
+        .await?;
+        return Ok(());
+    };
+    let files = recording.stop()?;
+
+    let mut msg = format!(
+        "{} {}, {} {}.\n{} `{}`",
+        RECORD_STOPPED,
+        format_duration(files.duration),
+        files.tracks.len(),
+        RECORD_TRACKS,
+        RECORD_SAVED_TO,
+        recording.dir.display(),
+    );
+    for (user_id, path) in &files.tracks {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        msg.push_str(&format!("\n{} `{}`", user_id.mention(), name));
+    }
+    let mut reply = CreateReply::default().content(msg);
+    let size = std::fs::metadata(&files.mixed)?.len();
+    if size <= UPLOAD_LIMIT {
+        reply = reply.attachment(CreateAttachment::path(&files.mixed).await?);
+    }
+    ctx.send(reply).await?;
+    Ok(())
+}
@@ -1,9 +1,11 @@
 use self::serenity::builder::CreateEmbed;
 use crate::{
+    commands::cmd_check_dj_or_vote,
     errors::{verify, CrackedError},
     handlers::track_end::update_queue_messages,
     messaging::message::CrackedMessage,
     messaging::messages::REMOVED_QUEUE,
+    poise_ext::ContextExt,
     utils::send_reply,
     utils::{get_track_handle_metadata, send_embed_response_poise},
     Context, Error,
@@ -37,7 +39,7 @@ pub async fn remove_internal(
     e_index: Option<usize>,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    let manager = ctx.data().songbird.clone();
+    let manager = ctx.get_songbird().await?;
     let call = manager.get(guild_id).ok_or(CrackedError::NotConnected)?;
 
     let remove_index = b_index;
@@ -46,6 +48,14 @@ pub async fn remove_internal(
         None => remove_index,
     };
 
+    let action = match e_index {
+        Some(end) => format!("remove {} {}", b_index, end),
+        None => format!("remove {}", b_index),
+    };
+    if !cmd_check_dj_or_vote(ctx, &action).await? {
+        return Ok(());
+    }
+
     let handler = call.lock().await;
     let queue = handler.queue().current_queue();
 
@@ -1,6 +1,6 @@
 use crate::{
     commands::cmd_check_music, errors::CrackedError, messaging::message::CrackedMessage,
-    messaging::messages::FAIL_LOOP, utils::send_reply, Context, Error,
+    messaging::messages::FAIL_LOOP, poise_ext::ContextExt, utils::send_reply, Context, Error,
 };
 use songbird::tracks::{LoopState, TrackHandle};
 
@@ -29,7 +29,7 @@ pub async fn repeat(
 #[cfg(not(tarpaulin_include))]
 pub async fn repeat_internal(ctx: Context<'_>) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    let songbird = ctx.data().songbird.clone();
+    let songbird = ctx.get_songbird().await?;
     let call = songbird.get(guild_id).ok_or(CrackedError::NotConnected)?;
 
     let handler = call.lock().await;
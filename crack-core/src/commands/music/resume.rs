@@ -1,6 +1,7 @@
 use crate::{
     errors::{verify, CrackedError},
     messaging::message::CrackedMessage,
+    poise_ext::ContextExt,
     utils::send_reply,
     {Context, Error},
 };
@@ -15,7 +16,7 @@ pub async fn resume(ctx: Context<'_>) -> Result<(), Error> {
 /// Internal function to resume the current track.
 pub async fn resume_internal(ctx: Context<'_>) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    let songbird = ctx.data().songbird.clone();
+    let songbird = ctx.get_songbird().await?;
     let call = songbird.get(guild_id).ok_or(CrackedError::NotConnected)?;
 
     let handler = call.lock().await;
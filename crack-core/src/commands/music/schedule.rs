@@ -0,0 +1,270 @@
+use crate::{
+    commands::{cmd_check_music, sub_help as help},
+    connection::get_voice_channel_for_user,
+    db::{Playlist, ScheduleAction, ScheduledPlayback},
+    errors::CrackedError,
+    messaging::message::CrackedMessage,
+    music::schedule::{parse_schedule_time, parse_timezone},
+    poise_ext::{ContextExt, PoiseContextExt},
+    utils::{build_schedule_list_embed, send_embed_response_poise, send_reply},
+    Context, Error,
+};
+use ::serenity::all::{Channel, ChannelType, Permissions};
+use chrono::Utc;
+
+/// Schedule playback ahead of time, e.g. for listening parties.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    subcommands(
+        "schedule_play",
+        "schedule_stop",
+        "schedule_list",
+        "schedule_cancel",
+        "help"
+    ),
+    subcommand_required
+)]
+pub async fn schedule(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Join a voice channel and play a playlist at a given time.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "play"
+)]
+pub async fn schedule_play(
+    ctx: Context<'_>,
+    #[description = "Playlist name or id"] playlist: String,
+    #[description = "When, e.g. `20:00`, `friday 20:00`, `2024-12-31 23:00` or `in 2h`"] at: String,
+    #[description = "Timezone, e.g. `Europe/Berlin` (default UTC)"] timezone: Option<String>,
+    #[description = "Voice channel to play in (default: yours)"] channel: Option<Channel>,
+    #[min = 1]
+    #[max = 365]
+    #[description = "Repeat every this many days, e.g. 7 for weekly"]
+    repeat_days: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::GuildOnly)?;
+    let tz = parse_timezone(timezone.as_deref())?;
+    let run_at = parse_schedule_time(&at, tz, Utc::now())?;
+
+    let voice_channel_id = match channel {
+        Some(channel) => channel.id(),
+        None => {
+            let guild = ctx.guild().ok_or(CrackedError::NoGuildCached)?.clone();
+            get_voice_channel_for_user(&guild, &ctx.author().id)?
+        },
+    };
+
+    // The bot joins on the scheduler's behalf later, so only let members pick
+    // channels they could join themselves, unless they manage music here.
+    let dj_role = match ctx.data().get_guild_settings(guild_id).await {
+        Some(settings) => settings.dj_role,
+        None => None,
+    };
+    let member = ctx
+        .author_member()
+        .await
+        .ok_or(CrackedError::NoGuildCached)?
+        .into_owned();
+    let is_dj = dj_role.is_some_and(|role| member.roles.iter().any(|r| r.get() == role));
+    let allowed = {
+        let guild = ctx.guild().ok_or(CrackedError::NoGuildCached)?;
+        let channel = guild
+            .channels
+            .get(&voice_channel_id)
+            .filter(|c| matches!(c.kind, ChannelType::Voice | ChannelType::Stage))
+            .ok_or(CrackedError::Other(
+                "That isn't a voice channel in this server.",
+            ))?;
+        let perms = guild.user_permissions_in(channel, &member);
+        is_dj
+            || perms.contains(Permissions::CONNECT)
+            || perms.intersects(Permissions::ADMINISTRATOR | Permissions::MANAGE_GUILD)
+    };
+    if !allowed {
+        return Err(CrackedError::UnauthorizedUser.into());
+    }
+
+    let pool = ctx.get_db_pool()?;
+    let user_id = ctx.author().id.get() as i64;
+    let playlist = match playlist.parse::<i32>() {
+        Ok(playlist_id) => {
+            Playlist::get_playlist_by_id_scoped(
+                &pool,
+                playlist_id,
+                user_id,
+                Some(guild_id.get() as i64),
+            )
+            .await?
+        },
+        Err(_) => {
+            Playlist::get_playlist_by_name_scoped(
+                &pool,
+                playlist,
+                user_id,
+                Some(guild_id.get() as i64),
+            )
+            .await?
+        },
+    };
+
+    let job = ScheduledPlayback::create(
+        &pool,
+        &ScheduledPlayback {
+            guild_id: guild_id.get() as i64,
+            voice_channel_id: Some(voice_channel_id.get() as i64),
+            text_channel_id: Some(ctx.channel_id().get() as i64),
+            user_id,
+            action: ScheduleAction::Play.to_string(),
+            playlist_id: Some(playlist.id),
+            run_at: run_at.naive_utc(),
+            timezone: tz.name().to_string(),
+            repeat_days: repeat_days.map(|d| d as i32),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    send_reply(
+        &ctx,
+        CrackedMessage::ScheduleCreated {
+            id: job.id,
+            action: job.action,
+            playlist: Some(playlist.name),
+            run_at: run_at.timestamp(),
+            repeat_days: job.repeat_days,
+        },
+        true,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Stop playback and leave at a given time.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "stop"
+)]
+pub async fn schedule_stop(
+    ctx: Context<'_>,
+    #[description = "When, e.g. `22:00`, `friday 22:00` or `2024-12-31 23:59`"] at: String,
+    #[description = "Timezone, e.g. `Europe/Berlin` (default UTC)"] timezone: Option<String>,
+    #[min = 1]
+    #[max = 365]
+    #[description = "Repeat every this many days, e.g. 7 for weekly"]
+    repeat_days: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::GuildOnly)?;
+    let tz = parse_timezone(timezone.as_deref())?;
+    let run_at = parse_schedule_time(&at, tz, Utc::now())?;
+    let pool = ctx.get_db_pool()?;
+    // Stop the session in the author's channel, or every session if they
+    // aren't in voice.
+    let voice_channel_id = ctx
+        .guild()
+        .and_then(|guild| get_voice_channel_for_user(&guild, &ctx.author().id).ok());
+
+    let job = ScheduledPlayback::create(
+        &pool,
+        &ScheduledPlayback {
+            guild_id: guild_id.get() as i64,
+            voice_channel_id: voice_channel_id.map(|id| id.get() as i64),
+            text_channel_id: Some(ctx.channel_id().get() as i64),
+            user_id: ctx.author().id.get() as i64,
+            action: ScheduleAction::Stop.to_string(),
+            run_at: run_at.naive_utc(),
+            timezone: tz.name().to_string(),
+            repeat_days: repeat_days.map(|d| d as i32),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    send_reply(
+        &ctx,
+        CrackedMessage::ScheduleCreated {
+            id: job.id,
+            action: job.action,
+            playlist: None,
+            run_at: run_at.timestamp(),
+            repeat_days: job.repeat_days,
+        },
+        true,
+    )
+    .await?;
+    Ok(())
+}
+
+/// List this server's scheduled jobs.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "list"
+)]
+pub async fn schedule_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::GuildOnly)?;
+    let pool = ctx.get_db_pool()?;
+
+    let mut jobs = Vec::new();
+    for job in ScheduledPlayback::get_for_guild(&pool, guild_id.get() as i64).await? {
+        let playlist = match job.playlist_id {
+            Some(id) => Playlist::get_playlist_by_id(&pool, id)
+                .await
+                .ok()
+                .map(|p| p.name),
+            None => None,
+        };
+        jobs.push((job, playlist));
+    }
+
+    send_embed_response_poise(ctx, build_schedule_list_embed(&jobs)).await?;
+    Ok(())
+}
+
+/// Cancel a scheduled job. Only its creator or someone with Manage Server can.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "cancel"
+)]
+pub async fn schedule_cancel(
+    ctx: Context<'_>,
+    #[description = "Job id, as shown by `/schedule list`"] id: i64,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::GuildOnly)?.get() as i64;
+    let pool = ctx.get_db_pool()?;
+
+    let job = ScheduledPlayback::get_for_guild(&pool, guild_id)
+        .await?
+        .into_iter()
+        .find(|job| job.id == id)
+        .ok_or(CrackedError::Other("Scheduled job not found."))?;
+    let is_creator = job.user_id == ctx.author().id.get() as i64;
+    if !is_creator && !ctx.author_permissions().await?.manage_guild() {
+        return Err(CrackedError::UnauthorizedUser.into());
+    }
+
+    ScheduledPlayback::delete_for_guild(&pool, id, guild_id).await?;
+    send_reply(&ctx, CrackedMessage::ScheduleCancelled(id), true).await?;
+    Ok(())
+}
@@ -1,15 +1,17 @@
 use crate::{
-    commands::cmd_check_music,
+    commands::{cmd_check_dj_or_vote, cmd_check_music},
+    duration::parse_duration,
     errors::{verify, CrackedError},
     messaging::message::CrackedMessage,
     messaging::messages::{FAIL_MINUTES_PARSING, FAIL_SECONDS_PARSING},
+    music::link::sync_followers,
     poise_ext::ContextExt,
     utils::send_reply,
     Context, Error,
 };
 use std::{borrow::Cow, time::Duration};
 
-/// Seek to timestamp, in format `mm:ss`.
+/// Seek to timestamp, in format `mm:ss` or e.g. `1m30s`.
 #[cfg(not(tarpaulin_include))]
 #[poise::command(
     category = "Music",
@@ -20,17 +22,17 @@ use std::{borrow::Cow, time::Duration};
 )]
 pub async fn seek(
     ctx: Context<'_>,
-    #[description = "Seek to timestamp, in format `mm:ss`."] seek_time: String,
+    #[description = "Seek to timestamp, in format `mm:ss` or e.g. `1m30s`."] seek_time: String,
 ) -> Result<(), Error> {
     seek_internal(ctx, seek_time).await
 }
 
-/// Internal seek function.
-pub async fn seek_internal(ctx: Context<'_>, seek_time: String) -> Result<(), Error> {
-    let call = ctx.get_call().await?;
-
-    let timestamp_str = seek_time.as_str();
-    let mut units_iter = timestamp_str.split(':');
+/// Parse a timestamp in format `mm:ss`, or a duration like `1m30s`.
+pub fn parse_timestamp(timestamp: &str) -> Result<Duration, CrackedError> {
+    if !timestamp.contains(':') {
+        return Ok(parse_duration(timestamp)?);
+    }
+    let mut units_iter = timestamp.split(':');
 
     let minutes = units_iter.next().and_then(|c| c.parse::<u64>().ok());
     let minutes = verify(minutes, CrackedError::Other(FAIL_MINUTES_PARSING))?;
@@ -38,7 +40,19 @@ pub async fn seek_internal(ctx: Context<'_>, seek_time: String) -> Result<(), Er
     let seconds = units_iter.next().and_then(|c| c.parse::<u64>().ok());
     let seconds = verify(seconds, CrackedError::Other(FAIL_SECONDS_PARSING))?;
 
-    let timestamp = minutes * 60 + seconds;
+    Ok(Duration::from_secs(minutes * 60 + seconds))
+}
+
+/// Internal seek function.
+pub async fn seek_internal(ctx: Context<'_>, seek_time: String) -> Result<(), Error> {
+    let call = ctx.get_call().await?;
+
+    let timestamp_str = seek_time.as_str();
+    let timestamp = parse_timestamp(timestamp_str)?;
+
+    if !cmd_check_dj_or_vote(ctx, &format!("seek {}", timestamp_str)).await? {
+        return Ok(());
+    }
 
     let handler = call.lock().await;
     let track = handler
@@ -47,10 +61,14 @@ pub async fn seek_internal(ctx: Context<'_>, seek_time: String) -> Result<(), Er
         .ok_or(CrackedError::Other("No track playing"))?;
     drop(handler);
 
-    let callback = track.seek(Duration::from_secs(timestamp));
+    let callback = track.seek(timestamp);
     let msg = match callback.result_async().await {
-        Ok(_) => CrackedMessage::Seek {
-            timestamp: timestamp_str.to_owned(),
+        Ok(_) => {
+            let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+            sync_followers(ctx.data().clone(), guild_id, call).await;
+            CrackedMessage::Seek {
+                timestamp: timestamp_str.to_owned(),
+            }
         },
         Err(e) => CrackedMessage::SeekFail {
             timestamp: Cow::Owned(timestamp_str.to_owned()),
@@ -61,3 +79,17 @@ pub async fn seek_internal(ctx: Context<'_>, seek_time: String) -> Result<(), Er
     let _ = send_reply(&ctx, msg, true).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("2:13").unwrap(), Duration::from_secs(133));
+        assert_eq!(parse_timestamp("00:05").unwrap(), Duration::from_secs(5));
+        assert!(parse_timestamp("2").is_err());
+        assert!(parse_timestamp("a:13").is_err());
+        assert_eq!(parse_timestamp("2m13s").unwrap(), Duration::from_secs(133));
+    }
+}
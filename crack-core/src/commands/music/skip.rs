@@ -1,7 +1,7 @@
 use crate::poise_ext::ContextExt;
 use crate::{
-    commands::cmd_check_music,
     commands::get_call_or_join_author,
+    commands::{cmd_check_dj_or_vote, cmd_check_music},
     errors::{verify, CrackedError},
     messaging::message::CrackedMessage,
     poise_ext::PoiseContextExt,
@@ -28,6 +28,13 @@ pub async fn skip(
 ) -> Result<(), Error> {
     let (call, guild_id) = ctx.get_call_guild_id().await?;
     let to_skip = num_tracks.unwrap_or(1);
+    let action = match to_skip {
+        1 => "skip".to_string(),
+        n => format!("skip {}", n),
+    };
+    if !cmd_check_dj_or_vote(ctx, &action).await? {
+        return Ok(());
+    }
 
     let handler = call.lock().await;
     let queue = handler.queue();
@@ -0,0 +1,163 @@
+use crate::{
+    commands::{cmd_check_music, help},
+    duration::parse_duration,
+    errors::CrackedError,
+    handlers::{sleep_timer::start_sleep_timer, SessionKey, SleepTimer, SleepTimerKind},
+    messaging::message::CrackedMessage,
+    poise_ext::ContextExt,
+    utils::send_reply,
+    Context, Error,
+};
+use std::time::Duration;
+
+/// Longest sleep timer we accept.
+const MAX_SLEEP: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Fade out, clear the queue and leave after a while. Use `off` to cancel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    prefix_command,
+    slash_command,
+    guild_only
+)]
+pub async fn sleep(
+    ctx: Context<'_>,
+    #[description = "How long until I leave, e.g. `30m`, `1h30m` or `until 23:00`, or `off`"]
+    duration: String,
+    #[flag]
+    #[description = "Show a help menu for this command."]
+    help: bool,
+) -> Result<(), Error> {
+    if help {
+        return help::wrapper(ctx).await;
+    }
+    sleep_internal(ctx, duration).await
+}
+
+/// Internal sleep function.
+#[cfg(not(tarpaulin_include))]
+pub async fn sleep_internal(ctx: Context<'_>, duration: String) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+
+    // The session in the caller's channel, or the main bot's.
+    let manager = ctx.get_songbird().await?;
+    if matches!(duration.trim(), "off" | "cancel" | "0") {
+        let key = SessionKey::new(guild_id, &manager);
+        let msg = match ctx.data().cancel_sleep_timer(key) {
+            Some(_) => CrackedMessage::SleepTimerCancelled,
+            None => CrackedMessage::CrackedError(CrackedError::Other("No sleep timer is set.")),
+        };
+        send_reply(&ctx, msg, true).await?;
+        return Ok(());
+    }
+
+    let duration = parse_sleep_duration(&duration)?;
+    // Nothing to stop if we're not in a call.
+    manager.get(guild_id).ok_or(CrackedError::NotConnected)?;
+
+    start_sleep_timer(
+        ctx.data().clone(),
+        ctx.serenity_context().http.clone(),
+        manager,
+        guild_id,
+        ctx.channel_id(),
+        duration,
+    );
+
+    send_reply(&ctx, CrackedMessage::SleepTimerSet(duration), true).await?;
+    Ok(())
+}
+
+/// Leave once the current track ends. Run it again to cancel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    prefix_command,
+    slash_command,
+    guild_only
+)]
+pub async fn stopafter(
+    ctx: Context<'_>,
+    #[flag]
+    #[description = "Show a help menu for this command."]
+    help: bool,
+) -> Result<(), Error> {
+    if help {
+        return help::wrapper(ctx).await;
+    }
+    stopafter_internal(ctx).await
+}
+
+/// Internal stopafter function.
+#[cfg(not(tarpaulin_include))]
+pub async fn stopafter_internal(ctx: Context<'_>) -> Result<(), Error> {
+    let (call, guild_id) = ctx.get_call_guild_id().await?;
+    let manager = ctx.data().songbird_for_call(guild_id, &call);
+    let key = SessionKey::new(guild_id, &manager);
+
+    if ctx.data().take_stop_after(key) {
+        send_reply(&ctx, CrackedMessage::StopAfterCancelled, true).await?;
+        return Ok(());
+    }
+
+    if call.lock().await.queue().is_empty() {
+        return Err(CrackedError::NothingPlaying.into());
+    }
+
+    // Replaces any running `/sleep` timer, which then won't go off.
+    ctx.data()
+        .sleep_timers
+        .insert(key, SleepTimer::new(SleepTimerKind::AfterCurrentTrack));
+
+    send_reply(&ctx, CrackedMessage::StopAfterSet, true).await?;
+    Ok(())
+}
+
+/// Parse a sleep duration like `30m`, `1h30m` or `until 23:00`. A bare number
+/// is taken as minutes.
+pub fn parse_sleep_duration(input: &str) -> Result<Duration, CrackedError> {
+    let input = input.trim();
+    if let Ok(minutes) = input.parse::<u64>() {
+        return check_sleep_duration(Duration::from_secs(minutes.saturating_mul(60)));
+    }
+    check_sleep_duration(parse_duration(input)?)
+}
+
+fn check_sleep_duration(duration: Duration) -> Result<Duration, CrackedError> {
+    if duration.is_zero() || duration > MAX_SLEEP {
+        return Err(CrackedError::Other(
+            "Sleep timer must be between 1 second and 24 hours.",
+        ));
+    }
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_sleep_duration() {
+        assert_eq!(
+            parse_sleep_duration("30").unwrap(),
+            Duration::from_secs(30 * 60)
+        );
+        assert_eq!(
+            parse_sleep_duration("1h30m").unwrap(),
+            Duration::from_secs(90 * 60)
+        );
+        assert_eq!(
+            parse_sleep_duration("1h 15s").unwrap(),
+            Duration::from_secs(60 * 60 + 15)
+        );
+        assert!(parse_sleep_duration("").is_err());
+        assert!(parse_sleep_duration("0").is_err());
+        assert!(parse_sleep_duration("10x").is_err());
+        assert!(parse_sleep_duration("10m5").is_err());
+        assert!(parse_sleep_duration("25h").is_err());
+        assert!(parse_sleep_duration("until 99:00").is_err());
+    }
+}
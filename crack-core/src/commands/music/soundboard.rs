@@ -0,0 +1,265 @@
+use crate::{
+    commands::cmd_check_music,
+    db::SoundboardClip,
+    errors::CrackedError,
+    http_utils,
+    messaging::{
+        message::CrackedMessage,
+        messages::{
+            SOUNDBOARD_ADDED, SOUNDBOARD_CLIPS, SOUNDBOARD_EMPTY, SOUNDBOARD_EXISTS,
+            SOUNDBOARD_NOT_FOUND, SOUNDBOARD_PLAYING, SOUNDBOARD_REMOVED,
+        },
+    },
+    poise_ext::ContextExt,
+    soundboard::{check_clip_size, clip_dir, clip_input, parse_clip_name, validate_clip},
+    utils::{send_reply, TrackData},
+    Context, Data, Error,
+};
+use ::serenity::all::{
+    Attachment, AutocompleteChoice, AutocompleteValue, CreateAutocompleteResponse, CreateEmbed,
+};
+use poise::CreateReply;
+use songbird::tracks::Track;
+use std::borrow::Cow;
+
+/// Most clips suggested while typing a name.
+const AUTOCOMPLETE_LEN: i64 = 25;
+
+/// Autocomplete a clip name from the guild's soundboard.
+pub async fn autocomplete_clip<'a>(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    searching: &'a str,
+) -> CreateAutocompleteResponse<'a> {
+    let (Some(guild_id), Some(pool)) = (ctx.guild_id(), ctx.data().database_pool.clone()) else {
+        return CreateAutocompleteResponse::new();
+    };
+    let search = searching.trim().to_lowercase();
+    let names = SoundboardClip::search(&pool, guild_id.get() as i64, &search, AUTOCOMPLETE_LEN)
+        .await
+        .unwrap_or_default();
+    let choices = names
+        .into_iter()
+        .map(|name| AutocompleteChoice {
+            name: name.clone().into(),
+            name_localizations: None,
+            value: AutocompleteValue::String(name.into()),
+        })
+        .collect::<Vec<_>>();
+    CreateAutocompleteResponse::new().set_choices(Cow::Owned(choices))
+}
+
+/// Short clips played over the music. Lists the server's clips.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    aliases("soundboard"),
+    subcommands("sb_play", "sb_add", "sb_remove", "sb_list")
+)]
+pub async fn sb(ctx: Context<'_>) -> Result<(), Error> {
+    list_clips_internal(ctx).await
+}
+
+/// Play a clip over whatever's playing.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "play"
+)]
+pub async fn sb_play(
+    ctx: Context<'_>,
+    #[description = "Clip to play."]
+    #[autocomplete = "autocomplete_clip"]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let name = name.trim().to_lowercase();
+    let Some(clip) = SoundboardClip::get(&pool, guild_id.get() as i64, &name).await? else {
+        return reply(ctx, format!("{} `{}`.", SOUNDBOARD_NOT_FOUND, name)).await;
+    };
+    let call = ctx.get_call().await?;
+    // Its own track, not the queue's, so the music keeps playing under it.
+    let track = Track::new_with_data(clip_input(guild_id, &clip.file_name), TrackData::overlay());
+    let _ = call.lock().await.play(track);
+    SoundboardClip::played(&pool, clip.id).await?;
+    reply(ctx, format!("{} `{}`", SOUNDBOARD_PLAYING, clip.name)).await
+}
+
+/// Add a clip to the soundboard.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD_EXPRESSIONS",
+    rename = "add"
+)]
+pub async fn sb_add(
+    ctx: Context<'_>,
+    #[description = "Name to play it by, e.g. airhorn."] name: String,
+    #[description = "Audio file, up to 10 seconds and 1 MiB."] file: Attachment,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let name = parse_clip_name(&name).map_err(CrackedError::from)?;
+    if SoundboardClip::get(&pool, guild_id.get() as i64, &name)
+        .await?
+        .is_some()
+    {
+        return reply(ctx, format!("{} `{}`.", SOUNDBOARD_EXISTS, name)).await;
+    }
+    check_clip_size(file.size).map_err(CrackedError::from)?;
+    ctx.defer().await?;
+
+    let audio = http_utils::get_client()
+        .get(file.url.as_str())
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?
+        .to_vec();
+    let size = audio.len() as i32;
+    let (extension, length) =
+        validate_clip(&file.filename, audio.clone()).map_err(CrackedError::from)?;
+
+    // Named by the command too, so a clip never overwrites another's file.
+    let file_name = format!("{}-{}.{}", name, ctx.id(), extension);
+    let dir = clip_dir(guild_id);
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::write(dir.join(&file_name), audio).await?;
+    let clip = SoundboardClip::create(
+        &pool,
+        guild_id.get() as i64,
+        &name,
+        &file_name,
+        length.as_millis() as i32,
+        size,
+        ctx.author().id.get() as i64,
+    )
+    .await?;
+    if clip.is_none() {
+        tokio::fs::remove_file(dir.join(&file_name)).await?;
+        return reply(ctx, format!("{} `{}`.", SOUNDBOARD_EXISTS, name)).await;
+    }
+    let msg = format!(
+        "{} `{}` ({:.1}s)",
+        SOUNDBOARD_ADDED,
+        name,
+        length.as_secs_f32()
+    );
+    reply(ctx, msg).await
+}
+
+/// Remove a clip from the soundboard.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD_EXPRESSIONS",
+    rename = "remove"
+)]
+pub async fn sb_remove(
+    ctx: Context<'_>,
+    #[description = "Clip to remove."]
+    #[autocomplete = "autocomplete_clip"]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let name = name.trim().to_lowercase();
+    let Some(clip) = SoundboardClip::delete(&pool, guild_id.get() as i64, &name).await? else {
+        return reply(ctx, format!("{} `{}`.", SOUNDBOARD_NOT_FOUND, name)).await;
+    };
+    let path = clip_dir(guild_id).join(&clip.file_name);
+    if let Err(e) = tokio::fs::remove_file(&path).await {
+        tracing::warn!("Couldn't delete clip {}: {}", path.display(), e);
+    }
+    reply(ctx, format!("{} `{}`", SOUNDBOARD_REMOVED, clip.name)).await
+}
+
+/// List the server's clips.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "list"
+)]
+pub async fn sb_list(ctx: Context<'_>) -> Result<(), Error> {
+    list_clips_internal(ctx).await
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn list_clips_internal(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let clips = SoundboardClip::list(&pool, guild_id.get() as i64).await?;
+    if clips.is_empty() {
+        return reply(ctx, SOUNDBOARD_EMPTY.to_string()).await;
+    }
+    let embed = CreateEmbed::default()
+        .title(SOUNDBOARD_CLIPS)
+        .description(format_clips(&clips));
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// One line per clip: its name, length and plays.
+fn format_clips(clips: &[SoundboardClip]) -> String {
+    clips
+        .iter()
+        .map(|clip| {
+            format!(
+                "`{}` {:.1}s · {} plays",
+                clip.name,
+                clip.duration_ms as f32 / 1000.0,
+                clip.plays
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn reply(ctx: Context<'_>, msg: String) -> Result<(), Error> {
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_clips() {
+        let clips = [
+            SoundboardClip {
+                name: "airhorn".to_string(),
+                duration_ms: 2_500,
+                plays: 3,
+                ..Default::default()
+            },
+            SoundboardClip {
+                name: "rimshot".to_string(),
+                duration_ms: 900,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            format_clips(&clips),
+            "`airhorn` 2.5s · 3 plays\n`rimshot` 0.9s · 0 plays"
+        );
+    }
+}
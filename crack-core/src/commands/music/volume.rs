@@ -1,5 +1,5 @@
 use self::serenity::builder::CreateEmbed;
-use crate::commands::{cmd_check_music, help};
+use crate::commands::{cmd_check_dj_or_vote, cmd_check_music, help};
 use crate::errors::CrackedError;
 use crate::guild::settings::GuildSettings;
 use crate::poise_ext::ContextExt;
@@ -50,9 +50,15 @@ pub async fn volume_internal(ctx: Context<'_>, level: Option<u32>) -> Result<(),
         },
     };
     tracing::error!("guild_id: {:?}", guild_id);
+    // Only changing the volume is DJ-only, anyone can look at it.
+    if let Some(level) = level {
+        if !cmd_check_dj_or_vote(ctx, &format!("volume {}", level)).await? {
+            return Ok(());
+        }
+    }
     let embed = {
         tracing::error!("embed");
-        let manager = ctx.data().songbird.clone();
+        let manager = ctx.get_songbird().await?;
         let call = match manager.get(guild_id) {
             Some(call) => call,
             None => {
@@ -3,7 +3,6 @@ use crate::{
         cmd_check_music,
         music::{create_skip_response, force_skip_top_track},
     },
-    connection::get_voice_channel_for_user,
     errors::{verify, CrackedError},
     messaging::message::CrackedMessage,
     poise_ext::{ContextExt, PoiseContextExt},
@@ -38,20 +37,20 @@ pub async fn voteskip(
 async fn voteskip_internal(ctx: Context<'_>) -> Result<(), Error> {
     // use crate::db::TrackReaction;
 
-    let guild_id = ctx.guild_id().unwrap();
-    let guild = ctx
-        .serenity_context()
-        .cache
-        .guild(guild_id)
-        .unwrap()
-        .clone();
-    let bot_channel_id =
-        get_voice_channel_for_user(&guild, &ctx.serenity_context().cache.current_user().id)
-            .unwrap();
-    let manager = ctx.data().songbird.clone();
-    let call = manager.get(guild_id).unwrap();
-
+    let (call, guild_id) = ctx.get_call_guild_id().await?;
     let handler = call.lock().await;
+    // Go by the call's channel, since it may be a helper's rather than the main bot's.
+    let bot_channel_id = handler
+        .current_channel()
+        .map(|c| serenity::ChannelId::new(c.get()))
+        .ok_or(CrackedError::NotConnected)?;
+    let listeners = ctx
+        .guild()
+        .ok_or(CrackedError::NoGuildCached)?
+        .voice_states
+        .iter()
+        .filter(|v| v.channel_id == Some(bot_channel_id))
+        .count();
     let queue = handler.queue();
 
     verify(!queue.is_empty(), CrackedError::NothingPlaying)?;
@@ -64,17 +63,7 @@ async fn voteskip_internal(ctx: Context<'_>) -> Result<(), Error> {
     let user_id = ctx.get_user_id();
     cache.current_skip_votes.insert(user_id);
 
-    let guild_users = ctx
-        .serenity_context()
-        .cache
-        .guild(guild_id)
-        .unwrap()
-        .clone()
-        .voice_states;
-    let channel_guild_users = guild_users
-        .iter()
-        .filter(|v| v.channel_id.unwrap() == bot_channel_id);
-    let skip_threshold = channel_guild_users.count() / 2;
+    let skip_threshold = listeners / 2;
 
     let _ = if cache.current_skip_votes.len() >= skip_threshold {
         // // Write the skip votes to the db
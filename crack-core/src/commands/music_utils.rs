@@ -1,13 +1,14 @@
 use crate::connection::get_voice_channel_for_user;
 use crate::guild::operations::GuildSettingsOperations;
-use crate::handlers::{IdleHandler, TrackEndHandler};
+use crate::handlers::{IdleHandler, TrackAnnounceHandler, TrackEndHandler};
 use crate::messaging::message::CrackedMessage;
-use crate::poise_ext::PoiseContextExt;
+use crate::music::link::{LinkSyncHandler, SYNC_INTERVAL};
+use crate::poise_ext::{ContextExt, PoiseContextExt};
 use crate::CrackedError;
-use crate::{Context, Error};
+use crate::{Context, Data, Error};
 // use crack_testing::ReplyHandleWrapper;
 use poise::serenity_prelude::Mentionable;
-use serenity::all::{ChannelId, GuildId};
+use serenity::all::{ChannelId, Context as SerenityContext, GuildId};
 use songbird::{Call, Event, TrackEvent};
 use std::{
     sync::{atomic::AtomicBool, Arc},
@@ -23,7 +24,25 @@ pub async fn set_global_handlers(
     guild_id: GuildId,
     channel_id: ChannelId,
 ) {
-    let data = ctx.data();
+    set_global_handlers_serenity(
+        ctx.serenity_context(),
+        ctx.data().clone(),
+        call,
+        guild_id,
+        channel_id,
+    )
+    .await
+}
+
+/// Set the global handlers for the bot in a call, without needing a command context.
+#[cfg(not(tarpaulin_include))]
+pub async fn set_global_handlers_serenity(
+    serenity_ctx: &SerenityContext,
+    data: Arc<Data>,
+    call: Arc<Mutex<Call>>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) {
     let mut handler = call.lock().await;
 
     handler.remove_all_global_events();
@@ -38,7 +57,7 @@ pub async fn set_global_handlers(
         handler.add_global_event(
             Event::Periodic(Duration::from_secs(60), None),
             IdleHandler {
-                serenity_ctx: Arc::new(ctx.serenity_context().clone()),
+                serenity_ctx: Arc::new(serenity_ctx.clone()),
                 guild_id,
                 channel_id,
                 limit: timeout as usize,
@@ -52,13 +71,34 @@ pub async fn set_global_handlers(
         Event::Track(TrackEvent::End),
         TrackEndHandler {
             guild_id,
-            cache: ctx.serenity_context().cache.clone(),
-            http: ctx.serenity_context().http.clone(),
+            cache: serenity_ctx.cache.clone(),
+            http: serenity_ctx.http.clone(),
             call: call.clone(),
-            data: ctx.data().clone(),
+            data: data.clone(),
         },
     );
 
+    handler.add_global_event(
+        Event::Track(TrackEvent::Play),
+        TrackAnnounceHandler::new(guild_id, data.clone(), call.clone()),
+    );
+
+    // Keep any linked sessions following this one in sync.
+    for event in [
+        Event::Track(TrackEvent::Play),
+        Event::Track(TrackEvent::Pause),
+        Event::Periodic(SYNC_INTERVAL, None),
+    ] {
+        handler.add_global_event(
+            event,
+            LinkSyncHandler {
+                guild_id,
+                data: data.clone(),
+                call: call.clone(),
+            },
+        );
+    }
+
     //drop(handler);
 }
 
@@ -67,7 +107,8 @@ pub async fn set_global_handlers(
 #[tracing::instrument(skip(ctx))]
 pub async fn get_call_or_join_author(ctx: Context<'_>) -> Result<Arc<Mutex<Call>>, CrackedError> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    let manager = ctx.data().songbird.clone();
+    // The bot identity in the author's channel, or a free one.
+    let manager = ctx.get_songbird().await?;
     // Return the call if it already exists.
     // Otherwise, try to join the channel of the user who sent the message.
     if let Some(call) = manager.get(guild_id) {
@@ -106,9 +147,38 @@ pub async fn do_join(
     guild_id: GuildId,
     channel_id: ChannelId,
 ) -> Result<Arc<Mutex<Call>>, Error> {
-    // let ctx_owned = ctx.clone();
+    let call = join_voice_channel(
+        ctx.serenity_context(),
+        ctx.data().clone(),
+        manager,
+        guild_id,
+        channel_id,
+    )
+    .await?;
+    let msg = CrackedMessage::Summon {
+        mention: channel_id.mention(),
+    };
+    match ctx.send_reply_embed(msg).await {
+        Ok(_) => (),
+        Err(err) => {
+            tracing::warn!("Error sending reply: {:?}", err);
+        },
+    };
+    Ok(call)
+}
+
+/// Join a voice channel and set up the call's handlers. Used by [`do_join`], and by
+/// anything that needs to join without a command context (e.g. scheduled playback).
+#[cfg(not(tarpaulin_include))]
+pub async fn join_voice_channel(
+    serenity_ctx: &SerenityContext,
+    data: Arc<Data>,
+    manager: &songbird::Songbird,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<Arc<Mutex<Call>>, CrackedError> {
     let guild = guild_id
-        .to_guild_cached(ctx.cache())
+        .to_guild_cached(&serenity_ctx.cache)
         .ok_or(CrackedError::NoGuildCached)?
         .clone();
     let guild_name = guild.name;
@@ -127,28 +197,10 @@ pub async fn do_join(
             Some(call) => call,
             None => {
                 tracing::warn!("Error joining channel: {:?}", err);
-                // let str = err.to_string().clone();
-                let my_err = CrackedError::JoinChannelError(err);
-                // let crack_msg = CrackedMessage::CrackedRed(str.clone());
-                // let msg = PoiseContextExt::send_reply_embed(ctx, crack_msg).await?;
-                // //ctx.defer().await;
-                // //msg.delete_after(ctx, Duration::from_secs(10)).await;
-                // let msg_or_reply =
-                //     MessageOrReplyHandle::from(ReplyHandleWrapper { handle: msg.into() });
-                // ctx.data().push_latest_msg(guild_id, msg_or_reply).await;
-                return Err(Box::new(my_err));
+                return Err(CrackedError::JoinChannelError(err));
             },
         },
     };
-    set_global_handlers(ctx, call.clone(), guild_id, channel_id).await;
-    let msg = CrackedMessage::Summon {
-        mention: channel_id.mention(),
-    };
-    match ctx.send_reply_embed(msg).await {
-        Ok(_) => (),
-        Err(err) => {
-            tracing::warn!("Error sending reply: {:?}", err);
-        },
-    };
+    set_global_handlers_serenity(serenity_ctx, data, call.clone(), guild_id, channel_id).await;
     Ok(call)
 }
@@ -1,11 +1,50 @@
 use crate::{
-    guild::operations::GuildSettingsOperations, utils::OptionTryUnwrap, Context, CrackedError,
-    Error,
+    connection::get_voice_channel_for_user,
+    guild::operations::GuildSettingsOperations,
+    messaging::message::CrackedMessage,
+    poise_ext::{ContextExt, PoiseContextExt},
+    utils::{get_requesting_user, send_reply, OptionTryUnwrap},
+    Context, CrackedError, Error,
 };
 use poise::serenity_prelude as serenity;
-use serenity::all::{ChannelId, Member, Permissions, RoleId};
+use serenity::all::{ChannelId, Member, Mentionable, Permissions, RoleId};
 use std::borrow::Cow;
 
+/// Global check run before every command. Enforces the guild's per-command allow
+/// and deny rules, inherited from the command's category down to its qualified
+/// name. Administrators are exempt so they can't lock themselves out.
+pub async fn cmd_check_command_permissions(ctx: Context<'_>) -> Result<bool, Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(true);
+    };
+    let settings = match ctx.data().get_guild_settings(guild_id).await {
+        Some(settings) if !settings.command_settings.is_empty() => settings,
+        _ => return Ok(true),
+    };
+    if ctx
+        .author_permissions()
+        .await?
+        .contains(Permissions::ADMINISTRATOR)
+    {
+        return Ok(true);
+    }
+
+    let roles = ctx
+        .author_member()
+        .await
+        .map(|m| m.roles.iter().map(|r| r.get()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let command = ctx.command();
+    settings.check_command_permissions(
+        command.category.as_deref(),
+        &command.qualified_name,
+        ctx.author().id.get(),
+        &roles,
+        ctx.channel_id(),
+    )?;
+    Ok(true)
+}
+
 /// Public function to check if the user is authorized to use the music commands.
 pub async fn cmd_check_music(ctx: Context<'_>) -> Result<bool, Error> {
     if ctx.author().bot() {
@@ -33,13 +72,20 @@ pub async fn cmd_check_music_internal(
         },
         None => return is_authorized_music(member, None),
     };
+    // In DJ mode, DJs (and admins) run the music from any channel. Everyone
+    // else is held to the music channel, and their DJ-only actions go to a vote.
+    let dj_role = guild_settings
+        .dj_mode
+        .then_some(guild_settings.dj_role)
+        .flatten()
+        .map(RoleId::new);
+    let is_dj = dj_role.is_some() && is_authorized_music(member.clone(), dj_role)?;
     let opt_allowed_channel = guild_settings.get_music_channel();
     match opt_allowed_channel {
         Some(allowed_channel) => {
-            if channel_id == allowed_channel {
-                is_authorized_music(member.clone(), None)
+            if channel_id == allowed_channel || is_dj {
+                Ok(true)
             } else {
-                // Ok(false)
                 Err(CrackedError::NotInMusicChannel(channel_id).into())
             }
         },
@@ -70,3 +116,112 @@ pub fn is_authorized_music(
     Ok(is_admin || has_role)
     // true // placeholder return value
 }
+
+/// Gate a DJ-only action such as `skip` or `remove 3`. Outside DJ mode everyone
+/// may go ahead. In DJ mode admins, DJs, the requester of the current track and
+/// anyone alone with the bot go ahead right away; everyone else casts a vote for
+/// `action`, which passes once a majority of the listeners have voted for it.
+/// Only members in the bot's voice channel can vote. Returns whether the caller
+/// should carry out the action.
+pub async fn cmd_check_dj_or_vote(ctx: Context<'_>, action: &str) -> Result<bool, Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let settings = match ctx.data().get_guild_settings(guild_id).await {
+        Some(settings) if settings.dj_mode => settings,
+        _ => return Ok(true),
+    };
+    if ctx
+        .author_permissions()
+        .await?
+        .contains(Permissions::ADMINISTRATOR)
+    {
+        return Ok(true);
+    }
+    if let Some(role) = settings.dj_role {
+        if is_authorized_music(ctx.author_member().await, Some(RoleId::new(role)))? {
+            return Ok(true);
+        }
+    }
+
+    let author_id = ctx.author().id;
+    let call = ctx.get_call().await?;
+    let (current, bot_channel_id) = {
+        let handler = call.lock().await;
+        let channel_id = handler.current_channel().map(|c| ChannelId::new(c.get()));
+        (handler.queue().current(), channel_id)
+    };
+    // The call's channel rather than the main bot's, since it may be a helper's.
+    let bot_channel_id = bot_channel_id.ok_or(CrackedError::NotConnected)?;
+    if let Some(track) = current {
+        if get_requesting_user(&track).ok() == Some(author_id) {
+            return Ok(true);
+        }
+    }
+
+    let (author_listening, listeners) = {
+        let guild = ctx.guild().ok_or(CrackedError::NoGuildCached)?;
+        let bot_id = ctx.serenity_context().cache.current_user().id;
+        let listeners = guild
+            .voice_states
+            .iter()
+            .filter(|v| v.channel_id == Some(bot_channel_id))
+            .filter(|v| ctx.data().songbird_of(bot_id, v.user_id).is_none())
+            .count();
+        let author_listening = get_voice_channel_for_user(&guild, &author_id).ok();
+        (author_listening == Some(bot_channel_id), listeners)
+    };
+    if !author_listening {
+        return Err(CrackedError::WrongVoiceChannel.into());
+    }
+    if listeners == 1 {
+        return Ok(true);
+    }
+
+    let threshold = dj_vote_threshold(listeners);
+    let votes = {
+        let mut cache_map = ctx.data().guild_cache_map.lock().await;
+        let cache = cache_map.entry(guild_id).or_default();
+        let votes = cache.dj_votes.entry(action.to_string()).or_default();
+        votes.insert(author_id);
+        let count = votes.len();
+        if count >= threshold {
+            cache.dj_votes.remove(action);
+        }
+        count
+    };
+    if votes >= threshold {
+        return Ok(true);
+    }
+
+    send_reply(
+        &ctx,
+        CrackedMessage::DjVote {
+            action: action.to_string(),
+            mention: author_id.mention(),
+            missing: threshold - votes,
+        },
+        true,
+    )
+    .await?;
+    Ok(false)
+}
+
+/// Number of votes needed for a DJ-only action to pass: a strict majority of
+/// the listeners.
+pub fn dj_vote_threshold(listeners: usize) -> usize {
+    listeners / 2 + 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dj_vote_threshold() {
+        assert_eq!(dj_vote_threshold(0), 1);
+        assert_eq!(dj_vote_threshold(1), 1);
+        assert_eq!(dj_vote_threshold(2), 2);
+        assert_eq!(dj_vote_threshold(3), 2);
+        assert_eq!(dj_vote_threshold(4), 3);
+        assert_eq!(dj_vote_threshold(9), 5);
+    }
+}
@@ -0,0 +1,87 @@
+use super::check_can_edit_playlist;
+use crate::{
+    commands::{cmd_check_music, parse_timestamp},
+    db::{Bookmark, Playlist, TrackOffsets},
+    errors::CrackedError,
+    messaging::message::CrackedMessage,
+    poise_ext::ContextExt as _,
+    utils::send_reply,
+    Context, Error,
+};
+
+/// Adds a bookmarked track to a playlist, starting at the bookmarked position.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "addbookmark"
+)]
+pub async fn add_bookmark(
+    ctx: Context<'_>,
+    #[description = "Bookmark id, as shown by `/bookmarks list`"] bookmark: i64,
+    #[description = "Where the entry should stop, in format `mm:ss`"] end: Option<String>,
+    #[rest]
+    #[description = "Playlist to add the bookmark to"]
+    playlist: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let end = end.as_deref().map(parse_timestamp).transpose()?;
+    let user_id = ctx.author().id.get() as i64;
+    let guild_id_i64 = guild_id.get() as i64;
+    let channel_id = ctx.channel_id().get() as i64;
+    let pool = ctx.get_db_pool()?;
+
+    let bookmark = Bookmark::get_for_user(&pool, bookmark, user_id)
+        .await?
+        .ok_or(CrackedError::Other("Bookmark not found."))?;
+    if let Some(end) = end {
+        if end <= bookmark.position() {
+            return Err(CrackedError::Other("The end must come after the bookmark.").into());
+        }
+    }
+    let metadata = bookmark.metadata(&pool).await?;
+
+    let playlist = match Playlist::get_playlist_by_name_scoped(
+        &pool,
+        playlist.clone(),
+        user_id,
+        Some(guild_id_i64),
+    )
+    .await
+    {
+        Ok(playlist) => playlist,
+        Err(_) => Playlist::create(&pool, &playlist, user_id).await?,
+    };
+    check_can_edit_playlist(ctx, &playlist).await?;
+
+    let offsets = TrackOffsets {
+        start_ms: Some(bookmark.position_ms),
+        end_ms: end.map(|end| end.as_millis() as i64),
+    };
+    Playlist::add_track_with_offsets(
+        &pool,
+        playlist.id,
+        metadata.id,
+        guild_id_i64,
+        channel_id,
+        offsets,
+    )
+    .await?;
+
+    send_reply(
+        &ctx,
+        CrackedMessage::BookmarkAddedToPlaylist {
+            title: metadata.title.or(metadata.track).unwrap_or_default(),
+            playlist: playlist.name,
+            start: bookmark.position(),
+            end,
+        },
+        true,
+    )
+    .await?;
+
+    Ok(())
+}
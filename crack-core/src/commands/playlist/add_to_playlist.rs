@@ -1,3 +1,4 @@
+use super::check_can_edit_playlist;
 use crate::{
     commands::cmd_check_music,
     db::aux_metadata_to_db_structures,
@@ -26,7 +27,7 @@ pub async fn add_to_playlist(
     playlist: String,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
-    let manager = ctx.data().songbird.clone();
+    let manager = ctx.get_songbird().await?;
     let call = manager.get(guild_id).ok_or(CrackedError::NotConnected)?;
     let queue = call.lock().await.queue().clone();
     let cur_track = queue.current().ok_or(CrackedError::NothingPlaying)?;
@@ -51,15 +52,22 @@ pub async fn add_to_playlist(
     let playlist_name = playlist;
 
     // Get playlist if exists, other create it.
-    let playlist =
-        match Playlist::get_playlist_by_name(&db_pool, playlist_name.clone(), user_id).await {
-            Ok(playlist) => Ok(playlist),
-            Err(e) => {
-                tracing::error!("Error getting playlist: {:?}", e);
-                tracing::info!("Creating playlist: {:?}", playlist_name);
-                Playlist::create(&db_pool, &playlist_name, user_id).await
-            },
-        }?;
+    let playlist = match Playlist::get_playlist_by_name_scoped(
+        &db_pool,
+        playlist_name.clone(),
+        user_id,
+        Some(guild_id_i64),
+    )
+    .await
+    {
+        Ok(playlist) => Ok(playlist),
+        Err(e) => {
+            tracing::error!("Error getting playlist: {:?}", e);
+            tracing::info!("Creating playlist: {:?}", playlist_name);
+            Playlist::create(&db_pool, &playlist_name, user_id).await
+        },
+    }?;
+    check_can_edit_playlist(ctx, &playlist).await?;
 
     let MetadataAnd::Track(in_metadata, _) =
         aux_metadata_to_db_structures(metadata, guild_id_i64, channel_id)?;
@@ -1,6 +1,7 @@
+use super::{can_edit_guild_playlists, PlaylistScope};
 use crate::{
     commands::cmd_check_music, db::playlist::Playlist, messaging::message::CrackedMessage,
-    utils::send_reply, Context, Error,
+    utils::send_reply, Context, CrackedError, Error,
 };
 
 /// Creates a playlist
@@ -13,11 +14,27 @@ use crate::{
     guild_only,
     rename = "create"
 )]
-pub async fn create_playlist(ctx: Context<'_>, name: String) -> Result<(), Error> {
+pub async fn create_playlist(
+    ctx: Context<'_>,
+    name: String,
+    #[description = "Create a personal playlist or a shared server playlist"] scope: Option<
+        PlaylistScope,
+    >,
+) -> Result<(), Error> {
     // Assuming you have a way to fetch the user_id of the command issuer
     let user_id = ctx.author().id.get() as i64;
+    let pool = ctx.data().database_pool.as_ref().unwrap();
 
-    let res = Playlist::create(ctx.data().database_pool.as_ref().unwrap(), &name, user_id).await?;
+    let res = match scope.unwrap_or(PlaylistScope::Personal) {
+        PlaylistScope::Personal => Playlist::create(pool, &name, user_id).await?,
+        PlaylistScope::Server => {
+            if !can_edit_guild_playlists(ctx).await? {
+                return Err(CrackedError::UnauthorizedUser.into());
+            }
+            let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?.get() as i64;
+            Playlist::create_for_guild(pool, &name, user_id, guild_id).await?
+        },
+    };
 
     send_reply(
         &ctx,
@@ -1,3 +1,4 @@
+use super::check_can_edit_playlist;
 use crate::{
     commands::cmd_check_music, db::playlist::Playlist, messaging::message::CrackedMessage,
     utils::send_reply, Context, Error,
@@ -18,6 +19,8 @@ pub async fn delete_playlist(ctx: Context<'_>, playlist_id: i32) -> Result<(), E
     let user_id = ctx.author().id.get() as i64;
     let pool = ctx.data().database_pool.as_ref().unwrap();
 
+    let playlist = Playlist::get_playlist_by_id(pool, playlist_id).await?;
+    check_can_edit_playlist(ctx, &playlist).await?;
     Playlist::delete_playlist_by_id(pool, playlist_id, user_id).await?;
 
     send_reply(
@@ -1,6 +1,6 @@
 use crate::{
     commands::cmd_check_music,
-    db::{metadata::aux_metadata_from_db, playlist::Playlist, Metadata},
+    db::{metadata::aux_metadata_from_db, playlist::Playlist, SmartEvalContext, TrackOffsets},
     utils::{build_tracks_embed_metadata, send_embed_response_poise},
     Context, CrackedError, Error,
 };
@@ -16,8 +16,11 @@ use crack_types::NewAuxMetadata;
     rename = "get"
 )]
 pub async fn get_playlist(ctx: Context<'_>, #[rest] playlist: String) -> Result<(), Error> {
-    let (aux_metadata, playlist_name): (Vec<NewAuxMetadata>, String) =
-        get_playlist_(ctx, playlist).await?;
+    let (entries, playlist_name) = get_playlist_(ctx, playlist).await?;
+    let aux_metadata = entries
+        .into_iter()
+        .map(|(metadata, _)| metadata)
+        .collect::<Vec<NewAuxMetadata>>();
     let embed = build_tracks_embed_metadata(playlist_name, aux_metadata.as_slice(), 0).await;
 
     // Send the embed
@@ -26,31 +29,36 @@ pub async fn get_playlist(ctx: Context<'_>, #[rest] playlist: String) -> Result<
     Ok(())
 }
 
-/// Get a playlist by name or id
+/// Get a playlist by name or id, along with each entry's start/end offsets.
 pub async fn get_playlist_(
     ctx: Context<'_>,
     playlist: String,
-) -> Result<(Vec<NewAuxMetadata>, String), Error> {
+) -> Result<(Vec<(NewAuxMetadata, TrackOffsets)>, String), Error> {
     let pool = ctx
         .data()
         .database_pool
         .as_ref()
         .ok_or(CrackedError::NoDatabasePool)?;
 
-    let metadata: Vec<Metadata> = match playlist.parse::<i32>() {
+    let user_id = ctx.author().id.get() as i64;
+    let guild_id = ctx.guild_id().map(|g| g.get() as i64);
+    let playlist_rec = match playlist.parse::<i32>() {
         // Try to parse the playlist as an ID
-        Ok(playlist_id) => Playlist::get_track_metadata_for_playlist(pool, playlist_id).await?,
+        Ok(playlist_id) => {
+            Playlist::get_playlist_by_id_scoped(pool, playlist_id, user_id, guild_id).await?
+        },
         Err(_) => {
-            let user_id = ctx.author().id.get() as i64;
-
-            Playlist::get_track_metadata_for_playlist_name(pool, playlist.clone(), user_id).await?
+            Playlist::get_playlist_by_name_scoped(pool, playlist.clone(), user_id, guild_id).await?
         },
     };
-    // Assuming you have a way to fetch the user_id of the command issuer
-    let aux_metadata = metadata
+
+    // Smart playlists are evaluated against the play history every time they're used.
+    let eval_ctx = SmartEvalContext { user_id, guild_id };
+    let entries = Playlist::get_queue_entries(pool, playlist_rec.id, &eval_ctx).await?;
+    let aux_metadata = entries
         .iter()
-        .flat_map(|m| match aux_metadata_from_db(m) {
-            Ok(aux) => Some(NewAuxMetadata(aux.clone())),
+        .flat_map(|(m, offsets)| match aux_metadata_from_db(m) {
+            Ok(aux) => Some((NewAuxMetadata(aux.clone()), *offsets)),
             Err(e) => {
                 tracing::error!("Error converting metadata to aux metadata: {}", e);
                 None
@@ -58,5 +66,5 @@ pub async fn get_playlist_(
         })
         .collect::<Vec<_>>();
     // playlist.print_playlist(ctx).await?;
-    Ok((aux_metadata, playlist))
+    Ok((aux_metadata, playlist_rec.name))
 }
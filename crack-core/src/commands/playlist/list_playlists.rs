@@ -1,3 +1,4 @@
+use super::PlaylistScope;
 use crate::errors::CrackedError;
 use crate::utils::{build_playlist_list_embed, send_embed_response_poise};
 use crate::{
@@ -6,7 +7,7 @@ use crate::{
     Context, Error,
 };
 
-/// List your saved playlists.
+/// List your saved playlists and the server's shared playlists.
 #[cfg(not(tarpaulin_include))]
 #[poise::command(
     category = "Music",
@@ -16,14 +17,27 @@ use crate::{
     check = "cmd_check_music",
     subcommands("help")
 )]
-pub async fn list_playlists(ctx: Context<'_>) -> Result<(), Error> {
+pub async fn list_playlists(
+    ctx: Context<'_>,
+    #[description = "Only show personal or server playlists"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
     let user_id = ctx.author().id.get() as i64;
+    let guild_id = ctx.guild_id().map(|g| g.get() as i64);
     let pool = ctx
         .data()
         .database_pool
         .as_ref()
         .ok_or(CrackedError::NoDatabasePool)?;
-    let playlists = Playlist::get_playlists_by_user_id(pool, user_id).await?;
+
+    let mut playlists = Vec::new();
+    if scope != Some(PlaylistScope::Server) {
+        playlists.extend(Playlist::get_playlists_by_user_id(pool, user_id).await?);
+    }
+    if scope != Some(PlaylistScope::Personal) {
+        if let Some(guild_id) = guild_id {
+            playlists.extend(Playlist::get_playlists_by_guild_id(pool, guild_id).await?);
+        }
+    }
 
     let embed = build_playlist_list_embed(&playlists, 0).await;
 
@@ -1,3 +1,4 @@
+pub mod add_bookmark;
 pub mod add_to_playlist;
 pub mod create_playlist;
 pub mod delete_playlist;
@@ -5,7 +6,9 @@ pub mod get_playlist;
 pub mod list_playlists;
 pub mod loadspotify;
 pub mod play_playlist;
+pub mod smart_playlist;
 
+pub use add_bookmark::add_bookmark as addbookmark;
 pub use add_to_playlist::add_to_playlist as addto;
 pub use create_playlist::create_playlist as create;
 pub use delete_playlist::delete_playlist as delete;
@@ -13,13 +16,73 @@ pub use get_playlist::get_playlist as get;
 pub use list_playlists::list_playlists as list;
 pub use loadspotify::loadspotify;
 pub use play_playlist::play_playlist as pplay;
+pub use smart_playlist::smart_playlist as smart;
 
 use crate::{
     commands::{cmd_check_music, sub_help as help},
+    db::Playlist,
+    guild::operations::GuildSettingsOperations,
     messaging::message::CrackedMessage,
+    poise_ext::PoiseContextExt,
     utils::send_reply,
-    Context, Error,
+    Context, CrackedError, Error,
 };
+use poise::serenity_prelude::Permissions;
+
+/// Who owns a playlist: the user who made it, or the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum PlaylistScope {
+    #[name = "personal"]
+    Personal,
+    #[name = "server"]
+    Server,
+}
+
+/// Check whether the author can edit the guild's shared playlists. Uses the guild's
+/// `playlist` command settings when they exist, otherwise requires Manage Server.
+pub async fn can_edit_guild_playlists(ctx: Context<'_>) -> Result<bool, CrackedError> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let perms = ctx.author_permissions().await?;
+    if perms.contains(Permissions::ADMINISTRATOR) {
+        return Ok(true);
+    }
+    let playlist_perms = ctx
+        .data()
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.get_playlist_permissions());
+    match playlist_perms {
+        Some(playlist_perms) => {
+            let roles = ctx
+                .author_member()
+                .await
+                .map(|m| m.roles.iter().map(|r| r.get()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            Ok(playlist_perms.is_member_allowed(ctx.author().id.get(), &roles))
+        },
+        None => Ok(perms.contains(Permissions::MANAGE_GUILD)),
+    }
+}
+
+/// Check whether the author can modify the given playlist. Personal playlists can
+/// only be changed by their owner, guild playlists by anyone allowed to edit them.
+pub async fn check_can_edit_playlist(
+    ctx: Context<'_>,
+    playlist: &Playlist,
+) -> Result<(), CrackedError> {
+    let allowed = match playlist.guild_id {
+        Some(guild_id) => {
+            ctx.guild_id().map(|g| g.get() as i64) == Some(guild_id)
+                && can_edit_guild_playlists(ctx).await?
+        },
+        None => playlist.user_id == Some(ctx.author().id.get() as i64),
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(CrackedError::UnauthorizedUser)
+    }
+}
 
 /// Playlist commands.
 #[poise::command(
@@ -27,12 +90,14 @@ use crate::{
     prefix_command,
     slash_command,
     subcommands(
+        "addbookmark",
         "addto",
         "create",
         "delete",
         "get",
         "list",
         "pplay",
+        "smart",
         "loadspotify",
         "help"
     ),
@@ -62,6 +127,7 @@ pub fn commands() -> [crate::Command; 1] {
     // loadspotify(),
 }
 
+#[cfg(test)]
 mod tests {
     #[test]
     fn test_commands() {
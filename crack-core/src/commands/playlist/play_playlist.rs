@@ -1,5 +1,5 @@
 use super::get_playlist::get_playlist_;
-use crate::commands::{cmd_check_music, queue_aux_metadata};
+use crate::commands::{cmd_check_music, queue_aux_metadata_with_offsets};
 use crate::messaging::message::CrackedMessage;
 use crate::utils::send_reply;
 use crate::{Context, Error};
@@ -20,12 +20,12 @@ pub async fn play_playlist(
     playlist: String,
 ) -> Result<(), Error> {
     // Check for playlist Id
-    let (aux_metadata, playlist_name) = get_playlist_(ctx, playlist).await?;
+    let (entries, playlist_name) = get_playlist_(ctx, playlist).await?;
 
     let handle = send_reply(&ctx, CrackedMessage::PlaylistQueuing(playlist_name), true).await?;
     let msg = handle.into_message().await?;
 
-    queue_aux_metadata(ctx, aux_metadata.as_slice(), msg).await?;
+    queue_aux_metadata_with_offsets(ctx, entries.as_slice(), msg).await?;
 
     send_reply(&ctx, CrackedMessage::PlaylistQueued, true).await?;
 
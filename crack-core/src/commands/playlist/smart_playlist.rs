@@ -0,0 +1,64 @@
+use super::check_can_edit_playlist;
+use crate::{
+    commands::cmd_check_music,
+    db::{playlist::Playlist, SmartPlaylist, SmartPlaylistRule},
+    errors::CrackedError,
+    messaging::message::CrackedMessage,
+    poise_ext::ContextExt as _,
+    utils::send_reply,
+    Context, Error,
+};
+
+/// Creates a smart playlist defined by a rule over the play history.
+///
+/// Example rules:
+/// `guild = here and played_within = 30 order by plays limit 25`
+/// `user = me and likes >= 3`
+/// `user = @someone or artist = "Daft Punk" order by random`
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Music",
+    check = "cmd_check_music",
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "smart"
+)]
+pub async fn smart_playlist(
+    ctx: Context<'_>,
+    #[description = "Name of the smart playlist"] name: String,
+    #[rest]
+    #[description = "Rule, e.g. guild = here and played_within = 30 order by plays"]
+    rule: String,
+) -> Result<(), Error> {
+    // Parse before touching the db so a bad rule doesn't leave an empty playlist behind.
+    let rule = rule.parse::<SmartPlaylistRule>()?;
+    let user_id = ctx.author().id.get() as i64;
+    let pool = ctx.get_db_pool()?;
+
+    let guild_id = ctx.guild_id().map(|g| g.get() as i64);
+
+    let playlist =
+        match Playlist::get_playlist_by_name_scoped(&pool, name.clone(), user_id, guild_id).await {
+            // Only a smart playlist's rule can be replaced; don't turn a normal one into one.
+            Ok(playlist) if SmartPlaylist::get_rule(&pool, playlist.id).await?.is_none() => {
+                return Err(CrackedError::Other("A playlist with that name already exists").into());
+            },
+            Ok(playlist) => playlist,
+            Err(_) => Playlist::create(&pool, &name, user_id).await?,
+        };
+    check_can_edit_playlist(ctx, &playlist).await?;
+    let smart = SmartPlaylist::set_rule(&pool, playlist.id, &rule).await?;
+
+    send_reply(
+        &ctx,
+        CrackedMessage::SmartPlaylistCreated {
+            name: playlist.name,
+            rule: smart.rule.to_string(),
+        },
+        true,
+    )
+    .await?;
+
+    Ok(())
+}
@@ -6,6 +6,7 @@ pub mod prefix;
 pub mod print_settings;
 pub mod set;
 pub mod toggle;
+pub mod welcome_preview;
 
 use crate::commands::help;
 pub use get::get;
@@ -13,6 +14,7 @@ pub use prefix::*;
 pub use print_settings::*;
 pub use set::set;
 pub use toggle::*;
+pub use welcome_preview::*;
 
 /// Settings commands
 #[poise::command(
@@ -26,6 +28,7 @@ pub use toggle::*;
         "get_prefixes",
         "clear_prefixes",
         "print_settings",
+        "welcome_preview",
     ),
     ephemeral
 )]
@@ -1,7 +1,4 @@
-use crate::{
-    guild::settings::GuildSettingsMap, messaging::message::CrackedMessage, utils::send_reply,
-    Context, Error,
-};
+use crate::{messaging::message::CrackedMessage, utils::send_reply, Context, Error};
 use serenity::{
     all::{Channel, Message, User},
     http::MessagePagination,
@@ -20,16 +17,6 @@ pub async fn print_settings(ctx: Context<'_>) -> Result<(), Error> {
         .await?;
     }
 
-    let guild_settings_map = ctx.serenity_context().data.read().await;
-
-    for (guild_id, settings) in guild_settings_map.get::<GuildSettingsMap>().unwrap().iter() {
-        send_reply(
-            &ctx,
-            CrackedMessage::Other(format!("Settings for guild {}: {:?}", guild_id, settings)),
-            true,
-        )
-        .await?;
-    }
     Ok(())
 }
 
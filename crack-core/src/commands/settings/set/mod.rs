@@ -5,19 +5,33 @@ use crate::{Context, Error};
 // pub mod welcome;
 pub mod set_all_log_channel;
 pub mod set_auto_role;
+pub mod set_automod;
+pub mod set_command_rule;
+pub mod set_dj;
 pub mod set_idle_timeout;
 pub mod set_join_leave_log_channel;
+pub mod set_mod_log_channel;
 pub mod set_music_channel;
 pub mod set_premium;
+pub mod set_raid;
+pub mod set_tts;
+pub mod set_verification;
 pub mod set_volume;
 pub mod set_welcome_settings;
 
 pub use set_all_log_channel::*;
 pub use set_auto_role::*;
+pub use set_automod::*;
+pub use set_command_rule::*;
+pub use set_dj::*;
 pub use set_idle_timeout::*;
 pub use set_join_leave_log_channel::*;
+pub use set_mod_log_channel::*;
 pub use set_music_channel::*;
 pub use set_premium::*;
+pub use set_raid::*;
+pub use set_tts::*;
+pub use set_verification::*;
 pub use set_volume::*;
 pub use set_welcome_settings::*;
 
@@ -30,12 +44,20 @@ pub use set_welcome_settings::*;
         "log_channel_for_guild",
         "join_leave_log_channel",
         "all_log_channel",
+        "mod_log_channel",
         "premium",
         "volume",
         "auto_role",
         "idle_timeout",
         "welcome_settings",
+        "leave_message",
         "music_channel",
+        "command_rule",
+        "dj",
+        "automod",
+        "verification",
+        "raid",
+        "tts",
         // "log_all",
         // "log_guild"
     ),
@@ -73,7 +95,10 @@ mod tests {
     #[test]
     fn test_commands() {
         let cmds = super::commands();
-        let names = cmds.iter().map(|c| c.name.clone()).collect::<Vec<String>>();
+        let names = cmds
+            .iter()
+            .map(|c| c.name.to_string())
+            .collect::<Vec<String>>();
         assert!(!names.contains(&String::from("premium")));
         assert!(!names.contains(&String::from("volume")));
         assert!(!names.contains(&String::from("idle_timeout")));
@@ -0,0 +1,87 @@
+use crate::duration::HumanDuration;
+use crate::guild::operations::GuildSettingsOperations;
+use crate::guild::settings::AutomodAction;
+use crate::{
+    errors::CrackedError, messaging::message::CrackedMessage, poise_ext::ContextExt,
+    utils::send_reply, Context, Error,
+};
+
+/// Configure automod: rate limits, duplicate messages, mass mentions, invite
+/// links and banned domains. A limit of 0 turns that check off. Admins and mods
+/// are never moderated.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Settings",
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn automod(
+    ctx: Context<'_>,
+    #[description = "Turn automod on or off."] enabled: Option<bool>,
+    #[description = "What to do with offending messages."] action: Option<AutomodAction>,
+    #[description = "How long the timeout action lasts, e.g. `10m`."] timeout: Option<
+        HumanDuration,
+    >,
+    #[description = "Most messages per user within `per_seconds`."] rate_limit: Option<u32>,
+    #[description = "Length of the rate limit window in seconds."] per_seconds: Option<u32>,
+    #[description = "Identical messages in a row that count as spam."] duplicates: Option<u32>,
+    #[description = "Most mentions in one message."] mentions: Option<u32>,
+    #[description = "Block Discord invite links."] invites: Option<bool>,
+    #[description = "Block links to the banned domains."] banned_domains: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    if per_seconds == Some(0) {
+        return Err(CrackedError::Other("The rate limit window must be at least a second.").into());
+    }
+    let pool = ctx.get_db_pool()?;
+    let data = ctx.data();
+
+    // Make sure there's an entry to modify.
+    let _ = data
+        .get_or_create_guild_settings(guild_id, None, None)
+        .await;
+    let settings = {
+        let mut map = data.guild_settings_map.write().await;
+        let settings = map
+            .get_mut(&guild_id)
+            .ok_or(CrackedError::NoGuildSettings)?;
+        let automod = settings
+            .automod_settings
+            .get_or_insert_with(Default::default);
+        if let Some(enabled) = enabled {
+            automod.enabled = enabled;
+        }
+        if let Some(action) = action {
+            automod.action = action;
+        }
+        if let Some(timeout) = timeout {
+            automod.timeout_seconds = timeout.clamp_to_timeout().as_secs() as u32;
+        }
+        if let Some(rate_limit) = rate_limit {
+            automod.rate_limit_messages = rate_limit;
+        }
+        if let Some(per_seconds) = per_seconds {
+            automod.rate_limit_seconds = per_seconds;
+        }
+        if let Some(duplicates) = duplicates {
+            automod.max_duplicates = duplicates;
+        }
+        if let Some(mentions) = mentions {
+            automod.max_mentions = mentions;
+        }
+        if let Some(invites) = invites {
+            automod.block_invites = invites;
+        }
+        if let Some(banned_domains) = banned_domains {
+            automod.block_banned_domains = banned_domains;
+        }
+        settings.clone()
+    };
+    settings.save(&pool).await?;
+
+    let summary = settings.automod_settings.unwrap_or_default().to_string();
+    send_reply(&ctx, CrackedMessage::AutomodSet(summary), true).await?;
+    Ok(())
+}
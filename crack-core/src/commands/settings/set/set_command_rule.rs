@@ -0,0 +1,192 @@
+use crate::db::GuildEntity;
+use crate::guild::operations::GuildSettingsOperations;
+use crate::{
+    errors::CrackedError, messaging::message::CrackedMessage, poise_ext::ContextExt,
+    utils::send_reply, Context, Error,
+};
+use serenity::all::{Channel, Mentionable, Role, User};
+
+/// What to do with a command rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum CommandRuleAction {
+    #[name = "allow"]
+    Allow,
+    #[name = "deny"]
+    Deny,
+    #[name = "reset"]
+    Reset,
+}
+
+/// Allow or deny a category or command for a role, user, channel, or subcommand.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Settings",
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn command_rule(
+    ctx: Context<'_>,
+    #[description = "Category or command the rule is for, e.g. `music` or `volume`."] scope: String,
+    #[description = "Allow, deny, or reset all rules for the scope."] action: CommandRuleAction,
+    #[description = "Role to allow or deny."] role: Option<Role>,
+    #[description = "User to allow or deny."] user: Option<User>,
+    #[description = "Channel to allow or deny."] channel: Option<Channel>,
+    #[description = "Command within the scope to allow or deny, e.g. `playlist create`."]
+    command: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let scope = normalize_command_name(&scope);
+    let command = command.as_deref().map(normalize_command_name);
+
+    let known = known_command_scopes(&ctx.framework().options().commands);
+    for name in std::iter::once(&scope).chain(command.as_ref()) {
+        if !known.contains(name) {
+            return Err(CrackedError::CommandNotFound(name.clone().into()).into());
+        }
+    }
+
+    let pool = ctx.get_db_pool()?;
+    let settings = ctx
+        .data()
+        .get_or_create_guild_settings(guild_id, None, None)
+        .await;
+
+    if action == CommandRuleAction::Reset {
+        GuildEntity::delete_command_settings(&pool, guild_id.get() as i64, &scope).await?;
+        ctx.data()
+            .guild_settings_map
+            .write()
+            .await
+            .entry(guild_id)
+            .and_modify(|s| {
+                s.command_settings.remove(&scope);
+            });
+        send_reply(
+            &ctx,
+            CrackedMessage::Other(format!("Reset command rules for `{}`", scope)),
+            true,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if role.is_none() && user.is_none() && channel.is_none() && command.is_none() {
+        return Err(
+            CrackedError::Other("Give a role, user, channel or command for the rule.").into(),
+        );
+    }
+
+    let mut perms = settings
+        .command_settings
+        .get(&scope)
+        .cloned()
+        .unwrap_or_default();
+    let allow = action == CommandRuleAction::Allow;
+    let mut targets = Vec::new();
+    if let Some(role) = &role {
+        let id = role.id.get();
+        if allow {
+            perms.remove_denied_role(id);
+            perms.add_allowed_role(id);
+        } else {
+            perms.remove_allowed_role(id);
+            perms.add_denied_role(id);
+        }
+        targets.push(role.mention().to_string());
+    }
+    if let Some(user) = &user {
+        let id = user.id.get();
+        if allow {
+            perms.remove_denied_user(id);
+            perms.add_allowed_user(id);
+        } else {
+            perms.remove_allowed_user(id);
+            perms.add_denied_user(id);
+        }
+        targets.push(user.mention().to_string());
+    }
+    if let Some(channel) = &channel {
+        let id = channel.id().get();
+        if allow {
+            perms.remove_denied_channel(id);
+            perms.add_allowed_channel(id);
+        } else {
+            perms.remove_allowed_channel(id);
+            perms.add_denied_channel(id);
+        }
+        targets.push(channel.id().mention().to_string());
+    }
+    if let Some(command) = &command {
+        if allow {
+            perms.remove_denied_command(command);
+            perms.add_allowed_command(command.clone());
+        } else {
+            perms.remove_allowed_command(command);
+            perms.add_denied_command(command.clone());
+        }
+        targets.push(format!("`{}`", command));
+    }
+
+    let perms =
+        GuildEntity::write_command_settings(&pool, guild_id.get() as i64, &scope, &perms).await?;
+    ctx.data()
+        .guild_settings_map
+        .write()
+        .await
+        .entry(guild_id)
+        .and_modify(|s| {
+            s.command_settings.insert(scope.clone(), perms);
+        });
+
+    send_reply(
+        &ctx,
+        CrackedMessage::Other(format!(
+            "{} {} for `{}`",
+            if allow { "Allowed" } else { "Denied" },
+            targets.join(", "),
+            scope
+        )),
+        true,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Lowercase a command name and collapse its whitespace, dropping any leading `/`.
+fn normalize_command_name(name: &str) -> String {
+    name.trim_start_matches('/')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// All names a command rule can be keyed by: every category and every qualified
+/// command name, including subcommands.
+fn known_command_scopes(commands: &[crate::Command]) -> std::collections::HashSet<String> {
+    let mut scopes = std::collections::HashSet::new();
+    let mut stack = commands.iter().collect::<Vec<_>>();
+    while let Some(command) = stack.pop() {
+        scopes.insert(command.qualified_name.to_string());
+        if let Some(category) = command.category.as_deref() {
+            scopes.insert(category.to_lowercase());
+        }
+        stack.extend(command.subcommands.iter());
+    }
+    scopes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalize_command_name() {
+        assert_eq!(
+            normalize_command_name("/Playlist   Create "),
+            "playlist create"
+        );
+        assert_eq!(normalize_command_name("music"), "music");
+    }
+}
@@ -0,0 +1,61 @@
+use crate::guild::operations::GuildSettingsOperations;
+use crate::{
+    errors::CrackedError, messaging::message::CrackedMessage, poise_ext::ContextExt,
+    utils::send_reply, Context, Error,
+};
+use serenity::all::{Role, RoleId};
+
+/// Set the DJ role and turn DJ mode on or off. In DJ mode only admins, DJs and
+/// the requester of the current track can skip, remove, clear, seek or change
+/// the volume; everyone else votes.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Settings",
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn dj(
+    ctx: Context<'_>,
+    #[description = "Turn DJ mode on or off."] enabled: Option<bool>,
+    #[description = "Role that can control playback in DJ mode."] role: Option<Role>,
+    #[flag]
+    #[description = "Remove the DJ role, leaving only admins as DJs."]
+    no_role: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let data = ctx.data();
+
+    // Make sure there's an entry to modify.
+    let _ = data
+        .get_or_create_guild_settings(guild_id, None, None)
+        .await;
+    let settings = {
+        let mut map = data.guild_settings_map.write().await;
+        let settings = map
+            .get_mut(&guild_id)
+            .ok_or(CrackedError::NoGuildSettings)?;
+        if let Some(enabled) = enabled {
+            settings.set_dj_mode(enabled);
+        }
+        if no_role {
+            settings.set_dj_role(None);
+        } else if let Some(role) = &role {
+            settings.set_dj_role(Some(role.id.get()));
+        }
+        settings.clone()
+    };
+    settings.save(&pool).await?;
+
+    send_reply(
+        &ctx,
+        CrackedMessage::DjModeSet {
+            enabled: settings.dj_mode,
+            role: settings.dj_role.map(RoleId::new),
+        },
+        true,
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use crate::errors::CrackedError;
+use crate::guild::operations::GuildSettingsOperations;
+use crate::messaging::message::CrackedMessage;
+use crate::poise_ext::ContextExt;
+use crate::utils::send_reply;
+use crate::Context;
+use crate::Error;
+use serenity::all::{Channel, Mentionable};
+
+/// Set the channel moderation cases are posted to.
+#[poise::command(
+    category = "Settings",
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    required_bot_permissions = "SEND_MESSAGES"
+)]
+pub async fn mod_log_channel(
+    ctx: Context<'_>,
+    #[description = "Channel to post moderation cases in"] channel: Channel,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let channel_id = channel.id();
+    let pool = ctx.get_db_pool()?;
+
+    let data = ctx.data();
+    let _ = data
+        .get_or_create_guild_settings(guild_id, None, None)
+        .await;
+    let settings = data
+        .guild_settings_map
+        .write()
+        .await
+        .get_mut(&guild_id)
+        .map(|s| s.set_mod_log_channel(channel_id.get()).clone())
+        .ok_or(CrackedError::NoGuildSettings)?;
+    settings.save(&pool).await?;
+
+    send_reply(
+        &ctx,
+        CrackedMessage::Other(format!("Mod log channel set to {}", channel_id.mention())),
+        true,
+    )
+    .await?;
+
+    Ok(())
+}
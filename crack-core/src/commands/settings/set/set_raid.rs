@@ -0,0 +1,87 @@
+use crate::guild::operations::GuildSettingsOperations;
+use crate::{
+    errors::CrackedError, messaging::message::CrackedMessage, poise_ext::ContextExt,
+    utils::send_reply, Context, Error,
+};
+use serenity::all::{Channel, Role};
+
+/// Configure raid protection. A burst of joins, new accounts or look-alike
+/// members within the window locks the server down until `/lockdown end`.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Settings",
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    required_bot_permissions = "SEND_MESSAGES|MANAGE_GUILD|MANAGE_ROLES"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn raid(
+    ctx: Context<'_>,
+    #[description = "Turn raid protection on or off."] enabled: Option<bool>,
+    #[description = "Joins within the window that count as a raid, 0 is off."]
+    join_threshold: Option<u32>,
+    #[description = "How many seconds of joins to look at."] window_seconds: Option<u32>,
+    #[description = "Accounts younger than this many days are new."] new_account_days: Option<u32>,
+    #[description = "New accounts within the window that count as a raid, 0 is off."]
+    new_account_threshold: Option<u32>,
+    #[description = "Look-alike names or avatars within the window that count as a raid, 0 is off."]
+    similar_threshold: Option<u32>,
+    #[description = "Channel for alerts, instead of the mod log."] alert_channel: Option<Channel>,
+    #[description = "Role to ping with alerts."] alert_role: Option<Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let data = ctx.data();
+
+    // Make sure there's an entry to modify.
+    let _ = data
+        .get_or_create_guild_settings(guild_id, None, None)
+        .await;
+    let mut raid = data
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.raid_settings)
+        .unwrap_or_default();
+    if let Some(enabled) = enabled {
+        raid.enabled = enabled;
+    }
+    if let Some(threshold) = join_threshold {
+        raid.join_threshold = threshold;
+    }
+    if let Some(seconds) = window_seconds {
+        raid.window_seconds = seconds;
+    }
+    if let Some(days) = new_account_days {
+        raid.new_account_days = days;
+    }
+    if let Some(threshold) = new_account_threshold {
+        raid.new_account_threshold = threshold;
+    }
+    if let Some(threshold) = similar_threshold {
+        raid.similar_threshold = threshold;
+    }
+    if let Some(channel) = alert_channel {
+        raid.alert_channel = Some(channel.id().get());
+    }
+    if let Some(role) = alert_role {
+        raid.alert_role = Some(role.id.get());
+    }
+    if raid.window_seconds == 0 {
+        return Err(CrackedError::Other("The window needs to be at least a second.").into());
+    }
+
+    let settings = {
+        let mut map = data.guild_settings_map.write().await;
+        let settings = map
+            .get_mut(&guild_id)
+            .ok_or(CrackedError::NoGuildSettings)?;
+        settings.raid_settings = Some(raid);
+        settings.clone()
+    };
+    settings.save(&pool).await?;
+
+    let summary = settings.raid_settings.unwrap_or_default().to_string();
+    send_reply(&ctx, CrackedMessage::RaidSet(summary), true).await?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use crate::guild::operations::GuildSettingsOperations;
+use crate::messaging::messages::TTS_INVALID_VOICE;
+use crate::sources::tts::valid_voice;
+use crate::{
+    errors::CrackedError, messaging::message::CrackedMessage, poise_ext::ContextExt,
+    utils::send_reply, Context, Error,
+};
+
+/// Configure text-to-speech. Track titles, members joining and `/say` are
+/// spoken in voice over the music.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Settings",
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    required_bot_permissions = "SEND_MESSAGES|SPEAK"
+)]
+pub async fn tts(
+    ctx: Context<'_>,
+    #[description = "Turn text-to-speech on or off."] enabled: Option<bool>,
+    #[description = "Voice to speak with, e.g. en-us or en-gb."] voice: Option<String>,
+    #[description = "Volume, from 0 to 200 percent."] volume: Option<u32>,
+    #[description = "Say the title of each track as it starts."] announce_tracks: Option<bool>,
+    #[description = "Say who joins the voice channel."] announce_joins: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let data = ctx.data();
+
+    // Make sure there's an entry to modify.
+    let _ = data
+        .get_or_create_guild_settings(guild_id, None, None)
+        .await;
+    let mut tts = data
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.tts_settings)
+        .unwrap_or_default();
+    if let Some(enabled) = enabled {
+        tts.enabled = enabled;
+    }
+    if let Some(voice) = voice {
+        let voice = voice.trim().to_lowercase();
+        if !valid_voice(&voice) {
+            send_reply(
+                &ctx,
+                CrackedMessage::Other(TTS_INVALID_VOICE.to_string()),
+                true,
+            )
+            .await?;
+            return Ok(());
+        }
+        tts.voice = voice;
+    }
+    if let Some(volume) = volume {
+        tts.volume = volume.min(200) as f32 / 100.0;
+    }
+    if let Some(announce) = announce_tracks {
+        tts.announce_tracks = announce;
+    }
+    if let Some(announce) = announce_joins {
+        tts.announce_joins = announce;
+    }
+
+    let settings = {
+        let mut map = data.guild_settings_map.write().await;
+        let settings = map
+            .get_mut(&guild_id)
+            .ok_or(CrackedError::NoGuildSettings)?;
+        settings.tts_settings = Some(tts);
+        settings.clone()
+    };
+    settings.save(&pool).await?;
+
+    let summary = settings.tts_settings.unwrap_or_default().to_string();
+    send_reply(&ctx, CrackedMessage::TtsSet(summary), true).await?;
+    Ok(())
+}
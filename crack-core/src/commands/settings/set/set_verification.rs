@@ -0,0 +1,99 @@
+use crate::guild::operations::GuildSettingsOperations;
+use crate::guild::settings::{VerificationDelivery, VerificationMethod};
+use crate::{
+    errors::CrackedError, messaging::message::CrackedMessage, poise_ext::ContextExt,
+    utils::send_reply, Context, Error,
+};
+use serenity::all::{Channel, Role};
+
+/// Configure member verification. New members get the unverified role and a
+/// captcha, password or rules challenge, and the welcome auto role once they pass.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Settings",
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    required_bot_permissions = "SEND_MESSAGES|MANAGE_ROLES|KICK_MEMBERS"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn verification(
+    ctx: Context<'_>,
+    #[description = "Turn verification on or off."] enabled: Option<bool>,
+    #[description = "How members verify."] method: Option<VerificationMethod>,
+    #[description = "Where to send the challenge."] delivery: Option<VerificationDelivery>,
+    #[description = "Channel for challenges, and for members who can't get DMs."] channel: Option<
+        Channel,
+    >,
+    #[description = "Role members hold until they pass."] unverified_role: Option<Role>,
+    #[description = "Password for the password method."] password: Option<String>,
+    #[description = "Hours before unverified members are kicked, 0 never kicks."]
+    kick_after_hours: Option<u32>,
+    #[rest]
+    #[description = "Rules shown with the challenge."]
+    rules: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let data = ctx.data();
+
+    // Make sure there's an entry to modify.
+    let _ = data
+        .get_or_create_guild_settings(guild_id, None, None)
+        .await;
+    let mut verification = data
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.verification_settings)
+        .unwrap_or_default();
+    if let Some(enabled) = enabled {
+        verification.enabled = enabled;
+    }
+    if let Some(method) = method {
+        verification.method = method;
+    }
+    if let Some(delivery) = delivery {
+        verification.delivery = delivery;
+    }
+    if let Some(channel) = channel {
+        verification.channel_id = Some(channel.id().get());
+    }
+    if let Some(role) = unverified_role {
+        verification.unverified_role = Some(role.id.get());
+    }
+    if let Some(password) = password {
+        verification.password = Some(password);
+    }
+    if let Some(rules) = rules {
+        verification.rules = Some(rules);
+    }
+    if let Some(hours) = kick_after_hours {
+        verification.kick_after_hours = hours;
+    }
+    if verification.enabled && verification.channel_id.is_none() {
+        return Err(CrackedError::Other("Verification needs a channel.").into());
+    }
+    if verification.enabled
+        && verification.method == VerificationMethod::Password
+        && verification.password.is_none()
+    {
+        return Err(CrackedError::Other("The password method needs a password.").into());
+    }
+
+    let settings = {
+        let mut map = data.guild_settings_map.write().await;
+        let settings = map
+            .get_mut(&guild_id)
+            .ok_or(CrackedError::NoGuildSettings)?;
+        settings.verification_settings = Some(verification);
+        settings.clone()
+    };
+    settings.save(&pool).await?;
+
+    let summary = settings
+        .verification_settings
+        .unwrap_or_default()
+        .to_string();
+    send_reply(&ctx, CrackedMessage::VerificationSet(summary), true).await?;
+    Ok(())
+}
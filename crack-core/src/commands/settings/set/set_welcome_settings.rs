@@ -1,7 +1,10 @@
 use crate::{
     errors::CrackedError,
-    guild::settings::{GuildSettings, WelcomeSettings},
-    utils::get_guild_name,
+    guild::operations::GuildSettingsOperations,
+    guild::settings::{GuildSettings, VerificationMethod, WelcomeSettings},
+    messaging::{message::CrackedMessage, template::Template},
+    poise_ext::ContextExt,
+    utils::{get_guild_name, send_reply},
     Context, Data, Error,
 };
 use serenity::all::{Channel, GuildId, Role};
@@ -22,9 +25,10 @@ pub async fn password_verify(
     #[description = "Password to verify"] password: String,
     #[description = "Role to add after successful verification"] auto_role: Role,
     #[rest]
-    #[description = "Welcome message template use {user} for username"]
+    #[description = "Welcome message template, see `welcome_preview`"]
     message: String,
 ) -> Result<(), Error> {
+    message.parse::<Template>()?;
     let prefix = ctx.data().bot_settings.get_prefix();
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
     let guild_name = get_guild_name(&ctx, guild_id).await;
@@ -32,7 +36,8 @@ pub async fn password_verify(
         channel_id: Some(channel.id().get()),
         message: Some(message.clone()),
         auto_role: Some(auto_role.id.get()),
-        password: Some(password),
+        password: Some(password.clone()),
+        leave_message: None,
     };
     let msg = set_welcome_settings(
         ctx.data().clone(),
@@ -42,6 +47,23 @@ pub async fn password_verify(
         welcome_settings,
     )
     .await?;
+
+    // The password challenge itself is run by verification.
+    let settings = {
+        let mut map = ctx.data().guild_settings_map.write().await;
+        let settings = map
+            .get_mut(&guild_id)
+            .ok_or(CrackedError::NoGuildSettings)?;
+        let verification = settings
+            .verification_settings
+            .get_or_insert_with(Default::default);
+        verification.enabled = true;
+        verification.method = VerificationMethod::Password;
+        verification.channel_id = Some(channel.id().get());
+        verification.password = Some(password);
+        settings.clone()
+    };
+    settings.save(&ctx.get_db_pool()?).await?;
     ctx.say(msg).await?;
     Ok(())
 }
@@ -59,9 +81,10 @@ pub async fn welcome_settings(
     ctx: Context<'_>,
     #[description = "The channel to send welcome messages"] channel: Channel,
     #[rest]
-    #[description = "Welcome message template use {user} for username"]
+    #[description = "Welcome message template, see `welcome_preview`"]
     message: String,
 ) -> Result<(), Error> {
+    message.parse::<Template>()?;
     let prefix = ctx.data().bot_settings.get_prefix();
     let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
     let guild_name = get_guild_name(&ctx, guild_id).await;
@@ -70,6 +93,7 @@ pub async fn welcome_settings(
         message: Some(message.clone()),
         auto_role: None,
         password: None,
+        leave_message: None,
     };
     let msg = set_welcome_settings(
         ctx.data(),
@@ -83,6 +107,50 @@ pub async fn welcome_settings(
     Ok(())
 }
 
+/// Set the message sent to the welcome channel when a member leaves. Leave it
+/// empty to stop sending one.
+#[poise::command(
+    category = "Settings",
+    prefix_command,
+    slash_command,
+    required_permissions = "ADMINISTRATOR"
+)]
+#[cfg(not(tarpaulin_include))]
+pub async fn leave_message(
+    ctx: Context<'_>,
+    #[rest]
+    #[description = "Leave message template, see `welcome_preview`"]
+    message: Option<String>,
+) -> Result<(), Error> {
+    if let Some(message) = &message {
+        message.parse::<Template>()?;
+    }
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let data = ctx.data();
+
+    // Make sure there's an entry to modify.
+    let _ = data
+        .get_or_create_guild_settings(guild_id, None, None)
+        .await;
+    let settings = {
+        let mut map = data.guild_settings_map.write().await;
+        let settings = map
+            .get_mut(&guild_id)
+            .ok_or(CrackedError::NoGuildSettings)?;
+        settings
+            .welcome_settings
+            .get_or_insert_with(Default::default)
+            .leave_message = message;
+        settings.clone()
+    };
+    settings.save(&pool).await?;
+
+    let welcome = settings.welcome_settings.unwrap_or_default().to_string();
+    send_reply(&ctx, CrackedMessage::WelcomeSettings(welcome), true).await?;
+    Ok(())
+}
+
 /// Set the welcome settings for a given guild.
 #[cfg(not(tarpaulin_include))]
 pub async fn set_welcome_settings(
@@ -0,0 +1,67 @@
+use crate::guild::operations::GuildSettingsOperations;
+use crate::messaging::template::{render_welcome, Template, TemplateContext};
+use crate::{errors::CrackedError, Context, Error};
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+
+/// Which message to preview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum WelcomeKind {
+    #[name = "welcome"]
+    Welcome,
+    #[name = "leave"]
+    Leave,
+}
+
+/// Preview the welcome or leave message as if you had just joined or left.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Settings",
+    slash_command,
+    prefix_command,
+    guild_only,
+    ephemeral,
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn welcome_preview(
+    ctx: Context<'_>,
+    #[description = "Which message to preview."] kind: Option<WelcomeKind>,
+    #[rest]
+    #[description = "A template to try instead of the saved one."]
+    template: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let kind = kind.unwrap_or(WelcomeKind::Welcome);
+    let saved = ctx
+        .data()
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.welcome_settings)
+        .and_then(|welcome| match kind {
+            WelcomeKind::Welcome => welcome.message,
+            WelcomeKind::Leave => welcome.leave_message,
+        });
+    let message = template
+        .or(saved)
+        .ok_or(CrackedError::Other("There's no message set to preview."))?;
+
+    let template_ctx = TemplateContext::for_guild(ctx.cache(), guild_id, ctx.author());
+    let rendered = match kind {
+        WelcomeKind::Welcome => render_welcome(&message, &template_ctx, &mut rand::thread_rng())?,
+        WelcomeKind::Leave => message
+            .parse::<Template>()?
+            .render(&template_ctx, &mut rand::thread_rng()),
+    };
+
+    let reply = if rendered.embed {
+        let mut embed = CreateEmbed::new().description(rendered.content);
+        if let Some(image) = rendered.image {
+            embed = embed.image(image);
+        }
+        CreateReply::default().embed(embed)
+    } else {
+        CreateReply::default().content(rendered.content)
+    };
+    ctx.send(reply).await?;
+    Ok(())
+}
@@ -5,7 +5,9 @@ pub mod invite;
 pub mod ping;
 mod say;
 mod smoketest;
+pub mod vc;
 pub mod version;
+pub mod voicestats;
 
 pub use clean::*;
 pub use debug::*;
@@ -14,7 +16,9 @@ pub use invite::*;
 pub use ping::*;
 pub use say::*;
 pub use smoketest::*;
+pub use vc::*;
 pub use version::*;
+pub use voicestats::*;
 
 use crate::{CommandResult, Context, CrackedMessage, Error};
 use poise::serenity_prelude::Mentionable;
@@ -64,17 +68,20 @@ pub async fn uptime_internal(ctx: Context<'_>) -> CrackedMessage {
 }
 
 /// Get all the utility commands.
-pub fn utility_commands() -> [crate::Command; 6] {
+pub fn utility_commands() -> [crate::Command; 9] {
     [
         clean(),
         debug(),
         invite(),
         ping(),
+        say(),
         // servers(),
         // saychan(),
         // saychanid(),
         // smoketest(),
         uptime(),
+        vc(),
         version(),
+        voicestats(),
     ]
 }
@@ -1,8 +1,54 @@
 //use crate::commands::help;
-use crate::{Context, Error};
+use crate::{
+    commands::cmd_check_music,
+    errors::CrackedError,
+    guild::operations::GuildSettingsOperations,
+    messaging::{
+        message::CrackedMessage,
+        messages::{TTS_DISABLED, TTS_NOTHING_TO_SAY, TTS_SAID},
+    },
+    poise_ext::ContextExt,
+    sources::tts::{clean_tts_text, speak},
+    utils::send_reply,
+    Context, Error,
+};
 use core::panic;
 use serenity::all::{Channel, ChannelId};
 
+/// Say something in voice, over whatever's playing.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    check = "cmd_check_music",
+    slash_command,
+    prefix_command,
+    guild_only
+)]
+pub async fn say(
+    ctx: Context<'_>,
+    #[rest]
+    #[description = "What to say."]
+    text: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let Some(settings) = ctx
+        .data()
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.tts_settings)
+        .filter(|settings| settings.enabled)
+    else {
+        return reply(ctx, TTS_DISABLED.to_string()).await;
+    };
+    let Some(text) = clean_tts_text(&text) else {
+        return reply(ctx, TTS_NOTHING_TO_SAY.to_string()).await;
+    };
+    let call = ctx.get_call().await?;
+    ctx.defer().await?;
+    speak(&call, &settings, &text).await?;
+    reply(ctx, format!("{} {}", TTS_SAID, text)).await
+}
+
 /// Have the bot say something in a channel.
 #[cfg(not(tarpaulin_include))]
 #[poise::command(
@@ -56,3 +102,9 @@ pub async fn say_internal(ctx: Context<'_>, chan_id: ChannelId, msg: String) ->
     chan_id.say(ctx.http(), msg).await?;
     Ok(())
 }
+
+#[cfg(not(tarpaulin_include))]
+async fn reply(ctx: Context<'_>, msg: String) -> Result<(), Error> {
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
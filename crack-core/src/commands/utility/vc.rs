@@ -0,0 +1,390 @@
+use crate::db::{TempVoiceChannel, TempVoiceHub};
+use crate::errors::CrackedError;
+use crate::guild::temp_voice::{
+    parse_name_template, set_locked, truncate_name, DEFAULT_TEMP_VOICE_NAME, MAX_USER_LIMIT,
+};
+use crate::messaging::message::CrackedMessage;
+use crate::messaging::messages::{
+    TEMP_VOICE_REASON, VC_HUBS, VC_HUBS_NONE, VC_HUB_NOT_FOUND, VC_HUB_NOT_VOICE, VC_HUB_REMOVED,
+    VC_HUB_SET, VC_KICKED, VC_LIMIT_REMOVED, VC_LIMIT_SET, VC_LOCKED, VC_NOT_IN_CHANNEL,
+    VC_NOT_OWNER, VC_NOT_TEMP, VC_RENAMED, VC_TRANSFERRED, VC_UNLOCKED,
+};
+use crate::poise_ext::ContextExt;
+use crate::utils::send_reply;
+use crate::{Context, Error};
+use serenity::all::{
+    Channel, ChannelId, ChannelType, EditChannel, Mention, PermissionOverwriteType, User, UserId,
+};
+use sqlx::PgPool;
+
+/// Control your temp voice channel. Shows who owns the one you're in.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands(
+        "vc_lock",
+        "vc_unlock",
+        "vc_limit",
+        "vc_rename",
+        "vc_kick",
+        "vc_transfer",
+        "vc_hub"
+    ),
+    ephemeral
+)]
+pub async fn vc(ctx: Context<'_>) -> Result<(), Error> {
+    let pool = ctx.get_db_pool()?;
+    let temp = match voice_channel_of(ctx, ctx.author().id) {
+        Some(channel_id) => TempVoiceChannel::get(&pool, channel_id.get() as i64).await?,
+        None => None,
+    };
+    let Some(temp) = temp else {
+        return reply(ctx, VC_NOT_TEMP.to_string()).await;
+    };
+    let msg = format!(
+        "{}{}: {}",
+        if temp.locked { "🔒 " } else { "🔊 " },
+        Mention::Channel(ChannelId::new(temp.channel_id as u64)),
+        Mention::User(UserId::new(temp.owner_id as u64)),
+    );
+    reply(ctx, msg).await
+}
+
+/// Lock your channel so nobody else can join.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_bot_permissions = "MANAGE_CHANNELS|MANAGE_ROLES",
+    rename = "lock",
+    ephemeral
+)]
+pub async fn vc_lock(ctx: Context<'_>) -> Result<(), Error> {
+    lock_internal(ctx, true).await
+}
+
+/// Unlock your channel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_bot_permissions = "MANAGE_CHANNELS|MANAGE_ROLES",
+    rename = "unlock",
+    ephemeral
+)]
+pub async fn vc_unlock(ctx: Context<'_>) -> Result<(), Error> {
+    lock_internal(ctx, false).await
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn lock_internal(ctx: Context<'_>, locked: bool) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(temp) = owned_channel(ctx, &pool).await? else {
+        return Ok(());
+    };
+    let channel_id = ChannelId::new(temp.channel_id as u64);
+    set_locked(ctx.http(), guild_id, channel_id, ctx.author().id, locked).await?;
+    TempVoiceChannel::set_locked(&pool, temp.channel_id, locked).await?;
+    let msg = if locked { VC_LOCKED } else { VC_UNLOCKED };
+    reply(ctx, msg.to_string()).await
+}
+
+/// Set how many members can be in your channel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_bot_permissions = "MANAGE_CHANNELS",
+    rename = "limit",
+    ephemeral
+)]
+pub async fn vc_limit(
+    ctx: Context<'_>,
+    #[description = "Most members allowed in, 0 for no limit."]
+    #[max = 99]
+    limit: u32,
+) -> Result<(), Error> {
+    let pool = ctx.get_db_pool()?;
+    let Some(temp) = owned_channel(ctx, &pool).await? else {
+        return Ok(());
+    };
+    let limit = limit.min(MAX_USER_LIMIT);
+    ChannelId::new(temp.channel_id as u64)
+        .edit(
+            ctx.http(),
+            EditChannel::new()
+                .user_limit(limit)
+                .audit_log_reason(TEMP_VOICE_REASON),
+        )
+        .await?;
+    let msg = match limit {
+        0 => VC_LIMIT_REMOVED.to_string(),
+        limit => format!("{} {}", VC_LIMIT_SET, limit),
+    };
+    reply(ctx, msg).await
+}
+
+/// Rename your channel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_bot_permissions = "MANAGE_CHANNELS",
+    rename = "rename",
+    ephemeral
+)]
+pub async fn vc_rename(
+    ctx: Context<'_>,
+    #[rest]
+    #[description = "New name for your channel."]
+    name: String,
+) -> Result<(), Error> {
+    let pool = ctx.get_db_pool()?;
+    let Some(temp) = owned_channel(ctx, &pool).await? else {
+        return Ok(());
+    };
+    let name = truncate_name(name.trim());
+    if name.is_empty() {
+        return Err(CrackedError::Other("The name can't be empty.").into());
+    }
+    ChannelId::new(temp.channel_id as u64)
+        .edit(
+            ctx.http(),
+            EditChannel::new()
+                .name(name.clone())
+                .audit_log_reason(TEMP_VOICE_REASON),
+        )
+        .await?;
+    reply(ctx, format!("{} {}", VC_RENAMED, name)).await
+}
+
+/// Disconnect a member from your channel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_bot_permissions = "MOVE_MEMBERS",
+    rename = "kick",
+    ephemeral
+)]
+pub async fn vc_kick(
+    ctx: Context<'_>,
+    #[description = "Member to disconnect."] member: User,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(temp) = owned_channel(ctx, &pool).await? else {
+        return Ok(());
+    };
+    if member.id == ctx.author().id || !in_channel(ctx, &temp, member.id) {
+        return reply(ctx, VC_NOT_IN_CHANNEL.to_string()).await;
+    }
+    guild_id.disconnect_member(ctx.http(), member.id).await?;
+    reply(ctx, format!("{} {}", VC_KICKED, Mention::User(member.id))).await
+}
+
+/// Give your channel to another member in it.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "transfer",
+    ephemeral
+)]
+pub async fn vc_transfer(
+    ctx: Context<'_>,
+    #[description = "Member to give the channel to."] member: User,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let Some(temp) = owned_channel(ctx, &pool).await? else {
+        return Ok(());
+    };
+    if member.bot() || member.id == ctx.author().id || !in_channel(ctx, &temp, member.id) {
+        return reply(ctx, VC_NOT_IN_CHANNEL.to_string()).await;
+    }
+    TempVoiceChannel::set_owner(&pool, temp.channel_id, member.id.get() as i64).await?;
+    if temp.locked {
+        // The new owner can get back in; the old one can't.
+        let channel_id = ChannelId::new(temp.channel_id as u64);
+        set_locked(ctx.http(), guild_id, channel_id, member.id, true).await?;
+        channel_id
+            .delete_permission(
+                ctx.http(),
+                PermissionOverwriteType::Member(ctx.author().id),
+                Some(TEMP_VOICE_REASON),
+            )
+            .await?;
+    }
+    reply(
+        ctx,
+        format!("{} {}", VC_TRANSFERRED, Mention::User(member.id)),
+    )
+    .await
+}
+
+/// Temp voice hubs: voice channels that create a temp channel for whoever joins.
+/// Lists the server's hubs.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("vc_hub_add", "vc_hub_remove"),
+    required_permissions = "MANAGE_CHANNELS",
+    rename = "hub",
+    ephemeral
+)]
+pub async fn vc_hub(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let hubs = TempVoiceHub::list(&pool, guild_id.get() as i64).await?;
+    if hubs.is_empty() {
+        return reply(ctx, VC_HUBS_NONE.to_string()).await;
+    }
+    let lines = hubs
+        .iter()
+        .map(|hub| {
+            let limit = match hub.user_limit {
+                0 => String::new(),
+                limit => format!(" (max {})", limit),
+            };
+            format!(
+                "{}: `{}`{}",
+                Mention::Channel(ChannelId::new(hub.channel_id as u64)),
+                hub.name_template,
+                limit
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    reply(ctx, format!("{}\n{}", VC_HUBS, lines)).await
+}
+
+/// Make a voice channel a hub, or change its name template and user limit.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS",
+    required_bot_permissions = "MANAGE_CHANNELS|MOVE_MEMBERS",
+    rename = "add",
+    ephemeral
+)]
+pub async fn vc_hub_add(
+    ctx: Context<'_>,
+    #[description = "Voice channel members join to get a temp channel."] channel: Channel,
+    #[description = "User limit for the temp channels, none by default."]
+    #[max = 99]
+    user_limit: Option<u32>,
+    #[rest]
+    #[description = "Name for the temp channels, e.g. {user.name}'s channel."]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let is_voice = channel
+        .guild()
+        .is_some_and(|channel| channel.kind == ChannelType::Voice);
+    if !is_voice {
+        return reply(ctx, VC_HUB_NOT_VOICE.to_string()).await;
+    }
+    let name = name.unwrap_or_else(|| DEFAULT_TEMP_VOICE_NAME.to_string());
+    parse_name_template(&name).map_err(CrackedError::from)?;
+    let hub = TempVoiceHub::set(
+        &pool,
+        guild_id.get() as i64,
+        channel.id().get() as i64,
+        name,
+        user_limit.unwrap_or_default().min(MAX_USER_LIMIT) as i32,
+    )
+    .await?;
+    let msg = format!(
+        "{} {} `{}`",
+        VC_HUB_SET,
+        Mention::Channel(channel.id()),
+        hub.name_template
+    );
+    reply(ctx, msg).await
+}
+
+/// Stop a voice channel being a hub.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS",
+    rename = "remove",
+    ephemeral
+)]
+pub async fn vc_hub_remove(
+    ctx: Context<'_>,
+    #[description = "The hub channel."] channel: Channel,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let removed =
+        TempVoiceHub::delete(&pool, guild_id.get() as i64, channel.id().get() as i64).await?;
+    let msg = match removed {
+        true => format!("{} {}", VC_HUB_REMOVED, Mention::Channel(channel.id())),
+        false => VC_HUB_NOT_FOUND.to_string(),
+    };
+    reply(ctx, msg).await
+}
+
+/// The voice channel a member is in, from the cache.
+fn voice_channel_of(ctx: Context<'_>, user_id: UserId) -> Option<ChannelId> {
+    ctx.guild()?
+        .voice_states
+        .get(&user_id)
+        .and_then(|state| state.channel_id)
+}
+
+/// Whether a member is in a temp channel.
+fn in_channel(ctx: Context<'_>, temp: &TempVoiceChannel, user_id: UserId) -> bool {
+    voice_channel_of(ctx, user_id).is_some_and(|id| id.get() as i64 == temp.channel_id)
+}
+
+/// The temp channel the author is in, if they own it. Otherwise tells them why
+/// not.
+#[cfg(not(tarpaulin_include))]
+async fn owned_channel(ctx: Context<'_>, pool: &PgPool) -> Result<Option<TempVoiceChannel>, Error> {
+    let temp = match voice_channel_of(ctx, ctx.author().id) {
+        Some(channel_id) => TempVoiceChannel::get(pool, channel_id.get() as i64).await?,
+        None => None,
+    };
+    let msg = match temp {
+        Some(temp) if temp.owner_id == ctx.author().id.get() as i64 => return Ok(Some(temp)),
+        Some(_) => VC_NOT_OWNER,
+        None => VC_NOT_TEMP,
+    };
+    reply(ctx, msg.to_string()).await?;
+    Ok(None)
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn reply(ctx: Context<'_>, msg: String) -> Result<(), Error> {
+    send_reply(&ctx, CrackedMessage::Other(msg), true).await?;
+    Ok(())
+}
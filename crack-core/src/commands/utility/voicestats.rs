@@ -0,0 +1,207 @@
+use crate::db::{VoiceActivity, VoiceTime};
+use crate::duration::format_duration;
+use crate::errors::CrackedError;
+use crate::guild::voice_activity::VoiceStatsPeriod;
+use crate::messaging::message::CrackedMessage;
+use crate::messaging::messages::{
+    VOICE_STATS_CAMERA, VOICE_STATS_CHANNEL, VOICE_STATS_NONE, VOICE_STATS_NO_CHANNEL,
+    VOICE_STATS_SERVER, VOICE_STATS_STREAMING, VOICE_STATS_USER, VOICE_STATS_VOICE,
+};
+use crate::poise_ext::ContextExt;
+use crate::utils::send_reply;
+use crate::{Context, Error};
+use poise::CreateReply;
+use serenity::all::{Channel, ChannelId, CreateEmbed, Mention, Mentionable, User, UserId};
+use std::time::Duration;
+
+/// How many members a leaderboard shows.
+const LEADERBOARD_LEN: i64 = 10;
+
+/// Voice time stats. Self-muted and AFK time doesn't count. Shows yours.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("voicestats_me", "voicestats_server", "voicestats_channel")
+)]
+pub async fn voicestats(ctx: Context<'_>) -> Result<(), Error> {
+    user_stats_internal(ctx, ctx.author().clone()).await
+}
+
+/// Show your voice time, or another member's.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "me"
+)]
+pub async fn voicestats_me(
+    ctx: Context<'_>,
+    #[description = "Member to look up, you by default."] user: Option<User>,
+) -> Result<(), Error> {
+    let user = user.unwrap_or_else(|| ctx.author().clone());
+    user_stats_internal(ctx, user).await
+}
+
+/// Show who spent the most time in voice.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "server"
+)]
+pub async fn voicestats_server(
+    ctx: Context<'_>,
+    #[description = "The past day, the past week or all time. The past week by default."]
+    period: Option<VoiceStatsPeriod>,
+) -> Result<(), Error> {
+    let period = period.unwrap_or_default();
+    let title = format!("{} ({})", VOICE_STATS_SERVER, period.label());
+    leaderboard_internal(ctx, None, period, title).await
+}
+
+/// Show who spent the most time in a voice channel.
+#[cfg(not(tarpaulin_include))]
+#[poise::command(
+    category = "Utility",
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "channel"
+)]
+pub async fn voicestats_channel(
+    ctx: Context<'_>,
+    #[description = "The past day, the past week or all time. The past week by default."]
+    period: Option<VoiceStatsPeriod>,
+    #[description = "Voice channel, the one you're in by default."] channel: Option<Channel>,
+) -> Result<(), Error> {
+    let channel_id = match channel {
+        Some(channel) => Some(channel.id()),
+        None => ctx
+            .guild()
+            .and_then(|guild| guild.voice_states.get(&ctx.author().id)?.channel_id),
+    };
+    let Some(channel_id) = channel_id else {
+        let msg = CrackedMessage::Other(VOICE_STATS_NO_CHANNEL.to_string());
+        send_reply(&ctx, msg, true).await?;
+        return Ok(());
+    };
+    let period = period.unwrap_or_default();
+    let title = format!(
+        "{} {} ({})",
+        VOICE_STATS_CHANNEL,
+        channel_id.mention(),
+        period.label()
+    );
+    leaderboard_internal(ctx, Some(channel_id), period, title).await
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn user_stats_internal(ctx: Context<'_>, user: User) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let now = chrono::Utc::now();
+
+    let mut lines = Vec::new();
+    for period in [
+        VoiceStatsPeriod::Day,
+        VoiceStatsPeriod::Week,
+        VoiceStatsPeriod::All,
+    ] {
+        let totals = VoiceActivity::totals(
+            &pool,
+            guild_id.get() as i64,
+            user.id.get() as i64,
+            period.since(now),
+        )
+        .await?;
+        lines.push(format!(
+            "**{}**: {} {} · {} {} · {} {}",
+            period.label(),
+            VOICE_STATS_VOICE,
+            format_seconds(totals.voice),
+            VOICE_STATS_STREAMING,
+            format_seconds(totals.streaming),
+            VOICE_STATS_CAMERA,
+            format_seconds(totals.video),
+        ));
+    }
+    let embed = CreateEmbed::default()
+        .title(format!("{} {}", VOICE_STATS_USER, user.name))
+        .description(lines.join("\n"));
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn leaderboard_internal(
+    ctx: Context<'_>,
+    channel_id: Option<ChannelId>,
+    period: VoiceStatsPeriod,
+    title: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(CrackedError::NoGuildId)?;
+    let pool = ctx.get_db_pool()?;
+    let top = VoiceActivity::leaderboard(
+        &pool,
+        guild_id.get() as i64,
+        channel_id.map(|id| id.get() as i64),
+        period.since(chrono::Utc::now()),
+        LEADERBOARD_LEN,
+    )
+    .await?;
+    if top.is_empty() {
+        let msg = CrackedMessage::Other(VOICE_STATS_NONE.to_string());
+        send_reply(&ctx, msg, true).await?;
+        return Ok(());
+    }
+
+    let embed = CreateEmbed::default()
+        .title(title)
+        .description(format_leaderboard(&top));
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// One line per member, most time first.
+fn format_leaderboard(top: &[VoiceTime]) -> String {
+    top.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let user = Mention::User(UserId::new(row.user_id as u64));
+            format!("{}. {} {}", i + 1, user, format_seconds(row.seconds))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_seconds(seconds: i64) -> String {
+    format_duration(Duration::from_secs(seconds.max(0) as u64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_leaderboard() {
+        let top = [
+            VoiceTime {
+                user_id: 1,
+                seconds: 3_660,
+            },
+            VoiceTime {
+                user_id: 2,
+                seconds: 45,
+            },
+        ];
+        assert_eq!(format_leaderboard(&top), "1. <@1> 1h 1m\n2. <@2> 45s");
+        assert_eq!(format_seconds(-5), "0s");
+    }
+}
@@ -12,12 +12,13 @@ use crate::{
     http_utils::CacheHttpExt,
     http_utils::SendMessageParams,
     messaging::message::CrackedMessage,
+    music::sessions::HelperBot,
     utils::{check_reply, count_command},
     BotConfig, Context, Data, DataInner, Error, EventLogAsync, PhoneCodeData,
 };
 use ::serenity::secrets::Token;
 use colored::Colorize;
-use poise::serenity_prelude::{Client, FullEvent, GatewayIntents, GuildId, UserId};
+use poise::serenity_prelude::{Client, FullEvent, GatewayIntents, GuildId, ShardManager, UserId};
 use songbird::driver::DecodeMode;
 use songbird::Songbird;
 use std::borrow::Cow;
@@ -189,7 +190,7 @@ pub async fn poise_framework(
                 }
                 .unwrap_or(to_fixed("Unknown"));
                 tracing::warn!("Guild: {}", name);
-                Ok(true)
+                crate::commands::cmd_check_command_permissions(ctx).await
             })
         }),
         //event_handler: |ctx, event, framework, data_global| {
@@ -232,12 +233,20 @@ pub async fn poise_framework(
     let songbird_config = songbird::Config::default().decode_mode(DecodeMode::Decode);
     let manager: Arc<Songbird> = songbird::Songbird::serenity_from_config(songbird_config);
 
+    let helper_tokens = config
+        .credentials
+        .as_ref()
+        .map(|creds| creds.helper_tokens.clone())
+        .unwrap_or_default();
+    let (helpers, helper_shard_managers) = start_helper_bots(&helper_tokens).await;
+
     let cloned_map = guild_settings_map.clone();
     let data = Data(Arc::new(DataInner {
         phone_data: PhoneCodeData::default(),
         bot_settings: config.clone(),
         guild_settings_map: Arc::new(RwLock::new(cloned_map)),
         songbird: manager.clone(),
+        helpers,
         event_log_async,
         database_pool,
         db_channel,
@@ -354,6 +363,9 @@ pub async fn poise_framework(
         tracing::trace!("Saved guilds: {:?}", saved_guilds);
 
         shard_manager.clone().shutdown_all().await;
+        for helper in helper_shard_managers {
+            helper.shutdown_all().await;
+        }
 
         exit(0);
     });
@@ -373,6 +385,47 @@ pub async fn poise_framework(
     Ok(client)
 }
 
+/// Log in the helper bots and start them. They only ever join voice, so they
+/// have no commands or event handlers and only get voice state events.
+async fn start_helper_bots(tokens: &[String]) -> (Vec<HelperBot>, Vec<Arc<ShardManager>>) {
+    let intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_VOICE_STATES;
+    let mut helpers = Vec::with_capacity(tokens.len());
+    let mut shard_managers = Vec::with_capacity(tokens.len());
+    for (n, token) in tokens.iter().enumerate() {
+        let token = match token.parse::<Token>() {
+            Ok(token) => token,
+            Err(e) => {
+                tracing::error!("Invalid token for helper bot {}: {}", n, e);
+                continue;
+            },
+        };
+        let songbird = Songbird::serenity();
+        let mut client = match Client::builder(token, intents)
+            .voice_manager::<Songbird>(songbird.clone())
+            .await
+        {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::error!("Error creating helper bot {}: {}", n, e);
+                continue;
+            },
+        };
+        helpers.push(HelperBot {
+            cache: client.cache.clone(),
+            http: client.http.clone(),
+            songbird,
+        });
+        shard_managers.push(client.shard_manager.clone());
+        tokio::spawn(async move {
+            if let Err(e) = client.start_autosharded().await {
+                tracing::error!("Helper bot {} stopped: {}", n, e);
+            }
+        });
+    }
+    tracing::info!("Started {} helper bots", helpers.len());
+    (helpers, shard_managers)
+}
+
 /// Checks if the message starts with any of the given prefixes.
 fn check_prefixes(prefixes: &[String], content: &str) -> Option<usize> {
     for prefix in prefixes {
@@ -0,0 +1,170 @@
+use crate::db::{Metadata, TrackOffsets};
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+use std::time::Duration;
+
+/// A saved position in a track.
+#[derive(Debug, Clone, Default)]
+pub struct Bookmark {
+    pub id: i64,
+    pub user_id: i64,
+    pub guild_id: Option<i64>,
+    pub metadata_id: i32,
+    pub position_ms: i64,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// A bookmark joined with enough of its track to list it.
+#[derive(Debug, Clone, Default)]
+pub struct BookmarkListing {
+    pub id: i64,
+    pub position_ms: i64,
+    pub note: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub source_url: Option<String>,
+}
+
+impl Bookmark {
+    /// Save a new bookmark for a user.
+    pub async fn create(
+        pool: &PgPool,
+        user_id: i64,
+        guild_id: Option<i64>,
+        metadata_id: i32,
+        position: Duration,
+        note: Option<String>,
+    ) -> Result<Bookmark, CrackedError> {
+        sqlx::query_as!(
+            Bookmark,
+            r#"
+            INSERT INTO bookmark
+                (user_id, guild_id, metadata_id, position_ms, note)
+            VALUES
+                ($1, $2, $3, $4, $5)
+            RETURNING id, user_id, guild_id, metadata_id, position_ms, note, created_at"#,
+            user_id,
+            guild_id,
+            metadata_id,
+            position.as_millis() as i64,
+            note,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get one of a user's bookmarks by id.
+    pub async fn get_for_user(
+        pool: &PgPool,
+        id: i64,
+        user_id: i64,
+    ) -> Result<Option<Bookmark>, CrackedError> {
+        sqlx::query_as!(
+            Bookmark,
+            "SELECT * FROM bookmark WHERE id = $1 AND user_id = $2",
+            id,
+            user_id,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// List a user's bookmarks, newest first.
+    pub async fn list_for_user(
+        pool: &PgPool,
+        user_id: i64,
+    ) -> Result<Vec<BookmarkListing>, CrackedError> {
+        sqlx::query_as!(
+            BookmarkListing,
+            r#"
+            SELECT
+                bookmark.id, bookmark.position_ms, bookmark.note,
+                metadata.title, metadata.artist, metadata.source_url
+            FROM
+                bookmark INNER JOIN metadata ON bookmark.metadata_id = metadata.id
+            WHERE
+                bookmark.user_id = $1
+            ORDER BY
+                bookmark.created_at DESC"#,
+            user_id,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Delete one of a user's bookmarks. Returns whether anything was deleted.
+    pub async fn delete_for_user(
+        pool: &PgPool,
+        id: i64,
+        user_id: i64,
+    ) -> Result<bool, CrackedError> {
+        sqlx::query!(
+            "DELETE FROM bookmark WHERE id = $1 AND user_id = $2",
+            id,
+            user_id,
+        )
+        .execute(pool)
+        .await
+        .map(|r| r.rows_affected() > 0)
+        .map_err(Into::into)
+    }
+
+    /// The track this bookmark points into.
+    pub async fn metadata(&self, pool: &PgPool) -> Result<Metadata, CrackedError> {
+        Metadata::get_by_id(pool, self.metadata_id)
+            .await?
+            .ok_or(CrackedError::Other("Bookmarked track no longer exists."))
+    }
+
+    /// The bookmarked position.
+    pub fn position(&self) -> Duration {
+        Duration::from_millis(self.position_ms.max(0) as u64)
+    }
+
+    /// Offsets to replay the track from the bookmarked position.
+    pub fn offsets(&self) -> TrackOffsets {
+        TrackOffsets::starting_at(self.position_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_bookmark_roundtrip(pool: PgPool) -> Result<(), CrackedError> {
+        let user_id = 1;
+        let bookmark = Bookmark::create(
+            &pool,
+            user_id,
+            Some(1),
+            1,
+            Duration::from_secs(133),
+            Some("the drop".to_string()),
+        )
+        .await?;
+        assert_eq!(bookmark.position_ms, 133_000);
+        assert_eq!(bookmark.offsets().start(), Some(Duration::from_secs(133)));
+
+        let listed = Bookmark::list_for_user(&pool, user_id).await?;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, bookmark.id);
+
+        // Other users can't see or delete it.
+        assert!(Bookmark::get_for_user(&pool, bookmark.id, 2)
+            .await?
+            .is_none());
+        assert!(!Bookmark::delete_for_user(&pool, bookmark.id, 2).await?);
+
+        assert!(Bookmark::delete_for_user(&pool, bookmark.id, user_id).await?);
+        assert!(Bookmark::list_for_user(&pool, user_id).await?.is_empty());
+        Ok(())
+    }
+}
@@ -2,7 +2,10 @@ use crate::{
     errors::CrackedError,
     guild::{
         permissions::{GenericPermissionSettings, GenericPermissionSettingsReadWCommand},
-        settings::{GuildSettings, WelcomeSettings},
+        settings::{
+            AutomodSettings, GuildSettings, RaidSettings, TtsSettings, VerificationSettings,
+            WelcomeSettings,
+        },
     },
     CrackedResult, Error as SerenityError,
 };
@@ -12,6 +15,9 @@ use serenity::small_fixed_array::FixedString;
 use sqlx::PgPool;
 use std::collections::HashMap;
 
+/// `command_channel.channel_id` of command settings that apply in every channel.
+pub const ALL_CHANNELS_ID: i64 = 0;
+
 pub struct GuildPermissionPivot {
     pub guild_id: i64,
     pub permission_id: i64,
@@ -34,6 +40,8 @@ pub struct GuildSettingsRead {
     pub self_deafen: bool,
     pub timeout_seconds: Option<i32>,
     pub additional_prefixes: Vec<String>,
+    pub dj_role_id: Option<i64>,
+    pub dj_mode: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -42,6 +50,57 @@ pub struct WelcomeSettingsRead {
     pub auto_role: Option<i64>,
     pub channel_id: Option<i64>,
     pub message: Option<String>,
+    pub leave_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AutomodSettingsRead {
+    pub guild_id: i64,
+    pub enabled: bool,
+    pub rate_limit_messages: i32,
+    pub rate_limit_seconds: i32,
+    pub max_duplicates: i32,
+    pub max_mentions: i32,
+    pub block_invites: bool,
+    pub block_banned_domains: bool,
+    pub action: String,
+    pub timeout_seconds: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RaidSettingsRead {
+    pub guild_id: i64,
+    pub enabled: bool,
+    pub join_threshold: i32,
+    pub window_seconds: i32,
+    pub new_account_days: i32,
+    pub new_account_threshold: i32,
+    pub similar_threshold: i32,
+    pub alert_channel_id: Option<i64>,
+    pub alert_role_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TtsSettingsRead {
+    pub guild_id: i64,
+    pub enabled: bool,
+    pub voice: String,
+    pub volume: f64,
+    pub announce_tracks: bool,
+    pub announce_joins: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct VerificationSettingsRead {
+    pub guild_id: i64,
+    pub enabled: bool,
+    pub method: String,
+    pub delivery: String,
+    pub channel_id: Option<i64>,
+    pub unverified_role_id: Option<i64>,
+    pub password: Option<String>,
+    pub rules: Option<String>,
+    pub kick_after_hours: i32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -61,6 +120,7 @@ pub struct LogSettingsRead {
     pub member_log_channel: Option<i64>,
     pub join_leave_log_channel: Option<i64>,
     pub voice_log_channel: Option<i64>,
+    pub mod_log_channel: Option<i64>,
 }
 
 impl GuildEntity {
@@ -112,10 +172,10 @@ impl GuildEntity {
     ) -> Result<(), crate::CrackedError> {
         sqlx::query!(
             r#"
-            INSERT INTO log_settings (guild_id, all_log_channel, raw_event_log_channel, server_log_channel, member_log_channel, join_leave_log_channel, voice_log_channel)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO log_settings (guild_id, all_log_channel, raw_event_log_channel, server_log_channel, member_log_channel, join_leave_log_channel, voice_log_channel, mod_log_channel)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             ON CONFLICT (guild_id)
-            DO UPDATE SET all_log_channel = $2, raw_event_log_channel = $3, server_log_channel = $4, member_log_channel = $5, join_leave_log_channel = $6, voice_log_channel = $7
+            DO UPDATE SET all_log_channel = $2, raw_event_log_channel = $3, server_log_channel = $4, member_log_channel = $5, join_leave_log_channel = $6, voice_log_channel = $7, mod_log_channel = $8
             "#,
             guild_id,
             settings.all_log_channel.map(|x| x as i64),
@@ -124,6 +184,7 @@ impl GuildEntity {
             settings.member_log_channel.map(|x| x as i64),
             settings.join_leave_log_channel.map(|x| x as i64),
             settings.voice_log_channel.map(|x| x as i64),
+            settings.mod_log_channel.map(|x| x as i64),
         )
         .execute(pool)
         .await?;
@@ -139,15 +200,130 @@ impl GuildEntity {
     ) -> Result<(), sqlx::Error> {
         sqlx::query!(
             r#"
-            INSERT INTO welcome_settings (guild_id, auto_role, channel_id, message)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO welcome_settings (guild_id, auto_role, channel_id, message, leave_message)
+            VALUES ($1, $2, $3, $4, $5)
             ON CONFLICT (guild_id)
-            DO UPDATE SET auto_role = $2, channel_id = $3, message = $4
+            DO UPDATE SET auto_role = $2, channel_id = $3, message = $4, leave_message = $5
             "#,
             guild_id,
             settings.auto_role.map(|x| x as i64),
             settings.channel_id.map(|x| x as i64),
             settings.message,
+            settings.leave_message,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Create or update the automod settings for a guild.
+    #[cfg(not(tarpaulin_include))]
+    pub async fn write_automod_settings(
+        pool: &PgPool,
+        guild_id: i64,
+        settings: &crate::guild::settings::AutomodSettings,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO automod_settings (guild_id, enabled, rate_limit_messages, rate_limit_seconds, max_duplicates, max_mentions, block_invites, block_banned_domains, action, timeout_seconds)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (guild_id)
+            DO UPDATE SET enabled = $2, rate_limit_messages = $3, rate_limit_seconds = $4, max_duplicates = $5, max_mentions = $6, block_invites = $7, block_banned_domains = $8, action = $9, timeout_seconds = $10
+            "#,
+            guild_id,
+            settings.enabled,
+            settings.rate_limit_messages as i32,
+            settings.rate_limit_seconds as i32,
+            settings.max_duplicates as i32,
+            settings.max_mentions as i32,
+            settings.block_invites,
+            settings.block_banned_domains,
+            settings.action.as_str(),
+            settings.timeout_seconds as i32,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Create or update the raid settings for a guild.
+    #[cfg(not(tarpaulin_include))]
+    pub async fn write_raid_settings(
+        pool: &PgPool,
+        guild_id: i64,
+        settings: &crate::guild::settings::RaidSettings,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO raid_settings (guild_id, enabled, join_threshold, window_seconds, new_account_days, new_account_threshold, similar_threshold, alert_channel_id, alert_role_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (guild_id)
+            DO UPDATE SET enabled = $2, join_threshold = $3, window_seconds = $4, new_account_days = $5, new_account_threshold = $6, similar_threshold = $7, alert_channel_id = $8, alert_role_id = $9
+            "#,
+            guild_id,
+            settings.enabled,
+            settings.join_threshold as i32,
+            settings.window_seconds as i32,
+            settings.new_account_days as i32,
+            settings.new_account_threshold as i32,
+            settings.similar_threshold as i32,
+            settings.alert_channel.map(|x| x as i64),
+            settings.alert_role.map(|x| x as i64),
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Create or update the TTS settings for a guild.
+    #[cfg(not(tarpaulin_include))]
+    pub async fn write_tts_settings(
+        pool: &PgPool,
+        guild_id: i64,
+        settings: &crate::guild::settings::TtsSettings,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO tts_settings (guild_id, enabled, voice, volume, announce_tracks, announce_joins)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (guild_id)
+            DO UPDATE SET enabled = $2, voice = $3, volume = $4, announce_tracks = $5, announce_joins = $6
+            "#,
+            guild_id,
+            settings.enabled,
+            settings.voice,
+            settings.volume as f64,
+            settings.announce_tracks,
+            settings.announce_joins,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Create or update the verification settings for a guild.
+    #[cfg(not(tarpaulin_include))]
+    pub async fn write_verification_settings(
+        pool: &PgPool,
+        guild_id: i64,
+        settings: &crate::guild::settings::VerificationSettings,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO verification_settings (guild_id, enabled, method, delivery, channel_id, unverified_role_id, password, rules, kick_after_hours)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (guild_id)
+            DO UPDATE SET enabled = $2, method = $3, delivery = $4, channel_id = $5, unverified_role_id = $6, password = $7, rules = $8, kick_after_hours = $9
+            "#,
+            guild_id,
+            settings.enabled,
+            settings.method.as_str(),
+            settings.delivery.as_str(),
+            settings.channel_id.map(|x| x as i64),
+            settings.unverified_role.map(|x| x as i64),
+            settings.password,
+            settings.rules,
+            settings.kick_after_hours as i32,
         )
         .execute(pool)
         .await?;
@@ -192,10 +368,10 @@ impl GuildEntity {
         let to_write = settings.guild_name.to_string();
         sqlx::query!(
             r#"
-            INSERT INTO guild_settings (guild_id, guild_name, prefix, premium, autopause, allow_all_domains, allowed_domains, banned_domains, ignored_channels, old_volume, volume, self_deafen, timeout_seconds, additional_prefixes)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10::FLOAT, $11::FLOAT, $12, $13, $14)
+            INSERT INTO guild_settings (guild_id, guild_name, prefix, premium, autopause, allow_all_domains, allowed_domains, banned_domains, ignored_channels, old_volume, volume, self_deafen, timeout_seconds, additional_prefixes, dj_role_id, dj_mode)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10::FLOAT, $11::FLOAT, $12, $13, $14, $15, $16)
             ON CONFLICT (guild_id)
-            DO UPDATE SET guild_name = $2, prefix = $3, premium = $4, autopause = $5, allow_all_domains = $6, allowed_domains = $7, banned_domains = $8, ignored_channels = $9, old_volume = $10::FLOAT, volume = $11::FLOAT, self_deafen = $12, timeout_seconds = $13, additional_prefixes = $14
+            DO UPDATE SET guild_name = $2, prefix = $3, premium = $4, autopause = $5, allow_all_domains = $6, allowed_domains = $7, banned_domains = $8, ignored_channels = $9, old_volume = $10::FLOAT, volume = $11::FLOAT, self_deafen = $12, timeout_seconds = $13, additional_prefixes = $14, dj_role_id = $15, dj_mode = $16
             "#,
             settings.guild_id.get() as i64,
             to_write,
@@ -211,6 +387,8 @@ impl GuildEntity {
             settings.self_deafen,
             settings.timeout as i32,
             &settings.additional_prefixes,
+            settings.dj_role.map(|x| x as i64),
+            settings.dj_mode,
         )
         .execute(pool)
         .await?;
@@ -259,6 +437,22 @@ impl GuildEntity {
                 .save(pool, guild_id)
                 .await?;
         }
+        if let Some(automod_settings) = &settings.automod_settings {
+            automod_settings.save(pool, guild_id).await?;
+        }
+        if let Some(verification_settings) = &settings.verification_settings {
+            verification_settings.save(pool, guild_id).await?;
+        }
+        if let Some(raid_settings) = &settings.raid_settings {
+            raid_settings.save(pool, guild_id).await?;
+        }
+        if let Some(tts_settings) = &settings.tts_settings {
+            tts_settings.save(pool, guild_id).await?;
+        }
+
+        for (command, perms) in settings.command_settings.iter() {
+            GuildEntity::write_command_settings(pool, guild_id as i64, command, perms).await?;
+        }
 
         Ok(())
     }
@@ -299,6 +493,78 @@ impl GuildEntity {
         Ok(settings_read.map(WelcomeSettings::from))
     }
 
+    /// Get the automod settings for a guild from the database.
+    pub async fn get_automod_settings(
+        pool: &PgPool,
+        id: i64,
+    ) -> Result<Option<AutomodSettings>, sqlx::Error> {
+        let settings_read = sqlx::query_as!(
+            AutomodSettingsRead,
+            r#"
+            SELECT * FROM automod_settings
+            WHERE guild_id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(settings_read.map(AutomodSettings::from))
+    }
+
+    /// Get the raid settings for a guild from the database.
+    pub async fn get_raid_settings(
+        pool: &PgPool,
+        id: i64,
+    ) -> Result<Option<RaidSettings>, sqlx::Error> {
+        let settings_read = sqlx::query_as!(
+            RaidSettingsRead,
+            r#"
+            SELECT * FROM raid_settings
+            WHERE guild_id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(settings_read.map(RaidSettings::from))
+    }
+
+    /// Get the TTS settings for a guild from the database.
+    pub async fn get_tts_settings(
+        pool: &PgPool,
+        id: i64,
+    ) -> Result<Option<TtsSettings>, sqlx::Error> {
+        let settings_read = sqlx::query_as!(
+            TtsSettingsRead,
+            r#"
+            SELECT * FROM tts_settings
+            WHERE guild_id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(settings_read.map(TtsSettings::from))
+    }
+
+    /// Get the verification settings for a guild from the database.
+    pub async fn get_verification_settings(
+        pool: &PgPool,
+        id: i64,
+    ) -> Result<Option<VerificationSettings>, sqlx::Error> {
+        let settings_read = sqlx::query_as!(
+            VerificationSettingsRead,
+            r#"
+            SELECT * FROM verification_settings
+            WHERE guild_id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(settings_read.map(VerificationSettings::from))
+    }
+
     /// Get the settings for a guild from the database.
     pub async fn get_settings(&self, pool: &PgPool) -> Result<GuildSettings, SerenityError> {
         let settings_opt = sqlx::query_as!(
@@ -332,11 +598,19 @@ impl GuildEntity {
         }?;
         let welcome_settings = GuildEntity::get_welcome_settings(pool, self.id).await?;
         let log_settings = GuildEntity::get_log_settings(pool, self.id).await?;
+        let automod_settings = GuildEntity::get_automod_settings(pool, self.id).await?;
+        let verification_settings = GuildEntity::get_verification_settings(pool, self.id).await?;
+        let raid_settings = GuildEntity::get_raid_settings(pool, self.id).await?;
+        let tts_settings = GuildEntity::get_tts_settings(pool, self.id).await?;
         let cmd_settings = GuildEntity::load_command_settings(self.id, pool).await?;
 
         Ok(GuildSettings::from(settings)
             .with_welcome_settings(welcome_settings)
             .with_log_settings(log_settings)
+            .with_automod_settings(automod_settings)
+            .with_verification_settings(verification_settings)
+            .with_raid_settings(raid_settings)
+            .with_tts_settings(tts_settings)
             .with_command_settings(cmd_settings))
     }
 
@@ -407,10 +681,18 @@ impl GuildEntity {
 
         let welcome_settings = GuildEntity::get_welcome_settings(pool, guild_id).await?;
         let log_settings = GuildEntity::get_log_settings(pool, guild_id).await?;
+        let automod_settings = GuildEntity::get_automod_settings(pool, guild_id).await?;
+        let verification_settings = GuildEntity::get_verification_settings(pool, guild_id).await?;
+        let raid_settings = GuildEntity::get_raid_settings(pool, guild_id).await?;
+        let tts_settings = GuildEntity::get_tts_settings(pool, guild_id).await?;
         let command_settings = GuildEntity::load_command_settings(guild_id, pool).await?;
         let guild_settings = GuildSettings::from(guild_settings)
             .with_welcome_settings(welcome_settings)
             .with_log_settings(log_settings)
+            .with_automod_settings(automod_settings)
+            .with_verification_settings(verification_settings)
+            .with_raid_settings(raid_settings)
+            .with_tts_settings(tts_settings)
             .with_command_settings(command_settings);
 
         Ok((guild_entity, guild_settings))
@@ -473,6 +755,85 @@ impl GuildEntity {
         })
         .map_err(|e| e.into())
     }
+
+    /// Create or update the guild-wide settings for a command or category.
+    pub async fn write_command_settings(
+        pool: &PgPool,
+        guild_id: i64,
+        command: &str,
+        settings: &GenericPermissionSettings,
+    ) -> CrackedResult<GenericPermissionSettings> {
+        let existing = sqlx::query_scalar!(
+            r#"
+            SELECT permission_settings_id FROM command_channel
+            WHERE command = $1 AND guild_id = $2 AND channel_id = $3
+            "#,
+            command,
+            guild_id,
+            ALL_CHANNELS_ID,
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        let saved = match existing {
+            Some(id) => {
+                GenericPermissionSettings {
+                    id,
+                    ..settings.clone()
+                }
+                .update_permission_settings(pool)
+                .await?
+            },
+            None => settings.insert_permission_settings(pool).await?,
+        };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO command_channel (command, guild_id, channel_id, permission_settings_id)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (command, guild_id, channel_id)
+            DO UPDATE SET permission_settings_id = $4
+            "#,
+            command,
+            guild_id,
+            ALL_CHANNELS_ID,
+            saved.id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(saved)
+    }
+
+    /// Delete the guild-wide settings for a command or category. Returns whether
+    /// there were any.
+    pub async fn delete_command_settings(
+        pool: &PgPool,
+        guild_id: i64,
+        command: &str,
+    ) -> CrackedResult<bool> {
+        let deleted = sqlx::query_scalar!(
+            r#"
+            DELETE FROM command_channel
+            WHERE command = $1 AND guild_id = $2 AND channel_id = $3
+            RETURNING permission_settings_id
+            "#,
+            command,
+            guild_id,
+            ALL_CHANNELS_ID,
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        match deleted {
+            Some(id) => {
+                sqlx::query!("DELETE FROM permission_settings WHERE id = $1", id as i32)
+                    .execute(pool)
+                    .await?;
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -554,6 +915,7 @@ mod test {
             channel_id: Some(123),
             message: Some("test".to_string()),
             password: None,
+            leave_message: Some("bye {user.name}".to_string()),
         };
 
         let settings = settings.with_welcome_settings(Some(welcome_settings_new.clone()));
@@ -640,6 +1002,7 @@ mod test {
             member_log_channel: Some(123),
             join_leave_log_channel: Some(123),
             voice_log_channel: Some(123),
+            mod_log_channel: Some(123),
         };
 
         GuildEntity::write_log_settings(&pool, guild_id, &log_settings)
@@ -650,4 +1013,123 @@ mod test {
 
         assert_eq!(settings.log_settings, Some(log_settings));
     }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_write_automod_settings(pool: PgPool) {
+        let (guild, settings) = crate::db::guild::GuildEntity::get_or_create(
+            &pool,
+            123,
+            to_fixed("test"),
+            "test".to_string(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(settings.automod_settings, None);
+
+        let automod_settings = AutomodSettings {
+            enabled: true,
+            max_mentions: 0,
+            action: crate::guild::settings::AutomodAction::Timeout,
+            ..Default::default()
+        };
+        GuildEntity::write_automod_settings(&pool, guild.id, &automod_settings)
+            .await
+            .unwrap();
+
+        let settings = guild.get_settings(&pool).await.unwrap();
+        assert_eq!(settings.automod_settings, Some(automod_settings));
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_write_raid_settings(pool: PgPool) {
+        let (guild, settings) = crate::db::guild::GuildEntity::get_or_create(
+            &pool,
+            123,
+            to_fixed("test"),
+            "test".to_string(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(settings.raid_settings, None);
+
+        let raid_settings = RaidSettings {
+            enabled: true,
+            similar_threshold: 0,
+            alert_channel: Some(456),
+            ..Default::default()
+        };
+        GuildEntity::write_raid_settings(&pool, guild.id, &raid_settings)
+            .await
+            .unwrap();
+
+        let settings = guild.get_settings(&pool).await.unwrap();
+        assert_eq!(settings.raid_settings, Some(raid_settings));
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_write_tts_settings(pool: PgPool) {
+        let (guild, settings) = crate::db::guild::GuildEntity::get_or_create(
+            &pool,
+            123,
+            to_fixed("test"),
+            "test".to_string(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(settings.tts_settings, None);
+
+        let tts_settings = TtsSettings {
+            enabled: true,
+            voice: "en-gb".to_string(),
+            volume: 0.5,
+            announce_joins: false,
+            ..Default::default()
+        };
+        GuildEntity::write_tts_settings(&pool, guild.id, &tts_settings)
+            .await
+            .unwrap();
+
+        let settings = guild.get_settings(&pool).await.unwrap();
+        assert_eq!(settings.tts_settings, Some(tts_settings));
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_write_command_settings(pool: PgPool) {
+        let (guild, _) = crate::db::guild::GuildEntity::get_or_create(
+            &pool,
+            123,
+            to_fixed("test"),
+            "test".to_string(),
+        )
+        .await
+        .unwrap();
+
+        let mut perms = GenericPermissionSettings::default();
+        perms.add_allowed_role(10);
+        perms.add_denied_command("playlist".to_string());
+        let saved = GuildEntity::write_command_settings(&pool, guild.id, "music", &perms)
+            .await
+            .unwrap();
+
+        // Writing again updates the same row instead of adding another.
+        perms.add_denied_command("volume".to_string());
+        let updated = GuildEntity::write_command_settings(&pool, guild.id, "music", &perms)
+            .await
+            .unwrap();
+        assert_eq!(saved.id, updated.id);
+
+        let settings = guild.get_settings(&pool).await.unwrap();
+        let music = settings.command_settings.get("music").unwrap();
+        assert!(music.allowed_roles.contains(&10));
+        assert!(!music.is_command_allowed("volume"));
+        assert!(!music.is_command_allowed("playlist create"));
+
+        assert!(
+            GuildEntity::delete_command_settings(&pool, guild.id, "music")
+                .await
+                .unwrap()
+        );
+        let settings = guild.get_settings(&pool).await.unwrap();
+        assert!(settings.command_settings.get("music").is_none());
+    }
 }
@@ -0,0 +1,165 @@
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+/// A member's join and the invite it's attributed to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InviteJoin {
+    pub id: i64,
+    pub guild_id: i64,
+    pub user_id: i64,
+    pub invite_code: Option<String>,
+    pub inviter_id: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+/// How many members an inviter brought in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InviterCount {
+    pub inviter_id: i64,
+    pub joins: i64,
+}
+
+impl InviteJoin {
+    /// Record a member's join.
+    pub async fn record(
+        pool: &PgPool,
+        guild_id: i64,
+        user_id: i64,
+        invite_code: Option<String>,
+        inviter_id: Option<i64>,
+    ) -> Result<InviteJoin, CrackedError> {
+        sqlx::query_as!(
+            InviteJoin,
+            r#"
+            INSERT INTO invite_join (guild_id, user_id, invite_code, inviter_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *"#,
+            guild_id,
+            user_id,
+            invite_code,
+            inviter_id,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a member's latest join.
+    pub async fn get_latest(
+        pool: &PgPool,
+        guild_id: i64,
+        user_id: i64,
+    ) -> Result<Option<InviteJoin>, CrackedError> {
+        sqlx::query_as!(
+            InviteJoin,
+            r#"
+            SELECT * FROM invite_join
+            WHERE guild_id = $1 AND user_id = $2
+            ORDER BY created_at DESC, id DESC
+            LIMIT 1"#,
+            guild_id,
+            user_id,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// The inviters who brought in the most members. Members who join more than
+    /// once count once.
+    pub async fn leaderboard(
+        pool: &PgPool,
+        guild_id: i64,
+        limit: i64,
+    ) -> Result<Vec<InviterCount>, CrackedError> {
+        sqlx::query_as!(
+            InviterCount,
+            r#"
+            SELECT inviter_id AS "inviter_id!", COUNT(DISTINCT user_id) AS "joins!"
+            FROM invite_join
+            WHERE guild_id = $1 AND inviter_id IS NOT NULL
+            GROUP BY inviter_id
+            ORDER BY 2 DESC, inviter_id
+            LIMIT $2"#,
+            guild_id,
+            limit,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// How many members an inviter brought in.
+    pub async fn count_for_inviter(
+        pool: &PgPool,
+        guild_id: i64,
+        inviter_id: i64,
+    ) -> Result<i64, CrackedError> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(DISTINCT user_id) AS "joins!"
+            FROM invite_join
+            WHERE guild_id = $1 AND inviter_id = $2"#,
+            guild_id,
+            inviter_id,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::GuildEntity;
+    use ::serenity::small_fixed_array::FixedString;
+    use std::str::FromStr;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_invite_joins(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+        let code = || Some("abc".to_string());
+
+        InviteJoin::record(&pool, 1, 10, code(), Some(100)).await?;
+        InviteJoin::record(&pool, 1, 11, code(), Some(100)).await?;
+        InviteJoin::record(&pool, 1, 12, Some("xyz".to_string()), Some(200)).await?;
+        // Rejoins count once.
+        InviteJoin::record(&pool, 1, 12, Some("xyz".to_string()), Some(200)).await?;
+        // Unattributed joins aren't on the leaderboard.
+        let unknown = InviteJoin::record(&pool, 1, 13, None, None).await?;
+
+        assert_eq!(
+            InviteJoin::leaderboard(&pool, 1, 10).await?,
+            vec![
+                InviterCount {
+                    inviter_id: 100,
+                    joins: 2
+                },
+                InviterCount {
+                    inviter_id: 200,
+                    joins: 1
+                },
+            ]
+        );
+        assert_eq!(InviteJoin::leaderboard(&pool, 1, 1).await?.len(), 1);
+        assert_eq!(InviteJoin::count_for_inviter(&pool, 1, 200).await?, 1);
+        assert_eq!(InviteJoin::count_for_inviter(&pool, 1, 300).await?, 0);
+
+        let latest = InviteJoin::get_latest(&pool, 1, 11).await?.unwrap();
+        assert_eq!(latest.inviter_id, Some(100));
+        assert_eq!(InviteJoin::get_latest(&pool, 1, 13).await?, Some(unknown));
+        assert_eq!(InviteJoin::get_latest(&pool, 1, 14).await?, None);
+        Ok(())
+    }
+}
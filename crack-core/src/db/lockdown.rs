@@ -0,0 +1,145 @@
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+/// A guild lockdown, with what it changed so ending it can put things back.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lockdown {
+    pub id: i64,
+    pub guild_id: i64,
+    pub reason: String,
+    /// `None` when raid detection started it.
+    pub actor_id: Option<i64>,
+    pub previous_verification_level: i16,
+    pub previous_everyone_permissions: i64,
+    pub invites_were_disabled: bool,
+    pub created_at: NaiveDateTime,
+    pub ended_at: Option<NaiveDateTime>,
+}
+
+impl Lockdown {
+    /// Record the start of a lockdown. Returns `None` if the guild is already
+    /// locked down.
+    pub async fn start(
+        pool: &PgPool,
+        guild_id: i64,
+        reason: String,
+        actor_id: Option<i64>,
+        previous_verification_level: i16,
+        previous_everyone_permissions: i64,
+        invites_were_disabled: bool,
+    ) -> Result<Option<Lockdown>, CrackedError> {
+        sqlx::query_as!(
+            Lockdown,
+            r#"
+            INSERT INTO lockdown (guild_id, reason, actor_id, previous_verification_level, previous_everyone_permissions, invites_were_disabled)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (guild_id) WHERE ended_at IS NULL DO NOTHING
+            RETURNING *"#,
+            guild_id,
+            reason,
+            actor_id,
+            previous_verification_level,
+            previous_everyone_permissions,
+            invites_were_disabled,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a guild's current lockdown.
+    pub async fn get_active(
+        pool: &PgPool,
+        guild_id: i64,
+    ) -> Result<Option<Lockdown>, CrackedError> {
+        sqlx::query_as!(
+            Lockdown,
+            r#"
+            SELECT * FROM lockdown
+            WHERE guild_id = $1 AND ended_at IS NULL"#,
+            guild_id,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Mark a lockdown as over.
+    pub async fn end(
+        pool: &PgPool,
+        id: i64,
+        ended_at: NaiveDateTime,
+    ) -> Result<Lockdown, CrackedError> {
+        sqlx::query_as!(
+            Lockdown,
+            r#"
+            UPDATE lockdown SET ended_at = $2
+            WHERE id = $1
+            RETURNING *"#,
+            id,
+            ended_at,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Forget a lockdown that never took effect.
+    pub async fn delete(pool: &PgPool, id: i64) -> Result<(), CrackedError> {
+        sqlx::query!("DELETE FROM lockdown WHERE id = $1", id)
+            .execute(pool)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::GuildEntity;
+    use ::serenity::small_fixed_array::FixedString;
+    use std::str::FromStr;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_lockdown(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+        assert_eq!(Lockdown::get_active(&pool, 1).await?, None);
+
+        let lockdown = Lockdown::start(&pool, 1, "raid".to_string(), None, 1, 2048, false)
+            .await?
+            .unwrap();
+        assert_eq!(lockdown.previous_everyone_permissions, 2048);
+        // Only one lockdown at a time.
+        assert_eq!(
+            Lockdown::start(&pool, 1, "again".to_string(), Some(10), 4, 0, true).await?,
+            None
+        );
+        assert_eq!(
+            Lockdown::get_active(&pool, 1).await?,
+            Some(lockdown.clone())
+        );
+
+        let ended = Lockdown::end(&pool, lockdown.id, chrono::Utc::now().naive_utc()).await?;
+        assert!(ended.ended_at.is_some());
+        assert_eq!(Lockdown::get_active(&pool, 1).await?, None);
+
+        // A new lockdown can start once the last one is over.
+        let again = Lockdown::start(&pool, 1, "again".to_string(), Some(10), 4, 0, true)
+            .await?
+            .unwrap();
+        Lockdown::delete(&pool, again.id).await?;
+        assert_eq!(Lockdown::get_active(&pool, 1).await?, None);
+        Ok(())
+    }
+}
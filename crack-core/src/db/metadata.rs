@@ -162,6 +162,23 @@ impl Metadata {
         //     None => Ok(None),
         // }
     }
+
+    /// Get a metadata entry by its id.
+    pub async fn get_by_id(pool: &PgPool, id: i32) -> Result<Option<Metadata>, CrackedError> {
+        sqlx::query_as!(MetadataRead,
+            r#"SELECT
+                metadata.id, metadata.track, metadata.artist, metadata.album, metadata.date, metadata.channels, metadata.channel, metadata.start_time, metadata.duration, metadata.sample_rate, metadata.source_url, metadata.title, metadata.thumbnail
+            FROM
+                metadata
+            WHERE
+                metadata.id = $1"#,
+            id
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(CrackedError::SQLX)
+        .map(|r| r.map(|r| r.into()))
+    }
 }
 
 impl From<MetadataRead> for Metadata {
@@ -276,6 +293,8 @@ pub fn aux_metadata_to_db_structures(
         guild_id: Some(guild_id),
         metadata_id: 0,
         channel_id: Some(channel_id),
+        start_offset_ms: None,
+        end_offset_ms: None,
     };
 
     Ok(MetadataAnd::Track(metadata, db_track))
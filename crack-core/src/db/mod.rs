@@ -1,15 +1,41 @@
+pub mod bookmark;
 pub mod guild;
+pub mod invites;
+pub mod lockdown;
 pub mod metadata;
+pub mod moderation;
 pub mod play_log;
 pub mod playlist;
+pub mod quiz_score;
+pub mod role_menu;
+pub mod scheduled_playback;
+pub mod session_link;
+pub mod smart_playlist;
+pub mod soundboard;
+pub mod temp_voice;
 pub mod track_reaction;
 pub mod user;
+pub mod verification;
+pub mod voice_activity;
 pub mod worker_pool;
 
+pub use bookmark::*;
 pub use guild::*;
+pub use invites::*;
+pub use lockdown::*;
 pub use metadata::*;
+pub use moderation::*;
 pub use play_log::*;
 pub use playlist::*;
+pub use quiz_score::*;
+pub use role_menu::*;
+pub use scheduled_playback::*;
+pub use session_link::*;
+pub use smart_playlist::*;
+pub use soundboard::*;
+pub use temp_voice::*;
 pub use track_reaction::*;
 pub use user::*;
+pub use verification::*;
+pub use voice_activity::*;
 pub use worker_pool::*;
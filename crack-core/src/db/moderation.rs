@@ -0,0 +1,500 @@
+use crate::CrackedError;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use sqlx::PgPool;
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+/// The kind of moderation action a case records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseAction {
+    Warn,
+    Kick,
+    Ban,
+    /// A ban that is lifted automatically once it expires.
+    TempBan,
+    Unban,
+    Timeout,
+    /// Voice server-mute.
+    Mute,
+    /// A voice server-mute that is lifted automatically once it expires.
+    TempMute,
+    Unmute,
+    Deafen,
+    Undeafen,
+    /// A message deleted by automod.
+    Delete,
+    /// Moved to another voice channel.
+    Move,
+    /// Disconnected from voice.
+    Disconnect,
+}
+
+impl CaseAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaseAction::Warn => "warn",
+            CaseAction::Kick => "kick",
+            CaseAction::Ban => "ban",
+            CaseAction::TempBan => "tempban",
+            CaseAction::Unban => "unban",
+            CaseAction::Timeout => "timeout",
+            CaseAction::Mute => "mute",
+            CaseAction::TempMute => "tempmute",
+            CaseAction::Unmute => "unmute",
+            CaseAction::Deafen => "deafen",
+            CaseAction::Undeafen => "undeafen",
+            CaseAction::Delete => "delete",
+            CaseAction::Move => "move",
+            CaseAction::Disconnect => "disconnect",
+        }
+    }
+
+    /// The action that undoes this one when it expires, if the bot has to do it.
+    /// Timeouts are lifted by Discord itself.
+    pub fn reversal(&self) -> Option<CaseAction> {
+        match self {
+            CaseAction::TempBan => Some(CaseAction::Unban),
+            CaseAction::TempMute => Some(CaseAction::Unmute),
+            _ => None,
+        }
+    }
+}
+
+impl Display for CaseAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CaseAction {
+    type Err = CrackedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(CaseAction::Warn),
+            "kick" => Ok(CaseAction::Kick),
+            "ban" => Ok(CaseAction::Ban),
+            "tempban" => Ok(CaseAction::TempBan),
+            "unban" => Ok(CaseAction::Unban),
+            "timeout" => Ok(CaseAction::Timeout),
+            "mute" => Ok(CaseAction::Mute),
+            "tempmute" => Ok(CaseAction::TempMute),
+            "unmute" => Ok(CaseAction::Unmute),
+            "deafen" => Ok(CaseAction::Deafen),
+            "undeafen" => Ok(CaseAction::Undeafen),
+            "delete" => Ok(CaseAction::Delete),
+            "move" => Ok(CaseAction::Move),
+            "disconnect" => Ok(CaseAction::Disconnect),
+            _ => Err(CrackedError::Other("Unknown moderation action")),
+        }
+    }
+}
+
+/// A numbered moderation case. Case numbers count up per guild.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModerationCase {
+    pub id: i64,
+    pub guild_id: i64,
+    pub case_number: i64,
+    pub action: String,
+    pub actor_id: i64,
+    pub target_id: i64,
+    pub reason: Option<String>,
+    pub duration_seconds: Option<i64>,
+    /// Links to screenshots, messages and the like.
+    pub evidence: Vec<String>,
+    /// When a timed action should be reversed, in UTC.
+    pub expires_at: Option<NaiveDateTime>,
+    /// When a timed action was reversed, by the bot or by hand.
+    pub reversed_at: Option<NaiveDateTime>,
+    /// Where the case was posted in the mod log, so it can be edited.
+    pub log_channel_id: Option<i64>,
+    pub log_message_id: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ModerationCase {
+    /// Create an unsaved case. Timed actions get an `expires_at` of `now + duration`.
+    pub fn new(
+        guild_id: i64,
+        action: CaseAction,
+        actor_id: i64,
+        target_id: i64,
+        reason: Option<String>,
+        duration: Option<Duration>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let expires_at = duration
+            .filter(|_| action.reversal().is_some())
+            .and_then(|d| chrono::Duration::from_std(d).ok())
+            .map(|d| (now + d).naive_utc());
+        ModerationCase {
+            guild_id,
+            action: action.to_string(),
+            actor_id,
+            target_id,
+            reason,
+            duration_seconds: duration.map(|d| d.as_secs() as i64),
+            expires_at,
+            created_at: now.naive_utc(),
+            ..Default::default()
+        }
+    }
+
+    /// Return the case with the given evidence links.
+    pub fn with_evidence(self, evidence: Vec<String>) -> Self {
+        Self { evidence, ..self }
+    }
+
+    /// Save a new case, giving it the guild's next case number. The id, case
+    /// number and log message of `case` are ignored.
+    pub async fn create(
+        pool: &PgPool,
+        case: &ModerationCase,
+    ) -> Result<ModerationCase, CrackedError> {
+        let mut tx = pool.begin().await?;
+        // Bumping the counter locks the guild's row until we commit, so two
+        // actions at once can't get the same number.
+        let case_number = sqlx::query_scalar!(
+            r#"
+            INSERT INTO moderation_case_counter (guild_id, last_case_number)
+            VALUES ($1, 1)
+            ON CONFLICT (guild_id) DO UPDATE
+                SET last_case_number = moderation_case_counter.last_case_number + 1
+            RETURNING last_case_number"#,
+            case.guild_id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let case = sqlx::query_as!(
+            ModerationCase,
+            r#"
+            INSERT INTO moderation_case
+                (guild_id, case_number, action, actor_id, target_id, reason, duration_seconds, evidence, expires_at)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *"#,
+            case.guild_id,
+            case_number,
+            case.action,
+            case.actor_id,
+            case.target_id,
+            case.reason,
+            case.duration_seconds,
+            &case.evidence,
+            case.expires_at,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(case)
+    }
+
+    /// Get a guild's case by its number.
+    pub async fn get(
+        pool: &PgPool,
+        guild_id: i64,
+        case_number: i64,
+    ) -> Result<Option<ModerationCase>, CrackedError> {
+        sqlx::query_as!(
+            ModerationCase,
+            "SELECT * FROM moderation_case WHERE guild_id = $1 AND case_number = $2",
+            guild_id,
+            case_number,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get every case against a user in a guild, newest first.
+    pub async fn get_for_target(
+        pool: &PgPool,
+        guild_id: i64,
+        target_id: i64,
+    ) -> Result<Vec<ModerationCase>, CrackedError> {
+        sqlx::query_as!(
+            ModerationCase,
+            r#"
+            SELECT * FROM moderation_case
+            WHERE guild_id = $1 AND target_id = $2
+            ORDER BY case_number DESC"#,
+            guild_id,
+            target_id,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Replace a case's reason and add evidence links to it.
+    pub async fn update_reason(
+        pool: &PgPool,
+        guild_id: i64,
+        case_number: i64,
+        reason: &str,
+        evidence: &[String],
+    ) -> Result<Option<ModerationCase>, CrackedError> {
+        sqlx::query_as!(
+            ModerationCase,
+            r#"
+            UPDATE moderation_case
+            SET reason = $3, evidence = evidence || $4
+            WHERE guild_id = $1 AND case_number = $2
+            RETURNING *"#,
+            guild_id,
+            case_number,
+            reason,
+            evidence,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Remember where a case was posted in the mod log.
+    pub async fn set_log_message(
+        pool: &PgPool,
+        id: i64,
+        channel_id: i64,
+        message_id: i64,
+    ) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "UPDATE moderation_case SET log_channel_id = $2, log_message_id = $3 WHERE id = $1",
+            id,
+            channel_id,
+            message_id,
+        )
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(Into::into)
+    }
+
+    /// Get all timed cases that have run out by `now` and are still in effect.
+    pub async fn get_expired(
+        pool: &PgPool,
+        now: NaiveDateTime,
+    ) -> Result<Vec<ModerationCase>, CrackedError> {
+        sqlx::query_as!(
+            ModerationCase,
+            r#"
+            SELECT * FROM moderation_case
+            WHERE expires_at <= $1 AND reversed_at IS NULL
+            ORDER BY expires_at"#,
+            now,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Mark a case as reversed.
+    pub async fn mark_reversed(
+        pool: &PgPool,
+        id: i64,
+        now: NaiveDateTime,
+    ) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "UPDATE moderation_case SET reversed_at = $2 WHERE id = $1",
+            id,
+            now,
+        )
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(Into::into)
+    }
+
+    /// Mark a user's pending timed cases of the given action as reversed, e.g. when
+    /// a tempban is lifted by hand. Returns how many were pending.
+    pub async fn mark_reversed_for_target(
+        pool: &PgPool,
+        guild_id: i64,
+        target_id: i64,
+        action: CaseAction,
+        now: NaiveDateTime,
+    ) -> Result<u64, CrackedError> {
+        sqlx::query!(
+            r#"
+            UPDATE moderation_case SET reversed_at = $4
+            WHERE guild_id = $1 AND target_id = $2 AND action = $3
+                AND expires_at IS NOT NULL AND reversed_at IS NULL"#,
+            guild_id,
+            target_id,
+            action.as_str(),
+            now,
+        )
+        .execute(pool)
+        .await
+        .map(|r| r.rows_affected())
+        .map_err(Into::into)
+    }
+
+    /// The parsed action of this case.
+    pub fn action(&self) -> Result<CaseAction, CrackedError> {
+        self.action.parse()
+    }
+
+    /// How long the action lasts, if it's timed.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration_seconds
+            .map(|secs| Duration::from_secs(secs.max(0) as u64))
+    }
+
+    /// When a timed action runs out, in UTC.
+    pub fn expires_at_utc(&self) -> Option<DateTime<Utc>> {
+        self.expires_at.map(|at| Utc.from_utc_datetime(&at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::GuildEntity;
+    use ::serenity::small_fixed_array::FixedString;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_case_action_roundtrip() {
+        for action in [
+            CaseAction::Warn,
+            CaseAction::Kick,
+            CaseAction::Ban,
+            CaseAction::TempBan,
+            CaseAction::Unban,
+            CaseAction::Timeout,
+            CaseAction::Mute,
+            CaseAction::TempMute,
+            CaseAction::Unmute,
+            CaseAction::Deafen,
+            CaseAction::Undeafen,
+            CaseAction::Delete,
+            CaseAction::Move,
+            CaseAction::Disconnect,
+        ] {
+            assert_eq!(action.to_string().parse::<CaseAction>().unwrap(), action);
+        }
+        assert!("smite".parse::<CaseAction>().is_err());
+    }
+
+    #[test]
+    fn test_new_case_expires_only_when_timed() {
+        let now = utc("2024-01-05T20:00:00Z");
+        let hour = Some(Duration::from_secs(60 * 60));
+
+        let tempban = ModerationCase::new(1, CaseAction::TempBan, 2, 3, None, hour, now);
+        assert_eq!(tempban.expires_at_utc(), Some(utc("2024-01-05T21:00:00Z")));
+        assert_eq!(tempban.duration(), hour);
+
+        // Discord lifts timeouts itself, so there's nothing for us to reverse.
+        let timeout = ModerationCase::new(1, CaseAction::Timeout, 2, 3, None, hour, now);
+        assert_eq!(timeout.expires_at, None);
+        assert_eq!(timeout.duration(), hour);
+
+        let ban = ModerationCase::new(1, CaseAction::Ban, 2, 3, None, None, now);
+        assert_eq!(ban.expires_at, None);
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_moderation_cases(pool: PgPool) -> Result<(), CrackedError> {
+        for guild_id in [1, 2] {
+            GuildEntity::get_or_create(
+                &pool,
+                guild_id,
+                FixedString::from_str("test").unwrap(),
+                "r!".to_string(),
+            )
+            .await?;
+        }
+        let now = utc("2024-01-05T20:00:00Z");
+
+        let first = ModerationCase::create(
+            &pool,
+            &ModerationCase::new(1, CaseAction::Warn, 10, 20, None, None, now),
+        )
+        .await?;
+        let second = ModerationCase::create(
+            &pool,
+            &ModerationCase::new(
+                1,
+                CaseAction::TempBan,
+                10,
+                20,
+                Some("spam".to_string()),
+                Some(Duration::from_secs(60)),
+                now,
+            )
+            .with_evidence(vec!["https://example.com/1".to_string()]),
+        )
+        .await?;
+        let other_guild = ModerationCase::create(
+            &pool,
+            &ModerationCase::new(2, CaseAction::Kick, 10, 20, None, None, now),
+        )
+        .await?;
+        assert_eq!(first.case_number, 1);
+        assert_eq!(second.case_number, 2);
+        assert_eq!(other_guild.case_number, 1);
+
+        let cases = ModerationCase::get_for_target(&pool, 1, 20).await?;
+        assert_eq!(
+            cases.iter().map(|c| c.case_number).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+
+        let updated = ModerationCase::update_reason(
+            &pool,
+            1,
+            2,
+            "spam and raid",
+            &["https://example.com/2".to_string()],
+        )
+        .await?
+        .unwrap();
+        assert_eq!(updated.reason.as_deref(), Some("spam and raid"));
+        assert_eq!(updated.evidence.len(), 2);
+        assert!(ModerationCase::update_reason(&pool, 1, 99, "nope", &[])
+            .await?
+            .is_none());
+
+        let later = utc("2024-01-05T20:01:00Z").naive_utc();
+        let expired = ModerationCase::get_expired(&pool, later).await?;
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, second.id);
+
+        let pending =
+            ModerationCase::mark_reversed_for_target(&pool, 1, 20, CaseAction::TempBan, later)
+                .await?;
+        assert_eq!(pending, 1);
+        assert!(ModerationCase::get_expired(&pool, later).await?.is_empty());
+        Ok(())
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_concurrent_case_numbers(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+        let now = utc("2024-01-05T20:00:00Z");
+        let case = ModerationCase::new(1, CaseAction::Warn, 10, 20, None, None, now);
+
+        let created =
+            futures::future::join_all((0..8).map(|_| ModerationCase::create(&pool, &case))).await;
+        let mut numbers = created
+            .into_iter()
+            .map(|c| c.map(|c| c.case_number))
+            .collect::<Result<Vec<_>, _>>()?;
+        numbers.sort();
+        assert_eq!(numbers, (1..=8).collect::<Vec<_>>());
+        Ok(())
+    }
+}
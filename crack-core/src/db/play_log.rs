@@ -5,7 +5,7 @@ use poise::serenity_prelude as serenity;
 use sqlx::types::chrono::NaiveDateTime;
 use sqlx::{Error, PgPool};
 
-use crate::db::Metadata;
+use crate::db::{Metadata, MetadataRead};
 
 #[derive(Debug, Clone)]
 pub struct PlayLog {
@@ -239,6 +239,32 @@ impl PlayLog {
         Ok(last_played.into_iter().map(|t| t.id as i64).collect())
     }
 
+    /// Tracks the given guild has played, once each, for picking from at
+    /// random. Tracks without a URL can't be played again, so are left out.
+    pub async fn get_played_tracks_by_guild(
+        conn: &PgPool,
+        guild_id: i64,
+        limit: i64,
+    ) -> Result<Vec<Metadata>, Error> {
+        sqlx::query_as!(
+            MetadataRead,
+            r#"
+            select distinct on (metadata.source_url)
+                metadata.id, track, artist, album, date, channels, channel, start_time, duration, sample_rate, source_url, title, thumbnail
+            from play_log
+            join metadata on
+            play_log.metadata_id = metadata.id
+            where guild_id = $1 and metadata.source_url is not null
+            order by metadata.source_url, created_at desc limit $2
+            "#,
+            guild_id,
+            limit
+        )
+        .fetch_all(conn)
+        .await
+        .map(|r| r.into_iter().map(Into::into).collect())
+    }
+
     /// Get the last played track for the given user.
     pub async fn get_last_played_by_user(
         conn: &PgPool,
@@ -1,6 +1,7 @@
-use crate::db::{user::User, Metadata, MetadataRead};
+use crate::db::{user::User, Metadata, MetadataRead, SmartEvalContext, SmartPlaylist};
 use crate::CrackedError;
 use sqlx::{postgres::PgQueryResult, query, PgPool};
+use std::time::Duration;
 
 /// Playlist db structure (does not old the tracks)
 #[derive(Debug, Default)]
@@ -9,6 +10,8 @@ pub struct Playlist {
     pub name: String,
     pub user_id: Option<i64>,
     pub privacy: String,
+    /// Set for shared "house" playlists owned by a guild rather than a user.
+    pub guild_id: Option<i64>,
 }
 
 /// PlaylistTrack db structure.
@@ -19,6 +22,45 @@ pub struct PlaylistTrack {
     pub metadata_id: i32,
     pub guild_id: Option<i64>,
     pub channel_id: Option<i64>,
+    /// Where playback of this entry starts, in milliseconds.
+    pub start_offset_ms: Option<i64>,
+    /// Where playback of this entry stops, in milliseconds.
+    pub end_offset_ms: Option<i64>,
+}
+
+/// Start and end offsets for a single queued track, used by playlist entries and
+/// bookmarks to play only part of a track.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TrackOffsets {
+    pub start_ms: Option<i64>,
+    pub end_ms: Option<i64>,
+}
+
+impl TrackOffsets {
+    /// Offsets that start playback at the given position and play to the end.
+    pub fn starting_at(start_ms: i64) -> Self {
+        Self {
+            start_ms: Some(start_ms),
+            end_ms: None,
+        }
+    }
+
+    /// Whether these offsets change playback at all.
+    pub fn is_empty(&self) -> bool {
+        self.start_ms.is_none() && self.end_ms.is_none()
+    }
+
+    /// The start offset as a duration, if any.
+    pub fn start(&self) -> Option<Duration> {
+        self.start_ms
+            .map(|ms| Duration::from_millis(ms.max(0) as u64))
+    }
+
+    /// The end offset as a duration, if any.
+    pub fn end(&self) -> Option<Duration> {
+        self.end_ms
+            .map(|ms| Duration::from_millis(ms.max(0) as u64))
+    }
 }
 
 /// Implementation of the Playlist struct for writing to the database
@@ -32,9 +74,35 @@ impl Playlist {
         }
         let rec = sqlx::query_as!(
             Playlist,
-            "INSERT INTO playlist (name, user_id) VALUES ($1, $2) RETURNING id, name, user_id, privacy",
+            "INSERT INTO playlist (name, user_id) VALUES ($1, $2) RETURNING id, name, user_id, privacy, guild_id",
+            name,
+            user_id,
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(rec)
+    }
+
+    /// Create a new playlist owned by a guild. The creator is recorded in `user_id`,
+    /// but the playlist belongs to the guild and outlives them.
+    pub async fn create_for_guild(
+        pool: &PgPool,
+        name: &str,
+        user_id: i64,
+        guild_id: i64,
+    ) -> Result<Playlist, CrackedError> {
+        if User::get_user(pool, user_id).await.is_none() {
+            return Err(CrackedError::Other(
+                "(playlist::create_for_guild) User does not exist",
+            ));
+        }
+        let rec = sqlx::query_as!(
+            Playlist,
+            "INSERT INTO playlist (name, user_id, guild_id, privacy) VALUES ($1, $2, $3, 'shared') RETURNING id, name, user_id, privacy, guild_id",
             name,
             user_id,
+            guild_id,
         )
         .fetch_one(pool)
         .await?;
@@ -42,6 +110,11 @@ impl Playlist {
         Ok(rec)
     }
 
+    /// Whether this playlist is owned by a guild instead of a user.
+    pub fn is_guild_playlist(&self) -> bool {
+        self.guild_id.is_some()
+    }
+
     /// Add a track to a playlist.
     pub async fn add_track(
         pool: &PgPool,
@@ -61,6 +134,28 @@ impl Playlist {
         .await
     }
 
+    /// Add a track to a playlist that only plays between the given offsets.
+    pub async fn add_track_with_offsets(
+        pool: &PgPool,
+        playlist_id: i32,
+        metadata_id: i32,
+        guild_id: i64,
+        channel_id: i64,
+        offsets: TrackOffsets,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        query!(
+            "INSERT INTO playlist_track (playlist_id, metadata_id, guild_id, channel_id, start_offset_ms, end_offset_ms) VALUES ($1, $2, $3, $4, $5, $6)",
+            playlist_id,
+            metadata_id,
+            guild_id,
+            channel_id,
+            offsets.start_ms,
+            offsets.end_ms,
+        )
+        .execute(pool)
+        .await
+    }
+
     // Additional functions to retrieve, update, and delete playlists and tracks
 
     /// Reterive a playlist by ID
@@ -78,14 +173,14 @@ impl Playlist {
         .map_err(CrackedError::SQLX)
     }
 
-    /// Retreive playlists by user ID
+    /// Retreive personal playlists by user ID
     pub async fn get_playlists_by_user_id(
         pool: &PgPool,
         user_id: i64,
     ) -> Result<Vec<Playlist>, CrackedError> {
         sqlx::query_as!(
             Playlist,
-            "SELECT * FROM playlist WHERE user_id = $1",
+            "SELECT * FROM playlist WHERE user_id = $1 AND guild_id IS NULL",
             user_id
         )
         .fetch_all(pool)
@@ -93,7 +188,22 @@ impl Playlist {
         .map_err(CrackedError::SQLX)
     }
 
-    /// Reterive a playlist by name and user ID.
+    /// Retreive the playlists owned by a guild.
+    pub async fn get_playlists_by_guild_id(
+        pool: &PgPool,
+        guild_id: i64,
+    ) -> Result<Vec<Playlist>, CrackedError> {
+        sqlx::query_as!(
+            Playlist,
+            "SELECT * FROM playlist WHERE guild_id = $1",
+            guild_id
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(CrackedError::SQLX)
+    }
+
+    /// Reterive a personal playlist by name and user ID.
     pub async fn get_playlist_by_name(
         pool: &PgPool,
         name: String,
@@ -101,7 +211,7 @@ impl Playlist {
     ) -> Result<Playlist, CrackedError> {
         sqlx::query_as!(
             Playlist,
-            "SELECT * FROM playlist WHERE user_id = $1 and name = $2",
+            "SELECT * FROM playlist WHERE user_id = $1 and name = $2 and guild_id IS NULL",
             user_id,
             name
         )
@@ -110,6 +220,68 @@ impl Playlist {
         .map_err(CrackedError::SQLX)
     }
 
+    /// Reterive a guild playlist by name and guild ID.
+    pub async fn get_guild_playlist_by_name(
+        pool: &PgPool,
+        name: String,
+        guild_id: i64,
+    ) -> Result<Playlist, CrackedError> {
+        sqlx::query_as!(
+            Playlist,
+            "SELECT * FROM playlist WHERE guild_id = $1 and name = $2",
+            guild_id,
+            name
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(CrackedError::SQLX)
+    }
+
+    /// Reterive a playlist by name, preferring the user's own playlist and falling
+    /// back to the guild's playlist of the same name.
+    pub async fn get_playlist_by_name_scoped(
+        pool: &PgPool,
+        name: String,
+        user_id: i64,
+        guild_id: Option<i64>,
+    ) -> Result<Playlist, CrackedError> {
+        match (
+            Self::get_playlist_by_name(pool, name.clone(), user_id).await,
+            guild_id,
+        ) {
+            // Only a missing personal playlist falls back; other errors are real.
+            (Err(CrackedError::SQLX(sqlx::Error::RowNotFound)), Some(guild_id)) => {
+                Self::get_guild_playlist_by_name(pool, name, guild_id).await
+            },
+            (res, _) => res,
+        }
+    }
+
+    /// Reterive a playlist by ID, if the user can see it from the given guild.
+    /// Playlists they can't see are reported as not found.
+    pub async fn get_playlist_by_id_scoped(
+        pool: &PgPool,
+        playlist_id: i32,
+        user_id: i64,
+        guild_id: Option<i64>,
+    ) -> Result<Playlist, CrackedError> {
+        let playlist = Self::get_playlist_by_id(pool, playlist_id).await?;
+        if playlist.is_visible_to(user_id, guild_id) {
+            Ok(playlist)
+        } else {
+            Err(CrackedError::SQLX(sqlx::Error::RowNotFound))
+        }
+    }
+
+    /// Whether a user can see this playlist: it's their personal playlist, or
+    /// it belongs to the guild they're in.
+    pub fn is_visible_to(&self, user_id: i64, guild_id: Option<i64>) -> bool {
+        match self.guild_id {
+            Some(owner) => guild_id == Some(owner),
+            None => self.user_id == Some(user_id),
+        }
+    }
+
     /// Function to update a playlist's name
     pub async fn update_playlist_name(
         pool: &PgPool,
@@ -121,10 +293,11 @@ impl Playlist {
             name: String,
             user_id: Option<i64>,
             privacy: String,
+            guild_id: Option<i64>,
         }
         let res = sqlx::query_as!(
             PlaylistOpt,
-            "UPDATE playlist SET name = $1 WHERE id = $2 RETURNING id, name, user_id, privacy",
+            "UPDATE playlist SET name = $1 WHERE id = $2 RETURNING id, name, user_id, privacy, guild_id",
             new_name,
             playlist_id
         )
@@ -136,6 +309,7 @@ impl Playlist {
             name: r.name,
             user_id: r.user_id,
             privacy: r.privacy,
+            guild_id: r.guild_id,
         })
         .map_err(CrackedError::SQLX)
     }
@@ -210,6 +384,93 @@ impl Playlist {
         .map(|r| r.into_iter().map(|r| r.into()).collect())
     }
 
+    /// Get the metadata for the tracks for a playlist along with the offsets of
+    /// each entry, in playlist order.
+    pub async fn get_track_entries_for_playlist(
+        pool: &PgPool,
+        playlist_id: i32,
+    ) -> Result<Vec<(Metadata, TrackOffsets)>, sqlx::Error> {
+        struct EntryRead {
+            id: i32,
+            track: Option<String>,
+            artist: Option<String>,
+            album: Option<String>,
+            date: Option<chrono::NaiveDate>,
+            channels: Option<i16>,
+            channel: Option<String>,
+            start_time: i64,
+            duration: i64,
+            sample_rate: Option<i32>,
+            source_url: Option<String>,
+            title: Option<String>,
+            thumbnail: Option<String>,
+            start_offset_ms: Option<i64>,
+            end_offset_ms: Option<i64>,
+        }
+        sqlx::query_as!(
+            EntryRead,
+            r#"
+                SELECT
+                    metadata.id, track, artist, album, date, channels, channel, start_time, duration, sample_rate, source_url, title, thumbnail,
+                    playlist_track.start_offset_ms, playlist_track.end_offset_ms
+                FROM
+                    (metadata INNER JOIN playlist_track ON playlist_track.metadata_id = metadata.id)
+                WHERE
+                    playlist_track.playlist_id = $1
+                ORDER BY
+                    playlist_track.id"#,
+            playlist_id,
+        )
+        .fetch_all(pool)
+        .await
+        .map(|r| {
+            r.into_iter()
+                .map(|r| {
+                    let offsets = TrackOffsets {
+                        start_ms: r.start_offset_ms,
+                        end_ms: r.end_offset_ms,
+                    };
+                    let metadata = Metadata {
+                        id: r.id,
+                        track: r.track,
+                        artist: r.artist,
+                        album: r.album,
+                        date: r.date,
+                        channels: r.channels,
+                        channel: r.channel,
+                        start_time: r.start_time,
+                        duration: r.duration,
+                        sample_rate: r.sample_rate,
+                        source_url: r.source_url,
+                        title: r.title,
+                        thumbnail: r.thumbnail,
+                    };
+                    (metadata, offsets)
+                })
+                .collect()
+        })
+    }
+
+    /// Get what to queue for a playlist: the evaluated rule for a smart playlist,
+    /// otherwise its saved entries with their offsets.
+    pub async fn get_queue_entries(
+        pool: &PgPool,
+        playlist_id: i32,
+        eval_ctx: &SmartEvalContext,
+    ) -> Result<Vec<(Metadata, TrackOffsets)>, CrackedError> {
+        match SmartPlaylist::get_rule(pool, playlist_id).await? {
+            Some(rule) => Ok(rule
+                .evaluate(pool, eval_ctx)
+                .await?
+                .into_iter()
+                .map(|m| (m, TrackOffsets::default()))
+                .collect()),
+            None => Self::get_track_entries_for_playlist(pool, playlist_id)
+                .await
+                .map_err(CrackedError::SQLX),
+        }
+    }
+
     /// Gets the metadata for a playlist for a user by playlist name.
     pub async fn get_track_metadata_for_playlist_name(
         pool: &PgPool,
@@ -223,7 +484,7 @@ impl Playlist {
                     metadata.id, track, artist, album, date, channels, channel, start_time, duration, sample_rate, source_url, title, thumbnail
                 FROM
                     (metadata INNER JOIN playlist_track ON playlist_track.metadata_id = metadata.id INNER JOIN playlist ON playlist_track.playlist_id = playlist.id)
-                WHERE playlist.name = $1 AND playlist.user_id = $2"#,
+                WHERE playlist.name = $1 AND playlist.user_id = $2 AND playlist.guild_id IS NULL"#,
             playlist_name,
             user_id,
         )
@@ -245,7 +506,7 @@ impl Playlist {
             I32Wrapper,
             r#"
                 SELECT id FROM playlist
-                WHERE name = $1 AND user_id = $2 
+                WHERE name = $1 AND user_id = $2 AND guild_id IS NULL
             "#,
             playlist_name,
             user_id,
@@ -256,3 +517,28 @@ impl Playlist {
         Self::delete_playlist(pool, playlist_id).await.map(|_| ())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_visible_to() {
+        let personal = Playlist {
+            user_id: Some(1),
+            ..Default::default()
+        };
+        assert!(personal.is_visible_to(1, None));
+        assert!(personal.is_visible_to(1, Some(7)));
+        assert!(!personal.is_visible_to(2, Some(7)));
+
+        let house = Playlist {
+            user_id: Some(1),
+            guild_id: Some(7),
+            ..Default::default()
+        };
+        assert!(house.is_visible_to(2, Some(7)));
+        assert!(!house.is_visible_to(1, Some(8)));
+        assert!(!house.is_visible_to(1, None));
+    }
+}
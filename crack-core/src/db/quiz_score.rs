@@ -0,0 +1,88 @@
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+/// A member's standing on a guild's music quiz leaderboard.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuizScore {
+    pub guild_id: i64,
+    pub user_id: i64,
+    pub points: i64,
+    pub games_played: i32,
+    pub updated_at: NaiveDateTime,
+}
+
+impl QuizScore {
+    /// Add a finished game's points to the leaderboard, counting a game played
+    /// for everyone who scored.
+    pub async fn record_game(
+        pool: &PgPool,
+        guild_id: i64,
+        scores: &[(i64, i64)],
+    ) -> Result<(), CrackedError> {
+        let mut tx = pool.begin().await?;
+        for (user_id, points) in scores {
+            sqlx::query!(
+                r#"
+                INSERT INTO quiz_score (guild_id, user_id, points, games_played)
+                VALUES ($1, $2, $3, 1)
+                ON CONFLICT (guild_id, user_id) DO UPDATE SET
+                    points = quiz_score.points + EXCLUDED.points,
+                    games_played = quiz_score.games_played + 1,
+                    updated_at = CURRENT_TIMESTAMP"#,
+                guild_id,
+                user_id,
+                points,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The guild's top scorers, most points first.
+    pub async fn leaderboard(
+        pool: &PgPool,
+        guild_id: i64,
+        limit: i64,
+    ) -> Result<Vec<QuizScore>, CrackedError> {
+        sqlx::query_as!(
+            QuizScore,
+            r#"
+            SELECT * FROM quiz_score
+            WHERE guild_id = $1
+            ORDER BY points DESC, games_played, user_id
+            LIMIT $2"#,
+            guild_id,
+            limit,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_quiz_score(pool: PgPool) -> Result<(), CrackedError> {
+        QuizScore::record_game(&pool, 1, &[(10, 5), (20, 3)]).await?;
+        QuizScore::record_game(&pool, 1, &[(20, 4)]).await?;
+        QuizScore::record_game(&pool, 2, &[(10, 1)]).await?;
+
+        let board = QuizScore::leaderboard(&pool, 1, 10).await?;
+        let board = board
+            .iter()
+            .map(|score| (score.user_id, score.points, score.games_played))
+            .collect::<Vec<_>>();
+        assert_eq!(board, vec![(20, 7, 2), (10, 5, 1)]);
+        assert_eq!(QuizScore::leaderboard(&pool, 1, 1).await?.len(), 1);
+        assert_eq!(QuizScore::leaderboard(&pool, 3, 10).await?, vec![]);
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+use crate::guild::role_menu::{RoleMenuMode, RoleMenuStyle};
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+/// A role menu members use to pick their own roles.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoleMenu {
+    pub id: i64,
+    pub guild_id: i64,
+    pub channel_id: i64,
+    /// The menu's message, once posted.
+    pub message_id: Option<i64>,
+    pub title: String,
+    pub mode: String,
+    pub style: String,
+    /// Most roles a member can hold from the menu in max mode.
+    pub max_roles: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// A role on a role menu.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoleMenuOption {
+    pub menu_id: i64,
+    pub role_id: i64,
+    pub label: String,
+    pub emoji: Option<String>,
+    pub position: i32,
+}
+
+impl RoleMenu {
+    pub fn mode(&self) -> RoleMenuMode {
+        self.mode.parse().unwrap_or_default()
+    }
+
+    pub fn style(&self) -> RoleMenuStyle {
+        self.style.parse().unwrap_or_default()
+    }
+
+    /// Create a role menu. It's posted separately, once it has roles.
+    pub async fn create(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: i64,
+        title: String,
+        mode: RoleMenuMode,
+        style: RoleMenuStyle,
+        max_roles: i32,
+    ) -> Result<RoleMenu, CrackedError> {
+        sqlx::query_as!(
+            RoleMenu,
+            r#"
+            INSERT INTO role_menu (guild_id, channel_id, title, mode, style, max_roles)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *"#,
+            guild_id,
+            channel_id,
+            title,
+            mode.as_str(),
+            style.as_str(),
+            max_roles,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get one of a guild's role menus.
+    pub async fn get(
+        pool: &PgPool,
+        guild_id: i64,
+        id: i64,
+    ) -> Result<Option<RoleMenu>, CrackedError> {
+        sqlx::query_as!(
+            RoleMenu,
+            "SELECT * FROM role_menu WHERE guild_id = $1 AND id = $2",
+            guild_id,
+            id,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a guild's role menus, oldest first.
+    pub async fn list(pool: &PgPool, guild_id: i64) -> Result<Vec<RoleMenu>, CrackedError> {
+        sqlx::query_as!(
+            RoleMenu,
+            "SELECT * FROM role_menu WHERE guild_id = $1 ORDER BY id",
+            guild_id,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Remember where the menu was posted.
+    pub async fn set_message(pool: &PgPool, id: i64, message_id: i64) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "UPDATE role_menu SET message_id = $2 WHERE id = $1",
+            id,
+            message_id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Delete a role menu and its roles. Returns whether it existed.
+    pub async fn delete(pool: &PgPool, guild_id: i64, id: i64) -> Result<bool, CrackedError> {
+        let result = sqlx::query!(
+            "DELETE FROM role_menu WHERE guild_id = $1 AND id = $2",
+            guild_id,
+            id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the menu's roles, in the order they were added.
+    pub async fn options(&self, pool: &PgPool) -> Result<Vec<RoleMenuOption>, CrackedError> {
+        sqlx::query_as!(
+            RoleMenuOption,
+            "SELECT * FROM role_menu_option WHERE menu_id = $1 ORDER BY position, role_id",
+            self.id,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+}
+
+impl RoleMenuOption {
+    /// Add a role to a menu, or update its label and emoji if it's already on it.
+    pub async fn add(
+        pool: &PgPool,
+        menu_id: i64,
+        role_id: i64,
+        label: String,
+        emoji: Option<String>,
+    ) -> Result<RoleMenuOption, CrackedError> {
+        sqlx::query_as!(
+            RoleMenuOption,
+            r#"
+            INSERT INTO role_menu_option (menu_id, role_id, label, emoji, position)
+            VALUES ($1, $2, $3, $4,
+                (SELECT COALESCE(MAX(position) + 1, 0) FROM role_menu_option WHERE menu_id = $1))
+            ON CONFLICT (menu_id, role_id)
+            DO UPDATE SET label = $3, emoji = $4
+            RETURNING *"#,
+            menu_id,
+            role_id,
+            label,
+            emoji,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Take a role off a menu. Returns whether it was on it.
+    pub async fn remove(pool: &PgPool, menu_id: i64, role_id: i64) -> Result<bool, CrackedError> {
+        let result = sqlx::query!(
+            "DELETE FROM role_menu_option WHERE menu_id = $1 AND role_id = $2",
+            menu_id,
+            role_id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::GuildEntity;
+    use ::serenity::small_fixed_array::FixedString;
+    use std::str::FromStr;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_role_menu(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+        let menu = RoleMenu::create(
+            &pool,
+            1,
+            2,
+            "Colours".to_string(),
+            RoleMenuMode::Unique,
+            RoleMenuStyle::Select,
+            0,
+        )
+        .await?;
+        assert_eq!(menu.mode(), RoleMenuMode::Unique);
+        assert_eq!(menu.style(), RoleMenuStyle::Select);
+        assert_eq!(menu.message_id, None);
+
+        RoleMenuOption::add(&pool, menu.id, 10, "Red".to_string(), None).await?;
+        RoleMenuOption::add(&pool, menu.id, 11, "Blue".to_string(), None).await?;
+        // Adding again updates in place.
+        RoleMenuOption::add(
+            &pool,
+            menu.id,
+            10,
+            "Red!".to_string(),
+            Some("🔴".to_string()),
+        )
+        .await?;
+        let options = menu.options(&pool).await?;
+        assert_eq!(
+            options
+                .iter()
+                .map(|o| (o.role_id, o.label.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(10, "Red!"), (11, "Blue")]
+        );
+        assert_eq!(options[0].emoji.as_deref(), Some("🔴"));
+
+        assert!(RoleMenuOption::remove(&pool, menu.id, 10).await?);
+        assert!(!RoleMenuOption::remove(&pool, menu.id, 10).await?);
+
+        RoleMenu::set_message(&pool, menu.id, 99).await?;
+        let menu = RoleMenu::get(&pool, 1, menu.id).await?.unwrap();
+        assert_eq!(menu.message_id, Some(99));
+        assert_eq!(RoleMenu::get(&pool, 2, menu.id).await?, None);
+        assert_eq!(RoleMenu::list(&pool, 1).await?, vec![menu.clone()]);
+
+        assert!(RoleMenu::delete(&pool, 1, menu.id).await?);
+        assert!(RoleMenu::list(&pool, 1).await?.is_empty());
+        assert!(menu.options(&pool).await?.is_empty());
+        Ok(())
+    }
+}
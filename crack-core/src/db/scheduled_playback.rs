@@ -0,0 +1,289 @@
+use crate::CrackedError;
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use sqlx::PgPool;
+use std::{fmt::Display, str::FromStr};
+
+/// What a scheduled playback job does when it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleAction {
+    /// Join the voice channel and queue a playlist.
+    Play,
+    /// Stop playback, clear the queue and leave.
+    Stop,
+}
+
+impl ScheduleAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleAction::Play => "play",
+            ScheduleAction::Stop => "stop",
+        }
+    }
+}
+
+impl Display for ScheduleAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ScheduleAction {
+    type Err = CrackedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "play" => Ok(ScheduleAction::Play),
+            "stop" => Ok(ScheduleAction::Stop),
+            _ => Err(CrackedError::Other("Unknown schedule action")),
+        }
+    }
+}
+
+/// A playback job scheduled to run at a given time.
+#[derive(Debug, Clone, Default)]
+pub struct ScheduledPlayback {
+    pub id: i64,
+    pub guild_id: i64,
+    /// Voice channel to join, only needed for `play`.
+    pub voice_channel_id: Option<i64>,
+    pub text_channel_id: Option<i64>,
+    pub user_id: i64,
+    pub action: String,
+    pub playlist_id: Option<i32>,
+    /// When the job runs, in UTC.
+    pub run_at: NaiveDateTime,
+    /// IANA name of the timezone the job was scheduled in.
+    pub timezone: String,
+    /// Run again every this many days.
+    pub repeat_days: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ScheduledPlayback {
+    /// Save a new job. The id and created_at of `job` are ignored.
+    pub async fn create(
+        pool: &PgPool,
+        job: &ScheduledPlayback,
+    ) -> Result<ScheduledPlayback, CrackedError> {
+        sqlx::query_as!(
+            ScheduledPlayback,
+            r#"
+            INSERT INTO scheduled_playback
+                (guild_id, voice_channel_id, text_channel_id, user_id, action, playlist_id, run_at, timezone, repeat_days)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *"#,
+            job.guild_id,
+            job.voice_channel_id,
+            job.text_channel_id,
+            job.user_id,
+            job.action,
+            job.playlist_id,
+            job.run_at,
+            job.timezone,
+            job.repeat_days,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get all jobs that are due to run at `now`.
+    pub async fn get_due(
+        pool: &PgPool,
+        now: NaiveDateTime,
+    ) -> Result<Vec<ScheduledPlayback>, CrackedError> {
+        sqlx::query_as!(
+            ScheduledPlayback,
+            "SELECT * FROM scheduled_playback WHERE run_at <= $1 ORDER BY run_at",
+            now,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a guild's upcoming jobs, soonest first.
+    pub async fn get_for_guild(
+        pool: &PgPool,
+        guild_id: i64,
+    ) -> Result<Vec<ScheduledPlayback>, CrackedError> {
+        sqlx::query_as!(
+            ScheduledPlayback,
+            "SELECT * FROM scheduled_playback WHERE guild_id = $1 ORDER BY run_at",
+            guild_id,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Cancel one of a guild's jobs. Returns whether anything was deleted.
+    pub async fn delete_for_guild(
+        pool: &PgPool,
+        id: i64,
+        guild_id: i64,
+    ) -> Result<bool, CrackedError> {
+        sqlx::query!(
+            "DELETE FROM scheduled_playback WHERE id = $1 AND guild_id = $2",
+            id,
+            guild_id,
+        )
+        .execute(pool)
+        .await
+        .map(|r| r.rows_affected() > 0)
+        .map_err(Into::into)
+    }
+
+    /// Delete a job once it has run.
+    pub async fn delete(pool: &PgPool, id: i64) -> Result<(), CrackedError> {
+        sqlx::query!("DELETE FROM scheduled_playback WHERE id = $1", id)
+            .execute(pool)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    /// Move a repeating job to its next run, recording how the last run went.
+    pub async fn reschedule(
+        pool: &PgPool,
+        id: i64,
+        run_at: NaiveDateTime,
+        last_error: Option<String>,
+    ) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "UPDATE scheduled_playback SET run_at = $2, last_error = $3 WHERE id = $1",
+            id,
+            run_at,
+            last_error,
+        )
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(Into::into)
+    }
+
+    /// The parsed action of this job.
+    pub fn action(&self) -> Result<ScheduleAction, CrackedError> {
+        self.action.parse()
+    }
+
+    /// The parsed timezone of this job, falling back to UTC.
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+
+    /// When the job runs, in UTC.
+    pub fn run_at_utc(&self) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&self.run_at)
+    }
+
+    /// The next time a repeating job should run after `now`, or `None` if it doesn't
+    /// repeat. Repeats keep the same local time in the job's timezone, so a weekly
+    /// 20:00 party stays at 20:00 across daylight saving changes. Runs missed while
+    /// the bot was down are skipped rather than replayed.
+    pub fn next_run_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let days = self.repeat_days.filter(|d| *d > 0)?;
+        let tz = self.tz();
+        let mut local = self.run_at_utc().with_timezone(&tz).naive_local();
+        loop {
+            local += ChronoDuration::days(days as i64);
+            // Skip over local times that don't exist (DST gaps).
+            if let Some(next) = tz.from_local_datetime(&local).earliest() {
+                let next = next.with_timezone(&Utc);
+                if next > now {
+                    return Some(next);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_schedule_action_roundtrip() {
+        for action in [ScheduleAction::Play, ScheduleAction::Stop] {
+            assert_eq!(
+                action.to_string().parse::<ScheduleAction>().unwrap(),
+                action
+            );
+        }
+        assert!("pause".parse::<ScheduleAction>().is_err());
+    }
+
+    #[test]
+    fn test_next_run_after_one_shot() {
+        let job = ScheduledPlayback {
+            run_at: utc("2024-03-01T19:00:00Z").naive_utc(),
+            ..Default::default()
+        };
+        assert_eq!(job.next_run_after(utc("2024-03-01T19:00:00Z")), None);
+    }
+
+    #[test]
+    fn test_next_run_after_keeps_local_time_across_dst() {
+        // 20:00 in Berlin is 19:00 UTC in winter and 18:00 UTC in summer.
+        let job = ScheduledPlayback {
+            run_at: utc("2024-03-29T19:00:00Z").naive_utc(),
+            timezone: "Europe/Berlin".to_string(),
+            repeat_days: Some(7),
+            ..Default::default()
+        };
+        assert_eq!(
+            job.next_run_after(utc("2024-03-29T19:00:00Z")),
+            Some(utc("2024-04-05T18:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_next_run_after_skips_missed_runs() {
+        let job = ScheduledPlayback {
+            run_at: utc("2024-01-05T20:00:00Z").naive_utc(),
+            timezone: "UTC".to_string(),
+            repeat_days: Some(7),
+            ..Default::default()
+        };
+        assert_eq!(
+            job.next_run_after(utc("2024-01-20T00:00:00Z")),
+            Some(utc("2024-01-26T20:00:00Z"))
+        );
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_scheduled_playback_due(pool: PgPool) -> Result<(), CrackedError> {
+        let job = ScheduledPlayback::create(
+            &pool,
+            &ScheduledPlayback {
+                guild_id: 1,
+                voice_channel_id: Some(2),
+                user_id: 1,
+                action: ScheduleAction::Stop.to_string(),
+                run_at: utc("2024-01-05T20:00:00Z").naive_utc(),
+                timezone: "UTC".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let before = utc("2024-01-05T19:59:59Z").naive_utc();
+        assert!(ScheduledPlayback::get_due(&pool, before).await?.is_empty());
+        let after = utc("2024-01-05T20:00:00Z").naive_utc();
+        assert_eq!(ScheduledPlayback::get_due(&pool, after).await?.len(), 1);
+
+        assert!(!ScheduledPlayback::delete_for_guild(&pool, job.id, 2).await?);
+        assert!(ScheduledPlayback::delete_for_guild(&pool, job.id, 1).await?);
+        assert!(ScheduledPlayback::get_for_guild(&pool, 1).await?.is_empty());
+        Ok(())
+    }
+}
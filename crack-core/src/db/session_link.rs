@@ -0,0 +1,165 @@
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+/// A link between two guilds' music sessions: the follower mirrors what the
+/// leader plays. Each guild can follow one leader, and both have to agree.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionLink {
+    pub follower_guild_id: i64,
+    pub leader_guild_id: i64,
+    pub leader_accepted: bool,
+    pub follower_accepted: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl SessionLink {
+    /// Whether both sides agreed.
+    pub fn is_active(&self) -> bool {
+        self.leader_accepted && self.follower_accepted
+    }
+
+    /// The leader agrees to lead the follower. Replaces a link the follower had
+    /// to another leader that it hadn't agreed to.
+    pub async fn lead(
+        pool: &PgPool,
+        leader_guild_id: i64,
+        follower_guild_id: i64,
+    ) -> Result<SessionLink, CrackedError> {
+        sqlx::query_as!(
+            SessionLink,
+            r#"
+            INSERT INTO session_link (follower_guild_id, leader_guild_id, leader_accepted)
+            VALUES ($1, $2, TRUE)
+            ON CONFLICT (follower_guild_id) DO UPDATE SET
+                leader_guild_id = EXCLUDED.leader_guild_id,
+                leader_accepted = TRUE,
+                follower_accepted = session_link.follower_accepted
+                    AND session_link.leader_guild_id = EXCLUDED.leader_guild_id
+            RETURNING *"#,
+            follower_guild_id,
+            leader_guild_id,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// The follower agrees to follow the leader, replacing any other leader.
+    pub async fn follow(
+        pool: &PgPool,
+        leader_guild_id: i64,
+        follower_guild_id: i64,
+    ) -> Result<SessionLink, CrackedError> {
+        sqlx::query_as!(
+            SessionLink,
+            r#"
+            INSERT INTO session_link (follower_guild_id, leader_guild_id, follower_accepted)
+            VALUES ($1, $2, TRUE)
+            ON CONFLICT (follower_guild_id) DO UPDATE SET
+                leader_guild_id = EXCLUDED.leader_guild_id,
+                follower_accepted = TRUE,
+                leader_accepted = session_link.leader_accepted
+                    AND session_link.leader_guild_id = EXCLUDED.leader_guild_id
+            RETURNING *"#,
+            follower_guild_id,
+            leader_guild_id,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// The guilds actively following a leader.
+    pub async fn followers(pool: &PgPool, leader_guild_id: i64) -> Result<Vec<i64>, CrackedError> {
+        let followers = sqlx::query_scalar!(
+            r#"
+            SELECT follower_guild_id FROM session_link
+            WHERE leader_guild_id = $1 AND leader_accepted AND follower_accepted
+            ORDER BY follower_guild_id"#,
+            leader_guild_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(followers)
+    }
+
+    /// The guild a follower is actively following, if any.
+    pub async fn leader_of(
+        pool: &PgPool,
+        follower_guild_id: i64,
+    ) -> Result<Option<i64>, CrackedError> {
+        let leader = sqlx::query_scalar!(
+            r#"
+            SELECT leader_guild_id FROM session_link
+            WHERE follower_guild_id = $1 AND leader_accepted AND follower_accepted"#,
+            follower_guild_id,
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(leader)
+    }
+
+    /// Every link a guild is part of, agreed to or not.
+    pub async fn by_guild(pool: &PgPool, guild_id: i64) -> Result<Vec<SessionLink>, CrackedError> {
+        sqlx::query_as!(
+            SessionLink,
+            r#"
+            SELECT * FROM session_link
+            WHERE leader_guild_id = $1 OR follower_guild_id = $1
+            ORDER BY created_at"#,
+            guild_id,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Remove the link between two guilds, whichever leads. Returns whether
+    /// there was one.
+    pub async fn delete(pool: &PgPool, guild_id: i64, other_id: i64) -> Result<bool, CrackedError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM session_link
+            WHERE (leader_guild_id = $1 AND follower_guild_id = $2)
+                OR (leader_guild_id = $2 AND follower_guild_id = $1)"#,
+            guild_id,
+            other_id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_session_link(pool: PgPool) -> Result<(), CrackedError> {
+        // Not active until both sides agree.
+        let link = SessionLink::lead(&pool, 1, 2).await?;
+        assert!(link.leader_accepted && !link.is_active());
+        assert!(SessionLink::followers(&pool, 1).await?.is_empty());
+        assert!(SessionLink::follow(&pool, 1, 2).await?.is_active());
+        assert_eq!(SessionLink::followers(&pool, 1).await?, vec![2]);
+        assert_eq!(SessionLink::leader_of(&pool, 2).await?, Some(1));
+
+        // Following someone else drops the old leader's agreement.
+        let link = SessionLink::follow(&pool, 3, 2).await?;
+        assert_eq!(link.leader_guild_id, 3);
+        assert!(!link.is_active());
+        assert!(SessionLink::followers(&pool, 1).await?.is_empty());
+        assert!(SessionLink::lead(&pool, 3, 2).await?.is_active());
+
+        SessionLink::follow(&pool, 3, 4).await?;
+        assert_eq!(SessionLink::by_guild(&pool, 3).await?.len(), 2);
+        assert!(SessionLink::delete(&pool, 2, 3).await?);
+        assert!(!SessionLink::delete(&pool, 3, 2).await?);
+        assert_eq!(SessionLink::leader_of(&pool, 2).await?, None);
+        Ok(())
+    }
+}
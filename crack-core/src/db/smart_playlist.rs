@@ -0,0 +1,800 @@
+use crate::db::{Metadata, MetadataRead};
+use crate::errors::CrackedError;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::fmt::{Display, Formatter};
+
+/// Default number of tracks a smart playlist evaluates to.
+pub const DEFAULT_SMART_PLAYLIST_LIMIT: i64 = 50;
+/// Upper bound on the number of tracks a smart playlist can evaluate to.
+pub const MAX_SMART_PLAYLIST_LIMIT: i64 = 500;
+
+/// A reference to a user inside a rule. `Me` is resolved when the rule is evaluated,
+/// so "my requests" means the user running `/playlist pplay`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRef {
+    Me,
+    Id(i64),
+}
+
+/// A reference to a guild inside a rule. `Here` is resolved when the rule is evaluated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuildRef {
+    Here,
+    Id(i64),
+}
+
+/// Filter part of a smart playlist rule, evaluated against `play_log`,
+/// `track_reaction` and `metadata`.
+///
+/// Reactions aren't stored per user, so the like and dislike rules compare a
+/// track's totals across all of its matching plays, not what any one user did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum SmartRule {
+    Guild(GuildRef),
+    RequestedBy(UserRef),
+    Artist(String),
+    Title(String),
+    PlayedWithinDays(i32),
+    #[serde(alias = "min_likes")]
+    MinTotalLikes(i32),
+    #[serde(alias = "max_dislikes")]
+    MaxTotalDislikes(i32),
+    And(Vec<SmartRule>),
+    Or(Vec<SmartRule>),
+    Not(Box<SmartRule>),
+}
+
+/// How the matched tracks are ordered before the limit is applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartOrder {
+    #[default]
+    MostPlayed,
+    MostLiked,
+    Recent,
+    Random,
+}
+
+/// A complete smart playlist definition, stored as JSON in the `smart_playlist` table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmartPlaylistRule {
+    pub filter: SmartRule,
+    pub order: SmartOrder,
+    pub limit: i64,
+}
+
+/// Values needed to resolve `me` and `here` when a rule is evaluated.
+#[derive(Debug, Clone, Copy)]
+pub struct SmartEvalContext {
+    pub user_id: i64,
+    pub guild_id: Option<i64>,
+}
+
+/// smart_playlist db structure.
+#[derive(Debug, Clone)]
+pub struct SmartPlaylist {
+    pub playlist_id: i32,
+    pub rule: SmartPlaylistRule,
+}
+
+impl Display for UserRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserRef::Me => f.write_str("me"),
+            UserRef::Id(id) => write!(f, "<@{}>", id),
+        }
+    }
+}
+
+impl Display for GuildRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuildRef::Here => f.write_str("here"),
+            GuildRef::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl Display for SmartRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmartRule::Guild(guild) => write!(f, "guild = {}", guild),
+            SmartRule::RequestedBy(user) => write!(f, "requested_by = {}", user),
+            SmartRule::Artist(artist) => write!(f, "artist = {:?}", artist),
+            SmartRule::Title(title) => write!(f, "title = {:?}", title),
+            SmartRule::PlayedWithinDays(days) => write!(f, "played_within = {}", days),
+            SmartRule::MinTotalLikes(likes) => write!(f, "likes >= {}", likes),
+            SmartRule::MaxTotalDislikes(dislikes) => write!(f, "dislikes <= {}", dislikes),
+            SmartRule::And(rules) => write_joined(f, rules, " and "),
+            SmartRule::Or(rules) => write_joined(f, rules, " or "),
+            SmartRule::Not(rule) => write!(f, "not ({})", rule),
+        }
+    }
+}
+
+fn write_joined(f: &mut Formatter<'_>, rules: &[SmartRule], sep: &str) -> std::fmt::Result {
+    let parts = rules
+        .iter()
+        .map(|r| match r {
+            SmartRule::And(_) | SmartRule::Or(_) => format!("({})", r),
+            _ => r.to_string(),
+        })
+        .collect::<Vec<_>>();
+    f.write_str(&parts.join(sep))
+}
+
+impl Display for SmartOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SmartOrder::MostPlayed => "plays",
+            SmartOrder::MostLiked => "likes",
+            SmartOrder::Recent => "recent",
+            SmartOrder::Random => "random",
+        })
+    }
+}
+
+impl Display for SmartPlaylistRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} order by {} limit {}",
+            self.filter, self.order, self.limit
+        )
+    }
+}
+
+impl SmartRule {
+    /// Whether this rule tests reaction totals, which only exist per track after
+    /// grouping, so it has to go in `HAVING` rather than `WHERE`.
+    fn is_aggregate(&self) -> bool {
+        match self {
+            SmartRule::MinTotalLikes(_) | SmartRule::MaxTotalDislikes(_) => true,
+            SmartRule::And(rules) | SmartRule::Or(rules) => rules.iter().any(Self::is_aggregate),
+            SmartRule::Not(rule) => rule.is_aggregate(),
+            _ => false,
+        }
+    }
+
+    /// The rules that all have to hold for this one to hold.
+    fn conjuncts(&self) -> Vec<&SmartRule> {
+        match self {
+            SmartRule::And(rules) => rules.iter().collect(),
+            rule => vec![rule],
+        }
+    }
+
+    /// Push the SQL for this rule onto the query as a boolean expression.
+    /// All user supplied values are bound, never interpolated. In `HAVING`,
+    /// per-play tests hold for a track if they hold for any of its plays.
+    fn push_sql(&self, qb: &mut QueryBuilder<'_, Postgres>, ctx: &SmartEvalContext, having: bool) {
+        let per_play = !matches!(
+            self,
+            SmartRule::MinTotalLikes(_)
+                | SmartRule::MaxTotalDislikes(_)
+                | SmartRule::And(_)
+                | SmartRule::Or(_)
+                | SmartRule::Not(_)
+        );
+        if having && per_play {
+            qb.push("BOOL_OR(");
+        }
+        match self {
+            SmartRule::Guild(guild) => {
+                let guild_id = match guild {
+                    GuildRef::Here => ctx.guild_id,
+                    GuildRef::Id(id) => Some(*id),
+                };
+                match guild_id {
+                    Some(id) => {
+                        qb.push("play_log.guild_id = ").push_bind(id);
+                    },
+                    // `here` outside of a guild matches nothing.
+                    None => {
+                        qb.push("FALSE");
+                    },
+                }
+            },
+            SmartRule::RequestedBy(user) => {
+                let user_id = match user {
+                    UserRef::Me => ctx.user_id,
+                    UserRef::Id(id) => *id,
+                };
+                qb.push("play_log.user_id = ").push_bind(user_id);
+            },
+            SmartRule::Artist(artist) => {
+                qb.push("metadata.artist ILIKE ")
+                    .push_bind(like_pattern(artist));
+            },
+            SmartRule::Title(title) => {
+                qb.push("metadata.title ILIKE ")
+                    .push_bind(like_pattern(title));
+            },
+            SmartRule::PlayedWithinDays(days) => {
+                qb.push("play_log.created_at >= NOW() - make_interval(days => ")
+                    .push_bind(*days)
+                    .push(")");
+            },
+            SmartRule::MinTotalLikes(likes) => {
+                qb.push("SUM(COALESCE(track_reaction.likes, 0)) >= ")
+                    .push_bind(*likes as i64);
+            },
+            SmartRule::MaxTotalDislikes(dislikes) => {
+                qb.push("SUM(COALESCE(track_reaction.dislikes, 0)) <= ")
+                    .push_bind(*dislikes as i64);
+            },
+            SmartRule::And(rules) => {
+                Self::push_joined(qb, ctx, rules.iter(), " AND ", "TRUE", having)
+            },
+            SmartRule::Or(rules) => {
+                Self::push_joined(qb, ctx, rules.iter(), " OR ", "FALSE", having)
+            },
+            SmartRule::Not(rule) => {
+                qb.push("NOT (");
+                rule.push_sql(qb, ctx, having);
+                qb.push(")");
+            },
+        }
+        if having && per_play {
+            qb.push(")");
+        }
+    }
+
+    fn push_joined<'r>(
+        qb: &mut QueryBuilder<'_, Postgres>,
+        ctx: &SmartEvalContext,
+        rules: impl ExactSizeIterator<Item = &'r SmartRule>,
+        sep: &str,
+        empty: &str,
+        having: bool,
+    ) {
+        match rules.len() {
+            0 => {
+                qb.push(empty);
+            },
+            1 => rules.for_each(|rule| rule.push_sql(qb, ctx, having)),
+            _ => {
+                qb.push("(");
+                for (i, rule) in rules.enumerate() {
+                    if i > 0 {
+                        qb.push(sep);
+                    }
+                    rule.push_sql(qb, ctx, having);
+                }
+                qb.push(")");
+            },
+        }
+    }
+}
+
+/// Escape the LIKE wildcards in a user supplied string and wrap it for a substring match.
+fn like_pattern(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+impl SmartPlaylistRule {
+    /// Create a rule with the default ordering and limit.
+    pub fn new(filter: SmartRule) -> Self {
+        Self {
+            filter,
+            order: SmartOrder::default(),
+            limit: DEFAULT_SMART_PLAYLIST_LIMIT,
+        }
+    }
+
+    /// Build the query that evaluates this rule.
+    pub fn build_query(&self, ctx: &SmartEvalContext) -> QueryBuilder<'static, Postgres> {
+        let mut qb = QueryBuilder::new(
+            r#"SELECT
+                metadata.id, metadata.track, metadata.artist, metadata.album, metadata.date, metadata.channels, metadata.channel, metadata.start_time, metadata.duration, metadata.sample_rate, metadata.source_url, metadata.title, metadata.thumbnail
+            FROM
+                play_log
+                JOIN metadata ON play_log.metadata_id = metadata.id
+                LEFT JOIN track_reaction ON track_reaction.play_log_id = play_log.id
+            WHERE "#,
+        );
+        // Reaction totals only exist once the plays are grouped by track, so
+        // the parts of the filter that test them are checked after grouping.
+        let (having, per_play): (Vec<_>, Vec<_>) = self
+            .filter
+            .conjuncts()
+            .into_iter()
+            .partition(|rule| rule.is_aggregate());
+        SmartRule::push_joined(&mut qb, ctx, per_play.into_iter(), " AND ", "TRUE", false);
+        qb.push(" GROUP BY metadata.id");
+        if !having.is_empty() {
+            qb.push(" HAVING ");
+            SmartRule::push_joined(&mut qb, ctx, having.into_iter(), " AND ", "TRUE", true);
+        }
+        qb.push(" ORDER BY ");
+        qb.push(match self.order {
+            SmartOrder::MostPlayed => "COUNT(play_log.id) DESC",
+            SmartOrder::MostLiked => "SUM(COALESCE(track_reaction.likes, 0)) DESC",
+            SmartOrder::Recent => "MAX(play_log.created_at) DESC",
+            SmartOrder::Random => "RANDOM()",
+        });
+        qb.push(" LIMIT ")
+            .push_bind(self.limit.clamp(1, MAX_SMART_PLAYLIST_LIMIT));
+        qb
+    }
+
+    /// Evaluate the rule against the play history and return the matching tracks.
+    pub async fn evaluate(
+        &self,
+        pool: &PgPool,
+        ctx: &SmartEvalContext,
+    ) -> Result<Vec<Metadata>, CrackedError> {
+        self.build_query(ctx)
+            .build_query_as::<MetadataRead>()
+            .fetch_all(pool)
+            .await
+            .map(|r| r.into_iter().map(Into::into).collect())
+            .map_err(CrackedError::SQLX)
+    }
+}
+
+impl std::str::FromStr for SmartPlaylistRule {
+    type Err = CrackedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse::parse_rule(s)
+    }
+}
+
+impl SmartPlaylist {
+    /// Attach a rule to an existing playlist, replacing any previous rule.
+    pub async fn set_rule(
+        pool: &PgPool,
+        playlist_id: i32,
+        rule: &SmartPlaylistRule,
+    ) -> Result<SmartPlaylist, CrackedError> {
+        let json = serde_json::to_value(rule)?;
+        sqlx::query!(
+            r#"
+            INSERT INTO smart_playlist (playlist_id, rule)
+            VALUES ($1, $2)
+            ON CONFLICT (playlist_id) DO UPDATE SET rule = $2
+            "#,
+            playlist_id,
+            json,
+        )
+        .execute(pool)
+        .await?;
+        Ok(SmartPlaylist {
+            playlist_id,
+            rule: rule.clone(),
+        })
+    }
+
+    /// Get the rule for a playlist, if the playlist is a smart playlist.
+    pub async fn get_rule(
+        pool: &PgPool,
+        playlist_id: i32,
+    ) -> Result<Option<SmartPlaylistRule>, CrackedError> {
+        let rec = sqlx::query!(
+            "SELECT rule FROM smart_playlist WHERE playlist_id = $1",
+            playlist_id
+        )
+        .fetch_optional(pool)
+        .await?;
+        match rec {
+            Some(r) => Ok(Some(serde_json::from_value(r.rule)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove the rule from a playlist.
+    pub async fn delete_rule(pool: &PgPool, playlist_id: i32) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "DELETE FROM smart_playlist WHERE playlist_id = $1",
+            playlist_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Parser for the textual rule expressions users type into `/playlist smart`.
+///
+/// ```text
+/// rule    := expr [ "order" "by" order ] [ "limit" number ]
+/// expr    := and_expr { "or" and_expr }
+/// and_expr:= unary { "and" unary }
+/// unary   := "not" unary | "(" expr ")" | term
+/// term    := field op value
+/// ```
+///
+/// e.g. `guild = here and played_within = 30 order by plays limit 25`.
+mod parse {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Token {
+        Word(String),
+        Str(String),
+        Op(&'static str),
+        LParen,
+        RParen,
+    }
+
+    fn tokenize(s: &str) -> Result<Vec<Token>, CrackedError> {
+        let mut tokens = Vec::new();
+        let mut chars = s.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            match c {
+                c if c.is_whitespace() => {
+                    chars.next();
+                },
+                '(' => {
+                    chars.next();
+                    tokens.push(Token::LParen);
+                },
+                ')' => {
+                    chars.next();
+                    tokens.push(Token::RParen);
+                },
+                '"' | '\'' => {
+                    let quote = c;
+                    chars.next();
+                    let mut value = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(c) if c == quote => break,
+                            Some(c) => value.push(c),
+                            None => return Err(CrackedError::Other("Unterminated string in rule")),
+                        }
+                    }
+                    tokens.push(Token::Str(value));
+                },
+                '=' => {
+                    chars.next();
+                    tokens.push(Token::Op("="));
+                },
+                '<' if chars.clone().nth(1) == Some('@') => {
+                    // User mention, e.g. <@123> or <@!123>.
+                    let mut word = String::new();
+                    for c in chars.by_ref() {
+                        word.push(c);
+                        if c == '>' {
+                            break;
+                        }
+                    }
+                    tokens.push(Token::Word(word));
+                },
+                '>' | '<' => {
+                    chars.next();
+                    if chars.next_if_eq(&'=').is_none() {
+                        return Err(CrackedError::Other("Expected `>=` or `<=` in rule"));
+                    }
+                    tokens.push(Token::Op(if c == '>' { ">=" } else { "<=" }));
+                },
+                _ => {
+                    let mut word = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || "()=<>\"'".contains(c) {
+                            break;
+                        }
+                        word.push(c);
+                        chars.next();
+                    }
+                    tokens.push(Token::Word(word));
+                },
+            }
+        }
+        Ok(tokens)
+    }
+
+    struct Parser {
+        tokens: Vec<Token>,
+        pos: usize,
+    }
+
+    impl Parser {
+        fn peek_keyword(&self, kw: &str) -> bool {
+            matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(kw))
+        }
+
+        fn next(&mut self) -> Option<Token> {
+            let tok = self.tokens.get(self.pos).cloned();
+            self.pos += 1;
+            tok
+        }
+
+        fn expect_keyword(&mut self, kw: &'static str) -> Result<(), CrackedError> {
+            if self.peek_keyword(kw) {
+                self.pos += 1;
+                Ok(())
+            } else {
+                Err(CrackedError::Other("Malformed `order by` clause in rule"))
+            }
+        }
+
+        fn expr(&mut self) -> Result<SmartRule, CrackedError> {
+            let mut rules = vec![self.and_expr()?];
+            while self.peek_keyword("or") {
+                self.pos += 1;
+                rules.push(self.and_expr()?);
+            }
+            Ok(if rules.len() == 1 {
+                rules.remove(0)
+            } else {
+                SmartRule::Or(rules)
+            })
+        }
+
+        fn and_expr(&mut self) -> Result<SmartRule, CrackedError> {
+            let mut rules = vec![self.unary()?];
+            while self.peek_keyword("and") {
+                self.pos += 1;
+                rules.push(self.unary()?);
+            }
+            Ok(if rules.len() == 1 {
+                rules.remove(0)
+            } else {
+                SmartRule::And(rules)
+            })
+        }
+
+        fn unary(&mut self) -> Result<SmartRule, CrackedError> {
+            if self.peek_keyword("not") {
+                self.pos += 1;
+                return Ok(SmartRule::Not(Box::new(self.unary()?)));
+            }
+            if self.tokens.get(self.pos) == Some(&Token::LParen) {
+                self.pos += 1;
+                let rule = self.expr()?;
+                return match self.next() {
+                    Some(Token::RParen) => Ok(rule),
+                    _ => Err(CrackedError::Other("Missing `)` in rule")),
+                };
+            }
+            self.term()
+        }
+
+        fn term(&mut self) -> Result<SmartRule, CrackedError> {
+            let field = match self.next() {
+                Some(Token::Word(w)) => w.to_lowercase(),
+                _ => return Err(CrackedError::Other("Expected a field name in rule")),
+            };
+            let op = match self.next() {
+                Some(Token::Op(op)) => op,
+                _ => return Err(CrackedError::Other("Expected `=`, `>=` or `<=` in rule")),
+            };
+            let value = match self.next() {
+                Some(Token::Word(w)) | Some(Token::Str(w)) => w,
+                _ => return Err(CrackedError::Other("Expected a value in rule")),
+            };
+            match (field.as_str(), op) {
+                ("guild", "=") => Ok(SmartRule::Guild(parse_guild(&value)?)),
+                ("user" | "requested_by" | "requester", "=") => {
+                    Ok(SmartRule::RequestedBy(parse_user(&value)?))
+                },
+                ("artist", "=") => Ok(SmartRule::Artist(value)),
+                ("title", "=") => Ok(SmartRule::Title(value)),
+                ("played_within" | "days", "=" | "<=") => {
+                    Ok(SmartRule::PlayedWithinDays(parse_int(&value)?))
+                },
+                ("likes", ">=") => Ok(SmartRule::MinTotalLikes(parse_int(&value)?)),
+                ("dislikes", "<=") => Ok(SmartRule::MaxTotalDislikes(parse_int(&value)?)),
+                _ => Err(CrackedError::Other("Unknown field or operator in rule")),
+            }
+        }
+    }
+
+    fn parse_int(value: &str) -> Result<i32, CrackedError> {
+        value
+            .trim_end_matches('d')
+            .parse::<i32>()
+            .ok()
+            .filter(|v| *v >= 0)
+            .ok_or(CrackedError::Other("Expected a positive number in rule"))
+    }
+
+    fn parse_user(value: &str) -> Result<UserRef, CrackedError> {
+        if value.eq_ignore_ascii_case("me") {
+            return Ok(UserRef::Me);
+        }
+        value
+            .trim_start_matches("<@")
+            .trim_start_matches('!')
+            .trim_end_matches('>')
+            .parse::<i64>()
+            .map(UserRef::Id)
+            .map_err(|_| CrackedError::Other("Expected `me` or a user mention in rule"))
+    }
+
+    fn parse_guild(value: &str) -> Result<GuildRef, CrackedError> {
+        if value.eq_ignore_ascii_case("here") {
+            return Ok(GuildRef::Here);
+        }
+        value
+            .parse::<i64>()
+            .map(GuildRef::Id)
+            .map_err(|_| CrackedError::Other("Expected `here` or a guild id in rule"))
+    }
+
+    pub(super) fn parse_rule(s: &str) -> Result<SmartPlaylistRule, CrackedError> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let filter = parser.expr()?;
+        let mut rule = SmartPlaylistRule::new(filter);
+        if parser.peek_keyword("order") {
+            parser.pos += 1;
+            parser.expect_keyword("by")?;
+            rule.order = match parser.next() {
+                Some(Token::Word(w)) => match w.to_lowercase().as_str() {
+                    "plays" | "most_played" => SmartOrder::MostPlayed,
+                    "likes" | "most_liked" => SmartOrder::MostLiked,
+                    "recent" => SmartOrder::Recent,
+                    "random" => SmartOrder::Random,
+                    _ => return Err(CrackedError::Other("Unknown ordering in rule")),
+                },
+                _ => return Err(CrackedError::Other("Malformed `order by` clause in rule")),
+            };
+        }
+        if parser.peek_keyword("limit") {
+            parser.pos += 1;
+            rule.limit = match parser.next() {
+                Some(Token::Word(w)) => parse_int(&w)? as i64,
+                _ => return Err(CrackedError::Other("Expected a number after `limit`")),
+            };
+        }
+        if parser.pos < parser.tokens.len() {
+            return Err(CrackedError::Other("Unexpected trailing input in rule"));
+        }
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CTX: SmartEvalContext = SmartEvalContext {
+        user_id: 42,
+        guild_id: Some(7),
+    };
+
+    #[test]
+    fn test_parse_most_played_in_guild() {
+        let rule: SmartPlaylistRule = "guild = here and played_within = 30 order by plays limit 25"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule,
+            SmartPlaylistRule {
+                filter: SmartRule::And(vec![
+                    SmartRule::Guild(GuildRef::Here),
+                    SmartRule::PlayedWithinDays(30),
+                ]),
+                order: SmartOrder::MostPlayed,
+                limit: 25,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_precedence_and_grouping() {
+        let rule: SmartPlaylistRule = r#"artist = "Daft Punk" or user = <@123> and likes >= 2"#
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule.filter,
+            SmartRule::Or(vec![
+                SmartRule::Artist("Daft Punk".to_string()),
+                SmartRule::And(vec![
+                    SmartRule::RequestedBy(UserRef::Id(123)),
+                    SmartRule::MinTotalLikes(2),
+                ]),
+            ])
+        );
+
+        let rule: SmartPlaylistRule = "not (user = me or likes >= 1)".parse().unwrap();
+        assert_eq!(
+            rule.filter,
+            SmartRule::Not(Box::new(SmartRule::Or(vec![
+                SmartRule::RequestedBy(UserRef::Me),
+                SmartRule::MinTotalLikes(1),
+            ])))
+        );
+        assert_eq!(rule.limit, DEFAULT_SMART_PLAYLIST_LIMIT);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("".parse::<SmartPlaylistRule>().is_err());
+        assert!("artist".parse::<SmartPlaylistRule>().is_err());
+        assert!("artist = 'unterminated"
+            .parse::<SmartPlaylistRule>()
+            .is_err());
+        assert!("likes = 3".parse::<SmartPlaylistRule>().is_err());
+        // Reactions aren't per user, so there's no "liked by me".
+        assert!("liked = true".parse::<SmartPlaylistRule>().is_err());
+        assert!("(user = me".parse::<SmartPlaylistRule>().is_err());
+        assert!("user = me order plays"
+            .parse::<SmartPlaylistRule>()
+            .is_err());
+        assert!("user = me limit 5 extra"
+            .parse::<SmartPlaylistRule>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let input = r#"(artist = "X" or title = "Y") and user = me order by recent limit 10"#;
+        let rule: SmartPlaylistRule = input.parse().unwrap();
+        let reparsed: SmartPlaylistRule = rule.to_string().parse().unwrap();
+        assert_eq!(rule, reparsed);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let rule: SmartPlaylistRule = "user = me and likes >= 1".parse().unwrap();
+        let json = serde_json::to_value(&rule).unwrap();
+        let back: SmartPlaylistRule = serde_json::from_value(json).unwrap();
+        assert_eq!(rule, back);
+
+        // Rules saved before the rename still load.
+        let old: SmartRule =
+            serde_json::from_value(serde_json::json!({"kind": "min_likes", "value": 1})).unwrap();
+        assert_eq!(old, SmartRule::MinTotalLikes(1));
+    }
+
+    #[test]
+    fn test_build_query_binds_values() {
+        let rule: SmartPlaylistRule = "guild = here and artist = '100%' order by likes limit 9000"
+            .parse()
+            .unwrap();
+        let qb = rule.build_query(&CTX);
+        let sql = qb.sql();
+        assert!(sql.contains("(play_log.guild_id = $1 AND metadata.artist ILIKE $2)"));
+        assert!(sql
+            .contains("GROUP BY metadata.id ORDER BY SUM(COALESCE(track_reaction.likes, 0)) DESC"));
+        assert!(sql.ends_with("LIMIT $3"));
+        assert!(!sql.contains("100"));
+    }
+
+    #[test]
+    fn test_build_query_reaction_totals_after_grouping() {
+        let rule: SmartPlaylistRule = "guild = here and likes >= 3 and dislikes <= 1"
+            .parse()
+            .unwrap();
+        let qb = rule.build_query(&CTX);
+        assert!(qb.sql().contains(
+            "WHERE play_log.guild_id = $1 GROUP BY metadata.id HAVING (SUM(COALESCE(track_reaction.likes, 0)) >= $2 AND SUM(COALESCE(track_reaction.dislikes, 0)) <= $3) ORDER BY"
+        ));
+
+        let rule: SmartPlaylistRule = "user = me or likes >= 3".parse().unwrap();
+        let qb = rule.build_query(&CTX);
+        assert!(qb.sql().contains(
+            "WHERE TRUE GROUP BY metadata.id HAVING (BOOL_OR(play_log.user_id = $1) OR SUM(COALESCE(track_reaction.likes, 0)) >= $2) ORDER BY"
+        ));
+    }
+
+    #[test]
+    fn test_build_query_here_outside_guild() {
+        let rule = SmartPlaylistRule::new(SmartRule::Guild(GuildRef::Here));
+        let ctx = SmartEvalContext {
+            user_id: 1,
+            guild_id: None,
+        };
+        let qb = rule.build_query(&ctx);
+        assert!(qb.sql().contains("WHERE FALSE GROUP BY"));
+    }
+
+    #[test]
+    fn test_like_pattern_escapes() {
+        assert_eq!(like_pattern("a%b_c"), "%a\\%b\\_c%");
+    }
+}
@@ -0,0 +1,183 @@
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+/// A soundboard clip. Its audio is a file in the guild's soundboard directory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SoundboardClip {
+    pub id: i64,
+    pub guild_id: i64,
+    pub name: String,
+    pub file_name: String,
+    pub duration_ms: i32,
+    pub size_bytes: i32,
+    pub uploaded_by: i64,
+    pub plays: i64,
+    pub created_at: NaiveDateTime,
+}
+
+impl SoundboardClip {
+    /// Add a clip. `None` if the guild already has one by that name.
+    pub async fn create(
+        pool: &PgPool,
+        guild_id: i64,
+        name: &str,
+        file_name: &str,
+        duration_ms: i32,
+        size_bytes: i32,
+        uploaded_by: i64,
+    ) -> Result<Option<SoundboardClip>, CrackedError> {
+        sqlx::query_as!(
+            SoundboardClip,
+            r#"
+            INSERT INTO soundboard_clip (guild_id, name, file_name, duration_ms, size_bytes,
+                uploaded_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (guild_id, name) DO NOTHING
+            RETURNING *"#,
+            guild_id,
+            name,
+            file_name,
+            duration_ms,
+            size_bytes,
+            uploaded_by,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a guild's clip by name.
+    pub async fn get(
+        pool: &PgPool,
+        guild_id: i64,
+        name: &str,
+    ) -> Result<Option<SoundboardClip>, CrackedError> {
+        sqlx::query_as!(
+            SoundboardClip,
+            "SELECT * FROM soundboard_clip WHERE guild_id = $1 AND name = $2",
+            guild_id,
+            name,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a guild's clips, by name.
+    pub async fn list(pool: &PgPool, guild_id: i64) -> Result<Vec<SoundboardClip>, CrackedError> {
+        sqlx::query_as!(
+            SoundboardClip,
+            "SELECT * FROM soundboard_clip WHERE guild_id = $1 ORDER BY name",
+            guild_id,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// The names of a guild's clips containing `search`, most played first.
+    pub async fn search(
+        pool: &PgPool,
+        guild_id: i64,
+        search: &str,
+        limit: i64,
+    ) -> Result<Vec<String>, CrackedError> {
+        let names = sqlx::query_scalar!(
+            r#"
+            SELECT name FROM soundboard_clip
+            WHERE guild_id = $1 AND strpos(name, $2) > 0
+            ORDER BY strpos(name, $2) = 1 DESC, plays DESC, name
+            LIMIT $3"#,
+            guild_id,
+            search,
+            limit,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(names)
+    }
+
+    /// Count a play of a clip.
+    pub async fn played(pool: &PgPool, id: i64) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "UPDATE soundboard_clip SET plays = plays + 1 WHERE id = $1",
+            id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remove a guild's clip by name, returning it so its file can be deleted.
+    pub async fn delete(
+        pool: &PgPool,
+        guild_id: i64,
+        name: &str,
+    ) -> Result<Option<SoundboardClip>, CrackedError> {
+        sqlx::query_as!(
+            SoundboardClip,
+            "DELETE FROM soundboard_clip WHERE guild_id = $1 AND name = $2 RETURNING *",
+            guild_id,
+            name,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::GuildEntity;
+    use ::serenity::small_fixed_array::FixedString;
+    use std::str::FromStr;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_soundboard_clip(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+        let airhorn = SoundboardClip::create(&pool, 1, "airhorn", "airhorn.mp3", 2_500, 40_000, 10)
+            .await?
+            .unwrap();
+        assert_eq!(airhorn.plays, 0);
+        SoundboardClip::create(&pool, 1, "horn", "horn.ogg", 1_000, 9_000, 10).await?;
+        SoundboardClip::create(&pool, 1, "rimshot", "rimshot.wav", 900, 80_000, 20).await?;
+        // Names are unique per guild.
+        assert!(
+            SoundboardClip::create(&pool, 1, "airhorn", "airhorn.wav", 1, 1, 20)
+                .await?
+                .is_none()
+        );
+
+        SoundboardClip::played(&pool, airhorn.id).await?;
+        assert_eq!(
+            SoundboardClip::get(&pool, 1, "airhorn")
+                .await?
+                .unwrap()
+                .plays,
+            1
+        );
+        // Prefix matches first, then the most played.
+        assert_eq!(
+            SoundboardClip::search(&pool, 1, "horn", 10).await?,
+            vec!["horn".to_string(), "airhorn".to_string()]
+        );
+        assert_eq!(SoundboardClip::search(&pool, 1, "", 2).await?.len(), 2);
+        assert_eq!(SoundboardClip::list(&pool, 1).await?.len(), 3);
+
+        let deleted = SoundboardClip::delete(&pool, 1, "horn").await?.unwrap();
+        assert_eq!(deleted.file_name, "horn.ogg");
+        assert!(SoundboardClip::delete(&pool, 1, "horn").await?.is_none());
+        assert!(SoundboardClip::get(&pool, 2, "airhorn").await?.is_none());
+        Ok(())
+    }
+}
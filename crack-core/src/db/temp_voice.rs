@@ -0,0 +1,236 @@
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+/// A voice channel that creates a temp channel for whoever joins it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TempVoiceHub {
+    pub channel_id: i64,
+    pub guild_id: i64,
+    /// The template temp channels are named from.
+    pub name_template: String,
+    /// The temp channels' user limit, 0 for none.
+    pub user_limit: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// A temp voice channel and the member who owns it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TempVoiceChannel {
+    pub channel_id: i64,
+    pub guild_id: i64,
+    /// The hub it was created from.
+    pub hub_id: i64,
+    pub owner_id: i64,
+    pub locked: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl TempVoiceHub {
+    /// Make a channel a hub, or update its template and limit if it's one already.
+    pub async fn set(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: i64,
+        name_template: String,
+        user_limit: i32,
+    ) -> Result<TempVoiceHub, CrackedError> {
+        sqlx::query_as!(
+            TempVoiceHub,
+            r#"
+            INSERT INTO temp_voice_hub (channel_id, guild_id, name_template, user_limit)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (channel_id)
+            DO UPDATE SET name_template = $3, user_limit = $4
+            RETURNING *"#,
+            channel_id,
+            guild_id,
+            name_template,
+            user_limit,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a hub by its channel.
+    pub async fn get(pool: &PgPool, channel_id: i64) -> Result<Option<TempVoiceHub>, CrackedError> {
+        sqlx::query_as!(
+            TempVoiceHub,
+            "SELECT * FROM temp_voice_hub WHERE channel_id = $1",
+            channel_id,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a guild's hubs, oldest first.
+    pub async fn list(pool: &PgPool, guild_id: i64) -> Result<Vec<TempVoiceHub>, CrackedError> {
+        sqlx::query_as!(
+            TempVoiceHub,
+            "SELECT * FROM temp_voice_hub WHERE guild_id = $1 ORDER BY created_at, channel_id",
+            guild_id,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Stop a channel being a hub. Its temp channels stay until they empty.
+    /// Returns whether it was one.
+    pub async fn delete(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: i64,
+    ) -> Result<bool, CrackedError> {
+        let result = sqlx::query!(
+            "DELETE FROM temp_voice_hub WHERE guild_id = $1 AND channel_id = $2",
+            guild_id,
+            channel_id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+impl TempVoiceChannel {
+    /// Record a temp channel created for a member.
+    pub async fn create(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: i64,
+        hub_id: i64,
+        owner_id: i64,
+    ) -> Result<TempVoiceChannel, CrackedError> {
+        sqlx::query_as!(
+            TempVoiceChannel,
+            r#"
+            INSERT INTO temp_voice_channel (channel_id, guild_id, hub_id, owner_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *"#,
+            channel_id,
+            guild_id,
+            hub_id,
+            owner_id,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a temp channel.
+    pub async fn get(
+        pool: &PgPool,
+        channel_id: i64,
+    ) -> Result<Option<TempVoiceChannel>, CrackedError> {
+        sqlx::query_as!(
+            TempVoiceChannel,
+            "SELECT * FROM temp_voice_channel WHERE channel_id = $1",
+            channel_id,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get every guild's temp channels, e.g. to clean up after a restart.
+    pub async fn list_all(pool: &PgPool) -> Result<Vec<TempVoiceChannel>, CrackedError> {
+        sqlx::query_as!(
+            TempVoiceChannel,
+            "SELECT * FROM temp_voice_channel ORDER BY guild_id, channel_id",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Give a temp channel to another member.
+    pub async fn set_owner(
+        pool: &PgPool,
+        channel_id: i64,
+        owner_id: i64,
+    ) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "UPDATE temp_voice_channel SET owner_id = $2 WHERE channel_id = $1",
+            channel_id,
+            owner_id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remember whether a temp channel is locked.
+    pub async fn set_locked(
+        pool: &PgPool,
+        channel_id: i64,
+        locked: bool,
+    ) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "UPDATE temp_voice_channel SET locked = $2 WHERE channel_id = $1",
+            channel_id,
+            locked,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Forget a temp channel. Returns whether it was one.
+    pub async fn delete(pool: &PgPool, channel_id: i64) -> Result<bool, CrackedError> {
+        let result = sqlx::query!(
+            "DELETE FROM temp_voice_channel WHERE channel_id = $1",
+            channel_id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::GuildEntity;
+    use ::serenity::small_fixed_array::FixedString;
+    use std::str::FromStr;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_temp_voice(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+
+        TempVoiceHub::set(&pool, 1, 10, "{user.name}'s channel".to_string(), 0).await?;
+        let hub = TempVoiceHub::set(&pool, 1, 10, "Lounge".to_string(), 5).await?;
+        assert_eq!(hub.name_template, "Lounge");
+        assert_eq!(hub.user_limit, 5);
+        assert_eq!(TempVoiceHub::get(&pool, 10).await?, Some(hub.clone()));
+        assert_eq!(TempVoiceHub::list(&pool, 1).await?, vec![hub]);
+
+        let channel = TempVoiceChannel::create(&pool, 1, 20, 10, 100).await?;
+        assert!(!channel.locked);
+        TempVoiceChannel::set_owner(&pool, 20, 200).await?;
+        TempVoiceChannel::set_locked(&pool, 20, true).await?;
+        let channel = TempVoiceChannel::get(&pool, 20).await?.unwrap();
+        assert_eq!(channel.owner_id, 200);
+        assert!(channel.locked);
+
+        // Removing the hub leaves its temp channels to empty out.
+        assert!(TempVoiceHub::delete(&pool, 1, 10).await?);
+        assert!(!TempVoiceHub::delete(&pool, 1, 10).await?);
+        assert_eq!(TempVoiceChannel::list_all(&pool).await?, vec![channel]);
+
+        assert!(TempVoiceChannel::delete(&pool, 20).await?);
+        assert_eq!(TempVoiceChannel::get(&pool, 20).await?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+/// A new member's pending or finished verification.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerificationChallenge {
+    pub id: i64,
+    pub guild_id: i64,
+    pub user_id: i64,
+    /// The expected captcha answer. Passwords come from the guild's settings and
+    /// the rules method has no answer.
+    pub answer: Option<String>,
+    pub attempts: i32,
+    pub created_at: NaiveDateTime,
+    /// When to kick the member if they haven't passed, in UTC.
+    pub expires_at: Option<NaiveDateTime>,
+    pub verified_at: Option<NaiveDateTime>,
+    pub kicked_at: Option<NaiveDateTime>,
+}
+
+impl VerificationChallenge {
+    /// Start a member's verification, replacing any earlier one, e.g. when they
+    /// leave and join again.
+    pub async fn start(
+        pool: &PgPool,
+        guild_id: i64,
+        user_id: i64,
+        answer: Option<String>,
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<VerificationChallenge, CrackedError> {
+        sqlx::query_as!(
+            VerificationChallenge,
+            r#"
+            INSERT INTO verification_challenge (guild_id, user_id, answer, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (guild_id, user_id)
+            DO UPDATE SET answer = $3, expires_at = $4, attempts = 0, created_at = CURRENT_TIMESTAMP,
+                verified_at = NULL, kicked_at = NULL
+            RETURNING *"#,
+            guild_id,
+            user_id,
+            answer,
+            expires_at,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a member's verification.
+    pub async fn get(
+        pool: &PgPool,
+        guild_id: i64,
+        user_id: i64,
+    ) -> Result<Option<VerificationChallenge>, CrackedError> {
+        sqlx::query_as!(
+            VerificationChallenge,
+            "SELECT * FROM verification_challenge WHERE guild_id = $1 AND user_id = $2",
+            guild_id,
+            user_id,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Count a wrong answer and set the answer for the next attempt.
+    pub async fn record_failure(
+        pool: &PgPool,
+        id: i64,
+        answer: Option<String>,
+    ) -> Result<VerificationChallenge, CrackedError> {
+        sqlx::query_as!(
+            VerificationChallenge,
+            r#"
+            UPDATE verification_challenge SET attempts = attempts + 1, answer = $2
+            WHERE id = $1
+            RETURNING *"#,
+            id,
+            answer,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Mark a member as verified.
+    pub async fn mark_verified(
+        pool: &PgPool,
+        id: i64,
+        now: NaiveDateTime,
+    ) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "UPDATE verification_challenge SET verified_at = $2 WHERE id = $1",
+            id,
+            now,
+        )
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(Into::into)
+    }
+
+    /// Get every unverified member whose time ran out by `now`.
+    pub async fn get_expired(
+        pool: &PgPool,
+        now: NaiveDateTime,
+    ) -> Result<Vec<VerificationChallenge>, CrackedError> {
+        sqlx::query_as!(
+            VerificationChallenge,
+            r#"
+            SELECT * FROM verification_challenge
+            WHERE expires_at <= $1 AND verified_at IS NULL AND kicked_at IS NULL
+            ORDER BY expires_at"#,
+            now,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Mark an unverified member as kicked.
+    pub async fn mark_kicked(
+        pool: &PgPool,
+        id: i64,
+        now: NaiveDateTime,
+    ) -> Result<(), CrackedError> {
+        sqlx::query!(
+            "UPDATE verification_challenge SET kicked_at = $2 WHERE id = $1",
+            id,
+            now,
+        )
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(Into::into)
+    }
+
+    /// Whether the member has passed.
+    pub fn is_verified(&self) -> bool {
+        self.verified_at.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::GuildEntity;
+    use ::serenity::small_fixed_array::FixedString;
+    use chrono::DateTime;
+    use std::str::FromStr;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    fn naive(s: &str) -> NaiveDateTime {
+        DateTime::parse_from_rfc3339(s).unwrap().naive_utc()
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_verification_challenge(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+        let expires_at = Some(naive("2024-01-06T20:00:00Z"));
+
+        let challenge =
+            VerificationChallenge::start(&pool, 1, 10, Some("ABC123".to_string()), expires_at)
+                .await?;
+        assert_eq!(challenge.attempts, 0);
+        assert!(!challenge.is_verified());
+
+        let challenge =
+            VerificationChallenge::record_failure(&pool, challenge.id, Some("XYZ789".to_string()))
+                .await?;
+        assert_eq!(challenge.attempts, 1);
+        assert_eq!(challenge.answer.as_deref(), Some("XYZ789"));
+
+        // Not expired yet.
+        let expired =
+            VerificationChallenge::get_expired(&pool, naive("2024-01-06T19:00:00Z")).await?;
+        assert!(expired.is_empty());
+        let expired =
+            VerificationChallenge::get_expired(&pool, naive("2024-01-06T21:00:00Z")).await?;
+        assert_eq!(expired, vec![challenge.clone()]);
+
+        // Verified members are never kicked.
+        VerificationChallenge::mark_verified(&pool, challenge.id, naive("2024-01-06T19:30:00Z"))
+            .await?;
+        let expired =
+            VerificationChallenge::get_expired(&pool, naive("2024-01-06T21:00:00Z")).await?;
+        assert!(expired.is_empty());
+
+        // Joining again starts over.
+        let challenge = VerificationChallenge::start(&pool, 1, 10, None, expires_at).await?;
+        assert_eq!(challenge.attempts, 0);
+        assert!(!challenge.is_verified());
+        assert_eq!(
+            VerificationChallenge::get(&pool, 1, 10).await?,
+            Some(challenge)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+use crate::guild::voice_activity::VoiceFlags;
+use crate::CrackedError;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+/// A member's time in a voice channel without their channel or voice state
+/// changing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VoiceActivity {
+    pub id: i64,
+    pub guild_id: i64,
+    pub user_id: i64,
+    pub channel_id: i64,
+    pub started_at: NaiveDateTime,
+    /// When they were last seen like this.
+    pub ended_at: NaiveDateTime,
+    pub self_mute: bool,
+    pub self_deaf: bool,
+    pub mute: bool,
+    pub deaf: bool,
+    pub streaming: bool,
+    pub video: bool,
+    pub afk: bool,
+}
+
+/// A member's voice time in seconds. Self-muted and AFK time doesn't count.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VoiceTotals {
+    pub voice: i64,
+    pub streaming: i64,
+    pub video: i64,
+}
+
+/// How long a member was in voice, in seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoiceTime {
+    pub user_id: i64,
+    pub seconds: i64,
+}
+
+impl VoiceActivity {
+    /// Start an interval for a member seen in a voice channel at `now`.
+    pub async fn open(
+        pool: &PgPool,
+        guild_id: i64,
+        user_id: i64,
+        channel_id: i64,
+        flags: VoiceFlags,
+        now: NaiveDateTime,
+    ) -> Result<VoiceActivity, CrackedError> {
+        sqlx::query_as!(
+            VoiceActivity,
+            r#"
+            INSERT INTO voice_activity (guild_id, user_id, channel_id, started_at, ended_at,
+                self_mute, self_deaf, mute, deaf, streaming, video, afk)
+            VALUES ($1, $2, $3, $4, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING *"#,
+            guild_id,
+            user_id,
+            channel_id,
+            now,
+            flags.self_mute,
+            flags.self_deaf,
+            flags.mute,
+            flags.deaf,
+            flags.streaming,
+            flags.video,
+            flags.afk,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Extend intervals whose members are still the same at `now`.
+    pub async fn extend(
+        pool: &PgPool,
+        ids: &[i64],
+        now: NaiveDateTime,
+    ) -> Result<(), CrackedError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        sqlx::query!(
+            "UPDATE voice_activity SET ended_at = $2 WHERE id = ANY($1)",
+            ids,
+            now,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// A member's voice, streaming and camera time since `since`, or ever.
+    pub async fn totals(
+        pool: &PgPool,
+        guild_id: i64,
+        user_id: i64,
+        since: Option<NaiveDateTime>,
+    ) -> Result<VoiceTotals, CrackedError> {
+        sqlx::query_as!(
+            VoiceTotals,
+            r#"
+            SELECT
+                COALESCE(SUM(seconds) FILTER (WHERE NOT self_mute AND NOT self_deaf AND NOT afk), 0)::BIGINT AS "voice!",
+                COALESCE(SUM(seconds) FILTER (WHERE streaming AND NOT afk), 0)::BIGINT AS "streaming!",
+                COALESCE(SUM(seconds) FILTER (WHERE video AND NOT afk), 0)::BIGINT AS "video!"
+            FROM (
+                SELECT EXTRACT(EPOCH FROM ended_at - GREATEST(started_at, COALESCE($3, started_at))) AS seconds,
+                    self_mute, self_deaf, streaming, video, afk
+                FROM voice_activity
+                WHERE guild_id = $1 AND user_id = $2
+                    AND ($3::TIMESTAMP IS NULL OR ended_at > $3)
+            ) intervals"#,
+            guild_id,
+            user_id,
+            since,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// The members with the most voice time since `since`, or ever, in a
+    /// channel or the whole guild. Self-muted and AFK time doesn't count.
+    pub async fn leaderboard(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: Option<i64>,
+        since: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<VoiceTime>, CrackedError> {
+        sqlx::query_as!(
+            VoiceTime,
+            r#"
+            SELECT user_id AS "user_id!",
+                SUM(EXTRACT(EPOCH FROM ended_at - GREATEST(started_at, COALESCE($3, started_at))))::BIGINT AS "seconds!"
+            FROM voice_activity
+            WHERE guild_id = $1
+                AND ($2::BIGINT IS NULL OR channel_id = $2)
+                AND ($3::TIMESTAMP IS NULL OR ended_at > $3)
+                AND NOT self_mute AND NOT self_deaf AND NOT afk
+            GROUP BY user_id
+            HAVING SUM(EXTRACT(EPOCH FROM ended_at - GREATEST(started_at, COALESCE($3, started_at)))) > 0
+            ORDER BY 2 DESC, user_id
+            LIMIT $4"#,
+            guild_id,
+            channel_id,
+            since,
+            limit,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::GuildEntity;
+    use ::serenity::small_fixed_array::FixedString;
+    use chrono::{DateTime, Duration};
+    use std::str::FromStr;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_voice_activity(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+        let start = DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .naive_utc();
+        let at = |minutes: i64| start + Duration::minutes(minutes);
+        let talking = VoiceFlags::default();
+        let streaming = VoiceFlags {
+            streaming: true,
+            ..Default::default()
+        };
+        let muted = VoiceFlags {
+            self_mute: true,
+            ..Default::default()
+        };
+        let afk = VoiceFlags {
+            afk: true,
+            ..Default::default()
+        };
+
+        // Member 10: an hour talking and half an hour streaming in channel 100,
+        // then muted for an hour.
+        let a = VoiceActivity::open(&pool, 1, 10, 100, talking, at(0)).await?;
+        let b = VoiceActivity::open(&pool, 1, 10, 100, streaming, at(60)).await?;
+        let c = VoiceActivity::open(&pool, 1, 10, 100, muted, at(90)).await?;
+        VoiceActivity::extend(&pool, &[a.id], at(60)).await?;
+        VoiceActivity::extend(&pool, &[b.id], at(90)).await?;
+        VoiceActivity::extend(&pool, &[c.id], at(150)).await?;
+        // Member 20: two hours in channel 200 and some time AFK.
+        let d = VoiceActivity::open(&pool, 1, 20, 200, talking, at(0)).await?;
+        let e = VoiceActivity::open(&pool, 1, 20, 300, afk, at(120)).await?;
+        VoiceActivity::extend(&pool, &[d.id], at(120)).await?;
+        VoiceActivity::extend(&pool, &[e.id], at(600)).await?;
+
+        assert_eq!(
+            VoiceActivity::totals(&pool, 1, 10, None).await?,
+            VoiceTotals {
+                voice: 90 * 60,
+                streaming: 30 * 60,
+                video: 0,
+            }
+        );
+        // Only the part of an interval after `since` counts.
+        let totals = VoiceActivity::totals(&pool, 1, 10, Some(at(75))).await?;
+        assert_eq!(totals.voice, 15 * 60);
+        assert_eq!(VoiceActivity::totals(&pool, 1, 30, None).await?.voice, 0);
+
+        assert_eq!(
+            VoiceActivity::leaderboard(&pool, 1, None, None, 10).await?,
+            vec![
+                VoiceTime {
+                    user_id: 20,
+                    seconds: 120 * 60
+                },
+                VoiceTime {
+                    user_id: 10,
+                    seconds: 90 * 60
+                },
+            ]
+        );
+        let in_channel = VoiceActivity::leaderboard(&pool, 1, Some(100), None, 10).await?;
+        assert_eq!(in_channel.len(), 1);
+        assert_eq!(in_channel[0].user_id, 10);
+        // AFK time only, so nobody's on the board.
+        assert!(
+            VoiceActivity::leaderboard(&pool, 1, None, Some(at(150)), 10)
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+}
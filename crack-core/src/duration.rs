@@ -0,0 +1,454 @@
+//! Human friendly durations shared by every command that takes one.
+//!
+//! Accepted forms:
+//! - unit sequences: `90s`, `1h30m`, `2d 12h`, `1 hour 15 minutes`,
+//! - ISO-8601 durations: `PT1H30M`, `P2DT3H`, `P1W`,
+//! - points in time, counted from now: `until 18:00`, `until 6:30pm`,
+//!   `until 2024-06-01 18:00`, or an RFC 3339 timestamp.
+use chrono::{DateTime, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+/// The longest timeout Discord allows.
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(28 * 24 * 60 * 60);
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// Why a duration couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DurationError {
+    Empty,
+    /// A number without a unit, e.g. `30`.
+    MissingUnit(String),
+    /// A unit without a number, e.g. `h`.
+    MissingNumber(String),
+    UnknownUnit(String),
+    /// ISO-8601 years and months have no fixed length.
+    AmbiguousIsoUnit(char),
+    InvalidIso(String),
+    InvalidTime(String),
+    /// `until` a time that has already passed.
+    InPast,
+    Zero,
+    TooLong,
+}
+
+impl Display for DurationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("No duration given. Try e.g. `30m` or `1h30m`."),
+            Self::MissingUnit(n) => write!(f, "`{n}` needs a unit, e.g. `{n}m` or `{n}h`."),
+            Self::MissingNumber(unit) => write!(f, "`{unit}` needs a number in front of it."),
+            Self::UnknownUnit(unit) => write!(
+                f,
+                "Unknown time unit `{unit}`. Use `s`, `m`, `h`, `d` or `w`."
+            ),
+            Self::AmbiguousIsoUnit(unit) => write!(
+                f,
+                "`{unit}` isn't supported in ISO-8601 durations, use days or weeks instead."
+            ),
+            Self::InvalidIso(input) => write!(f, "`{input}` isn't a valid ISO-8601 duration."),
+            Self::InvalidTime(input) => write!(
+                f,
+                "Couldn't read the time `{input}`. Try e.g. `until 18:00`."
+            ),
+            Self::InPast => f.write_str("That time has already passed."),
+            Self::Zero => f.write_str("The duration must be longer than zero."),
+            Self::TooLong => f.write_str("That duration is too long."),
+        }
+    }
+}
+
+impl std::error::Error for DurationError {}
+
+/// Parse a duration, resolving `until ...` against the current time in UTC.
+pub fn parse_duration(input: &str) -> Result<Duration, DurationError> {
+    parse_duration_at(input, Utc::now(), Tz::UTC)
+}
+
+/// Parse a duration, resolving `until ...` against `now` in `tz`. Clock times
+/// that have already passed today mean tomorrow.
+pub fn parse_duration_at(
+    input: &str,
+    now: DateTime<Utc>,
+    tz: Tz,
+) -> Result<Duration, DurationError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(DurationError::Empty);
+    }
+    let lower = input.to_lowercase();
+
+    let duration = if let Some(time) = lower.strip_prefix("until ") {
+        until(time.trim(), now, tz)?
+    } else if let Ok(at) = DateTime::parse_from_rfc3339(input) {
+        positive_until(at.with_timezone(&Utc), now)?
+    } else if lower.starts_with('p') {
+        parse_iso(&lower)?
+    } else {
+        parse_units(&lower)?
+    };
+    if duration.is_zero() {
+        return Err(DurationError::Zero);
+    }
+    Ok(duration)
+}
+
+/// Clamp a duration to what Discord accepts for a timeout.
+pub fn clamp_timeout(duration: Duration) -> Duration {
+    duration.min(MAX_TIMEOUT)
+}
+
+/// Format a duration as e.g. `2d 3h` or `45m`, the same way it's parsed.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let parts = [
+        (secs / DAY, "d"),
+        ((secs / HOUR) % 24, "h"),
+        ((secs / MINUTE) % 60, "m"),
+        (secs % MINUTE, "s"),
+    ];
+    let formatted = parts
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if formatted.is_empty() {
+        "0s".to_string()
+    } else {
+        formatted
+    }
+}
+
+/// A duration as a command argument, e.g. `/timeout user:@x duration:1h30m`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HumanDuration(pub Duration);
+
+impl HumanDuration {
+    /// The duration, clamped to what Discord accepts for a timeout.
+    pub fn clamp_to_timeout(self) -> Duration {
+        clamp_timeout(self.0)
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = DurationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_duration(s).map(HumanDuration)
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl Display for HumanDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format_duration(self.0))
+    }
+}
+
+/// Seconds in one of a unit, e.g. `h` or `hours`.
+fn unit_seconds(unit: &str) -> Option<u64> {
+    match unit {
+        "s" | "sec" | "secs" | "second" | "seconds" => Some(1),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(MINUTE),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(HOUR),
+        "d" | "day" | "days" => Some(DAY),
+        "w" | "wk" | "wks" | "week" | "weeks" => Some(WEEK),
+        _ => None,
+    }
+}
+
+/// Parse a sequence of `<number><unit>`, with optional spaces or commas between.
+fn parse_units(input: &str) -> Result<Duration, DurationError> {
+    let mut total: u64 = 0;
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace() || *c == ',') {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut number = String::new();
+        while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+            number.push(*c);
+            chars.next();
+        }
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut unit = String::new();
+        while let Some(c) = chars.peek().filter(|c| c.is_alphabetic()) {
+            unit.push(*c);
+            chars.next();
+        }
+
+        match (number.is_empty(), unit.is_empty()) {
+            (true, true) => {
+                // Neither a digit nor a letter, e.g. `1h-30m`.
+                let rest = chars.collect::<String>();
+                return Err(DurationError::UnknownUnit(rest));
+            },
+            (true, false) => return Err(DurationError::MissingNumber(unit)),
+            (false, true) => return Err(DurationError::MissingUnit(number)),
+            (false, false) => {},
+        }
+        let n = number.parse::<u64>().map_err(|_| DurationError::TooLong)?;
+        let secs = unit_seconds(&unit).ok_or(DurationError::UnknownUnit(unit))?;
+        total = n
+            .checked_mul(secs)
+            .and_then(|s| total.checked_add(s))
+            .ok_or(DurationError::TooLong)?;
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Parse an ISO-8601 duration such as `p1dt2h30m` (already lowercased).
+fn parse_iso(input: &str) -> Result<Duration, DurationError> {
+    let invalid = || DurationError::InvalidIso(input.to_uppercase());
+    let body = input.strip_prefix('p').ok_or_else(invalid)?;
+    if body.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total: u64 = 0;
+    let mut in_time = false;
+    let mut number = String::new();
+    let mut seen_component = false;
+    for c in body.chars() {
+        match c {
+            't' if !in_time && number.is_empty() => in_time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                if number.is_empty() {
+                    return Err(invalid());
+                }
+                let secs = match (in_time, unit) {
+                    (false, 'w') => WEEK,
+                    (false, 'd') => DAY,
+                    (false, 'y') | (false, 'm') => {
+                        return Err(DurationError::AmbiguousIsoUnit(unit.to_ascii_uppercase()))
+                    },
+                    (true, 'h') => HOUR,
+                    (true, 'm') => MINUTE,
+                    (true, 's') => 1,
+                    _ => return Err(invalid()),
+                };
+                let n = number.parse::<u64>().map_err(|_| DurationError::TooLong)?;
+                total = n
+                    .checked_mul(secs)
+                    .and_then(|s| total.checked_add(s))
+                    .ok_or(DurationError::TooLong)?;
+                number.clear();
+                seen_component = true;
+            },
+        }
+    }
+    if !number.is_empty() || !seen_component {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Time from `now` until `input`, a clock time or a date and time in `tz`.
+fn until(input: &str, now: DateTime<Utc>, tz: Tz) -> Result<Duration, DurationError> {
+    let invalid = || DurationError::InvalidTime(input.to_string());
+
+    if let Ok(at) = DateTime::parse_from_rfc3339(&input.to_uppercase()) {
+        return positive_until(at.with_timezone(&Utc), now);
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M") {
+        let at = tz
+            .from_local_datetime(&naive)
+            .earliest()
+            .ok_or_else(invalid)?;
+        return positive_until(at.with_timezone(&Utc), now);
+    }
+
+    let time = ["%H:%M", "%H:%M:%S", "%I:%M%p"]
+        .iter()
+        .find_map(|fmt| NaiveTime::parse_from_str(input, fmt).ok())
+        .ok_or_else(invalid)?;
+    let today = now.with_timezone(&tz).date_naive();
+    [today, today.succ_opt().ok_or_else(invalid)?]
+        .into_iter()
+        .filter_map(|date| tz.from_local_datetime(&date.and_time(time)).earliest())
+        .map(|at| at.with_timezone(&Utc))
+        .find(|at| *at > now)
+        .ok_or_else(invalid)
+        .and_then(|at| positive_until(at, now))
+}
+
+fn positive_until(at: DateTime<Utc>, now: DateTime<Utc>) -> Result<Duration, DurationError> {
+    (at - now).to_std().map_err(|_| DurationError::InPast)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn test_parse_units() {
+        assert_eq!(parse_duration("90s"), Ok(secs(90)));
+        assert_eq!(parse_duration("1h30m"), Ok(secs(90 * MINUTE)));
+        assert_eq!(parse_duration("2d"), Ok(secs(2 * DAY)));
+        assert_eq!(parse_duration("1w"), Ok(secs(WEEK)));
+        assert_eq!(
+            parse_duration("1d1h1m1s"),
+            Ok(secs(DAY + HOUR + MINUTE + 1))
+        );
+        assert_eq!(
+            parse_duration("1 hour, 15 Minutes"),
+            Ok(secs(HOUR + 15 * MINUTE))
+        );
+    }
+
+    #[test]
+    fn test_parse_units_errors() {
+        assert_eq!(parse_duration(""), Err(DurationError::Empty));
+        assert_eq!(
+            parse_duration("30"),
+            Err(DurationError::MissingUnit("30".to_string()))
+        );
+        assert_eq!(
+            parse_duration("h"),
+            Err(DurationError::MissingNumber("h".to_string()))
+        );
+        assert_eq!(
+            parse_duration("10x"),
+            Err(DurationError::UnknownUnit("x".to_string()))
+        );
+        assert_eq!(parse_duration("0s"), Err(DurationError::Zero));
+        assert_eq!(
+            parse_duration("99999999999999999999s"),
+            Err(DurationError::TooLong)
+        );
+        assert!(parse_duration("1h-30m").is_err());
+    }
+
+    #[test]
+    fn test_parse_iso() {
+        assert_eq!(parse_duration("PT1H30M"), Ok(secs(90 * MINUTE)));
+        assert_eq!(parse_duration("P2DT3H"), Ok(secs(2 * DAY + 3 * HOUR)));
+        assert_eq!(parse_duration("P1W"), Ok(secs(WEEK)));
+        assert_eq!(parse_duration("pt45s"), Ok(secs(45)));
+        assert_eq!(
+            parse_duration("P1M"),
+            Err(DurationError::AmbiguousIsoUnit('M'))
+        );
+        assert!(parse_duration("P").is_err());
+        assert!(parse_duration("PT").is_err());
+        assert!(parse_duration("P1H").is_err());
+        assert!(parse_duration("PT5").is_err());
+    }
+
+    #[test]
+    fn test_parse_until() {
+        let now = utc("2024-01-10T12:00:00Z");
+        assert_eq!(
+            parse_duration_at("until 18:00", now, Tz::UTC),
+            Ok(secs(6 * HOUR))
+        );
+        // Already passed today, so tomorrow.
+        assert_eq!(
+            parse_duration_at("until 11:00", now, Tz::UTC),
+            Ok(secs(23 * HOUR))
+        );
+        assert_eq!(
+            parse_duration_at("until 6:30pm", now, Tz::UTC),
+            Ok(secs(6 * HOUR + 30 * MINUTE))
+        );
+        // 18:00 in Berlin in winter is 17:00 UTC.
+        assert_eq!(
+            parse_duration_at("until 18:00", now, Tz::Europe__Berlin),
+            Ok(secs(5 * HOUR))
+        );
+        assert_eq!(
+            parse_duration_at("until 2024-01-11 12:00", now, Tz::UTC),
+            Ok(secs(DAY))
+        );
+        assert_eq!(
+            parse_duration_at("2024-01-10T13:00:00Z", now, Tz::UTC),
+            Ok(secs(HOUR))
+        );
+        assert_eq!(
+            parse_duration_at("until 2024-01-01 12:00", now, Tz::UTC),
+            Err(DurationError::InPast)
+        );
+        assert_eq!(
+            parse_duration_at("until teatime", now, Tz::UTC),
+            Err(DurationError::InvalidTime("teatime".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_clamp_timeout() {
+        assert_eq!(clamp_timeout(secs(HOUR)), secs(HOUR));
+        assert_eq!(clamp_timeout(secs(40 * DAY)), MAX_TIMEOUT);
+        let arg: HumanDuration = "60d".parse().unwrap();
+        assert_eq!(arg.clamp_to_timeout(), MAX_TIMEOUT);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(secs(0)), "0s");
+        assert_eq!(format_duration(secs(45 * MINUTE)), "45m");
+        assert_eq!(format_duration(secs(2 * DAY + 3 * HOUR)), "2d 3h");
+    }
+
+    proptest! {
+        #[test]
+        fn prop_format_roundtrips(n in 1u64..10_000_000_000) {
+            prop_assert_eq!(parse_duration(&format_duration(secs(n))), Ok(secs(n)));
+        }
+
+        #[test]
+        fn prop_iso_roundtrips(d in 0u64..1000, h in 0u64..24, m in 0u64..60, s in 0u64..60) {
+            prop_assume!(d + h + m + s > 0);
+            let iso = format!("P{d}DT{h}H{m}M{s}S");
+            prop_assert_eq!(
+                parse_duration(&iso),
+                Ok(secs(d * DAY + h * HOUR + m * MINUTE + s))
+            );
+        }
+
+        #[test]
+        fn prop_units_add_up(parts in proptest::collection::vec((1u64..1000, 0usize..5), 1..6)) {
+            let units = ["s", "m", "h", "d", "w"];
+            let input = parts
+                .iter()
+                .map(|(n, u)| format!("{n}{}", units[*u]))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let expected = parts
+                .iter()
+                .map(|(n, u)| n * unit_seconds(units[*u]).unwrap())
+                .sum::<u64>();
+            prop_assert_eq!(parse_duration(&input), Ok(secs(expected)));
+        }
+
+        #[test]
+        fn prop_never_panics(input in "\\PC*") {
+            let _ = parse_duration_at(&input, utc("2024-01-10T12:00:00Z"), Tz::UTC);
+        }
+    }
+}
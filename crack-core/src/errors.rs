@@ -1,13 +1,17 @@
+use crate::duration::DurationError;
 use crate::messaging::messages::{
+    CASE_NOT_FOUND, COMMAND_NOT_ALLOWED, COMMAND_NOT_ALLOWED_IN_CHANNEL, COMMAND_NOT_ALLOWED_USE,
     EMPTY_SEARCH_RESULT, FAIL_ANOTHER_CHANNEL, FAIL_AUDIO_STREAM_RUSTY_YTDL_METADATA,
     FAIL_AUTHOR_DISCONNECTED, FAIL_AUTHOR_NOT_FOUND, FAIL_EMPTY_VECTOR, FAIL_INSERT,
     FAIL_INVALID_PERMS, FAIL_INVALID_TOPGG_TOKEN, FAIL_NOTHING_PLAYING, FAIL_NOT_IMPLEMENTED,
-    FAIL_NO_QUERY_PROVIDED, FAIL_NO_SONGBIRD, FAIL_NO_VIRUSTOTAL_API_KEY, FAIL_NO_VOICE_CONNECTION,
-    FAIL_PARSE_TIME, FAIL_PLAYLIST_FETCH, FAIL_RESUME, FAIL_TO_SET_CHANNEL_SIZE,
-    FAIL_WRONG_CHANNEL, GUILD_ONLY, NOT_IN_MUSIC_CHANNEL, NO_CHANNEL_ID, NO_DATABASE_POOL,
-    NO_GUILD_CACHED, NO_GUILD_ID, NO_GUILD_SETTINGS, NO_METADATA, NO_USER_AUTOPLAY, QUEUE_IS_EMPTY,
-    ROLE_NOT_FOUND, SPOTIFY_AUTH_FAILED, UNAUTHORIZED_USER,
+    FAIL_NO_FREE_SESSION, FAIL_NO_QUERY_PROVIDED, FAIL_NO_SONGBIRD, FAIL_NO_VIRUSTOTAL_API_KEY,
+    FAIL_NO_VOICE_CONNECTION, FAIL_PARSE_TIME, FAIL_PLAYLIST_FETCH, FAIL_RESUME,
+    FAIL_TO_SET_CHANNEL_SIZE, FAIL_WRONG_CHANNEL, GUILD_ONLY, NOT_IN_MUSIC_CHANNEL, NO_CHANNEL_ID,
+    NO_DATABASE_POOL, NO_GUILD_CACHED, NO_GUILD_ID, NO_GUILD_SETTINGS, NO_METADATA,
+    NO_USER_AUTOPLAY, QUEUE_IS_EMPTY, ROLE_NOT_FOUND, SPOTIFY_AUTH_FAILED, UNAUTHORIZED_USER,
 };
+use crate::messaging::template::TemplateError;
+use crate::soundboard::ClipError;
 use std::borrow::Cow;
 pub use std::error::Error as StdError;
 pub type Error = Box<dyn StdError + Send + Sync>;
@@ -39,12 +43,16 @@ pub enum CrackedError {
     AuthorDisconnected(Mention),
     AuthorNotFound,
     Anyhow(anyhow::Error),
+    CaseNotFound(i64),
+    Clip(ClipError),
     #[cfg(feature = "crack-gpt")]
     CrackGPT(Error),
     CommandFailed(&'static str, ExitStatus, Cow<'static, str>),
+    CommandNotAllowed(Cow<'static, str>),
+    CommandNotAllowedInChannel(Option<ChannelId>),
     CommandNotFound(Cow<'static, str>),
     Control(ControlError),
-    DurationParseError(&'static str, &'static str),
+    DurationParseError(DurationError),
     EmptySearchResult,
     EmptyVector(&'static str),
     FailedResume,
@@ -65,6 +73,7 @@ pub enum CrackedError {
     NotImplemented,
     NoTrackName,
     NoDatabasePool,
+    NoFreeSession,
     NoGuildCached,
     NoGuildId,
     NoGuildForChannelId(ChannelId),
@@ -91,6 +100,7 @@ pub enum CrackedError {
     Songbird(Error),
     Serenity(SerenityError),
     SpotifyAuth,
+    Template(TemplateError),
     TrackResolveError(crack_types::TrackResolveError),
     TrackFail(Error),
     UrlParse(url::ParseError),
@@ -129,18 +139,31 @@ impl Display for CrackedError {
                 f.write_fmt(format_args!("{} {}", FAIL_ANOTHER_CHANNEL, mention))
             },
             Self::Anyhow(err) => f.write_str(&format!("{err}")),
+            Self::CaseNotFound(number) => f.write_str(&format!("{} #{}", CASE_NOT_FOUND, number)),
             #[cfg(feature = "crack-gpt")]
             Self::CrackGPT(err) => f.write_str(&format!("{err}")),
             Self::CommandFailed(program, status, output) => f.write_str(&format!(
                 "Command `{program}` failed with status `{status}` and output `{output}`"
             )),
+            Self::CommandNotAllowed(command) => {
+                f.write_str(&format!("{} `{}`", COMMAND_NOT_ALLOWED, command))
+            },
+            Self::CommandNotAllowedInChannel(channel_id) => match channel_id {
+                Some(channel_id) => f.write_str(&format!(
+                    "{} {} {}",
+                    COMMAND_NOT_ALLOWED_IN_CHANNEL,
+                    COMMAND_NOT_ALLOWED_USE,
+                    channel_id.mention()
+                )),
+                None => f.write_str(COMMAND_NOT_ALLOWED_IN_CHANNEL),
+            },
             Self::CommandNotFound(command) => {
                 f.write_fmt(format_args!("Command does not exist: {}", command))
             },
+            Self::Clip(err) => f.write_str(&format!("{err}")),
             Self::Control(err) => f.write_str(&format!("{err}")),
-            Self::DurationParseError(d, u) => {
-                f.write_str(&format!("Failed to parse duration `{d}` and `{u}`",))
-            },
+            Self::DurationParseError(err) => f.write_str(&format!("{err}")),
+            Self::Template(err) => f.write_str(&format!("{err}")),
             Self::EmptySearchResult => f.write_str(EMPTY_SEARCH_RESULT),
             Self::EmptyVector(msg) => f.write_str(&format!("{} {}", FAIL_EMPTY_VECTOR, msg)),
             Self::FailedResume => f.write_str(FAIL_RESUME),
@@ -171,6 +194,7 @@ impl Display for CrackedError {
             Self::NotImplemented => f.write_str(FAIL_NOT_IMPLEMENTED),
             Self::NoTrackName => f.write_str("No track name"),
             Self::NoDatabasePool => f.write_str(NO_DATABASE_POOL),
+            Self::NoFreeSession => f.write_str(FAIL_NO_FREE_SESSION),
             Self::NoGuildCached => f.write_str(NO_GUILD_CACHED),
             Self::NoGuildId => f.write_str(NO_GUILD_ID),
             Self::NoGuildForChannelId(channel_id) => {
@@ -233,6 +257,9 @@ impl PartialEq for CrackedError {
             (Self::AlreadyConnected(l0), Self::AlreadyConnected(r0)) => {
                 l0.to_string() == r0.to_string()
             },
+            (Self::DurationParseError(l0), Self::DurationParseError(r0)) => l0 == r0,
+            (Self::Template(l0), Self::Template(r0)) => l0 == r0,
+            (Self::Clip(l0), Self::Clip(r0)) => l0 == r0,
             (Self::Serenity(l0), Self::Serenity(r0)) => format!("{l0:?}") == format!("{r0:?}"),
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
@@ -246,6 +273,27 @@ impl From<TrackResolveError> for CrackedError {
     }
 }
 
+/// Provides an implementation to convert a [`DurationError`] to a [`CrackedError`].
+impl From<DurationError> for CrackedError {
+    fn from(err: DurationError) -> Self {
+        Self::DurationParseError(err)
+    }
+}
+
+/// Provides an implementation to convert a [`TemplateError`] to a [`CrackedError`].
+impl From<TemplateError> for CrackedError {
+    fn from(err: TemplateError) -> Self {
+        Self::Template(err)
+    }
+}
+
+/// Provides an implementation to convert a [`ClipError`] to a [`CrackedError`].
+impl From<ClipError> for CrackedError {
+    fn from(err: ClipError) -> Self {
+        Self::Clip(err)
+    }
+}
+
 /// Provides an implementation to convert a [`ControlError`] to a [`CrackedError`].
 impl From<ControlError> for CrackedError {
     fn from(err: ControlError) -> Self {
@@ -20,6 +20,8 @@ pub struct GuildCache {
     pub time_ordered_messages: BTreeMap<DateTime<Utc>, Message>,
     pub queue_messages: Vec<QueueMessage>,
     pub current_skip_votes: HashSet<UserId>,
+    /// Votes per DJ-only action (e.g. `remove 3`) for the current track.
+    pub dj_votes: HashMap<String, HashSet<UserId>>,
 }
 
 impl Default for GuildCache {
@@ -29,6 +31,7 @@ impl Default for GuildCache {
             time_ordered_messages: BTreeMap::new(),
             queue_messages: Vec::new(),
             current_skip_votes: HashSet::new(),
+            dj_votes: HashMap::new(),
         }
     }
 }
@@ -52,6 +55,7 @@ mod test {
         assert_eq!(guild_cache.time_ordered_messages.len(), 0);
         assert_eq!(guild_cache.queue_messages.len(), 0);
         assert_eq!(guild_cache.current_skip_votes.len(), 0);
+        assert_eq!(guild_cache.dj_votes.len(), 0);
     }
 
     // Test inserting queue messages and getting them out
@@ -0,0 +1,512 @@
+//! Camera policies. Members in a policed voice channel with their camera off
+//! past the grace period climb an escalation ladder, one step every
+//! `step_seconds`: a DM warning, a server mute, a move to another channel and
+//! finally a disconnect. Turning the camera on clears the ladder and lifts the
+//! mute.
+use crate::{db::CaseAction, CrackedError};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serenity::all::{ChannelId, GuildId, RoleId, UserId};
+use sqlx::PgPool;
+use std::{collections::HashMap, fmt::Display};
+
+/// A voice channel's camera policy.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CamPolicy {
+    pub channel_id: i64,
+    pub guild_id: i64,
+    /// How long a camera can be off before the first warning.
+    pub grace_seconds: i32,
+    /// How long between steps of the ladder.
+    pub step_seconds: i32,
+    /// Where members are moved to. Without one the ladder skips the move.
+    pub move_channel_id: Option<i64>,
+    pub exempt_roles: Vec<i64>,
+    /// The DM warning, instead of the default.
+    pub warn_message: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl CamPolicy {
+    pub fn grace(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.grace_seconds.max(0) as i64)
+    }
+
+    pub fn step(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.step_seconds.max(0) as i64)
+    }
+
+    pub fn move_channel(&self) -> Option<ChannelId> {
+        self.move_channel_id.map(|id| ChannelId::new(id as u64))
+    }
+
+    /// Whether any of a member's roles are exempt.
+    pub fn is_exempt(&self, roles: &[RoleId]) -> bool {
+        roles
+            .iter()
+            .any(|role| self.exempt_roles.contains(&(role.get() as i64)))
+    }
+
+    /// Police a channel, or change its policy. Exempt roles are kept.
+    pub async fn set(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: i64,
+        grace_seconds: i32,
+        step_seconds: i32,
+        move_channel_id: Option<i64>,
+        warn_message: Option<String>,
+    ) -> Result<CamPolicy, CrackedError> {
+        sqlx::query_as!(
+            CamPolicy,
+            r#"
+            INSERT INTO cam_policy (channel_id, guild_id, grace_seconds, step_seconds,
+                move_channel_id, warn_message)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (guild_id, channel_id)
+            DO UPDATE SET grace_seconds = $3, step_seconds = $4, move_channel_id = $5,
+                warn_message = $6
+            RETURNING *"#,
+            channel_id,
+            guild_id,
+            grace_seconds,
+            step_seconds,
+            move_channel_id,
+            warn_message,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Police a channel from the bot config, unless it already has a policy.
+    pub async fn seed(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: i64,
+        grace_seconds: i32,
+    ) -> Result<(), CrackedError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO cam_policy (channel_id, guild_id, grace_seconds)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (guild_id, channel_id) DO NOTHING"#,
+            channel_id,
+            guild_id,
+            grace_seconds,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get a channel's policy.
+    pub async fn get(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: i64,
+    ) -> Result<Option<CamPolicy>, CrackedError> {
+        sqlx::query_as!(
+            CamPolicy,
+            "SELECT * FROM cam_policy WHERE guild_id = $1 AND channel_id = $2",
+            guild_id,
+            channel_id,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get a guild's policies, oldest first.
+    pub async fn list(pool: &PgPool, guild_id: i64) -> Result<Vec<CamPolicy>, CrackedError> {
+        sqlx::query_as!(
+            CamPolicy,
+            "SELECT * FROM cam_policy WHERE guild_id = $1 ORDER BY created_at, channel_id",
+            guild_id,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Get every guild's policies by guild and channel, for the voice poll.
+    pub async fn by_guild(
+        pool: &PgPool,
+    ) -> Result<HashMap<GuildId, HashMap<ChannelId, CamPolicy>>, CrackedError> {
+        let policies = sqlx::query_as!(CamPolicy, "SELECT * FROM cam_policy")
+            .fetch_all(pool)
+            .await?;
+        let mut by_guild: HashMap<GuildId, HashMap<ChannelId, CamPolicy>> = HashMap::new();
+        for policy in policies {
+            by_guild
+                .entry(GuildId::new(policy.guild_id as u64))
+                .or_default()
+                .insert(ChannelId::new(policy.channel_id as u64), policy);
+        }
+        Ok(by_guild)
+    }
+
+    /// Stop policing a channel. Returns whether it was.
+    pub async fn delete(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: i64,
+    ) -> Result<bool, CrackedError> {
+        let result = sqlx::query!(
+            "DELETE FROM cam_policy WHERE guild_id = $1 AND channel_id = $2",
+            guild_id,
+            channel_id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Exempt a role from a channel's policy, or stop exempting it. `None` if the
+    /// channel has no policy.
+    pub async fn set_exempt(
+        pool: &PgPool,
+        guild_id: i64,
+        channel_id: i64,
+        role_id: i64,
+        exempt: bool,
+    ) -> Result<Option<CamPolicy>, CrackedError> {
+        sqlx::query_as!(
+            CamPolicy,
+            r#"
+            UPDATE cam_policy
+            SET exempt_roles = CASE
+                WHEN NOT $4 THEN array_remove(exempt_roles, $3)
+                WHEN $3 = ANY(exempt_roles) THEN exempt_roles
+                ELSE array_append(exempt_roles, $3)
+            END
+            WHERE guild_id = $1 AND channel_id = $2
+            RETURNING *"#,
+            guild_id,
+            channel_id,
+            role_id,
+            exempt,
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+}
+
+/// A step of the escalation ladder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CamStep {
+    Warn,
+    Mute,
+    Move,
+    Disconnect,
+}
+
+impl CamStep {
+    /// The step after this one, skipping the move if there's nowhere to move to.
+    pub fn next(self, policy: &CamPolicy) -> Option<CamStep> {
+        match self {
+            Self::Warn => Some(Self::Mute),
+            Self::Mute if policy.move_channel_id.is_some() => Some(Self::Move),
+            Self::Mute | Self::Move => Some(Self::Disconnect),
+            Self::Disconnect => None,
+        }
+    }
+
+    /// The moderation case the step is logged as.
+    pub fn case_action(self) -> CaseAction {
+        match self {
+            Self::Warn => CaseAction::Warn,
+            Self::Mute => CaseAction::Mute,
+            Self::Move => CaseAction::Move,
+            Self::Disconnect => CaseAction::Disconnect,
+        }
+    }
+}
+
+impl Display for CamStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.case_action().as_str())
+    }
+}
+
+/// A member seen in voice by the poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CamSample {
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub video: bool,
+    /// Whether they have a role exempt from their channel's policy.
+    pub exempt: bool,
+}
+
+/// A step to take against a member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CamAction {
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub step: CamStep,
+}
+
+/// What a poll means for a guild's members.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CamDecisions {
+    pub steps: Vec<CamAction>,
+    /// Members we muted who can be unmuted.
+    pub unmute: Vec<UserId>,
+}
+
+/// How far up the ladder a member is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CamViolation {
+    off_since: DateTime<Utc>,
+    step: Option<CamStep>,
+    last_step_at: DateTime<Utc>,
+    muted: bool,
+}
+
+/// Each guild's members on the ladder.
+#[derive(Debug, Default)]
+pub struct CamEnforcer {
+    guilds: HashMap<GuildId, HashMap<UserId, CamViolation>>,
+}
+
+impl CamEnforcer {
+    /// Decide the next steps from everyone in a guild's voice channels.
+    ///
+    /// Members who were moved stay on the ladder while they're in voice, so
+    /// coming back with the camera off gets them disconnected. Members we muted
+    /// are remembered until they can be unmuted, since a server mute outlasts
+    /// leaving voice.
+    pub fn observe(
+        &mut self,
+        guild_id: GuildId,
+        policies: &HashMap<ChannelId, CamPolicy>,
+        samples: &[CamSample],
+        now: DateTime<Utc>,
+    ) -> CamDecisions {
+        let states = self.guilds.entry(guild_id).or_default();
+        let mut decisions = CamDecisions::default();
+        for sample in samples {
+            let policy = policies.get(&sample.channel_id);
+            let Some(policy) = policy.filter(|_| !sample.video && !sample.exempt) else {
+                let Some(state) = states.get_mut(&sample.user_id) else {
+                    continue;
+                };
+                if state.muted {
+                    state.muted = false;
+                    decisions.unmute.push(sample.user_id);
+                }
+                // Only the moved keep climbing, if they come back.
+                let complied = policy.is_some() || state.step != Some(CamStep::Move);
+                if complied {
+                    states.remove(&sample.user_id);
+                }
+                continue;
+            };
+
+            let state = states.entry(sample.user_id).or_insert(CamViolation {
+                off_since: now,
+                step: None,
+                last_step_at: now,
+                muted: false,
+            });
+            let next = match state.step {
+                None if now - state.off_since >= policy.grace() => Some(CamStep::Warn),
+                Some(step) if now - state.last_step_at >= policy.step() => step.next(policy),
+                _ => None,
+            };
+            if let Some(step) = next {
+                state.step = Some(step);
+                state.last_step_at = now;
+                state.muted |= step == CamStep::Mute;
+                decisions.steps.push(CamAction {
+                    user_id: sample.user_id,
+                    channel_id: sample.channel_id,
+                    step,
+                });
+            }
+        }
+        states.retain(|user_id, state| {
+            state.muted || samples.iter().any(|sample| sample.user_id == *user_id)
+        });
+        decisions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::GuildEntity;
+    use ::serenity::small_fixed_array::FixedString;
+    use std::str::FromStr;
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    const POLICED: u64 = 100;
+    const LOBBY: u64 = 200;
+
+    fn policies(move_to: Option<u64>) -> HashMap<ChannelId, CamPolicy> {
+        let policy = CamPolicy {
+            channel_id: POLICED as i64,
+            guild_id: 1,
+            grace_seconds: 60,
+            step_seconds: 30,
+            move_channel_id: move_to.map(|id| id as i64),
+            ..Default::default()
+        };
+        HashMap::from([(ChannelId::new(POLICED), policy)])
+    }
+
+    fn sample(channel: u64, video: bool) -> CamSample {
+        CamSample {
+            user_id: UserId::new(10),
+            channel_id: ChannelId::new(channel),
+            video,
+            exempt: false,
+        }
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn steps(decisions: &CamDecisions) -> Vec<CamStep> {
+        decisions.steps.iter().map(|action| action.step).collect()
+    }
+
+    #[test]
+    fn test_ladder() {
+        let guild_id = GuildId::new(1);
+        let policies = policies(Some(LOBBY));
+        let mut enforcer = CamEnforcer::default();
+        let off = [sample(POLICED, false)];
+        let mut observe =
+            |samples: &[CamSample], secs| enforcer.observe(guild_id, &policies, samples, at(secs));
+
+        assert!(steps(&observe(&off, 0)).is_empty());
+        assert!(steps(&observe(&off, 30)).is_empty());
+        assert_eq!(steps(&observe(&off, 60)), vec![CamStep::Warn]);
+        assert!(steps(&observe(&off, 75)).is_empty());
+        assert_eq!(steps(&observe(&off, 90)), vec![CamStep::Mute]);
+        assert_eq!(steps(&observe(&off, 120)), vec![CamStep::Move]);
+
+        // Moved to the lobby: unmuted, but still on the ladder.
+        let moved = observe(&[sample(LOBBY, false)], 125);
+        assert_eq!(moved.unmute, vec![UserId::new(10)]);
+        assert!(moved.steps.is_empty());
+        // Back with the camera off, they're disconnected.
+        assert_eq!(steps(&observe(&off, 150)), vec![CamStep::Disconnect]);
+        assert!(steps(&observe(&off, 300)).is_empty());
+    }
+
+    #[test]
+    fn test_comply() {
+        let guild_id = GuildId::new(1);
+        let policies = policies(None);
+        let mut enforcer = CamEnforcer::default();
+        let off = [sample(POLICED, false)];
+        enforcer.observe(guild_id, &policies, &off, at(0));
+        enforcer.observe(guild_id, &policies, &off, at(60));
+        let muted = enforcer.observe(guild_id, &policies, &off, at(90));
+        assert_eq!(steps(&muted), vec![CamStep::Mute]);
+
+        // Camera on: unmuted and off the ladder.
+        let on = enforcer.observe(guild_id, &policies, &[sample(POLICED, true)], at(100));
+        assert_eq!(on.unmute, vec![UserId::new(10)]);
+        assert!(enforcer
+            .observe(guild_id, &policies, &off, at(120))
+            .steps
+            .is_empty());
+        // Without a move channel, the mute is followed by a disconnect.
+        enforcer.observe(guild_id, &policies, &off, at(180));
+        let next = enforcer.observe(guild_id, &policies, &off, at(210));
+        assert_eq!(steps(&next), vec![CamStep::Mute]);
+        let next = enforcer.observe(guild_id, &policies, &off, at(240));
+        assert_eq!(steps(&next), vec![CamStep::Disconnect]);
+    }
+
+    #[test]
+    fn test_exempt_and_muted_leavers() {
+        let guild_id = GuildId::new(1);
+        let policies = policies(None);
+        let mut enforcer = CamEnforcer::default();
+        let exempt = CamSample {
+            exempt: true,
+            ..sample(POLICED, false)
+        };
+        for secs in [0, 60, 120] {
+            let decisions = enforcer.observe(guild_id, &policies, &[exempt], at(secs));
+            assert_eq!(decisions, CamDecisions::default());
+        }
+
+        let off = [sample(POLICED, false)];
+        for secs in [200, 260, 290] {
+            enforcer.observe(guild_id, &policies, &off, at(secs));
+        }
+        // Muted and gone: remembered until they're back in voice to unmute.
+        assert_eq!(
+            enforcer.observe(guild_id, &policies, &[], at(300)),
+            CamDecisions::default()
+        );
+        let back = enforcer.observe(guild_id, &policies, &[sample(LOBBY, false)], at(400));
+        assert_eq!(back.unmute, vec![UserId::new(10)]);
+    }
+
+    #[test]
+    fn test_is_exempt() {
+        let policy = CamPolicy {
+            exempt_roles: vec![5],
+            ..Default::default()
+        };
+        assert!(policy.is_exempt(&[RoleId::new(4), RoleId::new(5)]));
+        assert!(!policy.is_exempt(&[RoleId::new(4)]));
+        assert!(!policy.is_exempt(&[]));
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_cam_policy(pool: PgPool) -> Result<(), CrackedError> {
+        for guild_id in [1, 2] {
+            GuildEntity::get_or_create(
+                &pool,
+                guild_id,
+                FixedString::from_str("test").unwrap(),
+                "r!".to_string(),
+            )
+            .await?;
+        }
+
+        CamPolicy::seed(&pool, 1, 10, 120).await?;
+        let policy = CamPolicy::set(&pool, 1, 10, 30, 45, Some(20), None).await?;
+        assert_eq!((policy.grace_seconds, policy.step_seconds), (30, 45));
+        // Seeding doesn't overwrite a policy.
+        CamPolicy::seed(&pool, 1, 10, 120).await?;
+        assert_eq!(CamPolicy::get(&pool, 1, 10).await?, Some(policy.clone()));
+        // Other guilds can't see or change it.
+        assert_eq!(CamPolicy::get(&pool, 2, 10).await?, None);
+        assert_eq!(CamPolicy::set_exempt(&pool, 2, 10, 5, true).await?, None);
+        assert!(!CamPolicy::delete(&pool, 2, 10).await?);
+        let other = CamPolicy::set(&pool, 2, 10, 5, 5, None, None).await?;
+        assert_eq!(other.guild_id, 2);
+        assert_eq!(CamPolicy::get(&pool, 1, 10).await?, Some(policy));
+        assert!(CamPolicy::delete(&pool, 2, 10).await?);
+
+        CamPolicy::set_exempt(&pool, 1, 10, 5, true).await?;
+        let policy = CamPolicy::set_exempt(&pool, 1, 10, 5, true).await?.unwrap();
+        assert_eq!(policy.exempt_roles, vec![5]);
+        // Changing the policy keeps its exempt roles.
+        let policy = CamPolicy::set(&pool, 1, 10, 60, 60, None, Some("Cam on!".into())).await?;
+        assert_eq!(policy.exempt_roles, vec![5]);
+        let policy = CamPolicy::set_exempt(&pool, 1, 10, 5, false)
+            .await?
+            .unwrap();
+        assert!(policy.exempt_roles.is_empty());
+        assert_eq!(CamPolicy::set_exempt(&pool, 1, 11, 5, true).await?, None);
+
+        assert_eq!(CamPolicy::list(&pool, 1).await?.len(), 1);
+        let by_guild = CamPolicy::by_guild(&pool).await?;
+        assert!(by_guild[&GuildId::new(1)].contains_key(&ChannelId::new(10)));
+        assert!(CamPolicy::delete(&pool, 1, 10).await?);
+        assert!(!CamPolicy::delete(&pool, 1, 10).await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+//! Attributes joins to the invite they used. Discord doesn't say which invite a
+//! member joined with, so we keep each guild's invite use counts and look for
+//! the one that went up.
+use crate::{db::InviteJoin, CrackedError, Data};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serenity::all::{GuildId, Http, Member, UserId};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    future::Future,
+    sync::Arc,
+};
+use tokio::sync::Mutex;
+
+/// How many recent joins per guild to remember, so that every join handler gets
+/// the same answer.
+const RECENT_JOINS: usize = 32;
+
+/// An invite's uses when we last looked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InviteUses {
+    pub uses: u64,
+    /// 0 for unlimited.
+    pub max_uses: u64,
+    pub inviter_id: Option<UserId>,
+    pub inviter_name: Option<String>,
+}
+
+/// A guild's invites by code.
+pub type InviteSnapshot = HashMap<String, InviteUses>;
+
+/// The invite a member joined with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InviteAttribution {
+    pub code: String,
+    pub inviter_id: Option<UserId>,
+    pub inviter_name: Option<String>,
+}
+
+impl Display for InviteAttribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.inviter_name, self.inviter_id) {
+            (Some(name), _) => write!(f, "{} (`{}`)", name, self.code),
+            (None, Some(id)) => write!(f, "{} (`{}`)", id, self.code),
+            (None, None) => write!(f, "`{}`", self.code),
+        }
+    }
+}
+
+impl InviteAttribution {
+    fn new(code: &str, uses: &InviteUses) -> Self {
+        Self {
+            code: code.to_string(),
+            inviter_id: uses.inviter_id,
+            inviter_name: uses.inviter_name.clone(),
+        }
+    }
+}
+
+/// Find the invite a member joined with from the invites before and after the
+/// join. An invite whose uses went up is the one; failing that, a limited invite
+/// that disappeared on its last use. `None` if it's not clear, e.g. two members
+/// joined at once or they used the vanity url.
+pub fn diff_invites(before: &InviteSnapshot, after: &InviteSnapshot) -> Option<InviteAttribution> {
+    let used = after
+        .iter()
+        .filter(|(code, now)| now.uses > before.get(*code).map(|then| then.uses).unwrap_or(0))
+        .collect::<Vec<_>>();
+    if let [(code, uses)] = used.as_slice() {
+        return Some(InviteAttribution::new(code, uses));
+    }
+    if !used.is_empty() {
+        return None;
+    }
+    let used_up = before
+        .iter()
+        .filter(|(code, then)| {
+            !after.contains_key(*code) && then.max_uses > 0 && then.uses + 1 >= then.max_uses
+        })
+        .collect::<Vec<_>>();
+    match used_up.as_slice() {
+        [(code, uses)] => Some(InviteAttribution::new(code, uses)),
+        _ => None,
+    }
+}
+
+/// The outcome of attributing a join.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinAttribution {
+    pub invite: Option<InviteAttribution>,
+    /// Whether this call attributed the join, rather than an earlier one.
+    pub first: bool,
+}
+
+#[derive(Debug, Default)]
+struct GuildInvites {
+    snapshot: Option<InviteSnapshot>,
+    recent: VecDeque<(UserId, Option<InviteAttribution>)>,
+}
+
+/// Each guild's invite uses and recent joins.
+#[derive(Debug, Clone, Default)]
+pub struct InviteTracker {
+    guilds: DashMap<GuildId, Arc<Mutex<GuildInvites>>>,
+}
+
+impl InviteTracker {
+    fn guild(&self, guild_id: GuildId) -> Arc<Mutex<GuildInvites>> {
+        self.guilds.entry(guild_id).or_default().clone()
+    }
+
+    /// Replace a guild's invites, e.g. on startup.
+    pub async fn set_snapshot(&self, guild_id: GuildId, snapshot: InviteSnapshot) {
+        self.guild(guild_id).lock().await.snapshot = Some(snapshot);
+    }
+
+    /// Add a new invite, so its first use is noticed.
+    pub async fn invite_created(&self, guild_id: GuildId, code: String, uses: InviteUses) {
+        if let Some(snapshot) = self.guild(guild_id).lock().await.snapshot.as_mut() {
+            snapshot.insert(code, uses);
+        }
+    }
+
+    /// Attribute a member's join, fetching the guild's invites with `fetch`. Joins
+    /// are attributed once; asking again returns the first answer. Without
+    /// earlier invites to compare with, the join can't be attributed.
+    pub async fn attribute<F, Fut>(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        fetch: F,
+    ) -> Result<JoinAttribution, CrackedError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<InviteSnapshot, CrackedError>>,
+    {
+        let guild = self.guild(guild_id);
+        let mut guild = guild.lock().await;
+        if let Some((_, invite)) = guild.recent.iter().find(|(id, _)| *id == user_id) {
+            return Ok(JoinAttribution {
+                invite: invite.clone(),
+                first: false,
+            });
+        }
+
+        let after = fetch().await?;
+        let invite = guild
+            .snapshot
+            .as_ref()
+            .and_then(|before| diff_invites(before, &after));
+        guild.snapshot = Some(after);
+        guild.recent.push_back((user_id, invite.clone()));
+        if guild.recent.len() > RECENT_JOINS {
+            guild.recent.pop_front();
+        }
+        Ok(JoinAttribution {
+            invite,
+            first: true,
+        })
+    }
+}
+
+/// Get a guild's invites from Discord. Needs the manage server permission.
+pub async fn fetch_invite_snapshot(
+    http: &Http,
+    guild_id: GuildId,
+) -> Result<InviteSnapshot, CrackedError> {
+    let invites = guild_id.invites(http).await?;
+    Ok(invites
+        .into_iter()
+        .map(|invite| {
+            let uses = InviteUses {
+                uses: invite.uses as u64,
+                max_uses: invite.max_uses as u64,
+                inviter_id: invite.inviter.as_ref().map(|user| user.id),
+                inviter_name: invite.inviter.as_ref().map(|user| user.name.to_string()),
+            };
+            (invite.code.to_string(), uses)
+        })
+        .collect())
+}
+
+/// Attribute a member's join to an invite and record it. Every join handler may
+/// call this; the join is only attributed and recorded once.
+#[cfg(not(tarpaulin_include))]
+pub async fn attribute_join(
+    http: &Http,
+    data: &Data,
+    member: &Member,
+) -> Result<Option<InviteAttribution>, CrackedError> {
+    let guild_id = member.guild_id;
+    let joined = data
+        .invites
+        .attribute(guild_id, member.user.id, || {
+            fetch_invite_snapshot(http, guild_id)
+        })
+        .await?;
+    if joined.first {
+        if let Some(pool) = &data.database_pool {
+            let invite = joined.invite.as_ref();
+            InviteJoin::record(
+                pool,
+                guild_id.get() as i64,
+                member.user.id.get() as i64,
+                invite.map(|invite| invite.code.clone()),
+                invite
+                    .and_then(|invite| invite.inviter_id)
+                    .map(|id| id.get() as i64),
+            )
+            .await?;
+        }
+    }
+    Ok(joined.invite)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn uses(uses: u64, max_uses: u64, inviter: u64) -> InviteUses {
+        InviteUses {
+            uses,
+            max_uses,
+            inviter_id: Some(UserId::new(inviter)),
+            inviter_name: Some(format!("user{}", inviter)),
+        }
+    }
+
+    fn snapshot(invites: &[(&str, InviteUses)]) -> InviteSnapshot {
+        invites
+            .iter()
+            .map(|(code, uses)| (code.to_string(), uses.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_invites() {
+        let before = snapshot(&[("abc", uses(1, 0, 10)), ("xyz", uses(4, 5, 20))]);
+
+        let after = snapshot(&[("abc", uses(2, 0, 10)), ("xyz", uses(4, 5, 20))]);
+        let invite = diff_invites(&before, &after).unwrap();
+        assert_eq!(invite.code, "abc");
+        assert_eq!(invite.inviter_id, Some(UserId::new(10)));
+        assert_eq!(invite.to_string(), "user10 (`abc`)");
+
+        // A new invite used right away.
+        let after = snapshot(&[
+            ("abc", uses(1, 0, 10)),
+            ("xyz", uses(4, 5, 20)),
+            ("new", uses(1, 0, 30)),
+        ]);
+        assert_eq!(diff_invites(&before, &after).unwrap().code, "new");
+
+        // A limited invite deleted on its last use.
+        let after = snapshot(&[("abc", uses(1, 0, 10))]);
+        assert_eq!(diff_invites(&before, &after).unwrap().code, "xyz");
+
+        // Two invites used at once, or none (vanity url).
+        let after = snapshot(&[("abc", uses(2, 0, 10)), ("xyz", uses(5, 5, 20))]);
+        assert_eq!(diff_invites(&before, &after), None);
+        assert_eq!(diff_invites(&before, &before), None);
+    }
+
+    #[tokio::test]
+    async fn test_attribute_once() {
+        let tracker = InviteTracker::default();
+        let guild_id = GuildId::new(1);
+        let member = UserId::new(42);
+        let fetch = |n: u64| move || async move { Ok(snapshot(&[("abc", uses(n, 0, 10))])) };
+
+        // Nothing to compare with yet.
+        let joined = tracker
+            .attribute(guild_id, UserId::new(41), fetch(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            joined,
+            JoinAttribution {
+                invite: None,
+                first: true
+            }
+        );
+
+        let joined = tracker.attribute(guild_id, member, fetch(2)).await.unwrap();
+        assert!(joined.first);
+        assert_eq!(joined.invite.as_ref().unwrap().code, "abc");
+
+        // The other join handler gets the same answer without fetching.
+        let joined2 = tracker
+            .attribute(guild_id, member, || async {
+                Err(CrackedError::Other("shouldn't fetch"))
+            })
+            .await
+            .unwrap();
+        assert!(!joined2.first);
+        assert_eq!(joined2.invite, joined.invite);
+    }
+
+    #[tokio::test]
+    async fn test_invite_created() {
+        let tracker = InviteTracker::default();
+        let guild_id = GuildId::new(1);
+        tracker.set_snapshot(guild_id, snapshot(&[])).await;
+        tracker
+            .invite_created(guild_id, "new".to_string(), uses(0, 1, 10))
+            .await;
+        // The invite is used up and deleted before the join arrives.
+        let joined = tracker
+            .attribute(guild_id, UserId::new(42), || async { Ok(snapshot(&[])) })
+            .await
+            .unwrap();
+        assert_eq!(joined.invite.unwrap().code, "new");
+    }
+}
@@ -1,4 +1,9 @@
 pub mod cache;
+pub mod cam_rules;
+pub mod invites;
 pub mod operations;
 pub mod permissions;
+pub mod role_menu;
 pub mod settings;
+pub mod temp_voice;
+pub mod voice_activity;
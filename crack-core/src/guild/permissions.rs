@@ -74,8 +74,10 @@ pub struct GenericPermissionSettings {
     pub allowed_channels: HashSet<u64>,
     #[serde(default)]
     pub denied_channels: HashSet<u64>,
-    // pub allowed_commands: HashSet<String>,
-    // pub denied_commands: HashSet<String>,
+    #[serde(default)]
+    pub allowed_commands: HashSet<String>,
+    #[serde(default)]
+    pub denied_commands: HashSet<String>,
 }
 
 impl From<GenericPermissionSettingsReadWCommand> for GenericPermissionSettings {
@@ -91,6 +93,8 @@ impl From<GenericPermissionSettingsReadWCommand> for GenericPermissionSettings {
             denied_users: read.denied_users.convert(),
             allowed_channels: read.allowed_channels.convert(),
             denied_channels: read.denied_channels.convert(),
+            allowed_commands: ConvertToHashSetString::convert(read.allowed_commands),
+            denied_commands: ConvertToHashSetString::convert(read.denied_commands),
         }
     }
 }
@@ -105,8 +109,8 @@ pub struct GenericPermissionSettingsReadWCommand {
     pub default_allow_all_users: bool,
     #[serde(default = "default_true")]
     pub default_allow_all_roles: bool,
-    // pub allowed_commands: serde_json::Value,
-    // pub denied_commands: serde_json::Value,
+    pub allowed_commands: Vec<String>,
+    pub denied_commands: Vec<String>,
     pub allowed_roles: Vec<i64>,
     pub denied_roles: Vec<i64>,
     pub allowed_users: Vec<i64>,
@@ -125,8 +129,8 @@ pub struct GenericPermissionSettingsRead {
     pub default_allow_all_users: bool,
     #[serde(default = "default_true")]
     pub default_allow_all_roles: bool,
-    // pub allowed_commands: serde_json::Value,
-    // pub denied_commands: serde_json::Value,
+    pub allowed_commands: Vec<String>,
+    pub denied_commands: Vec<String>,
     pub allowed_roles: Vec<i64>,
     pub denied_roles: Vec<i64>,
     pub allowed_users: Vec<i64>,
@@ -143,8 +147,8 @@ impl GenericPermissionSettingsRead {
             default_allow_all_commands: self.default_allow_all_commands,
             default_allow_all_users: self.default_allow_all_users,
             default_allow_all_roles: self.default_allow_all_roles,
-            // allowed_commands: ConvertToHashSetString::convert(self.allowed_commands),
-            // denied_commands: ConvertToHashSetString::convert(self.denied_commands),
+            allowed_commands: ConvertToHashSetString::convert(self.allowed_commands),
+            denied_commands: ConvertToHashSetString::convert(self.denied_commands),
             allowed_roles: self.allowed_roles.convert(),
             denied_roles: self.denied_roles.convert(),
             allowed_users: self.allowed_users.convert(),
@@ -168,8 +172,8 @@ impl Default for GenericPermissionSettings {
             default_allow_all_commands: true,
             default_allow_all_users: true,
             default_allow_all_roles: true,
-            // allowed_commands: HashSet::new(),
-            // denied_commands: HashSet::new(),
+            allowed_commands: HashSet::new(),
+            denied_commands: HashSet::new(),
             allowed_roles: HashSet::new(),
             denied_roles: HashSet::new(),
             allowed_users: HashSet::new(),
@@ -180,22 +184,45 @@ impl Default for GenericPermissionSettings {
     }
 }
 
+/// Iterate over a qualified command name and the names of its parent commands,
+/// most specific first, e.g. `playlist add`, then `playlist`.
+pub fn command_and_parents(qualified_name: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(qualified_name), |&name| {
+        name.rfind(' ').map(|i| &name[..i])
+    })
+}
+
 /// Implementation of GenericPermissionSettings.
 /// The behavior of this ACL is as follows:
 /// - If both white and black lists are empty, all commands are allowed.
 /// - If a command is in the denied commands, all other commands are allowed unless default_allow_all_commands is false.
-/// - If a command is in the allowed commands, all other commands are denied unless default_allow_all_commands is true.
+/// - If a command is in the allowed commands, all other commands are denied.
+/// - A rule for a parent command also covers its subcommands, and the most specific rule wins.
 impl GenericPermissionSettings {
-    // Check if a command is allowed by the permission settings.
-    // pub fn is_command_allowed(&self, command: &str) -> bool {
-    //     (self.allowed_commands.is_empty()
-    //         && self.denied_commands.is_empty()
-    //         && self.default_allow_all_commands)
-    //         || self.allowed_commands.is_empty()
-    //             && self.default_allow_all_commands
-    //             && !self.denied_commands.contains(command)
-    //         || self.allowed_commands.contains(command) && !self.denied_commands.contains(command)
-    // }
+    /// Get the rule explicitly set for a qualified command name or one of its
+    /// parents, if any. A deny wins over an allow for the same name.
+    pub fn explicit_command_rule(&self, qualified_name: &str) -> Option<bool> {
+        command_and_parents(qualified_name).find_map(|name| {
+            if self.denied_commands.contains(name) {
+                Some(false)
+            } else if self.allowed_commands.contains(name) {
+                Some(true)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Whether commands without an explicit rule are allowed.
+    pub fn allows_unlisted_commands(&self) -> bool {
+        self.allowed_commands.is_empty() && self.default_allow_all_commands
+    }
+
+    /// Check if a command is allowed by the permission settings.
+    pub fn is_command_allowed(&self, qualified_name: &str) -> bool {
+        self.explicit_command_rule(qualified_name)
+            .unwrap_or_else(|| self.allows_unlisted_commands())
+    }
 
     /// Check if a role is allowed by the permission settings.
     pub fn is_role_allowed(&self, role: u64) -> bool {
@@ -219,6 +246,24 @@ impl GenericPermissionSettings {
             || self.allowed_users.contains(&user) && !self.denied_users.contains(&user)
     }
 
+    /// Check if a guild member is allowed, taking both the user and all of their
+    /// roles into account. An explicitly allowed user bypasses the role lists.
+    pub fn is_member_allowed(&self, user: u64, roles: &[u64]) -> bool {
+        if !self.is_user_allowed(user) {
+            return false;
+        }
+        if self.allowed_users.contains(&user) {
+            return true;
+        }
+        if roles.iter().any(|r| self.denied_roles.contains(r)) {
+            return false;
+        }
+        if self.allowed_roles.is_empty() {
+            return self.default_allow_all_roles;
+        }
+        roles.iter().any(|r| self.allowed_roles.contains(r))
+    }
+
     pub fn is_channel_allowed(&self, channel: u64) -> bool {
         (self.allowed_channels.is_empty() && self.denied_channels.is_empty())
             || (self.allowed_channels.is_empty() && !self.denied_channels.contains(&channel))
@@ -226,25 +271,25 @@ impl GenericPermissionSettings {
                 && !self.denied_channels.contains(&channel))
     }
 
-    // /// Add a command to the allowed commands.
-    // pub fn add_allowed_command(&mut self, command: String) {
-    //     self.allowed_commands.insert(command);
-    // }
+    /// Add a command to the allowed commands.
+    pub fn add_allowed_command(&mut self, command: String) -> bool {
+        self.allowed_commands.insert(command)
+    }
 
-    // /// Remove a command from the allowed commands.
-    // pub fn remove_allowed_command(&mut self, command: &str) {
-    //     self.allowed_commands.remove(command);
-    // }
+    /// Remove a command from the allowed commands.
+    pub fn remove_allowed_command(&mut self, command: &str) -> bool {
+        self.allowed_commands.remove(command)
+    }
 
-    // /// Add a command to the denied commands.
-    // pub fn add_denied_command(&mut self, command: String) {
-    //     self.denied_commands.insert(command);
-    // }
+    /// Add a command to the denied commands.
+    pub fn add_denied_command(&mut self, command: String) -> bool {
+        self.denied_commands.insert(command)
+    }
 
-    // /// Remove a command from the denied commands.
-    // pub fn remove_denied_command(&mut self, command: &str) {
-    //     self.denied_commands.remove(command);
-    // }
+    /// Remove a command from the denied commands.
+    pub fn remove_denied_command(&mut self, command: &str) -> bool {
+        self.denied_commands.remove(command)
+    }
 
     /// Add a role to the allowed roles.
     pub fn add_allowed_role(&mut self, role: u64) -> bool {
@@ -308,8 +353,8 @@ impl GenericPermissionSettings {
 
     /// Clear all allowed and denied commands, roles, and users.
     pub fn clear(&mut self) {
-        // self.allowed_commands.clear();
-        // self.denied_commands.clear();
+        self.allowed_commands.clear();
+        self.denied_commands.clear();
         self.allowed_roles.clear();
         self.denied_roles.clear();
         self.allowed_users.clear();
@@ -334,15 +379,15 @@ impl GenericPermissionSettings {
                     allowed_users,
                     denied_users,
                     allowed_channels,
-                    denied_channels)
+                    denied_channels,
+                    allowed_commands,
+                    denied_commands)
             VALUES
-                ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             RETURNING *",
             self.default_allow_all_commands,
             self.default_allow_all_users,
             self.default_allow_all_roles,
-            // json!(settings.allowed_commands) as serde_json::Value, // Convert to JSON
-            // json!(settings.denied_commands) as serde_json::Value,
             &self
                 .allowed_roles
                 .iter()
@@ -373,6 +418,88 @@ impl GenericPermissionSettings {
                 .iter()
                 .map(|&x| x as i64)
                 .collect::<Vec<i64>>(),
+            &self
+                .allowed_commands
+                .iter()
+                .cloned()
+                .collect::<Vec<String>>(),
+            &self
+                .denied_commands
+                .iter()
+                .cloned()
+                .collect::<Vec<String>>(),
+        )
+        .fetch_one(pool)
+        .await
+        .map(|read| read.convert())
+        .map_err(Into::into)
+    }
+
+    /// Overwrite an existing row in the pg table with these settings.
+    pub async fn update_permission_settings(
+        &self,
+        pool: &PgPool,
+    ) -> Result<GenericPermissionSettings, CrackedError> {
+        sqlx::query_as!(
+            GenericPermissionSettingsRead,
+            "UPDATE permission_settings SET
+                default_allow_all_commands = $2,
+                default_allow_all_users = $3,
+                default_allow_all_roles = $4,
+                allowed_roles = $5,
+                denied_roles = $6,
+                allowed_users = $7,
+                denied_users = $8,
+                allowed_channels = $9,
+                denied_channels = $10,
+                allowed_commands = $11,
+                denied_commands = $12
+            WHERE id = $1
+            RETURNING *",
+            self.id as i32,
+            self.default_allow_all_commands,
+            self.default_allow_all_users,
+            self.default_allow_all_roles,
+            &self
+                .allowed_roles
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<i64>>(),
+            &self
+                .denied_roles
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<i64>>(),
+            &self
+                .allowed_users
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<i64>>(),
+            &self
+                .denied_users
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<i64>>(),
+            &self
+                .allowed_channels
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<i64>>(),
+            &self
+                .denied_channels
+                .iter()
+                .map(|&x| x as i64)
+                .collect::<Vec<i64>>(),
+            &self
+                .allowed_commands
+                .iter()
+                .cloned()
+                .collect::<Vec<String>>(),
+            &self
+                .denied_commands
+                .iter()
+                .cloned()
+                .collect::<Vec<String>>(),
         )
         .fetch_one(pool)
         .await
@@ -549,16 +676,39 @@ mod tests {
         }
     }
 
-    // #[test]
-    // fn test_is_command_allowed() {
-    //     let mut settings = GenericPermissionSettings::default();
-    //     settings.add_allowed_command("test".to_string());
-    //     assert!(settings.is_command_allowed("test"));
-    //     assert!(!settings.is_command_allowed("test2"));
-    //     settings.add_denied_command("test".to_string());
-    //     assert!(!settings.is_command_allowed("test"));
-    //     assert!(!settings.is_command_allowed("test2"));
-    // }
+    #[test]
+    fn test_is_command_allowed() {
+        let mut settings = GenericPermissionSettings::default();
+        settings.add_allowed_command("test".to_string());
+        assert!(settings.is_command_allowed("test"));
+        assert!(!settings.is_command_allowed("test2"));
+        settings.add_denied_command("test".to_string());
+        assert!(!settings.is_command_allowed("test"));
+        assert!(!settings.is_command_allowed("test2"));
+    }
+
+    #[test]
+    fn test_is_command_allowed_subcommands() {
+        let mut settings = GenericPermissionSettings::default();
+        settings.add_denied_command("playlist".to_string());
+        settings.add_allowed_command("playlist list".to_string());
+        assert!(!settings.is_command_allowed("playlist"));
+        assert!(!settings.is_command_allowed("playlist create"));
+        assert!(settings.is_command_allowed("playlist list"));
+        assert_eq!(settings.explicit_command_rule("volume"), None);
+        assert!(!settings.is_command_allowed("volume"));
+    }
+
+    #[test]
+    fn test_command_and_parents() {
+        let names = command_and_parents("settings set volume").collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["settings set volume", "settings set", "settings"]
+        );
+        let names = command_and_parents("play").collect::<Vec<_>>();
+        assert_eq!(names, vec!["play"]);
+    }
 
     #[test]
     fn test_is_role_allowed() {
@@ -580,25 +730,43 @@ mod tests {
         assert!(!settings.is_user_allowed(1));
     }
 
-    // #[test]
-    // fn test_add_remove_allowed_command() {
-    //     let mut settings = GenericPermissionSettings::default();
-    //     settings.add_allowed_command("test".to_string());
-    //     assert!(settings.is_command_allowed("test"));
-    //     settings.remove_allowed_command("test");
-    //     assert!(settings.is_command_allowed("test"));
-    //     settings.add_allowed_command("test2".to_string());
-    //     assert!(!settings.is_command_allowed("test"));
-    // }
+    #[test]
+    fn test_is_member_allowed() {
+        let mut settings = GenericPermissionSettings::default();
+        assert!(settings.is_member_allowed(1, &[]));
 
-    // #[test]
-    // fn test_add_remove_denied_command() {
-    //     let mut settings = GenericPermissionSettings::default();
-    //     settings.add_denied_command("test".to_string());
-    //     assert!(!settings.is_command_allowed("test"));
-    //     settings.remove_denied_command("test");
-    //     assert!(settings.is_command_allowed("test"));
-    // }
+        settings.add_allowed_role(10);
+        assert!(settings.is_member_allowed(1, &[10, 11]));
+        assert!(!settings.is_member_allowed(1, &[11]));
+        assert!(!settings.is_member_allowed(1, &[]));
+
+        settings.add_denied_role(11);
+        assert!(!settings.is_member_allowed(1, &[10, 11]));
+
+        settings.add_allowed_user(2);
+        assert!(settings.is_member_allowed(2, &[11]));
+        assert!(!settings.is_member_allowed(1, &[10]));
+    }
+
+    #[test]
+    fn test_add_remove_allowed_command() {
+        let mut settings = GenericPermissionSettings::default();
+        settings.add_allowed_command("test".to_string());
+        assert!(settings.is_command_allowed("test"));
+        settings.remove_allowed_command("test");
+        assert!(settings.is_command_allowed("test"));
+        settings.add_allowed_command("test2".to_string());
+        assert!(!settings.is_command_allowed("test"));
+    }
+
+    #[test]
+    fn test_add_remove_denied_command() {
+        let mut settings = GenericPermissionSettings::default();
+        settings.add_denied_command("test".to_string());
+        assert!(!settings.is_command_allowed("test"));
+        settings.remove_denied_command("test");
+        assert!(settings.is_command_allowed("test"));
+    }
 
     #[test]
     fn test_add_remove_allowed_role() {
@@ -643,14 +811,14 @@ mod tests {
     #[test]
     fn test_clear() {
         let mut settings = GenericPermissionSettings::default();
-        // settings.add_allowed_command("test".to_string());
-        // settings.add_denied_command("test".to_string());
+        settings.add_allowed_command("test".to_string());
+        settings.add_denied_command("test".to_string());
         settings.add_allowed_role(1);
         settings.add_denied_role(1);
         settings.add_allowed_user(1);
         settings.add_denied_user(1);
         settings.clear();
-        // assert!(settings.is_command_allowed("test"));
+        assert!(settings.is_command_allowed("test"));
         assert!(settings.is_role_allowed(1));
         assert!(settings.is_user_allowed(1));
     }
@@ -662,8 +830,8 @@ mod tests {
             default_allow_all_commands: true,
             default_allow_all_users: true,
             default_allow_all_roles: true,
-            // allowed_commands: json!(["test"]),
-            // denied_commands: json!(["test2"]),
+            allowed_commands: vec!["test".to_string()],
+            denied_commands: vec!["test2".to_string()],
             allowed_roles: vec![1, 2],
             denied_roles: vec![1],
             allowed_users: vec![1, 2],
@@ -672,7 +840,8 @@ mod tests {
             denied_channels: vec![1],
         };
         let settings = settings_read.convert();
-        // assert!(settings.is_command_allowed("test"));
+        assert!(settings.is_command_allowed("test"));
+        assert!(!settings.is_command_allowed("test2"));
         assert!(!settings.is_role_allowed(1));
         assert!(!settings.is_user_allowed(1));
         assert!(!settings.is_channel_allowed(1));
@@ -705,8 +874,8 @@ mod tests {
     #[sqlx::test(migrator = "MIGRATOR")]
     async fn test_insert_permission_settings(pool: PgPool) {
         let mut settings = GenericPermissionSettings::default();
-        // settings.add_allowed_command("test".to_string());
-        // settings.add_denied_command("test2".to_string());
+        settings.add_allowed_command("test".to_string());
+        settings.add_denied_command("test2".to_string());
         settings.add_allowed_role(1);
         settings.add_allowed_user(1);
         settings.insert_permission_settings(&pool).await.unwrap();
@@ -722,15 +891,22 @@ mod tests {
         assert!(settings_read.denied_roles == settings.denied_roles);
         assert!(settings_read.allowed_users == settings.allowed_users);
         assert!(settings_read.denied_users == settings.denied_users);
-        // assert!(settings_read.is_command_allowed("test"));
-        // assert!(!settings_read.is_command_allowed("test2"));
+        assert!(settings_read.is_command_allowed("test"));
+        assert!(!settings_read.is_command_allowed("test2"));
+
+        let mut updated = settings_read.clone();
+        updated.remove_allowed_command("test");
+        updated.add_denied_command("test".to_string());
+        let updated = updated.update_permission_settings(&pool).await.unwrap();
+        assert_eq!(updated.id, settings_read.id);
+        assert!(!updated.is_command_allowed("test"));
     }
 
     #[sqlx::test(migrator = "MIGRATOR")]
     async fn test_insert_command_channel(pool: PgPool) {
         let mut settings = GenericPermissionSettings::default();
-        // settings.add_allowed_command("test".to_string());
-        // settings.add_denied_command("test2".to_string());
+        settings.add_allowed_command("test".to_string());
+        settings.add_denied_command("test2".to_string());
         settings.add_allowed_role(1);
         settings.add_allowed_user(1);
         let channel = CommandChannel {
@@ -743,8 +919,12 @@ mod tests {
 
         let channel_read =
             CommandChannel::get_command_channels(&pool, "test".to_string(), GuildId::new(1)).await;
-        // assert!(channel_read.permission_settings.is_command_allowed("test"));
-        // assert!(!channel_read.permission_settings.is_command_allowed("test2"));
         assert!(channel_read.len() == 1);
+        assert!(channel_read[0]
+            .permission_settings
+            .is_command_allowed("test"));
+        assert!(!channel_read[0]
+            .permission_settings
+            .is_command_allowed("test2"));
     }
 }
@@ -0,0 +1,635 @@
+//! Role menus: messages with a button per role, or a select menu, that members
+//! use to pick their own roles.
+use crate::{
+    db::{RoleMenu, RoleMenuOption},
+    messaging::messages::{
+        ROLE_MENU_ABOVE_BOT, ROLE_MENU_ADDED, ROLE_MENU_ALREADY, ROLE_MENU_DANGEROUS,
+        ROLE_MENU_GONE, ROLE_MENU_NO_CHANGE, ROLE_MENU_PLACEHOLDER, ROLE_MENU_REASON,
+        ROLE_MENU_REMOVED, ROLE_MENU_TOO_HIGH, ROLE_MENU_TOO_MANY, ROLE_MENU_UNKNOWN_ROLE,
+    },
+    CrackedError, Data,
+};
+use serde::{Deserialize, Serialize};
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, ComponentInteractionDataKind, Context as SerenityContext,
+    CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption, Interaction, Mention, Permissions, ReactionType, RoleId,
+};
+use std::{borrow::Cow, fmt::Write, str::FromStr};
+
+/// Custom id prefix of role menu components. The menu id follows, and for
+/// buttons the role id.
+pub const ROLE_MENU_CUSTOM_ID: &str = "role_menu";
+/// Most roles on a menu: five rows of five buttons, or a full select menu.
+pub const MAX_ROLE_MENU_OPTIONS: usize = 25;
+const BUTTONS_PER_ROW: usize = 5;
+/// Permissions a role handed out by a menu must not carry, since anyone can pick it.
+pub const ROLE_MENU_DENIED_PERMISSIONS: Permissions = Permissions::ADMINISTRATOR
+    .union(Permissions::MANAGE_GUILD)
+    .union(Permissions::MANAGE_ROLES)
+    .union(Permissions::MANAGE_CHANNELS)
+    .union(Permissions::BAN_MEMBERS)
+    .union(Permissions::KICK_MEMBERS);
+
+/// How picks on a role menu combine.
+#[derive(
+    Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum RoleMenuMode {
+    /// Pick any roles, pick again to drop them.
+    #[default]
+    #[name = "toggle"]
+    Toggle,
+    /// Hold at most one role from the menu.
+    #[name = "unique"]
+    Unique,
+    /// Roles are only given, never taken, e.g. to accept the rules.
+    #[name = "verify"]
+    Verify,
+    /// Hold up to the menu's maximum.
+    #[name = "max"]
+    Max,
+}
+
+impl RoleMenuMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoleMenuMode::Toggle => "toggle",
+            RoleMenuMode::Unique => "unique",
+            RoleMenuMode::Verify => "verify",
+            RoleMenuMode::Max => "max",
+        }
+    }
+}
+
+impl FromStr for RoleMenuMode {
+    type Err = CrackedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toggle" => Ok(RoleMenuMode::Toggle),
+            "unique" => Ok(RoleMenuMode::Unique),
+            "verify" => Ok(RoleMenuMode::Verify),
+            "max" => Ok(RoleMenuMode::Max),
+            _ => Err(CrackedError::Other("Unknown role menu mode")),
+        }
+    }
+}
+
+/// What members click on.
+#[derive(
+    Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum RoleMenuStyle {
+    /// A button per role.
+    #[default]
+    #[name = "buttons"]
+    Buttons,
+    /// One select menu with every role.
+    #[name = "select"]
+    Select,
+}
+
+impl RoleMenuStyle {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoleMenuStyle::Buttons => "buttons",
+            RoleMenuStyle::Select => "select",
+        }
+    }
+}
+
+impl FromStr for RoleMenuStyle {
+    type Err = CrackedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "buttons" => Ok(RoleMenuStyle::Buttons),
+            "select" => Ok(RoleMenuStyle::Select),
+            _ => Err(CrackedError::Other("Unknown role menu style")),
+        }
+    }
+}
+
+/// The custom id of a role menu's button for a role, or of its select menu.
+pub fn role_menu_custom_id(menu_id: i64, role_id: Option<RoleId>) -> String {
+    match role_id {
+        Some(role_id) => format!("{}:{}:{}", ROLE_MENU_CUSTOM_ID, menu_id, role_id),
+        None => format!("{}:{}", ROLE_MENU_CUSTOM_ID, menu_id),
+    }
+}
+
+/// The menu, and for buttons the role, a custom id is for. `None` if it isn't a
+/// role menu's.
+pub fn parse_role_menu_custom_id(custom_id: &str) -> Option<(i64, Option<RoleId>)> {
+    let mut parts = custom_id.split(':');
+    if parts.next()? != ROLE_MENU_CUSTOM_ID {
+        return None;
+    }
+    let menu_id = parts.next()?.parse::<i64>().ok()?;
+    let role_id = match parts.next() {
+        Some(role_id) => Some(
+            role_id
+                .parse::<u64>()
+                .ok()
+                .filter(|id| *id != 0)
+                .map(RoleId::new)?,
+        ),
+        None => None,
+    };
+    match parts.next() {
+        Some(_) => None,
+        None => Some((menu_id, role_id)),
+    }
+}
+
+/// What a member picked on a menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoleMenuPick {
+    /// Pressed a role's button.
+    Button(RoleId),
+    /// Chose these roles in the select menu.
+    Select(Vec<RoleId>),
+}
+
+/// Roles to give and take after a pick.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoleChanges {
+    pub add: Vec<RoleId>,
+    pub remove: Vec<RoleId>,
+}
+
+impl RoleChanges {
+    pub fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// Work out the roles to give and take when a member holding `held` picks from
+/// a menu of `menu` roles, or why the pick isn't allowed.
+pub fn plan_role_changes(
+    mode: RoleMenuMode,
+    max_roles: u32,
+    menu: &[RoleId],
+    held: &[RoleId],
+    pick: &RoleMenuPick,
+) -> Result<RoleChanges, &'static str> {
+    let held_from_menu = menu
+        .iter()
+        .filter(|role| held.contains(role))
+        .copied()
+        .collect::<Vec<_>>();
+    let too_many = |n: usize| mode == RoleMenuMode::Max && max_roles > 0 && n > max_roles as usize;
+    match pick {
+        RoleMenuPick::Button(role) => {
+            if !menu.contains(role) {
+                return Err(ROLE_MENU_UNKNOWN_ROLE);
+            }
+            let mut changes = RoleChanges::default();
+            if held.contains(role) {
+                if mode == RoleMenuMode::Verify {
+                    return Err(ROLE_MENU_ALREADY);
+                }
+                changes.remove.push(*role);
+                return Ok(changes);
+            }
+            if too_many(held_from_menu.len() + 1) {
+                return Err(ROLE_MENU_TOO_MANY);
+            }
+            changes.add.push(*role);
+            if mode == RoleMenuMode::Unique {
+                changes.remove = held_from_menu;
+            }
+            Ok(changes)
+        },
+        RoleMenuPick::Select(picked) => {
+            let picked = picked
+                .iter()
+                .filter(|role| menu.contains(role))
+                .copied()
+                .collect::<Vec<_>>();
+            if (mode == RoleMenuMode::Unique && picked.len() > 1) || too_many(picked.len()) {
+                return Err(ROLE_MENU_TOO_MANY);
+            }
+            let add = picked
+                .iter()
+                .filter(|role| !held.contains(role))
+                .copied()
+                .collect();
+            let remove = match mode {
+                RoleMenuMode::Verify => vec![],
+                _ => held_from_menu
+                    .into_iter()
+                    .filter(|role| !picked.contains(role))
+                    .collect(),
+            };
+            Ok(RoleChanges { add, remove })
+        },
+    }
+}
+
+/// The embed and components of a role menu's message.
+pub fn role_menu_message(
+    menu: &RoleMenu,
+    options: &[RoleMenuOption],
+) -> (CreateEmbed<'static>, Vec<CreateActionRow<'static>>) {
+    let description = options
+        .iter()
+        .fold(String::new(), |mut description, option| {
+            let role = Mention::Role(RoleId::new(option.role_id as u64));
+            let _ = match &option.emoji {
+                Some(emoji) => writeln!(description, "{} {} {}", emoji, option.label, role),
+                None => writeln!(description, "{} {}", option.label, role),
+            };
+            description
+        });
+    let embed = CreateEmbed::default()
+        .title(menu.title.clone())
+        .description(description);
+
+    let emoji = |option: &RoleMenuOption| option.emoji.as_deref().and_then(parse_emoji);
+    let rows = match menu.style() {
+        RoleMenuStyle::Buttons => options
+            .chunks(BUTTONS_PER_ROW)
+            .map(|row| {
+                let buttons = row
+                    .iter()
+                    .map(|option| {
+                        let role_id = RoleId::new(option.role_id as u64);
+                        let mut button =
+                            CreateButton::new(role_menu_custom_id(menu.id, Some(role_id)))
+                                .style(ButtonStyle::Secondary)
+                                .label(option.label.clone());
+                        if let Some(emoji) = emoji(option) {
+                            button = button.emoji(emoji);
+                        }
+                        button
+                    })
+                    .collect::<Vec<_>>();
+                CreateActionRow::Buttons(Cow::Owned(buttons))
+            })
+            .collect(),
+        RoleMenuStyle::Select => {
+            let select_options = options
+                .iter()
+                .map(|option| {
+                    let mut select_option = CreateSelectMenuOption::new(
+                        option.label.clone(),
+                        option.role_id.to_string(),
+                    );
+                    if let Some(emoji) = emoji(option) {
+                        select_option = select_option.emoji(emoji);
+                    }
+                    select_option
+                })
+                .collect::<Vec<_>>();
+            let max_values = match menu.mode() {
+                RoleMenuMode::Unique => 1,
+                RoleMenuMode::Max if menu.max_roles > 0 => menu.max_roles as usize,
+                _ => options.len(),
+            };
+            let select = CreateSelectMenu::new(
+                role_menu_custom_id(menu.id, None),
+                CreateSelectMenuKind::String {
+                    options: Cow::Owned(select_options),
+                },
+            )
+            .placeholder(ROLE_MENU_PLACEHOLDER)
+            .min_values(0)
+            .max_values(max_values.min(options.len()).max(1) as u8);
+            vec![CreateActionRow::SelectMenu(select)]
+        },
+    };
+    (embed, rows)
+}
+
+/// Why a role can't go on a role menu, if it can't: it has to sit below both
+/// the invoker's and the bot's highest roles, and not grant moderation powers.
+/// `invoker_top` is `None` for the guild owner, who is above every role.
+pub fn role_menu_role_problem(
+    position: u16,
+    permissions: Permissions,
+    invoker_top: Option<u16>,
+    bot_top: u16,
+) -> Option<&'static str> {
+    if permissions.intersects(ROLE_MENU_DENIED_PERMISSIONS) {
+        Some(ROLE_MENU_DANGEROUS)
+    } else if invoker_top.is_some_and(|top| position >= top) {
+        Some(ROLE_MENU_TOO_HIGH)
+    } else if position >= bot_top {
+        Some(ROLE_MENU_ABOVE_BOT)
+    } else {
+        None
+    }
+}
+
+/// A unicode emoji or a custom one like `<:name:id>`.
+pub fn parse_emoji(emoji: &str) -> Option<ReactionType> {
+    ReactionType::try_from(emoji).ok()
+}
+
+/// Handle clicks on role menus. Returns whether the interaction was for one.
+#[cfg(not(tarpaulin_include))]
+pub async fn handle_role_menu_interaction(
+    ctx: &SerenityContext,
+    data: &Data,
+    interaction: &Interaction,
+) -> Result<bool, CrackedError> {
+    let Interaction::Component(int) = interaction else {
+        return Ok(false);
+    };
+    let Some((menu_id, role_id)) = parse_role_menu_custom_id(&int.data.custom_id) else {
+        return Ok(false);
+    };
+    let reply = match on_role_menu_pick(ctx, data, int, menu_id, role_id).await {
+        Ok(reply) => reply,
+        Err(e) => {
+            tracing::warn!("Role menu {} failed: {}", menu_id, e);
+            e.to_string()
+        },
+    };
+    int.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(reply)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(true)
+}
+
+/// Give and take the member's roles for a pick, returning what changed.
+#[cfg(not(tarpaulin_include))]
+async fn on_role_menu_pick(
+    ctx: &SerenityContext,
+    data: &Data,
+    int: &ComponentInteraction,
+    menu_id: i64,
+    role_id: Option<RoleId>,
+) -> Result<String, CrackedError> {
+    let guild_id = int.guild_id.ok_or(CrackedError::NoGuildId)?;
+    let member = int.member.as_ref().ok_or(CrackedError::NoGuildId)?;
+    let pool = data
+        .database_pool
+        .as_ref()
+        .ok_or(CrackedError::NoDatabasePool)?;
+    let Some(menu) = RoleMenu::get(pool, guild_id.get() as i64, menu_id).await? else {
+        return Ok(ROLE_MENU_GONE.to_string());
+    };
+    let menu_roles = menu
+        .options(pool)
+        .await?
+        .iter()
+        .map(|option| RoleId::new(option.role_id as u64))
+        .collect::<Vec<_>>();
+    let pick = match (role_id, &int.data.kind) {
+        (Some(role_id), _) => RoleMenuPick::Button(role_id),
+        (None, ComponentInteractionDataKind::StringSelect { values }) => RoleMenuPick::Select(
+            values
+                .iter()
+                .filter_map(|value| value.parse::<u64>().ok())
+                .filter(|id| *id != 0)
+                .map(RoleId::new)
+                .collect(),
+        ),
+        (None, _) => return Ok(ROLE_MENU_NO_CHANGE.to_string()),
+    };
+
+    let changes = match plan_role_changes(
+        menu.mode(),
+        menu.max_roles.max(0) as u32,
+        &menu_roles,
+        &member.roles,
+        &pick,
+    ) {
+        Ok(changes) => changes,
+        Err(reason) => return Ok(reason.to_string()),
+    };
+    if changes.is_empty() {
+        return Ok(ROLE_MENU_NO_CHANGE.to_string());
+    }
+    let user_id = int.user.id;
+    for role_id in &changes.remove {
+        guild_id
+            .remove_member_role(&ctx.http, user_id, *role_id, Some(ROLE_MENU_REASON))
+            .await?;
+    }
+    for role_id in &changes.add {
+        guild_id
+            .add_member_role(&ctx.http, user_id, *role_id, Some(ROLE_MENU_REASON))
+            .await?;
+    }
+    Ok(describe_changes(&changes))
+}
+
+fn describe_changes(changes: &RoleChanges) -> String {
+    let mentions = |roles: &[RoleId]| {
+        roles
+            .iter()
+            .map(|role| Mention::Role(*role).to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut lines = vec![];
+    if !changes.add.is_empty() {
+        lines.push(format!("{} {}", ROLE_MENU_ADDED, mentions(&changes.add)));
+    }
+    if !changes.remove.is_empty() {
+        lines.push(format!(
+            "{} {}",
+            ROLE_MENU_REMOVED,
+            mentions(&changes.remove)
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn roles(ids: &[u64]) -> Vec<RoleId> {
+        ids.iter().copied().map(RoleId::new).collect()
+    }
+
+    fn changes(add: &[u64], remove: &[u64]) -> RoleChanges {
+        RoleChanges {
+            add: roles(add),
+            remove: roles(remove),
+        }
+    }
+
+    #[test]
+    fn test_role_menu_role_problem() {
+        let none = Permissions::empty();
+        assert_eq!(role_menu_role_problem(3, none, Some(5), 5), None);
+        assert_eq!(role_menu_role_problem(3, none, None, 5), None);
+        // At or above the invoker's highest role.
+        assert_eq!(
+            role_menu_role_problem(5, none, Some(5), 9),
+            Some(ROLE_MENU_TOO_HIGH)
+        );
+        assert_eq!(
+            role_menu_role_problem(6, none, Some(5), 9),
+            Some(ROLE_MENU_TOO_HIGH)
+        );
+        // At or above the bot's highest role, even for the owner.
+        assert_eq!(
+            role_menu_role_problem(5, none, None, 5),
+            Some(ROLE_MENU_ABOVE_BOT)
+        );
+        for perm in [
+            Permissions::ADMINISTRATOR,
+            Permissions::MANAGE_GUILD,
+            Permissions::MANAGE_ROLES,
+            Permissions::MANAGE_CHANNELS,
+            Permissions::BAN_MEMBERS,
+            Permissions::KICK_MEMBERS,
+        ] {
+            let perms = Permissions::SEND_MESSAGES | perm;
+            assert_eq!(
+                role_menu_role_problem(1, perms, None, 9),
+                Some(ROLE_MENU_DANGEROUS)
+            );
+        }
+        assert_eq!(
+            role_menu_role_problem(1, Permissions::SEND_MESSAGES, Some(5), 9),
+            None
+        );
+    }
+
+    #[test]
+    fn test_custom_id() {
+        let role = RoleId::new(5);
+        assert_eq!(role_menu_custom_id(3, Some(role)), "role_menu:3:5");
+        assert_eq!(role_menu_custom_id(3, None), "role_menu:3");
+        assert_eq!(
+            parse_role_menu_custom_id("role_menu:3:5"),
+            Some((3, Some(role)))
+        );
+        assert_eq!(parse_role_menu_custom_id("role_menu:3"), Some((3, None)));
+        assert_eq!(parse_role_menu_custom_id("role_menu:3:0"), None);
+        assert_eq!(parse_role_menu_custom_id("role_menu:x"), None);
+        assert_eq!(parse_role_menu_custom_id("role_menu:3:5:7"), None);
+        assert_eq!(parse_role_menu_custom_id("verify:3"), None);
+    }
+
+    #[test]
+    fn test_buttons() {
+        let menu = roles(&[1, 2, 3]);
+        let held = roles(&[1, 9]);
+        let press = |mode, max, id| {
+            plan_role_changes(
+                mode,
+                max,
+                &menu,
+                &held,
+                &RoleMenuPick::Button(RoleId::new(id)),
+            )
+        };
+
+        assert_eq!(press(RoleMenuMode::Toggle, 0, 2), Ok(changes(&[2], &[])));
+        assert_eq!(press(RoleMenuMode::Toggle, 0, 1), Ok(changes(&[], &[1])));
+        assert_eq!(press(RoleMenuMode::Unique, 0, 2), Ok(changes(&[2], &[1])));
+        assert_eq!(press(RoleMenuMode::Unique, 0, 1), Ok(changes(&[], &[1])));
+        assert_eq!(press(RoleMenuMode::Verify, 0, 2), Ok(changes(&[2], &[])));
+        assert_eq!(press(RoleMenuMode::Verify, 0, 1), Err(ROLE_MENU_ALREADY));
+        assert_eq!(press(RoleMenuMode::Max, 2, 2), Ok(changes(&[2], &[])));
+        assert_eq!(press(RoleMenuMode::Max, 1, 2), Err(ROLE_MENU_TOO_MANY));
+        // Dropping a role is always fine.
+        assert_eq!(press(RoleMenuMode::Max, 1, 1), Ok(changes(&[], &[1])));
+        assert_eq!(
+            press(RoleMenuMode::Toggle, 0, 9),
+            Err(ROLE_MENU_UNKNOWN_ROLE)
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let menu = roles(&[1, 2, 3]);
+        let held = roles(&[1, 9]);
+        let select = |mode, max, ids: &[u64]| {
+            plan_role_changes(mode, max, &menu, &held, &RoleMenuPick::Select(roles(ids)))
+        };
+
+        assert_eq!(
+            select(RoleMenuMode::Toggle, 0, &[2, 3]),
+            Ok(changes(&[2, 3], &[1]))
+        );
+        assert_eq!(select(RoleMenuMode::Toggle, 0, &[1]), Ok(changes(&[], &[])));
+        // Roles that aren't on the menu are ignored, and other roles are kept.
+        assert_eq!(
+            select(RoleMenuMode::Toggle, 0, &[9]),
+            Ok(changes(&[], &[1]))
+        );
+        assert_eq!(
+            select(RoleMenuMode::Unique, 0, &[3]),
+            Ok(changes(&[3], &[1]))
+        );
+        assert_eq!(
+            select(RoleMenuMode::Unique, 0, &[2, 3]),
+            Err(ROLE_MENU_TOO_MANY)
+        );
+        assert_eq!(select(RoleMenuMode::Verify, 0, &[]), Ok(changes(&[], &[])));
+        assert_eq!(
+            select(RoleMenuMode::Verify, 0, &[2]),
+            Ok(changes(&[2], &[]))
+        );
+        assert_eq!(
+            select(RoleMenuMode::Max, 2, &[1, 2]),
+            Ok(changes(&[2], &[]))
+        );
+        assert_eq!(
+            select(RoleMenuMode::Max, 2, &[1, 2, 3]),
+            Err(ROLE_MENU_TOO_MANY)
+        );
+    }
+
+    #[test]
+    fn test_role_menu_message() {
+        let menu = RoleMenu {
+            id: 7,
+            title: "Pronouns".to_string(),
+            ..Default::default()
+        };
+        let options = (1..=7)
+            .map(|i| RoleMenuOption {
+                menu_id: 7,
+                role_id: i,
+                label: format!("role {}", i),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let (_, rows) = role_menu_message(&menu, &options);
+        assert_eq!(rows.len(), 2);
+
+        let menu = RoleMenu {
+            style: RoleMenuStyle::Select.as_str().to_string(),
+            ..menu
+        };
+        let (_, rows) = role_menu_message(&menu, &options);
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[test]
+    fn test_mode_round_trip() {
+        for mode in [
+            RoleMenuMode::Toggle,
+            RoleMenuMode::Unique,
+            RoleMenuMode::Verify,
+            RoleMenuMode::Max,
+        ] {
+            assert_eq!(mode.as_str().parse::<RoleMenuMode>().unwrap(), mode);
+        }
+        assert!("other".parse::<RoleMenuMode>().is_err());
+        assert_eq!(
+            "select".parse::<RoleMenuStyle>().unwrap(),
+            RoleMenuStyle::Select
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use self::serenity::model::id::GuildId;
 use self::serenity::model::prelude::UserId;
-use crate::db::{GuildEntity, WelcomeSettingsRead};
+use crate::db::{
+    AutomodSettingsRead, GuildEntity, RaidSettingsRead, TtsSettingsRead, VerificationSettingsRead,
+    WelcomeSettingsRead,
+};
 use crate::errors::CrackedError;
 use crate::CrackedResult;
 use ::serenity::small_fixed_array::FixedString;
@@ -24,12 +27,15 @@ pub(crate) const DEFAULT_ACTIVITY: &str = "/play";
 pub(crate) const DEFAULT_LOG_PREFIX: &str = "data/logs";
 pub(crate) const DEFAULT_ALLOW_ALL_DOMAINS: bool = true;
 pub(crate) const DEFAULT_SETTINGS_PATH: &str = "data/settings";
+pub(crate) const DEFAULT_RECORDINGS_PATH: &str = "data/recordings";
+pub(crate) const DEFAULT_SOUNDBOARD_PATH: &str = "data/soundboard";
 #[allow(dead_code)]
 pub(crate) const PIPED_WATCH_URL: &str = "https://piped.video/watch?v=";
 pub(crate) const YOUTUBE_WATCH_URL: &str = "https://www.youtube.com/watch?v=";
 pub(crate) const VIDEO_WATCH_URL: &str = YOUTUBE_WATCH_URL;
 pub(crate) const DEFAULT_ALLOWED_DOMAINS: [&str; 1] = ["youtube.com"];
 pub(crate) const DEFAULT_VOLUME_LEVEL: f32 = 1.0;
+pub(crate) const DEFAULT_TTS_VOICE: &str = "en-us";
 pub(crate) const DEFAULT_VIDEO_STATUS_POLL_INTERVAL: u64 = 120;
 pub(crate) const DEFAULT_PREFIX: &str = "r!";
 pub(crate) const DEFAULT_DB_URL: &str =
@@ -42,14 +48,16 @@ pub(crate) const ADDITIONAL_PREFIXES: [&str; 8] = [
 ];
 pub(crate) const MOD_VAL: u64 = 1 << 1;
 pub(crate) const ADMIN_VAL: u64 = 2 << 1;
-pub(crate) const DEFAULT_VALID_TOKEN: &str =
-    "XXXXXXXXXXXXXXXXXXXXXXXX.X_XXXX.XXXXXXXXXXXXXXXXXXXXXX_XXXX";
 
 lazy_static! {
     static ref SETTINGS_PATH: String =
         env::var("SETTINGS_PATH").unwrap_or(DEFAULT_SETTINGS_PATH.to_string());
     pub static ref LOG_PREFIX: String =
         env::var("LOG_PREFIX").unwrap_or(DEFAULT_LOG_PREFIX.to_string());
+    static ref RECORDINGS_PATH: String =
+        env::var("RECORDINGS_PATH").unwrap_or(DEFAULT_RECORDINGS_PATH.to_string());
+    static ref SOUNDBOARD_PATH: String =
+        env::var("SOUNDBOARD_PATH").unwrap_or(DEFAULT_SOUNDBOARD_PATH.to_string());
 }
 
 /// Get the settings path, global.
@@ -62,6 +70,16 @@ pub fn get_log_prefix() -> String {
     LOG_PREFIX.to_string()
 }
 
+/// Get the directory voice recordings are saved in, global.
+pub fn get_recordings_path() -> String {
+    RECORDINGS_PATH.to_string()
+}
+
+/// Get the directory soundboard clips are saved in, global.
+pub fn get_soundboard_path() -> String {
+    SOUNDBOARD_PATH.to_string()
+}
+
 #[derive(Default, Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct LogSettings {
     // TODO: Decide if I want to have separate raw events and all log channels.
@@ -73,6 +91,7 @@ pub struct LogSettings {
     pub member_log_channel: Option<u64>,
     pub join_leave_log_channel: Option<u64>,
     pub voice_log_channel: Option<u64>,
+    pub mod_log_channel: Option<u64>,
 }
 
 impl From<crate::db::LogSettingsRead> for LogSettings {
@@ -84,6 +103,7 @@ impl From<crate::db::LogSettingsRead> for LogSettings {
             member_log_channel: settings_db.member_log_channel.map(|x| x as u64),
             join_leave_log_channel: settings_db.join_leave_log_channel.map(|x| x as u64),
             voice_log_channel: settings_db.voice_log_channel.map(|x| x as u64),
+            mod_log_channel: settings_db.mod_log_channel.map(|x| x as u64),
         }
     }
 }
@@ -121,6 +141,13 @@ impl LogSettings {
             .or(DEFAULT_LOG_CHANNEL)
     }
 
+    /// Channel moderation cases are posted to, falling back to the all log channel.
+    pub fn get_mod_log_channel(&self) -> Option<ChannelId> {
+        self.mod_log_channel
+            .map(ChannelId::new)
+            .or(self.get_all_log_channel())
+    }
+
     pub fn set_all_log_channel(&mut self, channel_id: u64) -> &mut Self {
         self.all_log_channel = Some(channel_id);
         self
@@ -146,6 +173,11 @@ impl LogSettings {
         self
     }
 
+    pub fn set_mod_log_channel(&mut self, channel_id: u64) -> &mut Self {
+        self.mod_log_channel = Some(channel_id);
+        self
+    }
+
     /// Write the log settings to the database.
     pub async fn save(&self, pool: &PgPool, guild_id: u64) -> Result<(), CrackedError> {
         crate::db::GuildEntity::write_log_settings(pool, guild_id as i64, self).await
@@ -158,6 +190,9 @@ pub struct WelcomeSettings {
     pub message: Option<String>,
     pub auto_role: Option<u64>,
     pub password: Option<String>,
+    /// Sent to the welcome channel when a member leaves.
+    #[serde(default)]
+    pub leave_message: Option<String>,
 }
 
 impl Display for WelcomeSettings {
@@ -177,6 +212,7 @@ impl From<WelcomeSettingsRead> for WelcomeSettings {
             message: settings_db.message,
             auto_role: settings_db.auto_role.map(|x| x as u64),
             password: None,
+            leave_message: settings_db.leave_message,
         }
     }
 }
@@ -219,6 +255,14 @@ impl WelcomeSettings {
         }
     }
 
+    /// Set the leave message, returning a new WelcomeSettings.
+    pub fn with_leave_message(self, leave_message: String) -> Self {
+        Self {
+            leave_message: Some(leave_message),
+            ..self
+        }
+    }
+
     /// Save the welcome settings to the database.
     pub async fn save(&self, pool: &PgPool, guild_id: u64) -> Result<(), CrackedError> {
         crate::db::GuildEntity::write_welcome_settings(pool, guild_id as i64, self)
@@ -227,6 +271,436 @@ impl WelcomeSettings {
     }
 }
 
+/// What automod does to a message that breaks a rule. The message is always
+/// deleted; `warn` and `timeout` also warn or time out its author.
+#[derive(
+    Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AutomodAction {
+    #[default]
+    #[name = "delete"]
+    Delete,
+    #[name = "warn"]
+    Warn,
+    #[name = "timeout"]
+    Timeout,
+}
+
+impl AutomodAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AutomodAction::Delete => "delete",
+            AutomodAction::Warn => "warn",
+            AutomodAction::Timeout => "timeout",
+        }
+    }
+}
+
+impl FromStr for AutomodAction {
+    type Err = CrackedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "delete" => Ok(AutomodAction::Delete),
+            "warn" => Ok(AutomodAction::Warn),
+            "timeout" => Ok(AutomodAction::Timeout),
+            _ => Err(CrackedError::Other("Unknown automod action")),
+        }
+    }
+}
+
+/// Automod rules for a guild. A limit of 0 turns that check off.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct AutomodSettings {
+    pub enabled: bool,
+    /// Most messages a user may send within `rate_limit_seconds`.
+    pub rate_limit_messages: u32,
+    pub rate_limit_seconds: u32,
+    /// How many identical messages in a row count as spam.
+    pub max_duplicates: u32,
+    /// Most user, role and everyone mentions in one message.
+    pub max_mentions: u32,
+    pub block_invites: bool,
+    /// Block links to the guild's banned domains.
+    pub block_banned_domains: bool,
+    pub action: AutomodAction,
+    pub timeout_seconds: u32,
+}
+
+impl Default for AutomodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate_limit_messages: 5,
+            rate_limit_seconds: 5,
+            max_duplicates: 3,
+            max_mentions: 5,
+            block_invites: true,
+            block_banned_domains: true,
+            action: AutomodAction::Delete,
+            timeout_seconds: 10 * 60,
+        }
+    }
+}
+
+impl Display for AutomodSettings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let limit = |n: u32| {
+            if n == 0 {
+                "off".to_string()
+            } else {
+                n.to_string()
+            }
+        };
+        let blocked = |b: bool| if b { "blocked" } else { "allowed" };
+        writeln!(f, "Enabled: {}", if self.enabled { "yes" } else { "no" })?;
+        write!(f, "Action: {}", self.action.as_str())?;
+        if self.action == AutomodAction::Timeout {
+            write!(
+                f,
+                " ({})",
+                crate::duration::format_duration(std::time::Duration::from_secs(
+                    self.timeout_seconds as u64
+                ))
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Rate limit: {} messages per {}s",
+            limit(self.rate_limit_messages),
+            self.rate_limit_seconds
+        )?;
+        writeln!(f, "Duplicates: {}", limit(self.max_duplicates))?;
+        writeln!(f, "Mentions: {}", limit(self.max_mentions))?;
+        writeln!(f, "Invite links: {}", blocked(self.block_invites))?;
+        write!(f, "Banned domains: {}", blocked(self.block_banned_domains))
+    }
+}
+
+impl From<AutomodSettingsRead> for AutomodSettings {
+    fn from(settings_db: AutomodSettingsRead) -> Self {
+        AutomodSettings {
+            enabled: settings_db.enabled,
+            rate_limit_messages: settings_db.rate_limit_messages.max(0) as u32,
+            rate_limit_seconds: settings_db.rate_limit_seconds.max(0) as u32,
+            max_duplicates: settings_db.max_duplicates.max(0) as u32,
+            max_mentions: settings_db.max_mentions.max(0) as u32,
+            block_invites: settings_db.block_invites,
+            block_banned_domains: settings_db.block_banned_domains,
+            action: settings_db.action.parse().unwrap_or_default(),
+            timeout_seconds: settings_db.timeout_seconds.max(0) as u32,
+        }
+    }
+}
+
+impl AutomodSettings {
+    /// Save the automod settings to the database.
+    pub async fn save(&self, pool: &PgPool, guild_id: u64) -> Result<(), CrackedError> {
+        crate::db::GuildEntity::write_automod_settings(pool, guild_id as i64, self)
+            .await
+            .map_err(CrackedError::SQLX)
+    }
+}
+
+/// When a wave of joins counts as a raid. Every check looks at the joins within
+/// `window_seconds`, and a threshold of 0 turns it off.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RaidSettings {
+    pub enabled: bool,
+    /// Joins of any kind.
+    pub join_threshold: u32,
+    pub window_seconds: u32,
+    /// Accounts younger than this count as new.
+    pub new_account_days: u32,
+    pub new_account_threshold: u32,
+    /// Members with look-alike names or the same avatar.
+    pub similar_threshold: u32,
+    /// Where to alert moderators, instead of the mod log.
+    pub alert_channel: Option<u64>,
+    /// Role to ping with the alert.
+    pub alert_role: Option<u64>,
+}
+
+impl Default for RaidSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            join_threshold: 10,
+            window_seconds: 10,
+            new_account_days: 7,
+            new_account_threshold: 5,
+            similar_threshold: 4,
+            alert_channel: None,
+            alert_role: None,
+        }
+    }
+}
+
+impl Display for RaidSettings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let limit = |n: u32| {
+            if n == 0 {
+                "off".to_string()
+            } else {
+                n.to_string()
+            }
+        };
+        writeln!(f, "Enabled: {}", if self.enabled { "yes" } else { "no" })?;
+        writeln!(f, "Window: {}s", self.window_seconds)?;
+        writeln!(f, "Joins: {}", limit(self.join_threshold))?;
+        writeln!(
+            f,
+            "New accounts (under {} days): {}",
+            self.new_account_days,
+            limit(self.new_account_threshold)
+        )?;
+        writeln!(f, "Look-alikes: {}", limit(self.similar_threshold))?;
+        match self.alert_channel {
+            Some(channel) => write!(f, "Alerts: <#{}>", channel)?,
+            None => write!(f, "Alerts: mod log")?,
+        }
+        if let Some(role) = self.alert_role {
+            write!(f, " (pinging <@&{}>)", role)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<RaidSettingsRead> for RaidSettings {
+    fn from(settings_db: RaidSettingsRead) -> Self {
+        RaidSettings {
+            enabled: settings_db.enabled,
+            join_threshold: settings_db.join_threshold.max(0) as u32,
+            window_seconds: settings_db.window_seconds.max(0) as u32,
+            new_account_days: settings_db.new_account_days.max(0) as u32,
+            new_account_threshold: settings_db.new_account_threshold.max(0) as u32,
+            similar_threshold: settings_db.similar_threshold.max(0) as u32,
+            alert_channel: settings_db.alert_channel_id.map(|x| x as u64),
+            alert_role: settings_db.alert_role_id.map(|x| x as u64),
+        }
+    }
+}
+
+impl RaidSettings {
+    /// Save the raid settings to the database.
+    pub async fn save(&self, pool: &PgPool, guild_id: u64) -> Result<(), CrackedError> {
+        crate::db::GuildEntity::write_raid_settings(pool, guild_id as i64, self)
+            .await
+            .map_err(CrackedError::SQLX)
+    }
+}
+
+/// Spoken announcements in voice: track changes, members joining and `/say`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TtsSettings {
+    pub enabled: bool,
+    /// The TTS engine's voice, e.g. `en-us`.
+    pub voice: String,
+    /// Volume of announcements, 1.0 is as loud as the engine makes them.
+    pub volume: f32,
+    pub announce_tracks: bool,
+    pub announce_joins: bool,
+}
+
+impl Default for TtsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            voice: DEFAULT_TTS_VOICE.to_string(),
+            volume: DEFAULT_VOLUME_LEVEL,
+            announce_tracks: true,
+            announce_joins: true,
+        }
+    }
+}
+
+impl Display for TtsSettings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        writeln!(f, "Enabled: {}", yes_no(self.enabled))?;
+        writeln!(f, "Voice: {}", self.voice)?;
+        writeln!(f, "Volume: {}%", (self.volume * 100.0).round())?;
+        writeln!(f, "Track titles: {}", yes_no(self.announce_tracks))?;
+        write!(f, "Joins: {}", yes_no(self.announce_joins))
+    }
+}
+
+impl From<TtsSettingsRead> for TtsSettings {
+    fn from(settings_db: TtsSettingsRead) -> Self {
+        TtsSettings {
+            enabled: settings_db.enabled,
+            voice: settings_db.voice,
+            volume: settings_db.volume as f32,
+            announce_tracks: settings_db.announce_tracks,
+            announce_joins: settings_db.announce_joins,
+        }
+    }
+}
+
+impl TtsSettings {
+    /// Save the TTS settings to the database.
+    pub async fn save(&self, pool: &PgPool, guild_id: u64) -> Result<(), CrackedError> {
+        crate::db::GuildEntity::write_tts_settings(pool, guild_id as i64, self)
+            .await
+            .map_err(CrackedError::SQLX)
+    }
+}
+
+/// How new members prove they're human.
+#[derive(
+    Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationMethod {
+    /// Type back a code generated for them.
+    #[default]
+    #[name = "captcha"]
+    Captcha,
+    /// Type the guild's verification password.
+    #[name = "password"]
+    Password,
+    /// Press a button to accept the rules.
+    #[name = "rules"]
+    Rules,
+}
+
+impl VerificationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationMethod::Captcha => "captcha",
+            VerificationMethod::Password => "password",
+            VerificationMethod::Rules => "rules",
+        }
+    }
+}
+
+impl FromStr for VerificationMethod {
+    type Err = CrackedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "captcha" => Ok(VerificationMethod::Captcha),
+            "password" => Ok(VerificationMethod::Password),
+            "rules" => Ok(VerificationMethod::Rules),
+            _ => Err(CrackedError::Other("Unknown verification method")),
+        }
+    }
+}
+
+/// Where new members get their verification challenge.
+#[derive(
+    Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationDelivery {
+    /// A direct message, falling back to the verification channel if DMs are closed.
+    #[name = "dm"]
+    Dm,
+    /// A message with a button in the verification channel.
+    #[default]
+    #[name = "channel"]
+    Channel,
+}
+
+impl VerificationDelivery {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationDelivery::Dm => "dm",
+            VerificationDelivery::Channel => "channel",
+        }
+    }
+}
+
+impl FromStr for VerificationDelivery {
+    type Err = CrackedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dm" => Ok(VerificationDelivery::Dm),
+            "channel" => Ok(VerificationDelivery::Channel),
+            _ => Err(CrackedError::Other("Unknown verification delivery")),
+        }
+    }
+}
+
+/// Member verification for a guild. Members who pass get the welcome auto role.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct VerificationSettings {
+    pub enabled: bool,
+    pub method: VerificationMethod,
+    pub delivery: VerificationDelivery,
+    pub channel_id: Option<u64>,
+    /// Role new members hold until they pass.
+    pub unverified_role: Option<u64>,
+    pub password: Option<String>,
+    /// Shown with the challenge, and what members accept with the rules method.
+    pub rules: Option<String>,
+    /// Kick members who haven't passed after this many hours. 0 never kicks.
+    pub kick_after_hours: u32,
+}
+
+impl Default for VerificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            method: VerificationMethod::Captcha,
+            delivery: VerificationDelivery::Channel,
+            channel_id: None,
+            unverified_role: None,
+            password: None,
+            rules: None,
+            kick_after_hours: 24,
+        }
+    }
+}
+
+impl Display for VerificationSettings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Enabled: {}", if self.enabled { "yes" } else { "no" })?;
+        writeln!(f, "Method: {}", self.method.as_str())?;
+        writeln!(f, "Delivery: {}", self.delivery.as_str())?;
+        match self.channel_id {
+            Some(id) => writeln!(f, "Channel: <#{}>", id)?,
+            None => writeln!(f, "Channel: none")?,
+        }
+        match self.unverified_role {
+            Some(id) => writeln!(f, "Unverified role: <@&{}>", id)?,
+            None => writeln!(f, "Unverified role: none")?,
+        }
+        if self.kick_after_hours == 0 {
+            write!(f, "Kick unverified: never")
+        } else {
+            write!(f, "Kick unverified after: {}h", self.kick_after_hours)
+        }
+    }
+}
+
+impl From<VerificationSettingsRead> for VerificationSettings {
+    fn from(settings_db: VerificationSettingsRead) -> Self {
+        VerificationSettings {
+            enabled: settings_db.enabled,
+            method: settings_db.method.parse().unwrap_or_default(),
+            delivery: settings_db.delivery.parse().unwrap_or_default(),
+            channel_id: settings_db.channel_id.map(|x| x as u64),
+            unverified_role: settings_db.unverified_role_id.map(|x| x as u64),
+            password: settings_db.password,
+            rules: settings_db.rules,
+            kick_after_hours: settings_db.kick_after_hours.max(0) as u32,
+        }
+    }
+}
+
+impl VerificationSettings {
+    /// Save the verification settings to the database.
+    pub async fn save(&self, pool: &PgPool, guild_id: u64) -> Result<(), CrackedError> {
+        crate::db::GuildEntity::write_verification_settings(pool, guild_id as i64, self)
+            .await
+            .map_err(CrackedError::SQLX)
+    }
+}
+
 /// A struct that represents a user's permission level for a guild.
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct UserPermission {
@@ -301,7 +775,7 @@ impl UserPermission {
     }
 }
 
-use super::permissions::GenericPermissionSettings;
+use super::permissions::{command_and_parents, GenericPermissionSettings};
 
 // TODO
 //#[derive(Debug, Clone, Serialize, PartialEq)]
@@ -340,6 +814,18 @@ pub struct GuildSettings {
     pub log_settings: Option<LogSettings>,
     #[serde(default = "additional_prefixes_default")]
     pub additional_prefixes: Vec<String>,
+    #[serde(default)]
+    pub dj_role: Option<u64>,
+    #[serde(default = "default_false")]
+    pub dj_mode: bool,
+    #[serde(default)]
+    pub automod_settings: Option<AutomodSettings>,
+    #[serde(default)]
+    pub verification_settings: Option<VerificationSettings>,
+    #[serde(default)]
+    pub raid_settings: Option<RaidSettings>,
+    #[serde(default)]
+    pub tts_settings: Option<TtsSettings>,
 }
 
 /// Default value function for serialization that is false.
@@ -426,6 +912,8 @@ impl From<GuildSettingsRead> for GuildSettings {
         settings.welcome_settings = None; // FIXME
         settings.log_settings = None; //FIXME
         settings.additional_prefixes = settings_db.additional_prefixes;
+        settings.dj_role = settings_db.dj_role_id.map(|x| x as u64);
+        settings.dj_mode = settings_db.dj_mode;
         settings
     }
 }
@@ -470,6 +958,12 @@ impl GuildSettings {
             welcome_settings: None,
             log_settings: None,
             additional_prefixes: Vec::new(),
+            dj_role: None,
+            dj_mode: false,
+            automod_settings: None,
+            verification_settings: None,
+            raid_settings: None,
+            tts_settings: None,
         }
     }
 
@@ -686,6 +1180,18 @@ impl GuildSettings {
         Self { timeout, ..self }
     }
 
+    /// Set the DJ role, mutating.
+    pub fn set_dj_role(&mut self, dj_role: Option<u64>) -> &mut Self {
+        self.dj_role = dj_role;
+        self
+    }
+
+    /// Turn DJ mode on or off, mutating.
+    pub fn set_dj_mode(&mut self, dj_mode: bool) -> &mut Self {
+        self.dj_mode = dj_mode;
+        self
+    }
+
     /// Set the welcome settings, mutating
     pub fn set_welcome_settings(&mut self, welcome_settings: WelcomeSettings) -> &mut Self {
         self.welcome_settings = Some(welcome_settings);
@@ -726,6 +1232,7 @@ impl GuildSettings {
                 .clone()
                 .map(|x| x.auto_role)
                 .unwrap_or_default(),
+            leave_message: self.welcome_settings.clone().and_then(|x| x.leave_message),
 
             ..Default::default()
         });
@@ -788,6 +1295,41 @@ impl GuildSettings {
         }
     }
 
+    /// Return a copy of the settings with the given automod settings.
+    pub fn with_automod_settings(self, automod_settings: Option<AutomodSettings>) -> Self {
+        Self {
+            automod_settings,
+            ..self
+        }
+    }
+
+    /// Return a copy of the settings with the given verification settings.
+    pub fn with_verification_settings(
+        self,
+        verification_settings: Option<VerificationSettings>,
+    ) -> Self {
+        Self {
+            verification_settings,
+            ..self
+        }
+    }
+
+    /// Return a copy of the settings with the given raid settings.
+    pub fn with_raid_settings(self, raid_settings: Option<RaidSettings>) -> Self {
+        Self {
+            raid_settings,
+            ..self
+        }
+    }
+
+    /// Return a copy of the settings with the given TTS settings.
+    pub fn with_tts_settings(self, tts_settings: Option<TtsSettings>) -> Self {
+        Self {
+            tts_settings,
+            ..self
+        }
+    }
+
     /// Set the guild name, mutating.
     pub fn set_prefix(&mut self, prefix: &str) -> &mut Self {
         self.prefix = prefix.to_string();
@@ -988,6 +1530,20 @@ impl GuildSettings {
         None
     }
 
+    pub fn get_mod_log_channel(&self) -> Option<ChannelId> {
+        self.log_settings
+            .as_ref()
+            .and_then(|log_settings| log_settings.get_mod_log_channel())
+    }
+
+    /// Set the moderation log channel, mutating.
+    pub fn set_mod_log_channel(&mut self, channel_id: u64) -> &mut Self {
+        self.log_settings
+            .get_or_insert_with(LogSettings::default)
+            .set_mod_log_channel(channel_id);
+        self
+    }
+
     pub fn get_join_leave_log_channel(&self) -> Option<ChannelId> {
         if let Some(log_settings) = &self.log_settings {
             if let Some(channel_id) = log_settings.join_leave_log_channel {
@@ -1008,6 +1564,67 @@ impl GuildSettings {
         self.command_settings.get("music").cloned()
     }
 
+    /// Get the permissions for editing the guild's shared playlists.
+    pub fn get_playlist_permissions(&self) -> Option<GenericPermissionSettings> {
+        self.command_settings.get("playlist").cloned()
+    }
+
+    /// Get the command settings that apply to a command, most specific first: the
+    /// command itself, its parent commands, then its category.
+    pub fn get_command_permission_chain(
+        &self,
+        category: Option<&str>,
+        qualified_name: &str,
+    ) -> Vec<&GenericPermissionSettings> {
+        command_and_parents(qualified_name)
+            .map(str::to_string)
+            .chain(category.map(str::to_lowercase))
+            .filter_map(|key| self.command_settings.get(&key))
+            .collect()
+    }
+
+    /// Check whether a member may run a command in a channel. The most specific
+    /// explicit allow or deny for the command wins, while user, role and channel
+    /// restrictions from every level apply.
+    pub fn check_command_permissions(
+        &self,
+        category: Option<&str>,
+        qualified_name: &str,
+        user: u64,
+        roles: &[u64],
+        channel_id: ChannelId,
+    ) -> Result<(), CrackedError> {
+        let chain = self.get_command_permission_chain(category, qualified_name);
+
+        let command_allowed = chain
+            .iter()
+            .find_map(|perms| perms.explicit_command_rule(qualified_name))
+            .unwrap_or_else(|| chain.iter().all(|perms| perms.allows_unlisted_commands()));
+        if !command_allowed
+            || !chain
+                .iter()
+                .all(|perms| perms.is_member_allowed(user, roles))
+        {
+            return Err(CrackedError::CommandNotAllowed(
+                qualified_name.to_string().into(),
+            ));
+        }
+
+        match chain
+            .iter()
+            .find(|perms| !perms.is_channel_allowed(channel_id.get()))
+        {
+            Some(perms) => Err(CrackedError::CommandNotAllowedInChannel(
+                perms
+                    .allowed_channels
+                    .iter()
+                    .next()
+                    .map(|&c| ChannelId::new(c)),
+            )),
+            None => Ok(()),
+        }
+    }
+
     /// Adds a user to the denied music users list.
     pub async fn add_denied_music_user(&mut self, user_id: UserId) -> CrackedResult<bool> {
         let user_id = user_id.get();
@@ -1121,4 +1738,71 @@ mod test {
         assert_eq!(default_false(), false);
         assert_eq!(default_true(), true);
     }
+
+    #[test]
+    fn test_check_command_permissions_inherits_from_category() {
+        use crate::{errors::CrackedError, guild::permissions::GenericPermissionSettings};
+        use serenity::all::ChannelId;
+
+        let mut settings =
+            crate::guild::settings::GuildSettings::new(GuildId::new(123), None, None);
+        let channel = ChannelId::new(1);
+        let dj = 10;
+
+        // Only DJs may change the volume.
+        let mut volume = GenericPermissionSettings::default();
+        volume.add_allowed_role(dj);
+        settings
+            .command_settings
+            .insert("volume".to_string(), volume);
+        assert!(settings
+            .check_command_permissions(Some("Music"), "volume", 1, &[dj], channel)
+            .is_ok());
+        assert_eq!(
+            settings.check_command_permissions(Some("Music"), "volume", 1, &[], channel),
+            Err(CrackedError::CommandNotAllowed("volume".into()))
+        );
+        assert!(settings
+            .check_command_permissions(Some("Music"), "play", 1, &[], channel)
+            .is_ok());
+
+        // Deny the playlist commands for the whole category, except `playlist list`.
+        let mut music = GenericPermissionSettings::default();
+        music.add_denied_command("playlist".to_string());
+        music.add_allowed_channel(channel.get());
+        settings.command_settings.insert("music".to_string(), music);
+        let mut list = GenericPermissionSettings::default();
+        list.add_allowed_command("playlist list".to_string());
+        settings
+            .command_settings
+            .insert("playlist list".to_string(), list);
+        assert!(settings
+            .check_command_permissions(Some("Music"), "playlist create", 1, &[], channel)
+            .is_err());
+        assert!(settings
+            .check_command_permissions(Some("Music"), "playlist list", 1, &[], channel)
+            .is_ok());
+
+        // Category channel restrictions apply to every command in it.
+        assert_eq!(
+            settings.check_command_permissions(Some("Music"), "play", 1, &[], ChannelId::new(2)),
+            Err(CrackedError::CommandNotAllowedInChannel(Some(channel)))
+        );
+        assert!(settings
+            .check_command_permissions(Some("Utility"), "ping", 1, &[], ChannelId::new(2))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_dj_settings() {
+        let mut settings = GuildSettings::default();
+        assert!(!settings.dj_mode);
+        assert_eq!(settings.dj_role, None);
+
+        settings.set_dj_mode(true).set_dj_role(Some(42));
+        let json = serde_json::to_string(&settings).unwrap();
+        let read: GuildSettings = serde_json::from_str(&json).unwrap();
+        assert!(read.dj_mode);
+        assert_eq!(read.dj_role, Some(42));
+    }
 }
@@ -0,0 +1,299 @@
+//! Temp voice channels. Joining a hub channel creates a voice channel for the
+//! member, named from the hub's template, and moves them into it. The owner
+//! controls it with `/vc`, and it's deleted once it's empty. Temp channels are
+//! kept in the database, so ones left empty while we were offline are cleaned
+//! up on startup.
+use crate::{
+    db::{TempVoiceChannel, TempVoiceHub},
+    messaging::{
+        messages::{TEMP_VOICE_EMPTY, TEMP_VOICE_REASON},
+        template::{Template, TemplateContext, TemplateError},
+    },
+    CrackedError, Data,
+};
+use rand::Rng;
+use serenity::all::{
+    Cache, ChannelId, ChannelType, Context as SerenityContext, CreateChannel, GuildId, Http,
+    PermissionOverwrite, PermissionOverwriteType, Permissions, RoleId, UserId, VoiceState,
+};
+use sqlx::PgPool;
+
+/// The template temp channels are named from when a hub doesn't set one.
+pub const DEFAULT_TEMP_VOICE_NAME: &str = "{user.name}'s channel";
+/// Discord's limit on channel names.
+pub const MAX_CHANNEL_NAME: usize = 100;
+/// Discord's limit on a voice channel's user limit.
+pub const MAX_USER_LIMIT: u32 = 99;
+
+/// Check a hub's name template.
+pub fn parse_name_template(template: &str) -> Result<Template, TemplateError> {
+    template.parse()
+}
+
+/// Name a temp channel from a hub's template. Falls back to the default if it
+/// renders to nothing, and is cut to Discord's limit.
+pub fn render_channel_name<R: Rng + ?Sized>(
+    template: &str,
+    ctx: &TemplateContext,
+    rng: &mut R,
+) -> String {
+    let rendered = parse_name_template(template)
+        .map(|template| template.render(ctx, rng).content)
+        .unwrap_or_default();
+    let name = match rendered.trim() {
+        "" => format!("{}'s channel", ctx.user_name),
+        name => name.to_string(),
+    };
+    truncate_name(&name)
+}
+
+/// Cut a channel name to Discord's limit.
+pub fn truncate_name(name: &str) -> String {
+    name.chars().take(MAX_CHANNEL_NAME).collect()
+}
+
+/// How many members are in a voice channel, from the cache.
+pub fn occupants(cache: &Cache, guild_id: GuildId, channel_id: ChannelId) -> Option<usize> {
+    let guild = guild_id.to_guild_cached(cache)?;
+    Some(
+        guild
+            .voice_states
+            .iter()
+            .filter(|state| state.channel_id == Some(channel_id))
+            .count(),
+    )
+}
+
+/// The @everyone role shares the guild's id.
+fn everyone_role(guild_id: GuildId) -> RoleId {
+    RoleId::new(guild_id.get())
+}
+
+/// Lock or unlock a temp channel. Locked, only its owner can join.
+pub async fn set_locked(
+    http: &Http,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    owner_id: UserId,
+    locked: bool,
+) -> Result<(), CrackedError> {
+    let everyone = PermissionOverwriteType::Role(everyone_role(guild_id));
+    if !locked {
+        channel_id
+            .delete_permission(http, everyone, Some(TEMP_VOICE_REASON))
+            .await?;
+        return Ok(());
+    }
+    let overwrites = [
+        PermissionOverwrite {
+            allow: Permissions::empty(),
+            deny: Permissions::CONNECT,
+            kind: everyone,
+        },
+        PermissionOverwrite {
+            allow: Permissions::CONNECT,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Member(owner_id),
+        },
+    ];
+    for overwrite in overwrites {
+        channel_id
+            .create_permission(http, overwrite, Some(TEMP_VOICE_REASON))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Create a temp channel for a member who joined a hub and move them into it.
+#[cfg(not(tarpaulin_include))]
+async fn create_temp_channel(
+    ctx: &SerenityContext,
+    pool: &PgPool,
+    guild_id: GuildId,
+    hub: &TempVoiceHub,
+    state: &VoiceState,
+) -> Result<(), CrackedError> {
+    let user = match &state.member {
+        Some(member) => member.user.clone(),
+        None => state.user_id.to_user(&ctx.http).await?,
+    };
+    if user.bot() {
+        return Ok(());
+    }
+    let hub_id = ChannelId::new(hub.channel_id as u64);
+    let category = guild_id
+        .to_guild_cached(&ctx.cache)
+        .and_then(|guild| guild.channels.get(&hub_id).and_then(|hub| hub.parent_id));
+    let name = {
+        let template_ctx = TemplateContext::for_guild(&ctx.cache, guild_id, &user);
+        render_channel_name(&hub.name_template, &template_ctx, &mut rand::thread_rng())
+    };
+
+    let mut create = CreateChannel::new(name)
+        .kind(ChannelType::Voice)
+        .audit_log_reason(TEMP_VOICE_REASON);
+    if let Some(category) = category {
+        create = create.category(category);
+    }
+    if hub.user_limit > 0 {
+        create = create.user_limit(hub.user_limit as u32);
+    }
+    let channel = guild_id.create_channel(&ctx.http, create).await?;
+    TempVoiceChannel::create(
+        pool,
+        guild_id.get() as i64,
+        channel.id.get() as i64,
+        hub.channel_id,
+        user.id.get() as i64,
+    )
+    .await?;
+
+    // They may have left the hub already, leaving the channel empty.
+    if let Err(e) = guild_id.move_member(&ctx.http, user.id, channel.id).await {
+        tracing::debug!("Couldn't move {} into their temp channel: {}", user.id, e);
+        delete_temp_channel(&ctx.http, pool, channel.id).await?;
+    }
+    Ok(())
+}
+
+/// Delete a temp channel and forget it.
+#[cfg(not(tarpaulin_include))]
+async fn delete_temp_channel(
+    http: &Http,
+    pool: &PgPool,
+    channel_id: ChannelId,
+) -> Result<(), CrackedError> {
+    if let Err(e) = channel_id.delete(http, Some(TEMP_VOICE_EMPTY)).await {
+        // Already deleted by hand, most likely.
+        tracing::debug!("Couldn't delete temp channel {}: {}", channel_id, e);
+    }
+    TempVoiceChannel::delete(pool, channel_id.get() as i64).await?;
+    Ok(())
+}
+
+/// Delete a temp channel if nobody's left in it.
+#[cfg(not(tarpaulin_include))]
+async fn delete_if_empty(
+    ctx: &SerenityContext,
+    pool: &PgPool,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<(), CrackedError> {
+    if TempVoiceChannel::get(pool, channel_id.get() as i64)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+    if occupants(&ctx.cache, guild_id, channel_id) == Some(0) {
+        delete_temp_channel(&ctx.http, pool, channel_id).await?;
+    }
+    Ok(())
+}
+
+/// Create a temp channel when a member joins a hub, and delete a temp channel
+/// when its last member leaves.
+#[cfg(not(tarpaulin_include))]
+pub async fn handle_temp_voice(
+    ctx: &SerenityContext,
+    data: &Data,
+    old: Option<&VoiceState>,
+    new: &VoiceState,
+) -> Result<(), CrackedError> {
+    let (Some(guild_id), Some(pool)) = (new.guild_id, &data.database_pool) else {
+        return Ok(());
+    };
+    let before = old.and_then(|state| state.channel_id);
+    if before == new.channel_id {
+        return Ok(());
+    }
+    if let Some(channel_id) = new.channel_id {
+        if let Some(hub) = TempVoiceHub::get(pool, channel_id.get() as i64).await? {
+            create_temp_channel(ctx, pool, guild_id, &hub, new).await?;
+        }
+    }
+    if let Some(channel_id) = before {
+        delete_if_empty(ctx, pool, guild_id, channel_id).await?;
+    }
+    Ok(())
+}
+
+/// Delete temp channels left empty while we were offline, and forget ones that
+/// were deleted by hand. Returns how many were cleaned up.
+#[cfg(not(tarpaulin_include))]
+pub async fn cleanup_temp_channels(
+    cache: &Cache,
+    http: &Http,
+    pool: &PgPool,
+) -> Result<usize, CrackedError> {
+    let mut cleaned = 0;
+    for temp in TempVoiceChannel::list_all(pool).await? {
+        let guild_id = GuildId::new(temp.guild_id as u64);
+        let channel_id = ChannelId::new(temp.channel_id as u64);
+        let exists = match guild_id.to_guild_cached(cache) {
+            Some(guild) => guild.channels.contains_key(&channel_id),
+            // Not in the guild anymore, or it's unavailable.
+            None => continue,
+        };
+        if !exists {
+            TempVoiceChannel::delete(pool, temp.channel_id).await?;
+            cleaned += 1;
+        } else if occupants(cache, guild_id, channel_id) == Some(0) {
+            delete_temp_channel(http, pool, channel_id).await?;
+            cleaned += 1;
+        }
+    }
+    Ok(cleaned)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::Duration;
+
+    fn template_ctx(name: &str) -> TemplateContext {
+        TemplateContext {
+            user_id: UserId::new(1),
+            user_name: name.to_string(),
+            guild_name: "Guild".to_string(),
+            member_count: Some(10),
+            account_age: Duration::from_secs(0),
+            inviter: None,
+        }
+    }
+
+    #[test]
+    fn test_render_channel_name() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let ctx = template_ctx("alice");
+        assert_eq!(
+            render_channel_name(DEFAULT_TEMP_VOICE_NAME, &ctx, &mut rng),
+            "alice's channel"
+        );
+        assert_eq!(
+            render_channel_name("🎮 {user.name} @ {guild.name}", &ctx, &mut rng),
+            "🎮 alice @ Guild"
+        );
+        // Nothing left, or a broken template, gets the default.
+        assert_eq!(
+            render_channel_name("{if inviter}{inviter}{end}", &ctx, &mut rng),
+            "alice's channel"
+        );
+        assert_eq!(
+            render_channel_name("{oops", &ctx, &mut rng),
+            "alice's channel"
+        );
+
+        let long = template_ctx(&"a".repeat(150));
+        let name = render_channel_name("{user.name}", &long, &mut rng);
+        assert_eq!(name.chars().count(), MAX_CHANNEL_NAME);
+    }
+
+    #[test]
+    fn test_truncate_name() {
+        assert_eq!(truncate_name("short"), "short");
+        let name = "é".repeat(120);
+        assert_eq!(truncate_name(&name), "é".repeat(MAX_CHANNEL_NAME));
+    }
+}
@@ -0,0 +1,278 @@
+//! Voice time accounting. The voice state poll samples who's in which channel
+//! and how; each member's time is kept as intervals that split wherever their
+//! channel, mute, deafen, stream or camera state changes. An interval's end is
+//! moved up every poll it's still current, so it's never more than a poll
+//! behind, even if we go offline.
+use crate::{db::VoiceActivity, CrackedError};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serenity::all::{Cache, ChannelId, GuildId, UserId, VoiceState};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// How often the voice state poll runs.
+pub const VOICE_ACTIVITY_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How a member is sitting in voice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VoiceFlags {
+    pub self_mute: bool,
+    pub self_deaf: bool,
+    /// Server muted.
+    pub mute: bool,
+    /// Server deafened.
+    pub deaf: bool,
+    pub streaming: bool,
+    pub video: bool,
+    /// In the guild's AFK channel.
+    pub afk: bool,
+}
+
+impl VoiceFlags {
+    pub fn new(state: &VoiceState, afk: bool) -> Self {
+        Self {
+            self_mute: state.self_mute(),
+            self_deaf: state.self_deaf(),
+            mute: state.mute(),
+            deaf: state.deaf(),
+            streaming: state.self_stream().unwrap_or_default(),
+            video: state.self_video(),
+            afk,
+        }
+    }
+}
+
+/// A member seen in voice by a poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoiceSample {
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub flags: VoiceFlags,
+}
+
+/// An interval still being recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OpenInterval {
+    id: i64,
+    channel_id: ChannelId,
+    flags: VoiceFlags,
+}
+
+/// What a poll means for a guild's open intervals.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct VoiceChanges {
+    /// Intervals whose members are the same; they're extended to now.
+    pub extend: Vec<i64>,
+    /// New intervals to start, for members who joined or changed.
+    pub open: Vec<VoiceSample>,
+    /// Members who left. Their intervals end when they were last seen.
+    pub left: Vec<UserId>,
+}
+
+/// Each guild's open intervals.
+#[derive(Debug, Default)]
+pub struct VoiceActivityTracker {
+    guilds: HashMap<GuildId, HashMap<UserId, OpenInterval>>,
+}
+
+impl VoiceActivityTracker {
+    /// Compare a guild's poll with its open intervals. A member whose channel
+    /// or state changed has their interval extended to now and a new one
+    /// started.
+    pub fn diff(&self, guild_id: GuildId, samples: &[VoiceSample]) -> VoiceChanges {
+        let empty = HashMap::new();
+        let open = self.guilds.get(&guild_id).unwrap_or(&empty);
+        let mut changes = VoiceChanges::default();
+        for sample in samples {
+            match open.get(&sample.user_id) {
+                Some(interval)
+                    if interval.channel_id == sample.channel_id
+                        && interval.flags == sample.flags =>
+                {
+                    changes.extend.push(interval.id)
+                },
+                Some(interval) => {
+                    changes.extend.push(interval.id);
+                    changes.open.push(*sample);
+                },
+                None => changes.open.push(*sample),
+            }
+        }
+        changes.left = open
+            .keys()
+            .filter(|user_id| !samples.iter().any(|s| s.user_id == **user_id))
+            .copied()
+            .collect();
+        changes
+    }
+
+    /// Remember a newly started interval.
+    pub fn opened(&mut self, guild_id: GuildId, sample: &VoiceSample, id: i64) {
+        let interval = OpenInterval {
+            id,
+            channel_id: sample.channel_id,
+            flags: sample.flags,
+        };
+        self.guilds
+            .entry(guild_id)
+            .or_default()
+            .insert(sample.user_id, interval);
+    }
+
+    /// Forget the intervals of members who left.
+    pub fn left(&mut self, guild_id: GuildId, users: &[UserId]) {
+        if let Some(open) = self.guilds.get_mut(&guild_id) {
+            open.retain(|user_id, _| !users.contains(user_id));
+        }
+    }
+}
+
+/// Everyone in a guild's voice channels, except bots, from the cache.
+pub fn sample_voice(cache: &Cache, guild_id: GuildId) -> Vec<VoiceSample> {
+    let Some(guild) = guild_id.to_guild_cached(cache) else {
+        return vec![];
+    };
+    let afk_channel = guild.afk_metadata.as_ref().map(|afk| afk.afk_channel_id);
+    guild
+        .voice_states
+        .iter()
+        .filter(|state| !state.member.as_ref().is_some_and(|m| m.user.bot()))
+        .filter_map(|state| {
+            let channel_id = state.channel_id?;
+            Some(VoiceSample {
+                user_id: state.user_id,
+                channel_id,
+                flags: VoiceFlags::new(state, Some(channel_id) == afk_channel),
+            })
+        })
+        .collect()
+}
+
+/// Record a guild's voice poll.
+#[cfg(not(tarpaulin_include))]
+pub async fn record_voice_activity(
+    pool: &PgPool,
+    tracker: &mut VoiceActivityTracker,
+    guild_id: GuildId,
+    samples: &[VoiceSample],
+    now: NaiveDateTime,
+) -> Result<(), CrackedError> {
+    let changes = tracker.diff(guild_id, samples);
+    VoiceActivity::extend(pool, &changes.extend, now).await?;
+    tracker.left(guild_id, &changes.left);
+    for sample in &changes.open {
+        let interval = VoiceActivity::open(
+            pool,
+            guild_id.get() as i64,
+            sample.user_id.get() as i64,
+            sample.channel_id.get() as i64,
+            sample.flags,
+            now,
+        )
+        .await?;
+        tracker.opened(guild_id, sample, interval.id);
+    }
+    Ok(())
+}
+
+/// Which stretch of time voice stats cover.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum VoiceStatsPeriod {
+    /// The past 24 hours.
+    #[name = "day"]
+    Day,
+    /// The past 7 days.
+    #[default]
+    #[name = "week"]
+    Week,
+    #[name = "all time"]
+    All,
+}
+
+impl VoiceStatsPeriod {
+    /// When the period starts, or `None` for all time.
+    pub fn since(&self, now: DateTime<Utc>) -> Option<NaiveDateTime> {
+        match self {
+            Self::Day => Some((now - Duration::days(1)).naive_utc()),
+            Self::Week => Some((now - Duration::weeks(1)).naive_utc()),
+            Self::All => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Day => "past day",
+            Self::Week => "past week",
+            Self::All => "all time",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample(user: u64, channel: u64, flags: VoiceFlags) -> VoiceSample {
+        VoiceSample {
+            user_id: UserId::new(user),
+            channel_id: ChannelId::new(channel),
+            flags,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let guild_id = GuildId::new(1);
+        let mut tracker = VoiceActivityTracker::default();
+        let talking = VoiceFlags::default();
+        let muted = VoiceFlags {
+            self_mute: true,
+            ..Default::default()
+        };
+
+        let first = [sample(10, 100, talking), sample(20, 100, talking)];
+        let changes = tracker.diff(guild_id, &first);
+        assert_eq!(changes.open, first.to_vec());
+        assert!(changes.extend.is_empty() && changes.left.is_empty());
+        tracker.opened(guild_id, &first[0], 1);
+        tracker.opened(guild_id, &first[1], 2);
+
+        // 10 is the same, 20 muted and 30 joined.
+        let second = [
+            sample(10, 100, talking),
+            sample(20, 100, muted),
+            sample(30, 200, talking),
+        ];
+        let changes = tracker.diff(guild_id, &second);
+        assert_eq!(changes.extend, vec![1, 2]);
+        assert_eq!(changes.open, vec![second[1], second[2]]);
+        assert!(changes.left.is_empty());
+        tracker.opened(guild_id, &second[1], 3);
+        tracker.opened(guild_id, &second[2], 4);
+
+        // 10 moved channel and 30 left.
+        let third = [sample(10, 200, talking), sample(20, 100, muted)];
+        let changes = tracker.diff(guild_id, &third);
+        assert_eq!(changes.extend, vec![1, 3]);
+        assert_eq!(changes.open, vec![third[0]]);
+        assert_eq!(changes.left, vec![UserId::new(30)]);
+        tracker.left(guild_id, &changes.left);
+        assert_eq!(tracker.diff(guild_id, &[]).left.len(), 2);
+
+        // Other guilds are tracked on their own.
+        assert_eq!(tracker.diff(GuildId::new(2), &first).open.len(), 2);
+    }
+
+    #[test]
+    fn test_period_since() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(
+            VoiceStatsPeriod::Day.since(now),
+            DateTime::from_timestamp(1_700_000_000 - 86_400, 0).map(|t| t.naive_utc())
+        );
+        assert_eq!(
+            VoiceStatsPeriod::Week.since(now),
+            DateTime::from_timestamp(1_700_000_000 - 7 * 86_400, 0).map(|t| t.naive_utc())
+        );
+        assert_eq!(VoiceStatsPeriod::All.since(now), None);
+    }
+}
@@ -149,11 +149,22 @@ pub async fn handle_event(
             }
         },
         FullEvent::GuildMemberAddition { new_member } => {
+            let invite =
+                match crate::guild::invites::attribute_join(&ctx.http, &data_global, new_member)
+                    .await
+                {
+                    Ok(invite) => invite,
+                    Err(e) => {
+                        tracing::debug!("Couldn't attribute join to an invite: {}", e);
+                        None
+                    },
+                };
+            let log_data = (new_member, invite);
             log_event!(
                 log_guild_member_addition,
                 guild_settings,
                 event_in,
-                new_member,
+                &log_data,
                 new_member.guild_id,
                 &ctx,
                 event_log,
@@ -210,6 +221,12 @@ pub async fn handle_event(
             if new_message.author.bot() {
                 return Ok(());
             }
+            match crate::moderation::run_automod(ctx, &data_global, new_message).await {
+                // The message is gone, don't log it.
+                Ok(true) => return Ok(()),
+                Ok(false) => {},
+                Err(e) => tracing::warn!("Automod failed: {}", e),
+            }
             log_event!(
                 log_message,
                 guild_settings,
@@ -827,6 +844,18 @@ pub async fn handle_event(
             )
         },
         FullEvent::InviteCreate { data } => {
+            if let Some(guild_id) = data.guild_id {
+                let uses = crate::guild::invites::InviteUses {
+                    uses: 0,
+                    max_uses: data.max_uses as u64,
+                    inviter_id: data.inviter.as_ref().map(|user| user.id),
+                    inviter_name: data.inviter.as_ref().map(|user| user.name.to_string()),
+                };
+                data_global
+                    .invites
+                    .invite_created(guild_id, data.code.to_string(), uses)
+                    .await;
+            }
             let log_data = data;
             log_event!(
                 log_invite_create,
@@ -1,7 +1,7 @@
 use super::serenity::voice_state_diff_str;
 use crate::{
-    errors::CrackedError, http_utils::get_guild_name, messaging::interface::send_log_embed_thumb,
-    Error,
+    errors::CrackedError, guild::invites::InviteAttribution, http_utils::get_guild_name,
+    messaging::interface::send_log_embed_thumb, messaging::messages::UNKNOWN, Error,
 };
 use colored::Colorize;
 use serde::Serialize;
@@ -1104,14 +1104,20 @@ pub async fn log_guild_member_addition(
     channel_id: ChannelId,
     guild_id: GuildId,
     http: &impl CacheHttp,
-    new_member: &Member,
+    log_data: &(&Member, Option<InviteAttribution>),
 ) -> Result<serenity::model::prelude::Message, Error> {
+    let (new_member, invite) = log_data;
     let avatar_url = new_member.user.avatar_url().unwrap_or_default();
     let title = format!("Member Joined: {}", new_member.user.name);
+    let inviter = invite
+        .as_ref()
+        .map(|invite| invite.to_string())
+        .unwrap_or_else(|| UNKNOWN.to_string());
     let description = format!(
-        "Account Created: {}\nJoined: {:?}",
+        "Account Created: {}\nJoined: {:?}\nInvited by: {}",
         new_member.user.id.created_at(),
-        new_member.joined_at
+        new_member.joined_at,
+        inviter
     );
     let guild_name = get_guild_name(http, channel_id, guild_id).await?;
     send_log_embed_thumb(
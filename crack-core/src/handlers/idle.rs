@@ -6,7 +6,7 @@ use std::sync::{
     Arc,
 };
 
-use crate::messaging::messages::IDLE_ALERT;
+use crate::{handlers::SessionKey, messaging::messages::IDLE_ALERT};
 
 /// Handler for the idle event.
 pub struct IdleHandler {
@@ -25,7 +25,17 @@ use songbird::error::JoinError;
 #[async_trait]
 impl EventHandler for IdleHandler {
     async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
-        let manager = &self.serenity_ctx.data::<crate::Data>().songbird;
+        let data = self.serenity_ctx.data::<crate::Data>();
+        let manager = match data
+            .songbird_for(self.guild_id, Some(self.channel_id))
+            .await
+        {
+            Ok(manager) => manager,
+            Err(e) => {
+                tracing::warn!("No session for idle channel {}: {}", self.channel_id, e);
+                return Some(Event::Cancel);
+            },
+        };
         let EventContext::Track(track_list) = ctx else {
             return None;
         };
@@ -61,6 +71,8 @@ impl EventHandler for IdleHandler {
         {
             match manager.remove(self.guild_id).await {
                 Ok(_) => {
+                    // Nothing left for a sleep timer to stop.
+                    data.cancel_sleep_timer(SessionKey::new(self.guild_id, &manager));
                     match self
                         .channel_id
                         .say(&self.serenity_ctx.http, IDLE_ALERT)
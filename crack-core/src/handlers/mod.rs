@@ -2,12 +2,18 @@ pub mod event_log;
 pub mod event_log_impl;
 pub mod idle;
 pub mod serenity;
+pub mod sleep_timer;
 pub mod track_end;
+pub mod track_offset;
+pub mod tts;
 pub mod voice;
 pub mod voice_chat_stats;
 
 pub use self::event_log::handle_event;
 pub use self::idle::IdleHandler;
 pub use self::serenity::SerenityHandler;
+pub use self::sleep_timer::{SessionKey, SleepTimer, SleepTimerKind};
 pub use self::track_end::TrackEndHandler;
+pub use self::track_offset::TrackEndOffsetHandler;
+pub use self::tts::TrackAnnounceHandler;
 //pub use self::voice::VoiceEventHandler;
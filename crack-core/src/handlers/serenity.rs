@@ -1,10 +1,25 @@
+#[cfg(feature = "crack-music")]
+use crate::music::schedule::{DiscordScheduleRunner, ScheduledPlaybacks};
 use crate::{
+    clock::{Poller, SystemClock},
     // commands::queue_aux_metadata,
     db::GuildEntity,
     errors::CrackedError,
-    guild::settings::{GuildSettings, DEFAULT_ACTIVITY},
-    handlers::voice_chat_stats::cam_status_loop,
+    guild::{
+        invites::{attribute_join, fetch_invite_snapshot},
+        operations::GuildSettingsOperations,
+        role_menu::handle_role_menu_interaction,
+        settings::{GuildSettings, DEFAULT_ACTIVITY},
+        temp_voice::{cleanup_temp_channels, handle_temp_voice},
+    },
+    handlers::{
+        tts::announce_join,
+        voice_chat_stats::{cam_status_loop, voice_activity_loop},
+    },
+    messaging::template::{render_welcome, RenderedTemplate, Template, TemplateContext},
+    moderation::{CaseExpiry, DiscordCaseReverser},
     sources::spotify::{Spotify, SPOTIFY},
+    verification::{DiscordUnverifiedKicker, VerificationKicks},
     BotConfig,
     Data,
 };
@@ -16,7 +31,9 @@ use ::serenity::{
 use chrono::{DateTime, Utc};
 use colored::Colorize;
 // use dashmap;
-use poise::serenity_prelude::{self as serenity, Error as SerenityError, Member, Mentionable};
+use poise::serenity_prelude::{
+    self as serenity, Error as SerenityError, Interaction, Member, Mentionable, User,
+};
 use serenity::CacheHttp;
 use serenity::{
     async_trait,
@@ -80,6 +97,21 @@ impl EventHandler for SerenityHandler {
             "new member: ".white(),
             new_member.to_string().white()
         );
+        match crate::moderation::check_join(&ctx, &self.data, &new_member).await {
+            Ok(Some(trigger)) => tracing::warn!("Raid in {}: {}", new_member.guild_id, trigger),
+            Ok(None) => {},
+            Err(err) => tracing::error!("Error checking for a raid: {}", err),
+        }
+        // Members who have to verify first get the auto role once they pass.
+        let verifying =
+            match crate::verification::start_verification(&ctx, &self.data, &new_member).await {
+                Ok(verifying) => verifying,
+                Err(err) => {
+                    tracing::error!("Error starting verification: {}", err);
+                    true
+                },
+            };
+
         let guild_id = new_member.guild_id;
         let guild_settings_map = self.data.guild_settings_map.read().await.clone();
         let guild_settings = guild_settings_map.get(&guild_id);
@@ -99,32 +131,35 @@ impl EventHandler for SerenityHandler {
 
         tracing::trace!("welcome: {:?}", welcome);
 
-        match (welcome.message, welcome.channel_id) {
-            (None, _) => {},
-            (_, None) => {},
-            (Some(message), Some(channel)) => {
-                let channel = serenity::ChannelId::new(channel);
-                let x = channel
-                    .send_message(
-                        ctx.http(),
-                        CreateMessage::default().content({
-                            if message.contains("{user}") {
-                                message.replace(
-                                    "{user}",
-                                    new_member.user.mention().to_string().as_str(),
-                                )
-                            } else {
-                                format_args!("{} {user}", message, user = new_member.user.mention())
-                                    .to_string()
-                            }
-                        }),
-                    )
-                    .await;
-                tracing::info!("x: {:?}", x.unwrap());
-            },
-        };
+        if let (Some(message), Some(channel)) = (&welcome.message, welcome.channel_id) {
+            let invite = attribute_join(&ctx.http, &self.data, &new_member)
+                .await
+                .unwrap_or_else(|err| {
+                    tracing::debug!("Couldn't attribute join to an invite: {}", err);
+                    None
+                });
+            let inviter = invite.and_then(|invite| {
+                invite
+                    .inviter_name
+                    .or(invite.inviter_id.map(|id| id.mention().to_string()))
+            });
+            let template_ctx = TemplateContext::for_guild(&ctx.cache, guild_id, &new_member.user)
+                .with_inviter(inviter);
+            let rendered = render_welcome(message, &template_ctx, &mut rand::thread_rng())
+                .unwrap_or_else(|err| {
+                    tracing::warn!("Bad welcome message in {}, sent as is: {}", guild_id, err);
+                    RenderedTemplate::raw(message)
+                });
+            let channel = serenity::ChannelId::new(channel);
+            if let Err(err) = channel
+                .send_message(ctx.http(), rendered.to_message())
+                .await
+            {
+                tracing::error!("Error sending welcome message: {}", err);
+            }
+        }
 
-        if let Some(role_id) = welcome.auto_role {
+        if let Some(role_id) = welcome.auto_role.filter(|_| !verifying) {
             tracing::info!("{}{}", "role_id: ".white(), role_id.to_string().white());
             let role_id = serenity::RoleId::new(role_id);
             match new_member.add_role(ctx.http(), role_id, None).await {
@@ -138,16 +173,75 @@ impl EventHandler for SerenityHandler {
         }
     }
 
+    async fn guild_member_removal(
+        &self,
+        ctx: SerenityContext,
+        guild_id: GuildId,
+        user: User,
+        _member_data_if_available: Option<Member>,
+    ) {
+        let welcome = self
+            .data
+            .get_guild_settings(guild_id)
+            .await
+            .and_then(|settings| settings.welcome_settings);
+        let Some((message, channel)) =
+            welcome.and_then(|welcome| welcome.leave_message.zip(welcome.channel_id))
+        else {
+            return;
+        };
+        let rendered = match message.parse::<Template>() {
+            Ok(template) => {
+                let template_ctx = TemplateContext::for_guild(&ctx.cache, guild_id, &user);
+                template.render(&template_ctx, &mut rand::thread_rng())
+            },
+            Err(err) => {
+                tracing::warn!("Bad leave message in {}, sent as is: {}", guild_id, err);
+                RenderedTemplate::raw(&message)
+            },
+        };
+        let channel = serenity::ChannelId::new(channel);
+        if let Err(err) = channel
+            .send_message(ctx.http(), rendered.to_message())
+            .await
+        {
+            tracing::error!("Error sending leave message: {}", err);
+        }
+    }
+
+    async fn interaction_create(&self, ctx: SerenityContext, interaction: Interaction) {
+        if let Err(err) = handle_role_menu_interaction(&ctx, &self.data, &interaction).await {
+            tracing::error!("Error handling role menu: {}", err);
+        }
+        if let Err(err) =
+            crate::recording::handle_record_interaction(&ctx, &self.data, &interaction).await
+        {
+            tracing::error!("Error handling recording consent: {}", err);
+        }
+        if let Err(err) =
+            crate::verification::handle_verify_interaction(&ctx, &self.data, &interaction).await
+        {
+            tracing::error!("Error handling verification: {}", err);
+        }
+    }
+
     async fn voice_state_update(
         &self,
         ctx: SerenityContext,
-        _old: Option<VoiceState>,
+        old: Option<VoiceState>,
         new: VoiceState,
     ) {
-        // do nothing if this is a voice update event for a user, not a bot
-        if new.user_id != ctx.cache.current_user().id {
-            return;
+        if let Err(err) = handle_temp_voice(&ctx, &self.data, old.as_ref(), &new).await {
+            tracing::error!("Error handling temp voice channels: {}", err);
         }
+        announce_join(&ctx, &self.data, old.as_ref(), &new).await;
+
+        // do nothing if this is a voice update event for a user, not one of our
+        // identities, the main bot or a helper
+        let bot_id = ctx.cache.current_user().id;
+        let Some(manager) = self.data.songbird_of(bot_id, new.user_id) else {
+            return;
+        };
 
         if new.channel_id.is_some() {
             // check the data struct with this guild to see self deafen settings
@@ -167,8 +261,6 @@ impl EventHandler for SerenityHandler {
             }
             return;
         }
-        let manager = ctx.data::<Data>().songbird.clone();
-
         let guild_id = new.guild_id.unwrap();
 
         // This is a voice state update event for the bot
@@ -287,8 +379,68 @@ impl EventHandler for SerenityHandler {
             let ctx3 = arc_ctx.clone();
             let config3 = arc_config.clone();
             if video_status_poll_interval > 0 {
-                cam_status_loop(ctx3.clone(), config3.clone(), guilds.clone()).await;
+                cam_status_loop(ctx3.clone(), config3.clone()).await;
             };
+            voice_activity_loop(arc_ctx.clone());
+
+            #[cfg(feature = "crack-music")]
+            if let Some(pool) = self.data.database_pool.clone() {
+                tracing::info!("Starting playback scheduler");
+                let runner = DiscordScheduleRunner {
+                    serenity_ctx: arc_ctx.clone(),
+                    data: arc_ctx.data::<Data>(),
+                };
+                Poller::new(SystemClock, ScheduledPlaybacks::new(pool, runner)).spawn();
+            }
+
+            if let Some(pool) = self.data.database_pool.clone() {
+                tracing::info!("Starting moderation case expiry scheduler");
+                let reverser = DiscordCaseReverser {
+                    serenity_ctx: arc_ctx.clone(),
+                    data: arc_ctx.data::<Data>(),
+                };
+                Poller::new(SystemClock, CaseExpiry::new(pool, reverser)).spawn();
+            }
+
+            // Remember every configured guild's invites, so the next join can be
+            // attributed to one.
+            let guild_ids = self
+                .data
+                .guild_settings_map
+                .read()
+                .await
+                .keys()
+                .copied()
+                .collect::<Vec<_>>();
+            let (http, data) = (arc_ctx.http.clone(), arc_ctx.data::<Data>());
+            tokio::spawn(async move {
+                for guild_id in guild_ids {
+                    match fetch_invite_snapshot(&http, guild_id).await {
+                        Ok(snapshot) => data.invites.set_snapshot(guild_id, snapshot).await,
+                        Err(e) => tracing::debug!("Can't track invites in {}: {}", guild_id, e),
+                    }
+                }
+            });
+
+            // Temp voice channels that emptied while we were offline.
+            if let Some(pool) = self.data.database_pool.clone() {
+                let (cache, http) = (arc_ctx.cache.clone(), arc_ctx.http.clone());
+                tokio::spawn(async move {
+                    match cleanup_temp_channels(&cache, &http, &pool).await {
+                        Ok(cleaned) => tracing::info!("Cleaned up {} temp voice channels", cleaned),
+                        Err(e) => tracing::error!("Error cleaning up temp voice channels: {}", e),
+                    }
+                });
+            }
+
+            if let Some(pool) = self.data.database_pool.clone() {
+                tracing::info!("Starting verification kick scheduler");
+                let kicker = DiscordUnverifiedKicker {
+                    serenity_ctx: arc_ctx.clone(),
+                    data: arc_ctx.data::<Data>(),
+                };
+                Poller::new(SystemClock, VerificationKicks::new(pool, kicker)).spawn();
+            }
 
             //let pool = self.data.database_pool.clone().unwrap();
             //let tx = setup_workers(pool).await;
@@ -437,11 +589,8 @@ impl SerenityHandler {
             return;
         }
 
-        let Ok(user) = ctx.http.get_current_user().await else {
-            return;
-        };
-
-        if user.id == new.user_id && !new.deaf() {
+        // Only called for our own identities, so this may be a helper.
+        if !new.deaf() {
             guild
                 .unwrap()
                 .edit_member(ctx.http(), new.user_id, EditMember::default().deafen(true))
@@ -0,0 +1,201 @@
+use crate::{messaging::messages::SLEEP_TIMER_EXPIRED, CrackedError, Data};
+use ::serenity::all::{ChannelId, GuildId, Http};
+use songbird::Songbird;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// How long the fade out takes before the bot leaves.
+pub const FADE_OUT_DURATION: Duration = Duration::from_secs(5);
+/// Number of volume steps in the fade out.
+pub const FADE_OUT_STEPS: u32 = 10;
+
+/// Source of unique ids, so an expired task can tell whether its timer was
+/// replaced or cancelled while it slept.
+static NEXT_TIMER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Which music session a sleep timer belongs to. A guild has one session per
+/// bot identity in it, each with its own voice manager. Managers live as long as
+/// the bot, so the address of one tells the sessions apart, also across moves
+/// to another channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    pub guild_id: GuildId,
+    manager: usize,
+}
+
+impl SessionKey {
+    /// The session of `manager` in a guild.
+    pub fn new(guild_id: GuildId, manager: &Arc<Songbird>) -> Self {
+        Self {
+            guild_id,
+            manager: Arc::as_ptr(manager) as usize,
+        }
+    }
+}
+
+/// When a session's sleep timer goes off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepTimerKind {
+    /// Stop at a fixed point in time.
+    At(Instant),
+    /// Stop once the current track finishes playing.
+    AfterCurrentTrack,
+}
+
+/// A session's sleep timer. It lives on [`Data`] rather than on a track, so it
+/// survives skips and queue changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SleepTimer {
+    pub id: u64,
+    pub kind: SleepTimerKind,
+}
+
+impl SleepTimer {
+    /// Create a new timer with a fresh id.
+    pub fn new(kind: SleepTimerKind) -> Self {
+        Self {
+            id: NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed),
+            kind,
+        }
+    }
+
+    /// Time left before the timer goes off, or `None` if it waits for the
+    /// current track to end.
+    pub fn remaining(&self) -> Option<Duration> {
+        match self.kind {
+            SleepTimerKind::At(deadline) => {
+                Some(deadline.saturating_duration_since(Instant::now()))
+            },
+            SleepTimerKind::AfterCurrentTrack => None,
+        }
+    }
+}
+
+/// Start a sleep timer that goes off after `duration`, replacing any existing
+/// timer for the session of `manager`, which may be a helper's. It only stops
+/// that session, and the announcement is posted in `channel_id`.
+pub fn start_sleep_timer(
+    data: Arc<Data>,
+    http: Arc<Http>,
+    manager: Arc<Songbird>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    duration: Duration,
+) -> SleepTimer {
+    let key = SessionKey::new(guild_id, &manager);
+    let timer = SleepTimer::new(SleepTimerKind::At(Instant::now() + duration));
+    data.sleep_timers.insert(key, timer);
+
+    tokio::spawn(async move {
+        tokio::time::sleep(duration).await;
+        // Only go off if this timer wasn't cancelled or replaced in the meantime.
+        if data
+            .sleep_timers
+            .remove_if(&key, |_, t| t.id == timer.id)
+            .is_none()
+        {
+            return;
+        }
+        match fade_out_and_leave(&manager, guild_id).await {
+            Ok(true) => {
+                if let Err(e) = channel_id.say(&http, SLEEP_TIMER_EXPIRED).await {
+                    tracing::error!("Error sending sleep timer alert: {:?}", e);
+                }
+            },
+            Ok(false) => {},
+            Err(e) => tracing::error!("Error stopping for sleep timer: {}", e),
+        }
+    });
+
+    timer
+}
+
+/// Fade out the current track, clear the queue and leave the voice channel.
+/// Returns whether the bot was in a call.
+pub async fn fade_out_and_leave(
+    manager: &Arc<Songbird>,
+    guild_id: GuildId,
+) -> Result<bool, CrackedError> {
+    let Some(call) = manager.get(guild_id) else {
+        return Ok(false);
+    };
+
+    let current = call.lock().await.queue().current();
+    if let Some(track) = current {
+        let volume = track
+            .get_info()
+            .await
+            .map(|info| info.volume)
+            .unwrap_or(1.0);
+        for step in (0..FADE_OUT_STEPS).rev() {
+            let _ = track.set_volume(volume * step as f32 / FADE_OUT_STEPS as f32);
+            tokio::time::sleep(FADE_OUT_DURATION / FADE_OUT_STEPS).await;
+        }
+    }
+
+    stop_and_leave(manager, guild_id).await
+}
+
+/// Clear the queue and leave the voice channel right away. Returns whether the
+/// bot was in a call.
+pub async fn stop_and_leave(
+    manager: &Arc<Songbird>,
+    guild_id: GuildId,
+) -> Result<bool, CrackedError> {
+    let Some(call) = manager.get(guild_id) else {
+        return Ok(false);
+    };
+    call.lock().await.queue().stop();
+    manager.remove(guild_id).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sleep_timer_ids_are_unique() {
+        let a = SleepTimer::new(SleepTimerKind::AfterCurrentTrack);
+        let b = SleepTimer::new(SleepTimerKind::AfterCurrentTrack);
+        assert_ne!(a.id, b.id);
+    }
+
+    #[test]
+    fn test_sleep_timer_remaining() {
+        let timer = SleepTimer::new(SleepTimerKind::At(Instant::now() + Duration::from_secs(60)));
+        let remaining = timer.remaining().unwrap();
+        assert!(remaining <= Duration::from_secs(60));
+        assert!(remaining > Duration::from_secs(59));
+
+        let expired = SleepTimer::new(SleepTimerKind::At(Instant::now()));
+        assert_eq!(expired.remaining(), Some(Duration::ZERO));
+
+        let after = SleepTimer::new(SleepTimerKind::AfterCurrentTrack);
+        assert_eq!(after.remaining(), None);
+    }
+
+    #[test]
+    fn test_session_keys() {
+        let guild_id = GuildId::new(1);
+        let (main, helper) = (Songbird::serenity(), Songbird::serenity());
+        assert_eq!(
+            SessionKey::new(guild_id, &main),
+            SessionKey::new(guild_id, &main.clone())
+        );
+        assert_ne!(
+            SessionKey::new(guild_id, &main),
+            SessionKey::new(guild_id, &helper)
+        );
+        assert_ne!(
+            SessionKey::new(guild_id, &main),
+            SessionKey::new(GuildId::new(2), &main)
+        );
+    }
+}
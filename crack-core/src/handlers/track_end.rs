@@ -2,15 +2,19 @@ use crate::{
     db::PgPoolExtPlayLog,
     errors::{verify, CrackedError},
     guild::operations::GuildSettingsOperations,
+    handlers::{sleep_timer::stop_and_leave, SessionKey},
     messaging::{
         interface::{create_nav_btns, create_queue_embed, send_now_playing},
-        messages::SPOTIFY_AUTH_FAILED,
+        messages::{SLEEP_TIMER_EXPIRED, SPOTIFY_AUTH_FAILED},
+    },
+    music::{
+        link::{leader_of, sync_followers},
+        query::NewQueryType,
     },
-    music::query::NewQueryType,
     sources::spotify::{Spotify, SPOTIFY},
     utils::{
         calculate_num_pages, forget_queue_message, set_track_handle_metadata,
-        set_track_handle_requesting_user,
+        set_track_handle_requesting_user, TrackData,
     },
     CrackedResult,
     Data, //, Error,
@@ -26,7 +30,7 @@ use crack_types::NewAuxMetadata;
 use crack_types::QueryType;
 use serenity::all::{CacheHttp, UserId};
 use songbird::input::AuxMetadata;
-use songbird::{tracks::TrackHandle, Call, Event, EventContext, EventHandler};
+use songbird::{tracks::TrackHandle, Call, Event, EventContext, EventHandler, Songbird};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -86,11 +90,50 @@ fn get_track_states_union(track_states: TrackStates) -> TrackStatesUnion {
     union
 }
 
+/// Whether the tracks that ended were all played over the queue.
+pub(crate) fn is_overlay(track_states: TrackStates) -> bool {
+    !track_states.is_empty()
+        && track_states
+            .iter()
+            .all(|(_, handle)| handle.data::<TrackData>().overlay)
+}
+
 /// Event handler to handle the end of a track.
 #[async_trait]
 impl EventHandler for TrackEndHandler {
     async fn act(&self, event_ctx: &EventContext<'_>) -> Option<Event> {
         tracing::error!("TrackEndHandler");
+
+        // Clips and announcements played over the queue don't move it along.
+        if let EventContext::Track(x) = event_ctx {
+            if is_overlay(x) {
+                return None;
+            }
+        }
+
+        // A linked session's queue is driven by its leader, and followers
+        // move on when the leader does.
+        if leader_of(&self.data, self.guild_id).await.is_some() {
+            return None;
+        }
+        sync_followers(self.data.clone(), self.guild_id, self.call.clone()).await;
+
+        // Only a track that played to its end sets off "stop after current track",
+        // a skip stops the track instead, so the timer carries over to the next one.
+        // Other sessions in the guild keep their own timers.
+        if let EventContext::Track(x) = event_ctx {
+            if get_track_states_union(x).end {
+                let manager = self.data.songbird_for_call(self.guild_id, &self.call);
+                if self
+                    .data
+                    .take_stop_after(SessionKey::new(self.guild_id, &manager))
+                {
+                    self.stop_after_current_track(&manager).await;
+                    return None;
+                }
+            }
+        }
+
         // Handle track error
 
         let autoplay = self.data.get_autoplay(self.guild_id).await;
@@ -199,6 +242,33 @@ impl EventHandler for TrackEndHandler {
     }
 }
 
+impl TrackEndHandler {
+    /// The track a "stop after current track" timer was waiting on has ended, so
+    /// clear the queue, leave, and say goodnight.
+    async fn stop_after_current_track(&self, manager: &Arc<Songbird>) {
+        let channel = match self.data.get_music_channel(self.guild_id).await {
+            Some(c) => Some(c),
+            None => self
+                .call
+                .lock()
+                .await
+                .current_channel()
+                .map(|c| ChannelId::new(c.get())),
+        };
+        match stop_and_leave(manager, self.guild_id).await {
+            Ok(true) => {
+                if let Some(channel) = channel {
+                    if let Err(e) = channel.say(&self.http, SLEEP_TIMER_EXPIRED).await {
+                        tracing::warn!("Error sending sleep timer alert: {}", e);
+                    }
+                }
+            },
+            Ok(false) => {},
+            Err(e) => tracing::warn!("Error leaving after current track: {}", e),
+        }
+    }
+}
+
 use songbird::input::Input as SongbirdInput;
 /// Queues a query and returns the track handle.
 pub async fn queue_query(
@@ -240,6 +310,7 @@ impl EventHandler for ModifyQueueHandler {
         vol.map(|vol| queue.first().map(|track| track.set_volume(vol).unwrap()));
         let cache_http = (Some(&self.cache), self.http.as_ref());
         update_queue_messages(&cache_http, self.data.clone(), &queue, self.guild_id).await;
+        sync_followers(self.data.clone(), self.guild_id, self.call.clone()).await;
 
         None
     }
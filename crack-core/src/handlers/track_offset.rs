@@ -0,0 +1,35 @@
+use self::serenity::async_trait;
+use poise::serenity_prelude as serenity;
+use songbird::{tracks::PlayMode, Event, EventContext, EventHandler};
+use std::time::Duration;
+
+/// How often a track with an end offset checks its position.
+pub const END_OFFSET_POLL: Duration = Duration::from_millis(500);
+
+/// Handler that stops a track once it plays past its end offset, so the queue
+/// moves on as if the track had ended there.
+pub struct TrackEndOffsetHandler {
+    pub end: Duration,
+}
+
+#[cfg(not(tarpaulin_include))]
+#[async_trait]
+impl EventHandler for TrackEndOffsetHandler {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(track_list) = ctx else {
+            return None;
+        };
+        for (state, handle) in track_list.iter() {
+            if matches!(state.playing, PlayMode::End | PlayMode::Stop) {
+                return Some(Event::Cancel);
+            }
+            if state.position >= self.end {
+                if let Err(e) = handle.stop() {
+                    tracing::warn!("Failed to stop track at end offset: {}", e);
+                }
+                return Some(Event::Cancel);
+            }
+        }
+        None
+    }
+}
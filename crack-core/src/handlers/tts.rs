@@ -0,0 +1,130 @@
+use crate::{
+    guild::operations::GuildSettingsOperations,
+    handlers::track_end::is_overlay,
+    messaging::messages::{TTS_JOINED, TTS_NOW_PLAYING},
+    sources::tts::speak,
+    utils::TrackData,
+    Data,
+};
+use ::serenity::{
+    all::{Context as SerenityContext, GuildId, VoiceState},
+    async_trait,
+};
+use songbird::{Call, Event, EventContext, EventHandler};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Speaks the title of each track as it starts, when the guild has it on.
+pub struct TrackAnnounceHandler {
+    pub guild_id: GuildId,
+    pub data: Arc<Data>,
+    pub call: Arc<Mutex<Call>>,
+    /// The last track announced, so resuming from a pause isn't announced again.
+    pub last: std::sync::Mutex<Option<Arc<TrackData>>>,
+}
+
+impl TrackAnnounceHandler {
+    pub fn new(guild_id: GuildId, data: Arc<Data>, call: Arc<Mutex<Call>>) -> Self {
+        Self {
+            guild_id,
+            data,
+            call,
+            last: Default::default(),
+        }
+    }
+
+    /// Remember `track`, returning whether it wasn't the last one announced.
+    fn is_new(&self, track: &Arc<TrackData>) -> bool {
+        let mut last = self.last.lock().unwrap();
+        if last.as_ref().is_some_and(|last| Arc::ptr_eq(last, track)) {
+            return false;
+        }
+        *last = Some(Arc::clone(track));
+        true
+    }
+}
+
+#[async_trait]
+impl EventHandler for TrackAnnounceHandler {
+    async fn act(&self, event_ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(track_states) = event_ctx else {
+            return None;
+        };
+        if is_overlay(track_states) {
+            return None;
+        }
+        let (_, handle) = track_states.first()?;
+        let track = handle.data::<TrackData>();
+        if !self.is_new(&track) {
+            return None;
+        }
+        let settings = self
+            .data
+            .get_guild_settings(self.guild_id)
+            .await
+            .and_then(|settings| settings.tts_settings)?;
+        if !settings.announce_tracks {
+            return None;
+        }
+        let title = track.aux_metadata.read().await.clone()?.title?;
+        if let Err(e) = speak(&self.call, &settings, &now_playing_text(&title)).await {
+            tracing::warn!("Couldn't announce track in {}: {}", self.guild_id, e);
+        }
+        None
+    }
+}
+
+/// Speak "X joined" when a member joins the channel we're playing in.
+#[cfg(not(tarpaulin_include))]
+pub async fn announce_join(
+    ctx: &SerenityContext,
+    data: &Data,
+    old: Option<&VoiceState>,
+    new: &VoiceState,
+) {
+    let (Some(guild_id), Some(channel_id)) = (new.guild_id, new.channel_id) else {
+        return;
+    };
+    if old.and_then(|state| state.channel_id) == Some(channel_id)
+        || new.user_id == ctx.cache.current_user().id
+    {
+        return;
+    }
+    let Some(member) = new.member.as_ref().filter(|member| !member.user.bot()) else {
+        return;
+    };
+    let Some(call) = data.call_in_channel(guild_id, channel_id).await else {
+        return;
+    };
+    let Some(settings) = data
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.tts_settings)
+        .filter(|settings| settings.announce_joins)
+    else {
+        return;
+    };
+    let text = joined_text(member.display_name());
+    if let Err(e) = speak(&call, &settings, &text).await {
+        tracing::warn!("Couldn't announce join in {}: {}", guild_id, e);
+    }
+}
+
+fn now_playing_text(title: &str) -> String {
+    format!("{} {}", TTS_NOW_PLAYING, title)
+}
+
+fn joined_text(name: &str) -> String {
+    format!("{} {}", name, TTS_JOINED)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_announce_text() {
+        assert_eq!(now_playing_text("Song 2"), "Now playing Song 2");
+        assert_eq!(joined_text("cycle"), "cycle joined");
+    }
+}
@@ -1,289 +1,144 @@
-use crate::guild::settings::DEFAULT_VALID_TOKEN;
-use crate::serenity::client::{Context as SerenityContext, EventHandler};
-use serenity::all::{Cache, CacheHttp, Http};
+use crate::errors::CrackedError;
+use crate::recording::Recording;
+use serenity::all::UserId;
 use serenity::async_trait;
-use serenity::model::gateway::Ready;
-use serenity::prelude::RwLock;
-use serenity::secrets::Token;
-use songbird::tracks::PlayMode;
 use songbird::{
     model::payload::{ClientDisconnect, Speaking},
     Event, EventContext, EventHandler as VoiceEventHandler,
 };
-use songbird::{Call, CoreEvent, TrackEvent};
-use std::{mem, slice, sync::Arc};
-
-use crate::errors::CrackedError;
-
-// use crate::{Context, Error};
-// use typemap_rev::TypeMap;
-
-#[allow(dead_code)]
-struct Handler;
-
-#[async_trait]
-impl EventHandler for Handler {
-    async fn ready(&self, _: SerenityContext, ready: Ready) {
-        println!("{} is connected!", ready.user.name);
-    }
-}
-
-// struct AudioBuffer;
-
-// impl TypeMapKey for AudioBuffer {
-//     type Value = Vec<u8>;
-// }
-
-// 10MB (10s not powers of 2 since it gets stored on disk)
-const DEFAULT_BUFFER_SIZE: usize = 100_000_000;
+use songbird::{Call, CoreEvent};
+use std::{collections::HashMap, sync::Arc};
 
+/// Feeds a call's audio to a recording. Discord voice uses RTP, where every
+/// sender has a randomly allocated *Synchronisation Source* (SSRC); speaking
+/// updates tell us which member has which, and voice ticks carry each source's
+/// decoded audio every 20ms.
 pub struct Receiver {
-    pub data: Arc<RwLock<Vec<u8>>>,
-    pub cache: Option<Arc<Cache>>,
-    pub http: Arc<Http>,
-    buf_size: usize,
+    pub recording: Arc<Recording>,
 }
 
 impl Receiver {
-    pub fn new(data: Arc<RwLock<Vec<u8>>>, ctx: Option<SerenityContext>) -> Self {
-        Self {
-            data,
-            buf_size: DEFAULT_BUFFER_SIZE,
-            cache: ctx.clone().map(|x| x.cache().cloned()).unwrap_or_default(),
-            http: ctx.map(|x| x.http.clone()).unwrap_or(Arc::new(Http::new(
-                DEFAULT_VALID_TOKEN.parse::<Token>().expect("Invalid token"),
-            ))),
-        }
+    pub fn new(recording: Arc<Recording>) -> Self {
+        Self { recording }
     }
 
-    // Insert a buffer from the audio stream to the handlers internal buffer.
-    // Clear it every so often to bound the memory usage.
-    async fn insert(&self, buf: &[u8]) {
-        let mut lock = self.data.write().await;
-
-        let n = lock.len();
-        tracing::trace!("AudioBuffer size: {}", n);
-        if n > self.buf_size {
-            // let mut out_file = File::create(format!(
-            //     "file_{:?}.out",
-            //     SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
-            // ))
-            // .await
-            // .unwrap();
-            // use tokio::io::AsyncWriteExt as TokAsyncWriteExt; // for write_all()
-            // out_file.write_all(&buff).await.unwrap();
-            lock.clear();
+    /// Record one voice tick of decoded audio by source.
+    fn tick(&self, voices: HashMap<u32, Vec<i16>>) {
+        let full = self
+            .recording
+            .with_recorder(|recorder| recorder.tick(&voices));
+        match full {
+            Some(Ok(true)) | None => {},
+            Some(Ok(false)) => {
+                tracing::warn!("Recording in {} is full", self.recording.guild_id)
+            },
+            Some(Err(e)) => {
+                tracing::error!(
+                    "Failed to write recording in {}: {}",
+                    self.recording.guild_id,
+                    e
+                )
+            },
         }
-        lock.extend_from_slice(buf);
     }
 }
 
 #[async_trait]
 impl VoiceEventHandler for Receiver {
-    #[allow(unused_variables)]
     async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
         use EventContext as Ctx;
+        if self.recording.is_stopped() {
+            return Some(Event::Cancel);
+        }
         match ctx {
-            Ctx::SpeakingStateUpdate(Speaking {
-                delay,
-                speaking,
-                ssrc,
-                user_id,
-            }) => {
-                // Discord voice calls use RTP, where every sender uses a randomly allocated
-                // *Synchronisation Source* (SSRC) to allow receivers to tell which audio
-                // stream a received packet belongs to. As this number is not derived from
-                // the sender's user_id, only Discord Voice Gateway messages like this one
-                // inform us about which random SSRC a user has been allocated. Future voice
-                // packets will contain *only* the SSRC.
-                //
-                // You can implement logic here so that you can differentiate users'
-                // SSRCs and map the SSRC to the User ID and maintain this state.
-                // Using this map, you can map the `ssrc` in `voice_packet`
-                // to the user ID and handle their audio packets separately.
-                tracing::warn!(
-                    "Speaking state update: user {:?} has SSRC {:?}, using {:?}",
-                    user_id,
-                    ssrc,
-                    speaking,
-                );
-
-                let user_id = user_id.unwrap().0.to_be_bytes();
-                self.data.write().await.extend_from_slice(&user_id);
-
-                // You can implement logic here which reacts to a user starting
-                // or stopping speaking, and to map their SSRC to User ID.
-                tracing::warn!(
-                    "Source {} has {} speaking.",
-                    ssrc,
-                    if speaking.microphone() || speaking.soundshare() || speaking.priority() {
-                        "started"
-                    } else {
-                        "stopped"
-                    },
-                );
-            },
-            Ctx::RtpPacket(data) => {
-                // FIXME: update this to the new library
-                // An event which fires for every received audio packet,
-                // containing the decoded data.
-                let ssrc = data.rtp().get_ssrc();
-                let n = data.packet.len();
-                let (beg, end) = data.packet.split_at(n - data.payload_end_pad);
-                // Can we not do an unsafe?...
-                // Seven months later... We need to do an unsafe because we are not moving
-                // this memory, but reinterpreting the ptr as a slice of a different size.
-                // This is I believe analogous to  `new_type *new_obj = *(new_type *)(void*)&obj;`
-                // in C.
-                let slice_u8: &[u8] = unsafe {
-                    slice::from_raw_parts(beg.as_ptr(), beg.len() * mem::size_of::<u16>())
+            Ctx::SpeakingStateUpdate(Speaking { ssrc, user_id, .. }) => {
+                // Future voice packets will contain *only* the SSRC.
+                let Some(user_id) = user_id.filter(|id| id.0 != 0) else {
+                    return None;
                 };
-                self.insert(slice_u8).await;
-
-                // println!(
-                //     "Audio packet's first 5 samples: {:?}",
-                //     data.packet.get(..5.min(beg.len()))
-                // );
-                // tracing::trace!("RTP packet received: {:?}", data.packet);
+                self.recording
+                    .with_recorder(|recorder| recorder.speaking(*ssrc, UserId::new(user_id.0)));
             },
-            Ctx::RtcpPacket(data) => {
-                // An event which fires for every received rtcp packet,
-                // containing the call statistics and reporting information.
-                tracing::trace!("RTCP packet received: {:?}", data.packet);
+            Ctx::VoiceTick(tick) => {
+                let voices = tick
+                    .speaking
+                    .iter()
+                    .filter_map(|(ssrc, data)| {
+                        data.decoded_voice
+                            .as_ref()
+                            .map(|audio| (*ssrc, audio.clone()))
+                    })
+                    .collect();
+                self.tick(voices);
             },
             Ctx::ClientDisconnect(ClientDisconnect { user_id, .. }) => {
-                // You can implement your own logic here to handle a user who has left the
-                // voice channel e.g., finalise processing of statistics etc.
-                // You will typically need to map the User ID to their SSRC; observed when
-                // first speaking.
-
-                let user_name = tracing::warn!("Client disconnected: user {:?}", user_id);
-            },
-            Ctx::Track(track_data) => {
-                // An event which fires when a new track starts playing.
-                if track_data.is_empty() {
-                    return None;
-                }
-                tracing::warn!("{:?}", track_data);
-                for &(track_state, track_handle) in track_data.iter() {
-                    match track_state.playing {
-                        PlayMode::Play => {
-                            tracing::warn!(
-                                "Track started: {:?} (handle: {:?})",
-                                track_state,
-                                track_handle,
-                            );
-                        },
-                        PlayMode::Pause => {
-                            tracing::warn!(
-                                "Track paused: {:?} (handle: {:?})",
-                                track_state,
-                                track_handle,
-                            );
-                        },
-                        PlayMode::Stop | PlayMode::End => {
-                            tracing::warn!(
-                                "Track ended: {:?} (handle: {:?})",
-                                track_state,
-                                track_handle,
-                            );
-                        },
-                        PlayMode::Errored(_) => {
-                            tracing::warn!(
-                                "Track errored: {:?} (handle: {:?})",
-                                track_state,
-                                track_handle,
-                            );
-                        },
-                        _ => {
-                            // There is a new variant of PlayMode that is not handled.
-                            unimplemented!()
-                        },
-                    }
-                }
-            },
-            Ctx::VoiceTick(_)
-            | Ctx::DriverConnect(_)
-            | Ctx::DriverReconnect(_)
-            | Ctx::DriverDisconnect(_) => {
-                // We won't be registering this struct for any more event classes.
-                tracing::warn!("Event not handled: {:?}", ctx);
-            },
-            _ => {
-                // This should not happen.
-                unimplemented!()
+                // Their track keeps going as silence, so it lines up if they rejoin.
+                tracing::debug!("Client disconnected while recording: user {:?}", user_id);
             },
+            _ => {},
         }
-
         None
     }
 }
 
-/// Registers the voice handlers for a call instance for the bot.
-/// These are kept per guild.
-/// FIXME: This seems to be called too many times?
+/// Registers the recording's voice handlers on a call. They remove themselves
+/// once the recording stops.
 pub async fn register_voice_handlers(
-    buffer: Arc<RwLock<Vec<u8>>>,
+    recording: Arc<Recording>,
     call: Arc<tokio::sync::Mutex<Call>>,
-    ctx: SerenityContext,
 ) -> Result<(), CrackedError> {
-    // NOTE: this skips listening for the actual connection result.
     let mut handler = call.lock().await;
-
-    // allocating memory, need to drop this when y???
-    handler.add_global_event(
-        CoreEvent::SpeakingStateUpdate.into(),
-        Receiver::new(buffer.clone(), Some(ctx.clone())),
-    );
-
-    handler.add_global_event(
-        TrackEvent::End.into(),
-        Receiver::new(buffer.clone(), Some(ctx.clone())),
-    );
-
-    handler.add_global_event(
-        CoreEvent::RtpPacket.into(),
-        Receiver::new(buffer.clone(), Some(ctx.clone())),
-    );
-
-    handler.add_global_event(
-        CoreEvent::RtcpPacket.into(),
-        Receiver::new(buffer.clone(), Some(ctx.clone())),
-    );
-
-    handler.add_global_event(
-        CoreEvent::ClientDisconnect.into(),
-        Receiver::new(buffer.clone(), Some(ctx.clone())),
-    );
+    for event in [
+        CoreEvent::SpeakingStateUpdate,
+        CoreEvent::VoiceTick,
+        CoreEvent::ClientDisconnect,
+    ] {
+        handler.add_global_event(event.into(), Receiver::new(recording.clone()));
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use serenity::all::{ChannelId, GuildId};
     use serenity_voice_model::id::UserId as VoiceUserId;
     use songbird::model::{payload::Speaking, SpeakingState};
 
     #[tokio::test]
     async fn test_receiver() {
-        let buffer = Arc::new(RwLock::new(Vec::new()));
-        let receiver = Receiver::new(buffer.clone(), None);
-        let want = VoiceUserId(0xAA);
+        let dir = std::env::temp_dir().join(format!("crack-recording-{}", std::process::id()));
+        let recording = Arc::new(
+            Recording::start_in(
+                dir.clone(),
+                GuildId::new(1),
+                ChannelId::new(2),
+                UserId::new(3),
+                chrono::Utc::now(),
+            )
+            .unwrap(),
+        );
+        let receiver = Receiver::new(recording.clone());
 
         let speaking = Speaking {
             delay: Some(0),
             speaking: SpeakingState::MICROPHONE,
-            ssrc: 0,
-            user_id: Some(want),
+            ssrc: 7,
+            user_id: Some(VoiceUserId(0xAA)),
         };
-
         let ctx = EventContext::SpeakingStateUpdate(speaking);
-        let _ = receiver.act(&ctx).await;
-        let buf = receiver.data.read().await.clone();
-
-        let user_id = u64::from_be_bytes(buf.as_slice().try_into().unwrap());
-        let got = VoiceUserId(user_id);
-
-        assert_eq!(want, got);
+        assert!(receiver.act(&ctx).await.is_none());
+        let got = recording.with_recorder(|recorder| recorder.user_for(7));
+        assert_eq!(got, Some(Some(UserId::new(0xAA))));
+
+        receiver.tick(HashMap::new());
+        let files = recording.stop().unwrap();
+        assert_eq!(files.duration, std::time::Duration::from_millis(20));
+        assert!(files.mixed.exists());
+        assert!(files.tracks.is_empty());
+        // Handlers deregister once the recording stops.
+        assert!(matches!(receiver.act(&ctx).await, Some(Event::Cancel)));
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
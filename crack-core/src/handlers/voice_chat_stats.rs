@@ -1,27 +1,33 @@
 use crate::{
-    commands::admin::{deafen::deafen_internal, mute::mute_internal},
+    db::{CaseAction, ModerationCase},
     errors::CrackedError,
-    messaging::messages::UNKNOWN,
-    BotConfig, CamKickConfig,
+    guild::{
+        cam_rules::{CamAction, CamEnforcer, CamPolicy, CamSample, CamStep},
+        voice_activity::{
+            record_voice_activity, sample_voice, VoiceActivityTracker, VOICE_ACTIVITY_POLL_INTERVAL,
+        },
+    },
+    messaging::messages::{
+        CAM_POLICY_REASON, CAM_POLICY_UNMUTE, CAM_POLICY_WARN, CAM_POLICY_WARN_LADDER,
+        CAM_POLICY_WARN_UNDELIVERED,
+    },
+    moderation::record_case,
+    BotConfig, Data,
 };
-use crack_types::to_fixed;
+use chrono::{DateTime, Utc};
 use poise::serenity_prelude as serenity;
-
-use ::serenity::all::CacheHttp;
-use colored::Colorize;
 use serenity::{
-    builder::CreateMessage, model::id::GuildId, Channel, ChannelId, Context as SerenityContext,
-    Mentionable, UserId, VoiceState,
+    builder::{CreateMessage, EditMember},
+    model::id::GuildId,
+    Cache, ChannelId, Context as SerenityContext, Mentionable, UserId,
 };
+use sqlx::PgPool;
 use std::{
     cmp::{Eq, PartialEq},
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     sync::Arc,
 };
-use tokio::{
-    task::JoinHandle,
-    time::{Duration, Instant},
-};
+use tokio::{task::JoinHandle, time::Duration};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Enum for the Camera status.
@@ -51,261 +57,271 @@ impl From<bool> for CamStatus {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-/// Struct for the our derived Camera change event.
-struct CamPollEvent {
-    user_id: UserId,
+/// Everyone in a guild's voice channels, except bots, with whether their roles
+/// exempt them from their channel's camera policy.
+fn sample_cams(
+    cache: &Cache,
     guild_id: GuildId,
-    chan_id: ChannelId,
-    status: CamStatus,
-    last_change: Instant,
+    policies: &HashMap<ChannelId, CamPolicy>,
+) -> Vec<CamSample> {
+    let Some(guild) = guild_id.to_guild_cached(cache) else {
+        tracing::error!("Guild not found {guild_id}.");
+        return vec![];
+    };
+    guild
+        .voice_states
+        .iter()
+        .filter_map(|state| {
+            let channel_id = state.channel_id?;
+            let member = state
+                .member
+                .as_ref()
+                .or_else(|| guild.members.get(&state.user_id));
+            if member.is_some_and(|member| member.user.bot()) {
+                return None;
+            }
+            let roles = member
+                .map(|member| member.roles.iter().copied().collect::<Vec<_>>())
+                .unwrap_or_default();
+            let exempt = policies
+                .get(&channel_id)
+                .is_some_and(|policy| policy.is_exempt(&roles));
+            let video = state.self_video();
+            tracing::trace!(
+                "{}|{}|{}|{}",
+                guild.name,
+                state.user_id,
+                channel_id,
+                CamStatus::from(video)
+            );
+            Some(CamSample {
+                user_id: state.user_id,
+                channel_id,
+                video,
+                exempt,
+            })
+        })
+        .collect()
 }
 
-impl CamPollEvent {
-    /// Returns the key for the Camera change event.
-    fn key(&self) -> (UserId, ChannelId) {
-        (self.user_id, self.chan_id)
+/// Take a step of the ladder against a member, and log it as a moderation case.
+#[cfg(not(tarpaulin_include))]
+async fn apply_cam_step(
+    ctx: &SerenityContext,
+    data: &Data,
+    guild_id: GuildId,
+    policy: &CamPolicy,
+    action: CamAction,
+) -> Result<(), CrackedError> {
+    let http = &ctx.http;
+    let user_id = action.user_id;
+    let mut reason = format!("{} {}", CAM_POLICY_REASON, action.channel_id.mention());
+    match action.step {
+        CamStep::Warn => {
+            let content = policy.warn_message.clone().unwrap_or_else(|| {
+                format!(
+                    "{} {}. {}",
+                    CAM_POLICY_WARN,
+                    action.channel_id.mention(),
+                    CAM_POLICY_WARN_LADDER
+                )
+            });
+            // The ladder has moved on either way, so a warning that can't be
+            // delivered is still logged, saying so.
+            if let Err(e) = user_id
+                .direct_message(http, CreateMessage::new().content(content))
+                .await
+            {
+                tracing::warn!("Failed to DM camera warning to {}: {}", user_id, e);
+                reason = format!("{} {}", reason, CAM_POLICY_WARN_UNDELIVERED);
+            }
+        },
+        CamStep::Mute => {
+            let edit = EditMember::new().mute(true).audit_log_reason(&reason);
+            guild_id.edit_member(http, user_id, edit).await?;
+        },
+        CamStep::Move => {
+            let channel_id = policy
+                .move_channel()
+                .ok_or(CrackedError::Other("No channel to move to"))?;
+            guild_id.move_member(http, user_id, channel_id).await?;
+        },
+        CamStep::Disconnect => {
+            guild_id.disconnect_member(http, user_id).await?;
+        },
     }
+    tracing::info!("Camera policy: {} {} in {}", action.step, user_id, guild_id);
+    log_cam_case(
+        ctx,
+        data,
+        guild_id,
+        user_id,
+        action.step.case_action(),
+        reason,
+    )
+    .await
 }
 
-/// Check the camera status of a user and enforce the rules if necessary.
-async fn check_and_enforce_cams(
-    cur_cam: CamPollEvent,
-    new_cam: &CamPollEvent,
-    cam_states: &mut HashMap<(UserId, ChannelId), CamPollEvent>,
-    config_map: &HashMap<u64, &CamKickConfig>,
-    //status_changes: &mut Vec<CamStatusChangeEvent>,
-    //ctx: Arc<SerenityContext>,
-    cache_http: &impl CacheHttp,
+/// Lift a mute we gave a member, now that they turned their camera on or left
+/// the channel.
+#[cfg(not(tarpaulin_include))]
+async fn unmute_cam(
+    ctx: &SerenityContext,
+    data: &Data,
+    guild_id: GuildId,
+    user_id: UserId,
 ) -> Result<(), CrackedError> {
-    let kick_conf = config_map
-        .get(&cur_cam.chan_id.get())
-        .ok_or(CrackedError::Other("Channel not found"))?;
-    tracing::trace!("kick_conf: {}", format!("{:?}", kick_conf).blue());
-    if cur_cam.status != new_cam.status {
-        let cam_event = CamPollEvent {
-            last_change: Instant::now(),
-            ..*new_cam
-        };
-
-        cam_states.insert(cam_event.key(), cam_event);
-    } else {
-        tracing::trace!("cur: {}, prev: {}", cur_cam.status, new_cam.status);
-        tracing::trace!(
-            "elapsed: {:?}, timeout: {}",
-            cur_cam.last_change.elapsed(),
-            kick_conf.timeout
-        );
-        if cur_cam.status == CamStatus::Off
-            && cur_cam.last_change.elapsed() > Duration::from_secs(kick_conf.timeout)
-        {
-            let user = match new_cam.user_id.to_user(cache_http).await {
-                Ok(user) => user,
-                Err(err) => {
-                    tracing::error!("Error getting user: {err}");
-                    return Err(CrackedError::Other("Error getting user"));
-                },
-            };
-            tracing::info!(
-                "User {} has been cammed down for {} seconds",
-                user.name,
-                cur_cam.last_change.elapsed().as_secs()
-            );
-
-            // let guild = cam.guild_id.to_guild_cached(&ctx.cache).unwrap();
-            let guild_id = new_cam.guild_id;
-            tracing::info!("about to deafen {:?}", new_cam.user_id);
+    let edit = EditMember::new()
+        .mute(false)
+        .audit_log_reason(CAM_POLICY_UNMUTE);
+    guild_id.edit_member(&ctx.http, user_id, edit).await?;
+    log_cam_case(
+        ctx,
+        data,
+        guild_id,
+        user_id,
+        CaseAction::Unmute,
+        CAM_POLICY_UNMUTE.to_string(),
+    )
+    .await
+}
 
-            if false {
-                run_cam_enforcement(cache_http, new_cam, guild_id, user, kick_conf, cam_states)
-                    .await;
-            }
-        }
-    };
+#[cfg(not(tarpaulin_include))]
+async fn log_cam_case(
+    ctx: &SerenityContext,
+    data: &Data,
+    guild_id: GuildId,
+    user_id: UserId,
+    action: CaseAction,
+    reason: String,
+) -> Result<(), CrackedError> {
+    let bot_id = ctx.cache.current_user().id;
+    let case = ModerationCase::new(
+        guild_id.get() as i64,
+        action,
+        bot_id.get() as i64,
+        user_id.get() as i64,
+        Some(reason),
+        None,
+        Utc::now(),
+    );
+    record_case(&ctx.http, data, case).await?;
     Ok(())
 }
 
-/// Run the camera enforcement rules.
-async fn run_cam_enforcement(
-    //ctx: Arc<SerenityContext>,
-    cache_http: &impl CacheHttp,
-    new_cam: &CamPollEvent,
+/// Enforce a guild's camera policies against who's in voice now.
+#[cfg(not(tarpaulin_include))]
+async fn enforce_cam_policies(
+    ctx: &SerenityContext,
+    data: &Data,
+    enforcer: &mut CamEnforcer,
     guild_id: GuildId,
-    user: ::serenity::model::prelude::User,
-    kick_conf: &&CamKickConfig,
-    cam_states: &mut HashMap<(UserId, ChannelId), CamPollEvent>,
+    policies: &HashMap<ChannelId, CamPolicy>,
+    now: DateTime<Utc>,
 ) {
-    // WARN: Disconnect the user
-    // FIXME: Should this not be it's own function?
-    // let dc_res = disconnect_member(ctx.clone(), *cam, guild).await;
-    let dc_res1 = (
-        deafen_internal(cache_http, guild_id, user.clone(), true).await,
-        "deafen",
-    );
-    let dc_res2 = (
-        mute_internal(cache_http, user.clone(), guild_id, true).await,
-        "deafen",
-    );
-    // let dc_res1 = (
-    //     server_defeafen_member(ctx.clone(), *new_cam, guild_id).await,
-    //     "deafen",
-    // );
-    // let dc_res2 = (
-    //     server_mute_member(ctx.clone(), *new_cam, guild_id).await,
-    //     "mute",
-    // );
-
-    for (dc_res, state) in vec![dc_res1, dc_res2] {
-        match dc_res {
-            Ok(_) => {
-                tracing::error!("User {} has been violated: {}", user.name, state);
-                if state == "deafen" && kick_conf.msg_on_deafen
-                    || state == "mute" && kick_conf.msg_on_mute
-                    || state == "disconnect" && kick_conf.msg_on_dc
-                {
-                    let channel = ChannelId::new(kick_conf.chan_id);
-                    let _ = channel
-                        .send_message(
-                            cache_http.http(),
-                            CreateMessage::default().content({
-                                format!("{} {}: {}", user.mention(), kick_conf.dc_msg, state)
-                            }),
-                        )
-                        .await;
-                }
-                cam_states.remove(&new_cam.key());
-            },
-            Err(err) => {
-                tracing::error!("Error violating user: {}", err);
-            },
+    let samples = sample_cams(&ctx.cache, guild_id, policies);
+    let decisions = enforcer.observe(guild_id, policies, &samples, now);
+    for user_id in decisions.unmute {
+        if let Err(e) = unmute_cam(ctx, data, guild_id, user_id).await {
+            tracing::warn!("Failed to unmute {} in {}: {}", user_id, guild_id, e);
         }
     }
-}
-
-use extract_map::ExtractKey;
-use extract_map::ExtractMap;
-/// Check the camera statuses of all the users in voice channels per
-/// guild and if there's rules aroun camera usage, enforce them.
-async fn check_camera_status(
-    ctx: Arc<SerenityContext>,
-    guild_id: GuildId,
-) -> (Vec<CamPollEvent>, String) {
-    let (voice_states, guild_name): (ExtractMap<UserId, VoiceState>, String) =
-        match guild_id.to_guild_cached(&ctx.cache.clone()) {
-            Some(guild) => (guild.voice_states.clone(), guild.name.to_string()),
-            None => {
-                tracing::error!("Guild not found {guild_id}.");
-                return (vec![], "".to_string());
-            },
+    for action in decisions.steps {
+        let Some(policy) = policies.get(&action.channel_id) else {
+            continue;
         };
-
-    let mut cams = Vec::new();
-    let mut output: String = format!("{}\n", guild_name.bright_green());
-
-    for voice_state in voice_states.iter() {
-        let user_id = voice_state.extract_key();
-        let user = user_id.to_user(ctx.clone()).await;
-        if let Some(chan_id) = voice_state.channel_id {
-            let user_name = match user {
-                Ok(user) => user.name,
-                Err(err) => {
-                    tracing::error!("Error getting user: {err}");
-                    to_fixed(UNKNOWN)
-                },
-            };
-            let channel_name = match chan_id.to_channel(ctx.clone(), Some(guild_id)).await {
-                Ok(chan) => match chan {
-                    Channel::Guild(chan) => chan.name.to_string(),
-                    Channel::Private(chan) => chan.recipient.name.to_string(),
-                    _ => UNKNOWN.to_string(),
-                },
-                Err(err) => {
-                    tracing::error!(
-                        r#"Error getting channel name for channel
-                        {chan_id} in guild {guild_name}: {err}"#,
-                    );
-                    "Missing Access".to_string()
-                },
-            };
-            let status = CamStatus::from(voice_state.self_video());
-            let last_change = Instant::now();
-
-            let info = CamPollEvent {
-                user_id: *user_id,
+        if let Err(e) = apply_cam_step(ctx, data, guild_id, policy, action).await {
+            tracing::warn!(
+                "Failed to {} {} in {}: {}",
+                action.step,
+                action.user_id,
                 guild_id,
-                chan_id,
-                status,
-                last_change,
-            };
+                e
+            );
+        }
+    }
+}
 
-            cams.push(info);
-            output.push_str(&format!(
-                "{}|{}|{}|{}|{}|{}\n",
-                guild_name, &user_name, &user_id, &channel_name, &chan_id, status,
-            ));
+/// Turn the camera rules from the bot config into policies, for channels that
+/// don't have one yet.
+#[cfg(not(tarpaulin_include))]
+async fn seed_cam_policies(pool: &PgPool, config: &BotConfig) {
+    for cam_kick in config.cam_kick.clone().unwrap_or_default() {
+        let seeded = CamPolicy::seed(
+            pool,
+            cam_kick.guild_id as i64,
+            cam_kick.chan_id as i64,
+            cam_kick.timeout as i32,
+        )
+        .await;
+        if let Err(e) = seeded {
+            tracing::error!(
+                "Error seeding camera policy for {}: {}",
+                cam_kick.chan_id,
+                e
+            );
         }
     }
-    (cams, output)
 }
 
-/// The main loop that checks the camera status of all the users in voice channels
-pub async fn cam_status_loop(
-    ctx: Arc<SerenityContext>,
-    config: Arc<BotConfig>,
-    guilds: Vec<GuildId>,
-) -> JoinHandle<()> {
+/// Poll everyone in voice channels in every guild we're in, recording their
+/// voice time. Runs whether or not camera policies are polled.
+pub fn voice_activity_loop(ctx: Arc<SerenityContext>) -> JoinHandle<()> {
     tokio::spawn(async move {
-        tracing::info!("Starting camera status check loop");
-        let configs = config.cam_kick.clone().unwrap_or_default();
-        let conf_guilds = configs.iter().map(|x| x.guild_id).collect::<HashSet<_>>();
-
-        // This HashMap is used to keep track of the camera status of all the users in voice.
-        // channels. It gets initialized empty here and then is updated every iteration of the loop.
-        let mut cur_cams: HashMap<(UserId, ChannelId), CamPollEvent> =
-            HashMap::<(UserId, ChannelId), CamPollEvent>::new();
-        // This is
-        let channels: HashMap<u64, &CamKickConfig> = configs
-            .iter()
-            .map(|x| (x.chan_id, x))
-            .collect::<HashMap<_, _>>();
-
-        tracing::trace!("conf_guilds: {}", format!("{:?}", conf_guilds).green());
+        let data = ctx.data::<Data>();
+        let Some(pool) = data.database_pool.clone() else {
+            return;
+        };
+        tracing::info!("Starting voice activity loop");
+        // Open voice time intervals, carried from one poll to the next.
+        let mut tracker = VoiceActivityTracker::default();
         loop {
-            // We clone Context again here, because Arc is owned, so it moves to the
-            // new function.
-            tracing::error!("Checking camera status for {} guilds", guilds.len());
-            // Go through all the guilds we have cached and check the camera status
-            // for all the users we can see in voice channels.
-            let mut output = String::from("\n");
-            let mut new_cams = vec![];
-            for guild_id in &guilds {
-                let (add_new_cams, add_output) =
-                    check_camera_status(Arc::clone(&ctx), *guild_id).await;
-                new_cams.extend(add_new_cams);
-                output.push_str(&add_output);
+            let now = Utc::now().naive_utc();
+            // Read the guilds every poll, so ones joined since startup count.
+            for guild_id in ctx.cache.guilds() {
+                let samples = sample_voice(&ctx.cache, guild_id);
+                if let Err(e) =
+                    record_voice_activity(&pool, &mut tracker, guild_id, &samples, now).await
+                {
+                    tracing::error!("Error recording voice activity in {}: {}", guild_id, e);
+                }
             }
+            tokio::time::sleep(VOICE_ACTIVITY_POLL_INTERVAL).await;
+        }
+    })
+}
+
+/// The main loop that polls everyone in voice channels, enforcing camera
+/// policies.
+pub async fn cam_status_loop(ctx: Arc<SerenityContext>, config: Arc<BotConfig>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        tracing::info!("Starting camera status check loop");
+        let data = ctx.data::<Data>();
+        if let Some(pool) = &data.database_pool {
+            seed_cam_policies(pool, &config).await;
+        }
 
-            //let total_active_cams = cams.len();
-            let mut new_cams = Vec::<&CamPollEvent>::new();
-            //let mut status_changes = Vec::<CamStatusChangeEvent>::new();
+        // Members on the camera ladder, carried from one poll to the next.
+        let mut cams = CamEnforcer::default();
 
-            for new_cam in new_cams.iter_mut() {
-                if let Some(status) = cur_cams.get(&new_cam.key()) {
-                    let _ =
-                        check_and_enforce_cams(*status, new_cam, &mut cur_cams, &channels, &ctx)
-                            .await;
-                } else {
-                    cur_cams.insert(new_cam.key(), **new_cam);
+        loop {
+            if let Some(pool) = &data.database_pool {
+                let now = Utc::now();
+                let policies = CamPolicy::by_guild(pool).await.unwrap_or_else(|e| {
+                    tracing::error!("Error loading camera policies: {}", e);
+                    HashMap::new()
+                });
+                // Like the voice activity loop, pick up guilds joined since startup.
+                let guilds = ctx.cache.guilds();
+                tracing::debug!("Polling voice states for {} guilds", guilds.len());
+                for guild_id in guilds {
+                    let empty = HashMap::new();
+                    let policies = policies.get(&guild_id).unwrap_or(&empty);
+                    enforce_cam_policies(&ctx, &data, &mut cams, guild_id, policies, now).await;
                 }
             }
-            let res: i32 = new_cams
-                .iter()
-                .map(|x| Into::<i32>::into(cur_cams.insert(x.key(), **x).is_none()))
-                .sum();
 
-            tracing::warn!("{}", output);
-            tracing::warn!("num new cams: {}", res);
-            tracing::warn!(
+            tracing::debug!(
                 "Sleeping for {} seconds",
                 config.get_video_status_poll_interval()
             );
@@ -318,8 +334,6 @@ pub async fn cam_status_loop(
 mod test {
     // Test CamStatus enum
     use super::*;
-    use ::serenity::all::Token;
-    use crack_types::get_valid_token;
 
     #[test]
     fn test_cam_status() {
@@ -345,62 +359,9 @@ mod test {
         assert_eq!(off, CamStatus::Off);
     }
 
-    // CamPollEvent tests
     #[test]
-    fn test_cam_poll_event_key() {
-        let user_id = UserId::new(123);
-        let chan_id = ChannelId::new(456);
-        let cam = CamPollEvent {
-            user_id,
-            guild_id: GuildId::new(789),
-            chan_id,
-            status: CamStatus::On,
-            last_change: Instant::now(),
-        };
-        assert_eq!(cam.key(), (user_id, chan_id));
+    fn test_sample_cams_uncached_guild() {
+        let cache = Cache::new();
+        assert!(sample_cams(&cache, GuildId::new(1), &HashMap::new()).is_empty());
     }
-
-    #[tokio::test]
-    async fn test_check_and_enforce_cams() {
-        let user_id = UserId::new(123);
-        let chan_id = ChannelId::new(456);
-        let cam = CamPollEvent {
-            user_id,
-            guild_id: GuildId::new(789),
-            chan_id,
-            status: CamStatus::On,
-            last_change: Instant::now(),
-        };
-        let mut cam_states = HashMap::<(UserId, ChannelId), CamPollEvent>::new();
-        let config_map = HashMap::<u64, &CamKickConfig>::new();
-        let http = poise::serenity_prelude::http::Http::new(get_valid_token());
-        let cache = Arc::new(poise::serenity_prelude::Cache::new());
-        let cache_http = (Some(&cache), &http);
-        // let ctx = Arc::new(SerenityContext::new());
-        let res =
-            check_and_enforce_cams(cam, &cam, &mut cam_states, &config_map, &cache_http).await;
-        let want = CrackedError::Other("Channel not found");
-        assert_eq!(res, Err(want));
-    }
-
-    // fn new_serenity_context() -> Arc<SerenityContext> {
-    //     let token = std::env::var("DISCORD_BOT_TOKEN")?;
-    //     let shard_info = ShardInfo {
-    //         id: ShardId(0),
-    //         total: 1,
-    //     };
-
-    //     // retrieve the gateway response, which contains the URL to connect to
-    //     let gateway = Arc::new(Mutex::new(http.get_gateway().await?.url));
-    //     let shard = Shard::new(gateway, &token, shard_info, GatewayIntents::all(), None).await?;
-    //     Arc::new(SerenityContext {
-    //         data: Arc::new(tokio::sync::RwLock::new(
-    //             poise::serenity_prelude::prelude::TypeMap::new(),
-    //         )),
-    //         http: Arc::new(poise::serenity_prelude::http::Http::new("")),
-    //         shard: poise::serenity_prelude::Shard::new("".to_string(), "".to_string()),
-    //         cache: Arc::new(poise::serenity_prelude::Cache::new()),
-    //         shard_id: poise::serenity_prelude::ShardId(0),
-    //     })
-    // }
 }
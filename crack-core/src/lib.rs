@@ -1,10 +1,12 @@
 #![allow(internal_features)]
 #![feature(fmt_internals)]
 #![feature(formatting_options)]
+pub mod clock;
 pub mod commands;
 pub mod config;
 pub mod connection;
 pub mod db;
+pub mod duration;
 pub mod errors;
 pub mod guild;
 pub mod handlers;
@@ -13,13 +15,17 @@ pub mod http_utils;
 pub mod macros;
 pub mod messaging;
 // pub mod metrics;
+pub mod moderation;
 #[cfg(feature = "crack-music")]
 pub mod music;
 pub mod poise_ext;
+pub mod recording;
+pub mod soundboard;
 pub mod sources;
 #[cfg(test)]
 pub mod test;
 pub mod utils;
+pub mod verification;
 
 //#![feature(linked_list_cursors)]
 use crate::handlers::event_log::LogEntry;
@@ -134,6 +140,9 @@ pub struct BotCredentials {
     pub spotify_client_secret: Option<String>,
     pub openai_api_key: Option<String>,
     pub virustotal_api_key: Option<String>,
+    /// Tokens of helper bots, which each take their own voice channel.
+    #[serde(default)]
+    pub helper_tokens: Vec<String>,
 }
 
 impl Default for BotCredentials {
@@ -145,6 +154,7 @@ impl Default for BotCredentials {
             spotify_client_secret: None,
             openai_api_key: None,
             virustotal_api_key: None,
+            helper_tokens: Vec::new(),
         }
     }
 }
@@ -324,6 +334,10 @@ pub struct DataInner {
     pub authorized_users: HashSet<u64>,
     // Why not Arc here?
     pub join_vc_tokens: dashmap::DashMap<serenity::GuildId, Arc<tokio::sync::Mutex<()>>>,
+    /// Held while a linked session's followers are synced, per leader.
+    pub link_sync_locks: dashmap::DashMap<serenity::GuildId, Arc<tokio::sync::Mutex<()>>>,
+    /// Running music quizzes, each with whether it's been asked to stop.
+    pub quizzes: dashmap::DashMap<serenity::GuildId, Arc<std::sync::atomic::AtomicBool>>,
     pub phone_data: PhoneCodeData,
     pub event_log_async: EventLogAsync,
     // Why Option instead of Arc here? Certainly it's an indirection to allow for an uninitialized state
@@ -338,6 +352,12 @@ pub struct DataInner {
     pub id_cache_map: dashmap::DashMap<u64, guild::cache::GuildCache>,
     pub guild_command_msg_queue: dashmap::DashMap<GuildId, Vec<MessageOrReplyHandle>>,
     pub guild_cnt_map: dashmap::DashMap<GuildId, u64>,
+    pub sleep_timers: dashmap::DashMap<handlers::SessionKey, handlers::SleepTimer>,
+    pub invites: guild::invites::InviteTracker,
+    /// Recent joins, for raid detection.
+    pub raids: moderation::RaidDetector,
+    /// Voice recordings in progress.
+    pub recordings: dashmap::DashMap<GuildId, Arc<recording::Recording>>,
     // Option inside?
     #[cfg(feature = "crack-gpt")]
     pub gpt_ctx: Arc<RwLock<Option<GptContext>>>,
@@ -345,6 +365,8 @@ pub struct DataInner {
     // What fundemental limitation comes up that must be solved by this?
     pub ct_client: CrackTrackClient<'static>,
     pub songbird: Arc<Songbird>,
+    /// Extra bot identities, each holding its own music session per guild.
+    pub helpers: Vec<music::sessions::HelperBot>,
 }
 
 impl std::fmt::Debug for DataInner {
@@ -532,15 +554,22 @@ impl Default for DataInner {
             gpt_ctx: Arc::new(RwLock::new(None)),
             ct_client: CrackTrackClient::default(),
             songbird: Songbird::serenity(), // Initialize with an uninitialized Songbird instance
+            helpers: Vec::new(),
             phone_data: PhoneCodeData::default(),
             bot_settings: Default::default(),
             join_vc_tokens: Default::default(),
+            link_sync_locks: Default::default(),
+            quizzes: Default::default(),
             authorized_users: Default::default(),
             guild_settings_map: Arc::new(RwLock::new(HashMap::new())),
             guild_cache_map: Arc::new(Mutex::new(HashMap::new())),
             id_cache_map: dashmap::DashMap::default(),
             guild_command_msg_queue: Default::default(),
             guild_cnt_map: Default::default(),
+            sleep_timers: Default::default(),
+            invites: Default::default(),
+            raids: Default::default(),
+            recordings: Default::default(),
             http_client: http_utils::get_client().clone(),
             event_log_async: EventLogAsync::default(),
             database_pool: None,
@@ -694,6 +723,25 @@ impl Data {
         }
     }
 
+    /// Get the sleep timer for a session, if one is set.
+    pub fn get_sleep_timer(&self, key: handlers::SessionKey) -> Option<handlers::SleepTimer> {
+        self.sleep_timers.get(&key).map(|timer| *timer)
+    }
+
+    /// Cancel the sleep timer for a session, returning it if one was set.
+    pub fn cancel_sleep_timer(&self, key: handlers::SessionKey) -> Option<handlers::SleepTimer> {
+        self.sleep_timers.remove(&key).map(|(_, timer)| timer)
+    }
+
+    /// Take a "stop after the current track" timer for a session, if one is set.
+    pub fn take_stop_after(&self, key: handlers::SessionKey) -> bool {
+        self.sleep_timers
+            .remove_if(&key, |_, timer| {
+                timer.kind == handlers::SleepTimerKind::AfterCurrentTrack
+            })
+            .is_some()
+    }
+
     /// Push a message to the command message queue.
     pub async fn push_latest_msg(
         &self,
@@ -717,7 +765,10 @@ impl Data {
             .lock()
             .await
             .entry(guild_id)
-            .and_modify(|cache| cache.current_skip_votes = HashSet::new())
+            .and_modify(|cache| {
+                cache.current_skip_votes = HashSet::new();
+                cache.dj_votes = HashMap::new();
+            })
             .or_default();
 
         Ok(())
@@ -9,6 +9,7 @@ use songbird::error::ControlError;
 use std::time::Duration;
 
 use crate::{errors::CrackedError, messaging::messages::*, utils::duration_to_string};
+use crack_types::get_human_readable_timestamp;
 
 pub const RELEASES_LINK: &str = "https://github.com/cycle-five/cracktunes/releases";
 pub const REPO_LINK: &str = "https://github.com/cycle-five/cracktunes/";
@@ -39,6 +40,31 @@ pub enum CrackedMessage {
     },
     Clear,
     Clean(i32),
+    CaseReasonUpdated(i64),
+    AutomodSet(String),
+    AutomodWarning {
+        mention: Mention,
+        reason: String,
+    },
+    VerificationSet(String),
+    RaidSet(String),
+    TtsSet(String),
+    BookmarkAddedToPlaylist {
+        title: String,
+        playlist: String,
+        start: Duration,
+        end: Option<Duration>,
+    },
+    BookmarkDeleted(i64),
+    BookmarkQueued {
+        title: String,
+        position: Duration,
+    },
+    BookmarkSaved {
+        id: i64,
+        title: String,
+        position: Duration,
+    },
     CrackedError(CrackedError),
     CrackedRed(String),
     CreateEmbed(Box<CreateEmbed<'static>>),
@@ -48,6 +74,15 @@ pub enum CrackedMessage {
         sides: u32,
         results: Vec<u32>,
     },
+    DjModeSet {
+        enabled: bool,
+        role: Option<serenity::RoleId>,
+    },
+    DjVote {
+        action: String,
+        mention: Mention,
+        missing: usize,
+    },
     DomainInfo(String),
     Error,
     ErrorHttp(serenity::http::HttpError),
@@ -101,6 +136,18 @@ pub enum CrackedMessage {
     ScanResultQueued {
         id: String,
     },
+    ScheduleCancelled(i64),
+    ScheduleCreated {
+        id: i64,
+        action: String,
+        playlist: Option<String>,
+        run_at: i64,
+        repeat_days: Option<i32>,
+    },
+    ScheduledPlaybackStarted {
+        playlist: String,
+    },
+    ScheduledPlaybackStopped,
     Search,
     Seek {
         timestamp: String,
@@ -116,7 +163,15 @@ pub enum CrackedMessage {
         title: String,
         url: String,
     },
+    SleepTimerCancelled,
+    SleepTimerSet(Duration),
+    SmartPlaylistCreated {
+        name: String,
+        rule: String,
+    },
     Stop,
+    StopAfterCancelled,
+    StopAfterSet,
     SubcommandNotFound {
         group: Cow<'static, String>,
         subcommand: Cow<'static, String>,
@@ -237,6 +292,39 @@ impl Display for CrackedMessage {
             Self::AutoplayOff => f.write_str(AUTOPLAY_OFF),
             Self::AutoplayOn => f.write_str(AUTOPLAY_ON),
             Self::AutoRole(role_id) => f.write_str(&format!("{} {}", AUTO_ROLE, role_id.mention())),
+            Self::BookmarkAddedToPlaylist {
+                title,
+                playlist,
+                start,
+                end,
+            } => f.write_str(&format!(
+                "{} **{}** {} **{}** ({} - {})",
+                BOOKMARK_PLAYLIST_ADDED,
+                title,
+                BOOKMARK_PLAYLIST_TO,
+                playlist,
+                get_human_readable_timestamp(Some(*start)),
+                end.map(|end| get_human_readable_timestamp(Some(end)))
+                    .unwrap_or_else(|| BOOKMARK_TRACK_END.to_string()),
+            )),
+            Self::BookmarkDeleted(id) => f.write_str(&format!("{} `#{}`", BOOKMARK_DELETED, id)),
+            Self::BookmarkQueued { title, position } => f.write_str(&format!(
+                "{} **{}** @ `{}`",
+                BOOKMARK_QUEUED,
+                title,
+                get_human_readable_timestamp(Some(*position))
+            )),
+            Self::BookmarkSaved {
+                id,
+                title,
+                position,
+            } => f.write_str(&format!(
+                "{} `#{}` **{}** @ `{}`",
+                BOOKMARK_SAVED,
+                id,
+                title,
+                get_human_readable_timestamp(Some(*position))
+            )),
             Self::BugNone(variable) => f.write_str(&format!("{} {} {}", BUG, variable, BUG_END)),
             Self::InvalidIP(ip) => f.write_str(&format!("{} {}", ip, FAIL_INVALID_IP)),
             Self::InviteLink => f.write_str(&format!(
@@ -266,6 +354,36 @@ impl Display for CrackedMessage {
             Self::CreateEmbed(embed) => f.write_str(&format!("{:#?}", embed)),
             Self::CommandFound(s) => f.write_str(s),
             Self::DomainInfo(info) => f.write_str(info),
+            Self::AutomodSet(settings) => {
+                f.write_str(&format!("{}\n{}", AUTOMOD_SETTINGS, settings))
+            },
+            Self::AutomodWarning { mention, reason } => {
+                f.write_str(&format!("{} {} {}", AUTOMOD_WARNING, mention, reason))
+            },
+            Self::VerificationSet(settings) => {
+                f.write_str(&format!("{}\n{}", VERIFY_SETTINGS, settings))
+            },
+            Self::RaidSet(settings) => f.write_str(&format!("{}\n{}", RAID_SETTINGS, settings)),
+            Self::TtsSet(settings) => f.write_str(&format!("{}\n{}", TTS_SETTINGS, settings)),
+            Self::CaseReasonUpdated(number) => {
+                f.write_str(&format!("{} #{}.", CASE_REASON_UPDATED, number))
+            },
+            Self::DjModeSet { enabled, role } => f.write_str(&format!(
+                "{} {}\n{} {}",
+                DJ_MODE,
+                if *enabled { DJ_MODE_ON } else { DJ_MODE_OFF },
+                DJ_ROLE,
+                role.map(|r| r.mention().to_string())
+                    .unwrap_or_else(|| DJ_ROLE_NONE.to_string())
+            )),
+            Self::DjVote {
+                action,
+                mention,
+                missing,
+            } => f.write_str(&format!(
+                "{}{} {} `{}`! {} {}",
+                SKIP_VOTE_EMOJI, mention, DJ_VOTE_USER, action, missing, DJ_VOTE_MISSING
+            )),
             Self::DiceRoll {
                 dice,
                 sides,
@@ -311,6 +429,33 @@ impl Display for CrackedMessage {
             },
             #[cfg(feature = "crack-osint")]
             Self::ScanResultQueued { id } => f.write_str(&format!("{} {}", SCAN_QUEUED, id)),
+            Self::ScheduleCancelled(id) => {
+                f.write_str(&format!("{} `#{}`", SCHEDULE_CANCELLED, id))
+            },
+            Self::ScheduleCreated {
+                id,
+                action,
+                playlist,
+                run_at,
+                repeat_days,
+            } => {
+                f.write_str(&format!("{} `#{}` **{}**", SCHEDULE_CREATED, id, action))?;
+                if let Some(playlist) = playlist {
+                    f.write_str(&format!(" **{}**", playlist))?;
+                }
+                f.write_str(&format!(" <t:{}:F> (<t:{}:R>)", run_at, run_at))?;
+                match repeat_days {
+                    Some(days) => f.write_str(&format!(
+                        "\n{} {} {}",
+                        SCHEDULE_REPEATS, days, SCHEDULE_DAYS
+                    )),
+                    None => Ok(()),
+                }
+            },
+            Self::ScheduledPlaybackStarted { playlist } => {
+                f.write_str(&format!("{} **{}**", SCHEDULED_PLAYBACK_STARTED, playlist))
+            },
+            Self::ScheduledPlaybackStopped => f.write_str(SCHEDULED_PLAYBACK_STOPPED),
             Self::Search => f.write_str(SEARCHING),
 
             Self::RemoveMultiple => f.write_str(REMOVED_QUEUE_MULTIPLE),
@@ -323,7 +468,13 @@ impl Display for CrackedMessage {
             },
             Self::RoleNotFound => f.write_str(ROLE_NOT_FOUND),
             Self::Shuffle => f.write_str(SHUFFLED_SUCCESS),
+            Self::SmartPlaylistCreated { name, rule } => f.write_str(&format!(
+                "{} **{}**\n`{}`",
+                SMART_PLAYLIST_CREATED, name, rule
+            )),
             Self::Stop => f.write_str(STOPPED),
+            Self::StopAfterCancelled => f.write_str(STOP_AFTER_CANCELLED),
+            Self::StopAfterSet => f.write_str(STOP_AFTER_SET),
             Self::SubcommandNotFound { group, subcommand } => f.write_str(
                 &SUBCOMMAND_NOT_FOUND
                     .replace("{group}", group)
@@ -350,6 +501,12 @@ impl Display for CrackedMessage {
             Self::SkipTo { title, url } => {
                 f.write_str(&format!("{} [**{}**]({})!", SKIPPED_TO, title, url))
             },
+            Self::SleepTimerCancelled => f.write_str(SLEEP_TIMER_CANCELLED),
+            Self::SleepTimerSet(duration) => f.write_str(&format!(
+                "{} **{}**.",
+                SLEEP_TIMER_SET,
+                duration_to_string(*duration)
+            )),
             Self::Summon { mention } => f.write_str(&format!("{} **{}**!", JOINING, mention)),
             Self::TextChannelCreated {
                 channel_id,
@@ -1,4 +1,12 @@
 pub const ADDED_QUEUE: &str = "📃 Added to queue!";
+pub const AUTOMOD_BANNED_DOMAIN: &str = "Links to a banned domain:";
+pub const AUTOMOD_DUPLICATE: &str = "Repeating the same message";
+pub const AUTOMOD_INVITE_LINK: &str = "Invite links aren't allowed here.";
+pub const AUTOMOD_MASS_MENTION: &str = "Too many mentions";
+pub const AUTOMOD_RATE_LIMIT: &str = "Sending messages too fast";
+pub const AUTOMOD_REASON: &str = "Automod:";
+pub const AUTOMOD_SETTINGS: &str = "🛡️ Automod settings";
+pub const AUTOMOD_WARNING: &str = "⚠️";
 pub const AUTOPAUSE_OFF: &str = "🤖 Autopause OFF!";
 pub const AUTOPAUSE_ON: &str = "🤖 Autopause ON!";
 pub const AUTOPLAY_OFF: &str = "🤖 Autoplay OFF!";
@@ -29,10 +37,50 @@ macro_rules! DICE_ROLL {
 pub const AUTHORIZED: &str = "✅ User has been authorized.";
 pub const AUTO_ROLE: &str = "Auto Role";
 pub const BANNED: &str = "Banned";
+pub const BOOKMARK_DELETED: &str = "🗑️ Deleted bookmark";
+pub const BOOKMARK_PLAYLIST_ADDED: &str = "📃 Added";
+pub const BOOKMARK_PLAYLIST_TO: &str = "to playlist";
+pub const BOOKMARK_QUEUED: &str = "🔖 Queued bookmark";
+pub const BOOKMARK_SAVED: &str = "🔖 Saved bookmark";
+pub const BOOKMARK_TRACK_END: &str = "end";
+pub const BOOKMARKS: &str = "🔖 Bookmarks";
+pub const BOOKMARKS_EMPTY: &str = "🔖 You have no bookmarks. Save one with `/bookmark`.";
 pub const BUG: &str = "🐞 Bug!";
 pub const BUG_END: &str = "was None!";
 pub const BUG_REPORTED: &str = "🐞 Bug Reported!";
 pub const BUG_REPORT: &str = "🐞 Bug Report";
+pub const CAM_POLICIES: &str = "📷 Camera policies";
+pub const CAM_POLICIES_NONE: &str = "📷 No voice channels have a camera policy.";
+pub const CAM_POLICY_EXEMPT: &str = "📷 Exempt from the camera policy in";
+pub const CAM_POLICY_NOT_EXEMPT: &str = "📷 No longer exempt from the camera policy in";
+pub const CAM_POLICY_NOT_FOUND: &str = "📷 That channel has no camera policy.";
+pub const CAM_POLICY_NOT_VOICE: &str =
+    "📷 A camera policy has to be on one of this server's voice channels.";
+pub const CAM_POLICY_REASON: &str = "Camera off in";
+pub const CAM_POLICY_REMOVED: &str = "📷 Removed the camera policy in";
+pub const CAM_POLICY_SET: &str = "📷 Cameras have to be on in";
+pub const CAM_POLICY_UNMUTE: &str = "Camera back on";
+pub const CAM_POLICY_WARN: &str = "📷 Cameras have to be on in";
+pub const CAM_POLICY_WARN_LADDER: &str =
+    "Turn yours on, or you'll be muted, moved and then disconnected.";
+pub const CAM_POLICY_WARN_UNDELIVERED: &str = "(warning couldn't be sent)";
+pub const CASE_ACTION: &str = "Action";
+pub const CASE_DURATION: &str = "Duration";
+pub const CASE_EVIDENCE: &str = "Evidence";
+pub const CASE_EXPIRES: &str = "Expires";
+pub const CASE_MODERATOR: &str = "Moderator";
+pub const CASE_NO_REASON: &str = "No reason given";
+pub const CASE_NOT_FOUND: &str = "📁 No such case";
+pub const CASE_REASON: &str = "Reason";
+pub const CASE_REASON_UPDATED: &str = "📁 Updated the reason for case";
+pub const CASE_REVERSED: &str = "Reversed";
+pub const CASE_TARGET: &str = "User";
+pub const CASE_TITLE: &str = "📁 Case";
+pub const CASES_NONE: &str = "📁 No cases for";
+pub const CASES_TITLE: &str = "📁 Cases for";
+pub const COMMAND_NOT_ALLOWED: &str = "🚫 You're not allowed to use";
+pub const COMMAND_NOT_ALLOWED_IN_CHANNEL: &str = "🚫 That command can't be used in this channel!";
+pub const COMMAND_NOT_ALLOWED_USE: &str = "Use";
 pub const CONNECTED: &str = "Connected!";
 pub const DEAUTHORIZED: &str = "❌ User has been deauthorized.";
 pub const UNBANNED: &str = "Unbanned";
@@ -45,6 +93,14 @@ pub const UNDEAFENED_FAIL: &str = "User failed to be undeafened.";
 pub const MUTED: &str = "Muted";
 pub const UNMUTED: &str = "Unmuted";
 
+pub const DJ_MODE: &str = "🎧 DJ mode is";
+pub const DJ_MODE_OFF: &str = "**off**.";
+pub const DJ_MODE_ON: &str = "**on**.";
+pub const DJ_ROLE: &str = "DJ role:";
+pub const DJ_ROLE_NONE: &str = "none (admins only)";
+pub const DJ_VOTE_MISSING: &str = "more vote(s) needed.";
+pub const DJ_VOTE_USER: &str = "has voted to";
+
 pub const DOMAIN_FORM_ALLOWED_TITLE: &str = "Allowed domains";
 pub const DOMAIN_FORM_BANNED_TITLE: &str = "Banned domains";
 pub const DOMAIN_FORM_ALLOWED_PLACEHOLDER: &str =
@@ -70,6 +126,8 @@ pub const FAIL_INSERT: &str = "⚠️ Failed to insert!";
 pub const FAIL_INVALID_TOPGG_TOKEN: &str = "⚠️ Invalid top.gg token!";
 pub const FAIL_INVALID_PERMS: &str = "⚠️ Invalid permissions!!";
 pub const FAIL_MINUTES_PARSING: &str = "⚠️ Invalid formatting for 'minutes'";
+pub const FAIL_NO_FREE_SESSION: &str =
+    "⚠️ I'm already playing in other channels here, join one of those!";
 pub const FAIL_NO_SONG_ON_INDEX: &str = "⚠️ There is no queued song on that index!";
 pub const FAIL_NO_SONGBIRD: &str = "⚠️ Failed to get songbird!";
 pub const FAIL_NO_VIRUSTOTAL_API_KEY: &str =
@@ -91,11 +149,34 @@ pub const GUILD_ONLY: &str = "⚠️ This command can only be used in a server!"
 pub const IDLE_ALERT: &str = "⚠️ I've been idle for a while so I'm going to hop off, set the idle timeout to change this! Also support my development and I won't have to premium-gate features!\n[CrackTunes Patreon](https://patreon.com/CrackTunes)";
 pub const IP_DETAILS: &str = "🌐 IP details for";
 pub const JOINING: &str = "Joining";
+pub const KARAOKE_INVALID_FILE: &str = "⚠️ Lyrics have to be an `.lrc` file of up to 64 KiB.";
+pub const KARAOKE_NOT_FOUND: &str =
+    "🎤 Couldn't find synced lyrics for this track. Attach an `.lrc` file to sing along anyway.";
+pub const KARAOKE_TRACK_CHANGED: &str = "The track changed before the vocals could be turned down.";
+pub const KARAOKE_VOCALS_FAILED: &str = "Couldn't turn the vocals down on this track.";
 pub const KICKED: &str = "Kicked";
 pub const GRABBED_NOTICE: &str = "📃 Sent you a DM with the current track!";
 pub const LEAVING: &str = "👋 See you soon!";
+pub const LINK_ALREADY_FOLLOWING: &str = "⚠️ This server follows another one, so it can't lead.";
+pub const LINK_ALREADY_LEADING: &str = "⚠️ This server leads others, so it can't follow.";
+pub const LINK_INVALID_SERVER: &str =
+    "⚠️ That's not a server I'm in. Use its ID, from **Copy Server ID**.";
+pub const LINK_LEADER_FOLLOWS: &str = "⚠️ That server follows another one, so it can't lead.";
+pub const LINK_LINKS: &str = "🔗 Linked sessions";
+pub const LINK_NONE: &str = "🔗 This server isn't linked to any other.";
+pub const LINK_NOT_FOUND: &str = "⚠️ This server isn't linked to that one.";
+pub const LINK_REMOVED: &str = "🔗 Unlinked from";
+pub const LINK_SELF: &str = "⚠️ A server can't link to itself.";
+pub const LINK_WAITING: &str = "⏳ Waiting for the other server to agree.";
 pub const LOOP_DISABLED: &str = "🔁 Disabled loop!";
 pub const LOOP_ENABLED: &str = "🔁 Enabled loop!";
+pub const LOCKDOWN_ALERT: &str = "🚨 Lockdown started:";
+pub const LOCKDOWN_ALREADY: &str = "🔒 The server is already locked down.";
+pub const LOCKDOWN_ACTIVE: &str = "🔒 Locked down";
+pub const LOCKDOWN_ENDED: &str = "🔓 Lockdown ended by";
+pub const LOCKDOWN_NOT_ACTIVE: &str = "🔓 The server isn't locked down.";
+pub const LOCKDOWN_NO_REASON: &str = "Lockdown";
+pub const LOCKDOWN_STARTED: &str = "🔒 Locked down. Run `/lockdown end` to lift it.";
 pub const NO_AUTO_ROLE: &str = "⚠️ No auto role set for this server!";
 pub const NO_CHANNEL_ID: &str = "⚠️ No ChannelId Found!";
 pub const NO_DATABASE_POOL: &str = "⚠️ No Database Pool Found!";
@@ -119,6 +200,7 @@ pub const PLAYLIST_REMOVE: &str = "❌ Removed from playlist!";
 pub const PLAYLIST_LIST_EMPTY: &str = "📃 You have no playlists currently.";
 pub const PLAYLIST_EMPTY: &str = "📃 This playlist has no songs!";
 pub const PLAYLISTS: &str = "Playlists";
+pub const PLAYLIST_SERVER_TAG: &str = " 🏠";
 pub const PLAY_FAILED_BLOCKED_DOMAIN: &str =
     "**is either not allowed in this server or is not supported!** \n\nTo explicitely allow this domain, ask a moderator to run the `/managesources` command. [Click to see a list of supported sources.](https://github.com/yt-dlp/yt-dlp/blob/master/supportedsites.md)";
 pub const PLAY_ALL_FAILED: &str =
@@ -143,6 +225,37 @@ pub const QUEUE_NOW_PLAYING: &str = "🔊 Now playing";
 pub const QUEUE_PAGE_OF: &str = "of";
 pub const QUEUE_PAGE: &str = "Page";
 pub const QUEUE_UP_NEXT: &str = "⌛ Up next";
+pub const QUIZ_ALREADY_RUNNING: &str = "🎲 There's already a quiz going in this server.";
+pub const QUIZ_FINAL_SCORES: &str = "🏁 Final scores";
+pub const QUIZ_IT_WAS: &str = "It was";
+pub const QUIZ_LEADERBOARD: &str = "🏆 Quiz leaderboard";
+pub const QUIZ_LEADERBOARD_EMPTY: &str = "🏆 Nobody's scored in a quiz here yet.";
+pub const QUIZ_NOBODY_SCORED: &str = "Nobody scored.";
+pub const QUIZ_NOT_ENOUGH_TRACKS: &str =
+    "⚠️ Not enough tracks to play a quiz from. Play some music first, or pick a playlist or genre.";
+pub const QUIZ_NOT_RUNNING: &str = "🎲 There's no quiz going in this server.";
+pub const QUIZ_NO_NAME: &str = "⚠️ Name the playlist or genre to play from.";
+pub const QUIZ_ROUND: &str = "🎵 What's this song? Guess the title or artist in chat!";
+pub const QUIZ_STARTING: &str =
+    "🎲 Music quiz! Guessing the title is worth 2 points, and the artist 1.";
+pub const QUIZ_STOPPING: &str = "🛑 The quiz will stop after this round.";
+pub const RAID_DETECTED: &str = "Raid detected:";
+pub const RAID_JOIN_BURST: &str = "joins";
+pub const RAID_NEW_ACCOUNTS: &str = "new accounts";
+pub const RAID_SAME_AVATAR: &str = "members with the same avatar";
+pub const RAID_SETTINGS: &str = "🚨 Raid protection settings";
+pub const RAID_SIMILAR_NAMES: &str = "members with look-alike names";
+pub const RECORD_ALREADY: &str = "⏺️ Already recording.";
+pub const RECORD_ANNOUNCE: &str =
+    "⏺️ This channel is being recorded. Only members who opt in are recorded, started by";
+pub const RECORD_BUTTON_IN: &str = "Record me";
+pub const RECORD_BUTTON_OUT: &str = "Don't record me";
+pub const RECORD_NOT_RECORDING: &str = "⏺️ Not recording.";
+pub const RECORD_OPTED_IN: &str = "You're being recorded.";
+pub const RECORD_OPTED_OUT: &str = "You're not being recorded.";
+pub const RECORD_SAVED_TO: &str = "Saved to";
+pub const RECORD_STOPPED: &str = "⏹️ Recording stopped after";
+pub const RECORD_TRACKS: &str = "members recorded";
 pub const REMOVED_QUEUE_MULTIPLE: &str = "❌ Removed multiple tracks from queue!";
 pub const REMOVED_QUEUE: &str = "❌ Removed from queue";
 pub const RESUMED: &str = "▶ Resumed!";
@@ -150,7 +263,41 @@ pub const REQUESTED_BY: &str = "Requested by";
 pub const ROLE_CREATED: &str = "📝 Created role!";
 pub const ROLE_DELETED: &str = "🗑️ Deleted role!";
 pub const ROLE_NOT_FOUND: &str = "⚠️ Role not found!";
+pub const ROLE_MENU_ABOVE_BOT: &str = "🎭 That role is above mine, so I can't hand it out.";
+pub const ROLE_MENU_ADDED: &str = "Added";
+pub const ROLE_MENU_ALREADY: &str = "You already have that role.";
+pub const ROLE_MENU_CREATED: &str = "🎭 Created role menu";
+pub const ROLE_MENU_DANGEROUS: &str =
+    "🎭 Roles with admin or moderation permissions can't go on a role menu.";
+pub const ROLE_MENU_DELETED: &str = "🎭 Deleted role menu";
+pub const ROLE_MENU_EMPTY: &str = "🎭 Add some roles to the menu first.";
+pub const ROLE_MENU_EVERYONE: &str = "🎭 @everyone can't go on a role menu.";
+pub const ROLE_MENU_FULL: &str = "🎭 A role menu can't have more than 25 roles.";
+pub const ROLE_MENU_GONE: &str = "This role menu was deleted.";
+pub const ROLE_MENU_INVALID_EMOJI: &str = "🎭 That isn't an emoji.";
+pub const ROLE_MENU_NO_CHANGE: &str = "Your roles are unchanged.";
+pub const ROLE_MENU_NOT_FOUND: &str = "🎭 No role menu with that id.";
+pub const ROLE_MENU_OPTION_ADDED: &str = "🎭 Added to role menu";
+pub const ROLE_MENU_OPTION_NOT_FOUND: &str = "🎭 That role isn't on the menu.";
+pub const ROLE_MENU_OPTION_REMOVED: &str = "🎭 Removed from role menu";
+pub const ROLE_MENU_PLACEHOLDER: &str = "Pick your roles";
+pub const ROLE_MENU_POSTED: &str = "🎭 Posted role menu";
+pub const ROLE_MENU_REASON: &str = "Role menu";
+pub const ROLE_MENU_REMOVED: &str = "Removed";
+pub const ROLE_MENU_TOO_HIGH: &str = "🎭 That role isn't below your highest role.";
+pub const ROLE_MENU_TOO_MANY: &str = "You can't have any more roles from this menu.";
+pub const ROLE_MENU_UNKNOWN_ROLE: &str = "That role isn't on this menu anymore.";
+pub const ROLE_MENUS: &str = "🎭 Role menus";
+pub const ROLE_MENUS_NONE: &str = "🎭 No role menus yet.";
 pub const SCAN_QUEUED: &str = "🔍 Scan queued! Use";
+pub const SCHEDULE_CANCELLED: &str = "🗑️ Cancelled scheduled job";
+pub const SCHEDULE_CREATED: &str = "🗓️ Scheduled";
+pub const SCHEDULE_DAYS: &str = "days.";
+pub const SCHEDULE_LIST_EMPTY: &str = "🗓️ Nothing is scheduled in this server.";
+pub const SCHEDULE_REPEATS: &str = "🔁 Repeats every";
+pub const SCHEDULED_JOBS: &str = "🗓️ Scheduled";
+pub const SCHEDULED_PLAYBACK_STARTED: &str = "🎉 Scheduled playback started:";
+pub const SCHEDULED_PLAYBACK_STOPPED: &str = "⏹️ Scheduled playback ended, see you next time!";
 pub const SEARCHING: &str = "🔎 Searching...";
 pub const SEEKED: &str = "⏩ Seeked current track to";
 pub const SEEK_FAIL: &str = "❌⏩ Failed to seek to";
@@ -161,6 +308,20 @@ pub const SKIP_VOTE_USER: &str = "has voted to skip!";
 pub const SKIPPED_ALL: &str = "⏭️ Skipped until infinity!";
 pub const SKIPPED_TO: &str = "⏭️ Skipped to";
 pub const SKIPPED: &str = "⏭️ Skipped!";
+pub const SLEEP_TIMER: &str = "💤 Sleep timer";
+pub const SLEEP_TIMER_AFTER_TRACK: &str = "Leaving after this track";
+pub const SLEEP_TIMER_CANCELLED: &str = "⏰ Sleep timer cancelled.";
+pub const SLEEP_TIMER_EXPIRED: &str = "💤 Time's up, good night!";
+pub const SLEEP_TIMER_LEFT: &str = "left";
+pub const SLEEP_TIMER_SET: &str = "💤 I'll fade out and leave in";
+pub const SMART_PLAYLIST_CREATED: &str = "🧠 Saved smart playlist";
+pub const SOUNDBOARD_ADDED: &str = "🔊 Added clip";
+pub const SOUNDBOARD_CLIPS: &str = "🔊 Soundboard";
+pub const SOUNDBOARD_EMPTY: &str = "🔊 The soundboard has no clips yet. Add one with `/sb add`.";
+pub const SOUNDBOARD_EXISTS: &str = "🔊 There's already a clip called";
+pub const SOUNDBOARD_NOT_FOUND: &str = "🔊 No clip called";
+pub const SOUNDBOARD_PLAYING: &str = "🔊 Playing";
+pub const SOUNDBOARD_REMOVED: &str = "🔊 Removed clip";
 pub const SPOTIFY_AUTH_FAILED: &str = "⚠️ **Could not authenticate with Spotify!**\nDid you forget to provide your Spotify application's client ID and secret?";
 pub const SPOTIFY_INVALID_QUERY: &str =
     "⚠️ **Could not find any tracks with that link!**\nAre you sure that is a valid Spotify URL?";
@@ -169,6 +330,8 @@ pub const SONG_MOVED: &str = "🔀 Moved song";
 pub const SONG_MOVED_FROM: &str = "from index";
 pub const SONG_MOVED_TO: &str = "to index";
 pub const STOPPED: &str = "⏹️ Stopped!";
+pub const STOP_AFTER_CANCELLED: &str = "▶️ I'll keep playing after this track.";
+pub const STOP_AFTER_SET: &str = "💤 I'll leave once the current track ends.";
 pub const SUGGESTION: &str = "📝 Suggestion";
 pub const SUBCOMMAND_NOT_FOUND: &str = "⚠️ Subcommand {subcommand} for group {group} not found!";
 pub const TIMEOUT: &str = "⏱️ User Timed Out!";
@@ -176,20 +339,67 @@ pub const TRACK_DURATION: &str = "Track duration:";
 pub const TRACK_NOT_FOUND: &str = "⚠️ **Could not play track!**\nYour request yielded no results.";
 pub const TRACK_INAPPROPRIATE: &str = "⚠️ **Could not play track!**\nThe video you requested may be inappropriate for some users, so sign-in is required.";
 pub const TRACK_TIME_TO_PLAY: &str = "Estimated time until play:";
+pub const TEMP_VOICE_EMPTY: &str = "Temp voice channel is empty";
+pub const TEMP_VOICE_REASON: &str = "Temp voice channel";
 pub const TEST: &str = "🔧 Test";
 pub const TEXT_CHANNEL_CREATED: &str = "📝 Created text channel!";
+pub const TTS_DISABLED: &str = "🗣️ Text-to-speech is off. Turn it on with `/settings set tts`.";
+pub const TTS_INVALID_VOICE: &str =
+    "⚠️ Voice names are letters, numbers, `-`, `_`, `+` or `/`, e.g. `en-us`.";
+pub const TTS_JOINED: &str = "joined";
+pub const TTS_NOTHING_TO_SAY: &str = "⚠️ There's nothing in that to say.";
+pub const TTS_NOW_PLAYING: &str = "Now playing";
+pub const TTS_SAID: &str = "🗣️ Said:";
+pub const TTS_SETTINGS: &str = "🗣️ Text-to-speech settings";
 pub const CATEGORY_CREATED: &str = "📝 Created category!";
 pub const UNTIL: &str = "Until";
 pub const UNKNOWN: &str = "Unknown";
 pub const UNAUTHORIZED_USER: &str = "⚠️ You are not authorized to use this command!";
 pub const UNKNOWN_LIT: &str = UNKNOWN;
 pub const WAYBACK_SNAPSHOT: &str = "Wayback snapshot for";
+pub const VC_HUB_NOT_FOUND: &str = "🔊 That channel isn't a temp voice hub.";
+pub const VC_HUB_NOT_VOICE: &str = "🔊 A temp voice hub has to be a voice channel.";
+pub const VC_HUB_REMOVED: &str = "🔊 No longer a temp voice hub:";
+pub const VC_HUB_SET: &str = "🔊 Joining this channel now creates a temp voice channel:";
+pub const VC_HUBS: &str = "🔊 Temp voice hubs";
+pub const VC_HUBS_NONE: &str = "🔊 This server has no temp voice hubs.";
+pub const VC_KICKED: &str = "🔊 Disconnected";
+pub const VC_LIMIT_REMOVED: &str = "🔊 Your channel has no user limit.";
+pub const VC_LIMIT_SET: &str = "🔊 Your channel's user limit is now";
+pub const VC_LOCKED: &str = "🔒 Locked your channel. Nobody else can join.";
+pub const VC_NOT_IN_CHANNEL: &str = "🔊 They're not in your channel.";
+pub const VC_NOT_OWNER: &str = "🔊 Only the channel's owner can do that.";
+pub const VC_NOT_TEMP: &str = "🔊 Join your temp voice channel first.";
+pub const VC_RENAMED: &str = "🔊 Renamed your channel to";
+pub const VC_TRANSFERRED: &str = "🔊 Your channel now belongs to";
+pub const VC_UNLOCKED: &str = "🔓 Unlocked your channel.";
+pub const VERIFY_ALREADY: &str = "✅ You're already verified.";
+pub const VERIFY_BUTTON: &str = "Verify";
+pub const VERIFY_CAPTCHA_LABEL: &str = "Type the code:";
+pub const VERIFY_FAILED: &str = "❌ That's not right. Press the button to try again.";
+pub const VERIFY_KICK_REASON: &str = "Didn't verify in time.";
+pub const VERIFY_NOT_PENDING: &str = "⚠️ You have no pending verification.";
+pub const VERIFY_PASSWORD_LABEL: &str = "Enter the server password:";
+pub const VERIFY_PROMPT: &str = "welcome! Press the button below to verify before joining in.";
+pub const VERIFY_RULES_PROMPT: &str = "By pressing the button you accept the rules.";
+pub const VERIFY_SETTINGS: &str = "🔐 Verification settings";
+pub const VERIFY_SUCCESS: &str = "✅ You're verified, welcome!";
+pub const VERIFY_TOO_MANY_ATTEMPTS: &str =
+    "⛔ Too many wrong answers. Ask a moderator to let you in.";
 pub const VERSION_LATEST: &str = "Find the latest version [here]";
 pub const VERSION: &str = "Version";
 pub const VERSION_LATEST_HASH: &str = "Build hash [here]";
 pub const VOLUME: &str = "🔊 Volume";
 pub const OLD_VOLUME: &str = "Old Volume";
 pub const VOICE_CHANNEL_CREATED: &str = "🔊 Created voice channel!";
+pub const VOICE_STATS_CAMERA: &str = "Camera on";
+pub const VOICE_STATS_CHANNEL: &str = "🎙️ Most time in";
+pub const VOICE_STATS_NONE: &str = "🎙️ No voice time yet.";
+pub const VOICE_STATS_NO_CHANNEL: &str = "🎙️ Pick a voice channel, or join one.";
+pub const VOICE_STATS_SERVER: &str = "🎙️ Most time in voice";
+pub const VOICE_STATS_STREAMING: &str = "Streaming";
+pub const VOICE_STATS_USER: &str = "🎙️ Voice time for";
+pub const VOICE_STATS_VOICE: &str = "In voice";
 
 pub const VOTE_TOPGG_TEXT: &str = "✅ Vote for CrackTunes on";
 pub const VOTE_TOPGG_LINK_TEXT: &str = "top.gg!";
@@ -201,4 +411,9 @@ pub const VOTE_TOPGG_NOT_VOTED: &str = "You haven't voted recently! Here is the
 pub const INVITE_TEXT: &str = "🔗 ";
 pub const INVITE_LINK_TEXT: &str = "Invite Crack Tunes to your server!";
 pub const INVITE_LINK_TEXT_SHORT: &str = "invite";
+pub const INVITES_LEADERBOARD: &str = "🔗 Top inviters";
+pub const INVITES_NONE: &str = "🔗 No tracked joins yet.";
+pub const INVITES_INVITED_BY: &str = "was invited by";
+pub const INVITES_UNKNOWN: &str = "🔗 No idea who invited";
+pub const INVITES_COUNT: &str = "invited";
 pub const INVITE_URL: &str = "https://discord.com/oauth2/authorize?client_id=1115229568006103122&permissions=551940115520&scope=bot+applications.commands";
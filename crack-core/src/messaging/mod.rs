@@ -1,3 +1,4 @@
 pub mod interface;
 pub mod message;
 pub mod messages;
+pub mod template;
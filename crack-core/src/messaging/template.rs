@@ -0,0 +1,657 @@
+//! Templates for welcome and leave messages.
+//!
+//! - variables: `{user.mention}`, `{user.name}`, `{user.id}`, `{guild.name}`,
+//!   `{member_count}`, `{account_age}`, `{inviter}`, and `{user}` for the mention,
+//! - conditionals: `{if inviter}...{else}...{end}`, `{if not inviter}`,
+//!   `{if account_age < 7d}`, `{if member_count >= 1000}`,
+//! - random variants: `{choose}Hi{or}Hello{or}Hey{end}`,
+//! - `{image <url>}` sends the message as an embed with the image, and `{embed}`
+//!   sends it as an embed without one.
+//!
+//! `{{` and `}}` are literal braces, as is a `{` that's never closed.
+use crate::duration::parse_duration;
+use crate::messaging::messages::UNKNOWN;
+use rand::Rng;
+use serenity::all::{Cache, CreateEmbed, CreateMessage, GuildId, Mention, User, UserId};
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+/// Why a template couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    UnknownVariable(String),
+    /// A block like `{if ...}` without its `{end}`.
+    Unclosed(&'static str),
+    /// An `{else}`, `{or}` or `{end}` outside of a block it belongs to.
+    Unexpected(String),
+    InvalidCondition(String),
+    MissingImageUrl,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownVariable(name) => write!(
+                f,
+                "Unknown variable `{{{name}}}`. Try `{{user.mention}}`, `{{user.name}}`, \
+                 `{{guild.name}}`, `{{member_count}}`, `{{account_age}}` or `{{inviter}}`."
+            ),
+            Self::Unclosed(block) => write!(f, "`{{{block}}}` needs a matching `{{end}}`."),
+            Self::Unexpected(tag) => write!(f, "`{{{tag}}}` doesn't belong here."),
+            Self::InvalidCondition(cond) => write!(f, "Can't read the condition `{cond}`."),
+            Self::MissingImageUrl => {
+                f.write_str("`{image}` needs a url, e.g. `{image https://...}`.")
+            },
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// What a template knows about the member.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateContext {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub guild_name: String,
+    pub member_count: Option<u64>,
+    pub account_age: Duration,
+    /// Who invited the member, if known.
+    pub inviter: Option<String>,
+}
+
+impl TemplateContext {
+    /// The context for a user, with their account age as of `now`.
+    pub fn new(
+        user: &User,
+        guild_name: String,
+        member_count: Option<u64>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        let created_at = user.id.created_at().unix_timestamp();
+        let account_age = (now.timestamp() - created_at).max(0) as u64;
+        Self {
+            user_id: user.id,
+            user_name: user.name.to_string(),
+            guild_name,
+            member_count,
+            account_age: Duration::from_secs(account_age),
+            inviter: None,
+        }
+    }
+
+    /// The context for a user joining or leaving a guild, from the cache.
+    pub fn for_guild(cache: &Cache, guild_id: GuildId, user: &User) -> Self {
+        let (guild_name, member_count) = guild_id
+            .to_guild_cached(cache)
+            .map(|guild| (guild.name.to_string(), Some(guild.member_count)))
+            .unwrap_or_default();
+        Self::new(user, guild_name, member_count, chrono::Utc::now())
+    }
+
+    /// Set who invited the member.
+    pub fn with_inviter(self, inviter: Option<String>) -> Self {
+        Self { inviter, ..self }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Var {
+    UserMention,
+    UserName,
+    UserId,
+    GuildName,
+    MemberCount,
+    AccountAge,
+    Inviter,
+}
+
+impl FromStr for Var {
+    type Err = TemplateError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "user" | "user.mention" => Ok(Self::UserMention),
+            "user.name" => Ok(Self::UserName),
+            "user.id" => Ok(Self::UserId),
+            "guild.name" | "guild" => Ok(Self::GuildName),
+            "member_count" => Ok(Self::MemberCount),
+            "account_age" => Ok(Self::AccountAge),
+            "inviter" => Ok(Self::Inviter),
+            _ => Err(TemplateError::UnknownVariable(name.to_string())),
+        }
+    }
+}
+
+impl Var {
+    fn render(self, ctx: &TemplateContext) -> String {
+        match self {
+            Self::UserMention => Mention::User(ctx.user_id).to_string(),
+            Self::UserName => ctx.user_name.clone(),
+            Self::UserId => ctx.user_id.to_string(),
+            Self::GuildName => ctx.guild_name.clone(),
+            Self::MemberCount => ctx
+                .member_count
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| UNKNOWN.to_string()),
+            Self::AccountAge => format_account_age(ctx.account_age),
+            Self::Inviter => ctx.inviter.clone().unwrap_or_else(|| UNKNOWN.to_string()),
+        }
+    }
+
+    /// Whether the variable has a value worth mentioning.
+    fn is_set(self, ctx: &TemplateContext) -> bool {
+        match self {
+            Self::MemberCount => ctx.member_count.is_some_and(|n| n > 0),
+            Self::AccountAge => !ctx.account_age.is_zero(),
+            Self::Inviter => ctx.inviter.is_some(),
+            _ => !self.render(ctx).is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn parse(op: &str) -> Option<Self> {
+        Some(match op {
+            "==" | "=" => Self::Eq,
+            "!=" => Self::Ne,
+            "<" => Self::Lt,
+            "<=" => Self::Le,
+            ">" => Self::Gt,
+            ">=" => Self::Ge,
+            _ => return None,
+        })
+    }
+
+    fn compare<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Self::Eq => left == right,
+            Self::Ne => left != right,
+            Self::Lt => left < right,
+            Self::Le => left <= right,
+            Self::Gt => left > right,
+            Self::Ge => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Set(Var),
+    NotSet(Var),
+    Count(Op, u64),
+    Age(Op, Duration),
+    Text(Var, bool, String),
+}
+
+impl Condition {
+    fn parse(cond: &str) -> Result<Self, TemplateError> {
+        let invalid = || TemplateError::InvalidCondition(cond.to_string());
+        let mut words = cond.split_whitespace();
+        let first = words.next().ok_or_else(invalid)?;
+        let Some(second) = words.next() else {
+            return Ok(Self::Set(first.parse()?));
+        };
+        if first == "not" {
+            return match words.next() {
+                None => Ok(Self::NotSet(second.parse()?)),
+                Some(_) => Err(invalid()),
+            };
+        }
+
+        let var: Var = first.parse()?;
+        let op = Op::parse(second).ok_or_else(invalid)?;
+        let value = words.collect::<Vec<_>>().join(" ");
+        if value.is_empty() {
+            return Err(invalid());
+        }
+        match var {
+            Var::MemberCount => Ok(Self::Count(op, value.parse().map_err(|_| invalid())?)),
+            Var::AccountAge => Ok(Self::Age(
+                op,
+                parse_duration(&value).map_err(|_| invalid())?,
+            )),
+            _ => match op {
+                Op::Eq => Ok(Self::Text(var, true, value)),
+                Op::Ne => Ok(Self::Text(var, false, value)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+
+    fn eval(&self, ctx: &TemplateContext) -> bool {
+        match self {
+            Self::Set(var) => var.is_set(ctx),
+            Self::NotSet(var) => !var.is_set(ctx),
+            Self::Count(op, n) => ctx.member_count.is_some_and(|count| op.compare(count, *n)),
+            Self::Age(op, age) => op.compare(ctx.account_age, *age),
+            Self::Text(var, equal, value) => var.render(ctx).eq_ignore_ascii_case(value) == *equal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var(Var),
+    If(Condition, Vec<Node>, Vec<Node>),
+    Choose(Vec<Vec<Node>>),
+    Image(String),
+    Embed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    Tag(String),
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            },
+            '{' => {
+                let mut ahead = chars.clone();
+                let mut tag = String::new();
+                let closed = loop {
+                    match ahead.next() {
+                        Some('}') => break true,
+                        Some('{') | None => break false,
+                        Some(c) => tag.push(c),
+                    }
+                };
+                // A stray `{`, e.g. in ":-{", is just text.
+                if !closed {
+                    text.push('{');
+                    continue;
+                }
+                chars = ahead;
+                if !text.is_empty() {
+                    tokens.push(Token::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(Token::Tag(tag.trim().to_string()));
+            },
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    tokens
+}
+
+/// Parse nodes until one of the `stops` tags, returning the nodes and the stop
+/// that ended them, or `None` at the end of the input.
+fn parse_nodes(
+    tokens: &mut std::vec::IntoIter<Token>,
+    stops: &[&str],
+) -> Result<(Vec<Node>, Option<String>), TemplateError> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text));
+                continue;
+            },
+            Token::Tag(tag) => tag,
+        };
+        let (keyword, rest) = tag
+            .split_once(char::is_whitespace)
+            .map(|(k, r)| (k, r.trim()))
+            .unwrap_or((tag.as_str(), ""));
+        match keyword {
+            "else" | "or" | "end" if rest.is_empty() => {
+                if stops.contains(&keyword) {
+                    return Ok((nodes, Some(keyword.to_string())));
+                }
+                return Err(TemplateError::Unexpected(tag));
+            },
+            "if" => {
+                let cond = Condition::parse(rest)?;
+                let (then, stop) = parse_nodes(tokens, &["else", "end"])?;
+                let otherwise = match stop.as_deref() {
+                    Some("else") => match parse_nodes(tokens, &["end"])? {
+                        (otherwise, Some(_)) => otherwise,
+                        (_, None) => return Err(TemplateError::Unclosed("if")),
+                    },
+                    Some(_) => Vec::new(),
+                    None => return Err(TemplateError::Unclosed("if")),
+                };
+                nodes.push(Node::If(cond, then, otherwise));
+            },
+            "choose" if rest.is_empty() => {
+                let mut variants = Vec::new();
+                loop {
+                    let (variant, stop) = parse_nodes(tokens, &["or", "end"])?;
+                    variants.push(variant);
+                    match stop.as_deref() {
+                        Some("or") => continue,
+                        Some(_) => break,
+                        None => return Err(TemplateError::Unclosed("choose")),
+                    }
+                }
+                nodes.push(Node::Choose(variants));
+            },
+            "image" => {
+                if rest.is_empty() {
+                    return Err(TemplateError::MissingImageUrl);
+                }
+                nodes.push(Node::Image(rest.to_string()));
+            },
+            "embed" if rest.is_empty() => nodes.push(Node::Embed),
+            _ => nodes.push(Node::Var(tag.parse()?)),
+        }
+    }
+    Ok((nodes, None))
+}
+
+/// A parsed welcome or leave message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut tokens = tokenize(input).into_iter();
+        let (nodes, _) = parse_nodes(&mut tokens, &[])?;
+        Ok(Self { nodes })
+    }
+}
+
+/// A rendered template, ready to send.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderedTemplate {
+    pub content: String,
+    pub image: Option<String>,
+    pub embed: bool,
+}
+
+impl RenderedTemplate {
+    /// A message sent as written, for templates that don't parse.
+    pub fn raw(message: &str) -> Self {
+        Self {
+            content: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The message to send, as plain text or as an embed.
+    pub fn to_message<'a>(&self) -> CreateMessage<'a> {
+        if !self.embed {
+            return CreateMessage::new().content(self.content.clone());
+        }
+        let mut embed = CreateEmbed::new().description(self.content.clone());
+        if let Some(image) = &self.image {
+            embed = embed.image(image.clone());
+        }
+        CreateMessage::new().embed(embed)
+    }
+}
+
+impl Template {
+    /// Render the template, picking `{choose}` variants with `rng`.
+    pub fn render<R: Rng + ?Sized>(&self, ctx: &TemplateContext, rng: &mut R) -> RenderedTemplate {
+        let mut rendered = RenderedTemplate::default();
+        render_nodes(&self.nodes, ctx, rng, &mut rendered);
+        rendered.content = rendered.content.trim().to_string();
+        rendered
+    }
+
+    /// Whether the template mentions or names the member anywhere.
+    pub fn names_user(&self) -> bool {
+        fn any(nodes: &[Node]) -> bool {
+            nodes.iter().any(|node| match node {
+                Node::Var(var) => matches!(var, Var::UserMention | Var::UserName),
+                Node::If(_, then, otherwise) => any(then) || any(otherwise),
+                Node::Choose(variants) => variants.iter().any(|v| any(v)),
+                _ => false,
+            })
+        }
+        any(&self.nodes)
+    }
+}
+
+fn render_nodes<R: Rng + ?Sized>(
+    nodes: &[Node],
+    ctx: &TemplateContext,
+    rng: &mut R,
+    out: &mut RenderedTemplate,
+) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.content.push_str(text),
+            Node::Var(var) => out.content.push_str(&var.render(ctx)),
+            Node::If(cond, then, otherwise) => {
+                let branch = if cond.eval(ctx) { then } else { otherwise };
+                render_nodes(branch, ctx, rng, out);
+            },
+            Node::Choose(variants) => {
+                let variant = &variants[rng.gen_range(0..variants.len())];
+                render_nodes(variant, ctx, rng, out);
+            },
+            Node::Image(url) => {
+                out.image = Some(url.clone());
+                out.embed = true;
+            },
+            Node::Embed => out.embed = true,
+        }
+    }
+}
+
+/// Render a welcome message. Messages that never name the member get their
+/// mention appended, like before templates.
+pub fn render_welcome<R: Rng + ?Sized>(
+    message: &str,
+    ctx: &TemplateContext,
+    rng: &mut R,
+) -> Result<RenderedTemplate, TemplateError> {
+    let template: Template = message.parse()?;
+    let mut rendered = template.render(ctx, rng);
+    if !template.names_user() {
+        rendered.content = format!("{} {}", rendered.content, Mention::User(ctx.user_id));
+    }
+    Ok(rendered)
+}
+
+/// Render an account age in its largest whole unit, e.g. `3 years` or `5 days`.
+pub fn format_account_age(age: Duration) -> String {
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+    const YEAR: u64 = 365 * DAY;
+    let secs = age.as_secs();
+    let (n, unit) = match secs {
+        s if s >= YEAR => (s / YEAR, "year"),
+        s if s >= DAY => (s / DAY, "day"),
+        s if s >= HOUR => (s / HOUR, "hour"),
+        _ => return "less than an hour".to_string(),
+    };
+    format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn ctx() -> TemplateContext {
+        TemplateContext {
+            user_id: UserId::new(42),
+            user_name: "cracky".to_string(),
+            guild_name: "Crack Den".to_string(),
+            member_count: Some(1234),
+            account_age: Duration::from_secs(3 * DAY),
+            inviter: None,
+        }
+    }
+
+    fn render(template: &str, ctx: &TemplateContext) -> RenderedTemplate {
+        template
+            .parse::<Template>()
+            .unwrap()
+            .render(ctx, &mut StdRng::seed_from_u64(1))
+    }
+
+    #[test]
+    fn test_variables() {
+        let rendered = render(
+            "Welcome {user.mention} ({user.name}) to {guild.name}, member #{member_count}!",
+            &ctx(),
+        );
+        assert_eq!(
+            rendered.content,
+            "Welcome <@42> (cracky) to Crack Den, member #1234!"
+        );
+        assert!(!rendered.embed);
+        assert_eq!(render("{user}", &ctx()).content, "<@42>");
+        assert_eq!(render("{account_age}", &ctx()).content, "3 days");
+        assert_eq!(render("{inviter}", &ctx()).content, UNKNOWN);
+        assert_eq!(render("{{literal}}", &ctx()).content, "{literal}");
+        assert_eq!(render("Hi {user :-{", &ctx()).content, "Hi {user :-{");
+        assert_eq!(render("{ {user}", &ctx()).content, "{ <@42>");
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let template = "{if inviter}Invited by {inviter}.{else}Found us yourself!{end}";
+        assert_eq!(render(template, &ctx()).content, "Found us yourself!");
+        let invited = ctx().with_inviter(Some("mod".to_string()));
+        assert_eq!(render(template, &invited).content, "Invited by mod.");
+
+        let template = "{if account_age < 7d}New account!{end}";
+        assert_eq!(render(template, &ctx()).content, "New account!");
+        let old = TemplateContext {
+            account_age: Duration::from_secs(30 * DAY),
+            ..ctx()
+        };
+        assert_eq!(render(template, &old).content, "");
+
+        assert_eq!(
+            render("{if member_count >= 1000}big{end}", &ctx()).content,
+            "big"
+        );
+        assert_eq!(render("{if not inviter}solo{end}", &ctx()).content, "solo");
+        assert_eq!(
+            render("{if user.name == Cracky}me{else}you{end}", &ctx()).content,
+            "me"
+        );
+        // Nested blocks.
+        assert_eq!(
+            render(
+                "{if member_count > 1}{if inviter}a{else}b{end}{else}c{end}",
+                &ctx()
+            )
+            .content,
+            "b"
+        );
+    }
+
+    #[test]
+    fn test_choose() {
+        let template: Template = "{choose}Hi{or}Hello{or}Hey {user.name}{end}"
+            .parse()
+            .unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let seen = (0..50)
+            .map(|_| template.render(&ctx(), &mut rng).content)
+            .collect::<std::collections::HashSet<_>>();
+        let expected = ["Hi", "Hello", "Hey cracky"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_embed_and_image() {
+        let rendered = render("{image https://example.com/hi.png}Welcome {user}!", &ctx());
+        assert!(rendered.embed);
+        assert_eq!(
+            rendered.image.as_deref(),
+            Some("https://example.com/hi.png")
+        );
+        assert_eq!(rendered.content, "Welcome <@42>!");
+
+        let rendered = render("{embed}Hi", &ctx());
+        assert!(rendered.embed);
+        assert_eq!(rendered.image, None);
+    }
+
+    #[test]
+    fn test_names_user() {
+        let names = |t: &str| t.parse::<Template>().unwrap().names_user();
+        assert!(names("Hi {user}"));
+        assert!(names("{choose}a{or}{user.name}{end}"));
+        assert!(!names("Welcome to {guild.name}"));
+    }
+
+    #[test]
+    fn test_render_welcome() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let rendered = render_welcome("Welcome!", &ctx(), &mut rng).unwrap();
+        assert_eq!(rendered.content, "Welcome! <@42>");
+        let rendered = render_welcome("Welcome {user}!", &ctx(), &mut rng).unwrap();
+        assert_eq!(rendered.content, "Welcome <@42>!");
+    }
+
+    #[test]
+    fn test_errors() {
+        let parse = |t: &str| t.parse::<Template>().unwrap_err();
+        assert_eq!(
+            parse("{nope}"),
+            TemplateError::UnknownVariable("nope".to_string())
+        );
+        assert_eq!(parse("{if inviter}hi"), TemplateError::Unclosed("if"));
+        assert_eq!(parse("{choose}a{or}b"), TemplateError::Unclosed("choose"));
+        assert_eq!(
+            parse("hi{end}"),
+            TemplateError::Unexpected("end".to_string())
+        );
+        assert_eq!(
+            parse("{if account_age < soon}x{end}"),
+            TemplateError::InvalidCondition("account_age < soon".to_string())
+        );
+        assert_eq!(
+            parse("{if guild.name > a}x{end}"),
+            TemplateError::InvalidCondition("guild.name > a".to_string())
+        );
+        assert_eq!(parse("{image}"), TemplateError::MissingImageUrl);
+    }
+
+    #[test]
+    fn test_format_account_age() {
+        assert_eq!(
+            format_account_age(Duration::from_secs(60)),
+            "less than an hour"
+        );
+        assert_eq!(format_account_age(Duration::from_secs(60 * 60)), "1 hour");
+        assert_eq!(format_account_age(Duration::from_secs(DAY)), "1 day");
+        assert_eq!(
+            format_account_age(Duration::from_secs(800 * DAY)),
+            "2 years"
+        );
+    }
+}
@@ -0,0 +1,508 @@
+use crate::{
+    db::{CaseAction, ModerationCase},
+    duration::clamp_timeout,
+    guild::{
+        cache::GuildCache,
+        operations::GuildSettingsOperations,
+        settings::{AutomodAction, AutomodSettings},
+    },
+    messaging::{
+        message::CrackedMessage,
+        messages::{
+            AUTOMOD_BANNED_DOMAIN, AUTOMOD_DUPLICATE, AUTOMOD_INVITE_LINK, AUTOMOD_MASS_MENTION,
+            AUTOMOD_RATE_LIMIT, AUTOMOD_REASON,
+        },
+    },
+    moderation::record_case,
+    CrackedError, Data,
+};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serenity::all::{
+    Context as SerenityContext, EditMember, GuildId, Mentionable, Message, UserId,
+};
+use std::{collections::HashSet, fmt::Display, time::Duration};
+
+/// How far back identical messages count towards the duplicate limit.
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+lazy_static! {
+    static ref LINK_REGEX: Regex = Regex::new(r"https?://[^\s<>]+").unwrap();
+    static ref INVITE_REGEX: Regex =
+        Regex::new(r"(?i)\b(?:discord\.gg|discord(?:app)?\.com/invite)/[a-z0-9-]+").unwrap();
+}
+
+/// Which automod rule a message broke.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    RateLimit { messages: usize, seconds: u32 },
+    Duplicate(usize),
+    MassMention(usize),
+    InviteLink,
+    BannedDomain(String),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RateLimit { messages, seconds } => write!(
+                f,
+                "{} ({} messages in {}s)",
+                AUTOMOD_RATE_LIMIT, messages, seconds
+            ),
+            Self::Duplicate(n) => write!(f, "{} ({} times)", AUTOMOD_DUPLICATE, n),
+            Self::MassMention(n) => write!(f, "{} ({})", AUTOMOD_MASS_MENTION, n),
+            Self::InviteLink => f.write_str(AUTOMOD_INVITE_LINK),
+            Self::BannedDomain(domain) => write!(f, "{} `{}`", AUTOMOD_BANNED_DOMAIN, domain),
+        }
+    }
+}
+
+/// The parts of a message automod looks at.
+#[derive(Debug, Clone)]
+pub struct AutomodMessage<'a> {
+    pub content: &'a str,
+    /// User, role and everyone mentions.
+    pub mentions: usize,
+}
+
+impl<'a> AutomodMessage<'a> {
+    pub fn new(message: &'a Message) -> Self {
+        Self {
+            content: &message.content,
+            mentions: message.mentions.len()
+                + message.mention_roles.len()
+                + usize::from(message.mention_everyone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RecentMessage {
+    at: DateTime<Utc>,
+    content: String,
+}
+
+/// Check a message against the guild's rules. The rate limit and duplicate rules
+/// look back over the author's messages in the guild's message cache, which the
+/// message is added to first. Users' messages only stay in the cache for as long
+/// as a rule can look back on them; the bot's own are left alone.
+pub fn check_message(
+    cache: &mut GuildCache,
+    settings: &AutomodSettings,
+    banned_domains: &HashSet<String>,
+    message: &Message,
+    at: DateTime<Utc>,
+) -> Option<Violation> {
+    let checked = AutomodMessage::new(message);
+    if let Some(violation) = check_content(settings, banned_domains, &checked) {
+        return Some(violation);
+    }
+
+    let keep = Duration::from_secs(settings.rate_limit_seconds as u64).max(DUPLICATE_WINDOW);
+    let keep = chrono::Duration::from_std(keep).unwrap_or(chrono::Duration::MAX);
+    let since = at
+        .checked_sub_signed(keep)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let messages = &mut cache.time_ordered_messages;
+    messages.retain(|sent, m| m.author.bot() || *sent >= since);
+    messages.insert(at, message.clone());
+
+    let history = messages
+        .range(since..)
+        .filter(|(_, m)| m.author.id == message.author.id)
+        .map(|(sent, m)| RecentMessage {
+            at: *sent,
+            content: normalize(&m.content),
+        })
+        .collect::<Vec<_>>();
+    check_rate(settings, &history, at).or_else(|| check_duplicates(settings, &history))
+}
+
+/// Forget a user's cached messages, so one burst is only punished once.
+pub fn forget_messages(cache: &mut GuildCache, user_id: UserId) {
+    cache
+        .time_ordered_messages
+        .retain(|_, m| m.author.id != user_id);
+}
+
+/// Rules that only need the message itself.
+fn check_content(
+    settings: &AutomodSettings,
+    banned_domains: &HashSet<String>,
+    message: &AutomodMessage<'_>,
+) -> Option<Violation> {
+    if settings.max_mentions > 0 && message.mentions > settings.max_mentions as usize {
+        return Some(Violation::MassMention(message.mentions));
+    }
+    if settings.block_invites && INVITE_REGEX.is_match(message.content) {
+        return Some(Violation::InviteLink);
+    }
+    if settings.block_banned_domains {
+        if let Some(domain) = find_banned_domain(message.content, banned_domains) {
+            return Some(Violation::BannedDomain(domain));
+        }
+    }
+    None
+}
+
+fn check_rate(
+    settings: &AutomodSettings,
+    history: &[RecentMessage],
+    now: DateTime<Utc>,
+) -> Option<Violation> {
+    if settings.rate_limit_messages == 0 {
+        return None;
+    }
+    let window = chrono::Duration::seconds(settings.rate_limit_seconds as i64);
+    let messages = history.iter().filter(|m| now - m.at < window).count();
+    (messages > settings.rate_limit_messages as usize).then_some(Violation::RateLimit {
+        messages,
+        seconds: settings.rate_limit_seconds,
+    })
+}
+
+fn check_duplicates(settings: &AutomodSettings, history: &[RecentMessage]) -> Option<Violation> {
+    let last = history.last()?;
+    if settings.max_duplicates == 0 || last.content.is_empty() {
+        return None;
+    }
+    let repeats = history
+        .iter()
+        .rev()
+        .take_while(|m| m.content == last.content)
+        .count();
+    (repeats >= settings.max_duplicates as usize).then_some(Violation::Duplicate(repeats))
+}
+
+/// The first banned domain linked to in `content`. Subdomains count, so banning
+/// `example.com` also bans `www.example.com`.
+pub fn find_banned_domain(content: &str, banned_domains: &HashSet<String>) -> Option<String> {
+    if banned_domains.is_empty() {
+        return None;
+    }
+    LINK_REGEX
+        .find_iter(content)
+        .filter_map(|link| url::Url::parse(link.as_str()).ok())
+        .filter_map(|url| url.host_str().map(str::to_lowercase))
+        .find_map(|host| {
+            banned_domains
+                .iter()
+                .map(|domain| domain.trim().to_lowercase())
+                .find(|domain| {
+                    !domain.is_empty()
+                        && (host == *domain || host.ends_with(&format!(".{}", domain)))
+                })
+        })
+}
+
+fn normalize(content: &str) -> String {
+    content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Check a new message against the guild's automod rules and act on it. Returns
+/// whether the message broke a rule.
+#[cfg(not(tarpaulin_include))]
+pub async fn run_automod(
+    ctx: &SerenityContext,
+    data: &Data,
+    message: &Message,
+) -> Result<bool, CrackedError> {
+    let Some(guild_id) = message.guild_id else {
+        return Ok(false);
+    };
+    if message.author.bot() {
+        return Ok(false);
+    }
+    let Some(settings) = data.get_guild_settings(guild_id).await else {
+        return Ok(false);
+    };
+    let Some(automod) = settings.automod_settings.as_ref().filter(|a| a.enabled) else {
+        return Ok(false);
+    };
+    let user_id = message.author.id;
+    if settings.check_mod_user_id(user_id) {
+        return Ok(false);
+    }
+
+    let violation = {
+        let mut caches = data.guild_cache_map.lock().await;
+        let cache = caches.entry(guild_id).or_default();
+        let violation = check_message(
+            cache,
+            automod,
+            &settings.banned_domains,
+            message,
+            Utc::now(),
+        );
+        if violation.is_some() {
+            forget_messages(cache, user_id);
+        }
+        violation
+    };
+    let Some(violation) = violation else {
+        return Ok(false);
+    };
+    enforce(ctx, data, message, guild_id, automod, &violation).await?;
+    Ok(true)
+}
+
+/// Delete the message, carry out the configured action and record it as a case.
+#[cfg(not(tarpaulin_include))]
+async fn enforce(
+    ctx: &SerenityContext,
+    data: &Data,
+    message: &Message,
+    guild_id: GuildId,
+    automod: &AutomodSettings,
+    violation: &Violation,
+) -> Result<(), CrackedError> {
+    let http = &ctx.http;
+    let reason = format!("{} {}", AUTOMOD_REASON, violation);
+    if let Err(e) = message.delete(http, Some(reason.as_str())).await {
+        tracing::warn!("Automod failed to delete message {}: {}", message.id, e);
+    }
+
+    let user_id = message.author.id;
+    let (action, duration) = match automod.action {
+        AutomodAction::Delete => (CaseAction::Delete, None),
+        AutomodAction::Warn => {
+            let warning = CrackedMessage::AutomodWarning {
+                mention: user_id.mention(),
+                reason: violation.to_string(),
+            };
+            message.channel_id.say(http, warning.to_string()).await?;
+            (CaseAction::Warn, None)
+        },
+        AutomodAction::Timeout => {
+            let duration = clamp_timeout(Duration::from_secs(automod.timeout_seconds as u64));
+            let until = Utc::now() + duration;
+            guild_id
+                .edit_member(
+                    http,
+                    user_id,
+                    EditMember::new()
+                        .disable_communication_until(until.to_rfc3339())
+                        .audit_log_reason(&reason),
+                )
+                .await?;
+            (CaseAction::Timeout, Some(duration))
+        },
+    };
+
+    let bot_id = ctx.cache.current_user().id;
+    let case = ModerationCase::new(
+        guild_id.get() as i64,
+        action,
+        bot_id.get() as i64,
+        user_id.get() as i64,
+        Some(reason),
+        duration,
+        Utc::now(),
+    );
+    record_case(http, data, case).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::serenity::small_fixed_array::FixedString;
+
+    fn settings() -> AutomodSettings {
+        AutomodSettings {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn message(user: u64, content: &str) -> Message {
+        let mut message = Message::default();
+        message.author.id = UserId::new(user);
+        message.content = FixedString::from_str_trunc(content);
+        message
+    }
+
+    fn content(content: &str) -> AutomodMessage<'_> {
+        AutomodMessage {
+            content,
+            mentions: 0,
+        }
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut cache = GuildCache::default();
+        let none = HashSet::new();
+        for i in 0..5 {
+            let msg = message(2, &format!("message {}", i));
+            assert_eq!(
+                check_message(&mut cache, &settings(), &none, &msg, at(i)),
+                None
+            );
+        }
+        // Keys in the cache are send times, so this can't be exactly at(4).
+        let burst = at(4) + chrono::Duration::milliseconds(500);
+        let msg = message(2, "one more");
+        assert_eq!(
+            check_message(&mut cache, &settings(), &none, &msg, burst),
+            Some(Violation::RateLimit {
+                messages: 6,
+                seconds: 5
+            })
+        );
+
+        // Slowing down is fine.
+        forget_messages(&mut cache, UserId::new(2));
+        assert!(cache.time_ordered_messages.is_empty());
+        for i in 0..10 {
+            let msg = message(2, &format!("message {}", i));
+            assert_eq!(
+                check_message(&mut cache, &settings(), &none, &msg, at(i * 2)),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut cache = GuildCache::default();
+        let none = HashSet::new();
+        let spam = message(2, "Buy  my stuff");
+        assert_eq!(
+            check_message(&mut cache, &settings(), &none, &spam, at(0)),
+            None
+        );
+        assert_eq!(
+            check_message(&mut cache, &settings(), &none, &spam, at(10)),
+            None
+        );
+        assert_eq!(
+            check_message(
+                &mut cache,
+                &settings(),
+                &none,
+                &message(2, "buy my STUFF"),
+                at(20)
+            ),
+            Some(Violation::Duplicate(3))
+        );
+
+        // Other users don't count, and neither do empty messages.
+        assert_eq!(
+            check_message(
+                &mut cache,
+                &settings(),
+                &none,
+                &message(3, "buy my stuff"),
+                at(30)
+            ),
+            None
+        );
+        for i in 0..5 {
+            assert_eq!(
+                check_message(
+                    &mut cache,
+                    &settings(),
+                    &none,
+                    &message(3, ""),
+                    at(40 + i * 10)
+                ),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_old_messages_leave_the_cache() {
+        let mut cache = GuildCache::default();
+        let none = HashSet::new();
+        let mut own = message(1, "now playing");
+        own.author.set_bot(true);
+        cache.time_ordered_messages.insert(at(-60), own);
+        check_message(&mut cache, &settings(), &none, &message(2, "hi"), at(0));
+        check_message(
+            &mut cache,
+            &settings(),
+            &none,
+            &message(3, "hi"),
+            at(10 * 60),
+        );
+
+        // The user's old message is gone, the bot's own messages are left alone.
+        let authors = cache
+            .time_ordered_messages
+            .values()
+            .map(|m| m.author.id.get())
+            .collect::<Vec<_>>();
+        assert_eq!(authors, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_mass_mentions() {
+        let mut msg = content("hi all");
+        msg.mentions = 6;
+        assert_eq!(
+            check_content(&settings(), &HashSet::new(), &msg),
+            Some(Violation::MassMention(6))
+        );
+        let off = AutomodSettings {
+            max_mentions: 0,
+            ..settings()
+        };
+        assert_eq!(check_content(&off, &HashSet::new(), &msg), None);
+    }
+
+    #[test]
+    fn test_invite_links() {
+        let none = HashSet::new();
+        for link in [
+            "join discord.gg/abc123",
+            "https://discord.com/invite/abc",
+            "DiscordApp.com/invite/xyz",
+        ] {
+            assert_eq!(
+                check_content(&settings(), &none, &content(link)),
+                Some(Violation::InviteLink)
+            );
+        }
+        assert_eq!(
+            check_content(&settings(), &none, &content("discord is great")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_banned_domain() {
+        let banned: HashSet<String> = ["example.com".to_string()].into_iter().collect();
+        assert_eq!(
+            find_banned_domain("see https://www.example.com/x", &banned),
+            Some("example.com".to_string())
+        );
+        assert_eq!(
+            find_banned_domain("see http://EXAMPLE.com", &banned),
+            Some("example.com".to_string())
+        );
+        assert_eq!(
+            find_banned_domain("see https://notexample.com", &banned),
+            None
+        );
+        assert_eq!(
+            find_banned_domain("example.com without a scheme", &banned),
+            None
+        );
+        assert_eq!(
+            find_banned_domain("https://example.com", &HashSet::new()),
+            None
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{
+    clock::PollJob,
+    db::{CaseAction, ModerationCase},
+    moderation::record_case,
+    CrackedError, Data,
+};
+use chrono::{DateTime, Utc};
+use serenity::all::{Context as SerenityContext, EditMember, GuildId, UserId};
+use sqlx::PgPool;
+use std::{future::Future, sync::Arc, time::Duration};
+
+/// How often to check for timed moderation actions that have run out.
+pub const CASE_EXPIRY_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Undoes a timed moderation action once it expires.
+pub trait CaseReverser: Send + Sync + 'static {
+    fn reverse(
+        &self,
+        case: &ModerationCase,
+        action: CaseAction,
+    ) -> impl Future<Output = Result<(), CrackedError>> + Send;
+}
+
+/// Reverses expired tempbans and tempmutes from the database. Drive it with a
+/// [`Poller`](crate::clock::Poller).
+pub struct CaseExpiry<R: CaseReverser> {
+    pool: PgPool,
+    reverser: R,
+}
+
+impl<R: CaseReverser> CaseExpiry<R> {
+    pub fn new(pool: PgPool, reverser: R) -> Self {
+        Self { pool, reverser }
+    }
+}
+
+impl<R: CaseReverser> PollJob for CaseExpiry<R> {
+    const NAME: &'static str = "Case expiry";
+    const POLL_INTERVAL: Duration = CASE_EXPIRY_POLL_INTERVAL;
+
+    /// Reverse every case that has expired by now. A case is marked reversed even
+    /// if reversing fails, e.g. because the user was already unbanned by hand, so
+    /// it isn't retried forever. Returns the number of cases handled.
+    async fn tick(&self, now: DateTime<Utc>) -> Result<usize, CrackedError> {
+        let now = now.naive_utc();
+        let expired = ModerationCase::get_expired(&self.pool, now).await?;
+        for case in expired.iter() {
+            match case.action().map(|a| a.reversal()) {
+                Ok(Some(action)) => {
+                    if let Err(e) = self.reverser.reverse(case, action).await {
+                        tracing::warn!("Failed to reverse case {}: {}", case.id, e);
+                    }
+                },
+                Ok(None) => {},
+                Err(e) => tracing::warn!("Case {} has a bad action: {}", case.id, e),
+            }
+            // Keep going so one bad row doesn't hold up the other expired cases.
+            if let Err(e) = ModerationCase::mark_reversed(&self.pool, case.id, now).await {
+                tracing::error!("Failed to mark case {} reversed: {}", case.id, e);
+            }
+        }
+        Ok(expired.len())
+    }
+}
+
+/// Lifts expired tempbans and tempmutes on Discord and records the reversal as
+/// a new case by the bot.
+#[derive(Clone)]
+pub struct DiscordCaseReverser {
+    pub serenity_ctx: Arc<SerenityContext>,
+    pub data: Arc<Data>,
+}
+
+impl CaseReverser for DiscordCaseReverser {
+    async fn reverse(&self, case: &ModerationCase, action: CaseAction) -> Result<(), CrackedError> {
+        let http = &self.serenity_ctx.http;
+        let guild_id = GuildId::new(case.guild_id as u64);
+        let user_id = UserId::new(case.target_id as u64);
+        let reason = format!("Case #{} expired", case.case_number);
+        match action {
+            CaseAction::Unban => guild_id.unban(http, user_id, Some(&reason)).await?,
+            CaseAction::Unmute => {
+                guild_id
+                    .edit_member(http, user_id, EditMember::new().mute(false))
+                    .await?;
+            },
+            _ => return Err(CrackedError::Other("Can't reverse this moderation action")),
+        }
+
+        let bot_id = self.serenity_ctx.cache.current_user().id;
+        let reversal = ModerationCase::new(
+            case.guild_id,
+            action,
+            bot_id.get() as i64,
+            case.target_id,
+            Some(reason),
+            None,
+            chrono::Utc::now(),
+        );
+        record_case(http, &self.data, reversal).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        clock::{Clock, MockClock, Poller},
+        db::GuildEntity,
+    };
+    use ::serenity::small_fixed_array::FixedString;
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    /// Records reversals instead of touching Discord.
+    #[derive(Clone, Default)]
+    struct RecordingReverser {
+        reversed: Arc<Mutex<Vec<(i64, CaseAction)>>>,
+    }
+
+    impl CaseReverser for RecordingReverser {
+        async fn reverse(
+            &self,
+            case: &ModerationCase,
+            action: CaseAction,
+        ) -> Result<(), CrackedError> {
+            self.reversed.lock().unwrap().push((case.id, action));
+            Ok(())
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_case_expiry_reverses_timed_cases(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+        let start = utc("2024-01-05T20:00:00Z");
+        let clock = MockClock::new(start);
+        let reverser = RecordingReverser::default();
+        let scheduler = Poller::new(
+            clock.clone(),
+            CaseExpiry::new(pool.clone(), reverser.clone()),
+        )
+        .with_poll_interval(Duration::from_secs(60));
+
+        let hour = Some(Duration::from_secs(60 * 60));
+        let tempban = ModerationCase::create(
+            &pool,
+            &ModerationCase::new(1, CaseAction::TempBan, 10, 20, None, hour, start),
+        )
+        .await?;
+        let tempmute = ModerationCase::create(
+            &pool,
+            &ModerationCase::new(
+                1,
+                CaseAction::TempMute,
+                10,
+                30,
+                None,
+                Some(Duration::from_secs(2 * 60 * 60)),
+                start,
+            ),
+        )
+        .await?;
+        ModerationCase::create(
+            &pool,
+            &ModerationCase::new(1, CaseAction::Ban, 10, 40, None, None, start),
+        )
+        .await?;
+
+        assert_eq!(scheduler.tick().await?, 0);
+
+        for _ in 0..60 {
+            clock.sleep(Duration::from_secs(60)).await;
+            scheduler.tick().await?;
+        }
+        assert_eq!(
+            *reverser.reversed.lock().unwrap(),
+            vec![(tempban.id, CaseAction::Unban)]
+        );
+
+        for _ in 0..60 {
+            clock.sleep(Duration::from_secs(60)).await;
+            scheduler.tick().await?;
+        }
+        assert_eq!(
+            *reverser.reversed.lock().unwrap(),
+            vec![
+                (tempban.id, CaseAction::Unban),
+                (tempmute.id, CaseAction::Unmute)
+            ]
+        );
+
+        // Nothing is reversed twice.
+        clock.sleep(Duration::from_secs(24 * 60 * 60)).await;
+        assert_eq!(scheduler.tick().await?, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+pub mod automod;
+pub mod expiry;
+pub mod raid;
+
+pub use automod::*;
+pub use expiry::*;
+pub use raid::*;
+
+use crate::{
+    db::ModerationCase,
+    duration::format_duration,
+    guild::operations::GuildSettingsOperations,
+    messaging::messages::{
+        CASE_ACTION, CASE_DURATION, CASE_EVIDENCE, CASE_EXPIRES, CASE_MODERATOR, CASE_NO_REASON,
+        CASE_REASON, CASE_REVERSED, CASE_TARGET, CASE_TITLE,
+    },
+    CrackedError, Data,
+};
+use serenity::all::{
+    ChannelId, Colour, CreateEmbed, CreateMessage, EditMessage, GuildId, Http, MessageId,
+};
+use std::fmt::Write;
+
+/// Save a moderation case and post it to the guild's mod log channel, if it has one.
+pub async fn record_case(
+    http: &Http,
+    data: &Data,
+    case: ModerationCase,
+) -> Result<ModerationCase, CrackedError> {
+    let pool = data
+        .database_pool
+        .as_ref()
+        .ok_or(CrackedError::NoDatabasePool)?;
+    let mut case = ModerationCase::create(pool, &case).await?;
+
+    let guild_id = GuildId::new(case.guild_id as u64);
+    let Some(channel_id) = data
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.get_mod_log_channel())
+    else {
+        return Ok(case);
+    };
+    match channel_id
+        .send_message(http, CreateMessage::new().embed(case_embed(&case)))
+        .await
+    {
+        Ok(msg) => {
+            let (channel_id, message_id) = (channel_id.get() as i64, msg.id.get() as i64);
+            ModerationCase::set_log_message(pool, case.id, channel_id, message_id).await?;
+            case.log_channel_id = Some(channel_id);
+            case.log_message_id = Some(message_id);
+        },
+        Err(e) => tracing::warn!("Failed to post case {} to the mod log: {}", case.id, e),
+    }
+    Ok(case)
+}
+
+/// Update a case's mod log post after the case was edited.
+pub async fn refresh_case_log(http: &Http, case: &ModerationCase) -> Result<(), CrackedError> {
+    let (Some(channel_id), Some(message_id)) = (case.log_channel_id, case.log_message_id) else {
+        return Ok(());
+    };
+    ChannelId::new(channel_id as u64)
+        .edit_message(
+            http,
+            MessageId::new(message_id as u64),
+            EditMessage::new().embed(case_embed(case)),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Build the embed describing a case, used both in the mod log and by `/case`.
+pub fn case_embed(case: &ModerationCase) -> CreateEmbed<'static> {
+    let mut embed = CreateEmbed::default()
+        .title(format!("{} #{}", CASE_TITLE, case.case_number))
+        .colour(case_colour(case))
+        .field(CASE_ACTION, case.action.clone(), true)
+        .field(CASE_TARGET, format!("<@{}>", case.target_id), true)
+        .field(CASE_MODERATOR, format!("<@{}>", case.actor_id), true)
+        .field(
+            CASE_REASON,
+            case.reason
+                .clone()
+                .unwrap_or_else(|| CASE_NO_REASON.to_string()),
+            false,
+        );
+    if let Some(duration) = case.duration() {
+        embed = embed.field(CASE_DURATION, format_duration(duration), true);
+    }
+    if let Some(expires_at) = case.expires_at_utc() {
+        let ts = expires_at.timestamp();
+        embed = embed.field(CASE_EXPIRES, format!("<t:{}:F> (<t:{}:R>)", ts, ts), true);
+    }
+    if let Some(reversed_at) = case.reversed_at {
+        embed = embed.field(
+            CASE_REVERSED,
+            format!("<t:{}:F>", reversed_at.and_utc().timestamp()),
+            true,
+        );
+    }
+    if !case.evidence.is_empty() {
+        embed = embed.field(CASE_EVIDENCE, case.evidence.join("\n"), false);
+    }
+    embed
+}
+
+/// One line per case, for `/cases`.
+pub fn case_list_line(case: &ModerationCase) -> String {
+    let mut line = format!(
+        "**#{}** `{}` by <@{}> <t:{}:R>",
+        case.case_number,
+        case.action,
+        case.actor_id,
+        case.created_at.and_utc().timestamp()
+    );
+    if let Some(duration) = case.duration() {
+        let _ = write!(line, " ({})", format_duration(duration));
+    }
+    let _ = write!(
+        line,
+        ": {}",
+        case.reason.as_deref().unwrap_or(CASE_NO_REASON)
+    );
+    line
+}
+
+/// Split evidence given as links separated by spaces or commas. Anything that
+/// isn't an http(s) link is rejected.
+pub fn parse_evidence(input: Option<&str>) -> Result<Vec<String>, CrackedError> {
+    let Some(input) = input else {
+        return Ok(Vec::new());
+    };
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|link| match url::Url::parse(link) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(link.to_string()),
+            _ => Err(CrackedError::Other("Evidence must be http(s) links.")),
+        })
+        .collect()
+}
+
+fn case_colour(case: &ModerationCase) -> Colour {
+    match case.action.as_str() {
+        "ban" | "tempban" | "kick" => Colour::RED,
+        "unban" | "unmute" | "undeafen" => Colour::DARK_GREEN,
+        _ => Colour::GOLD,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_evidence() {
+        assert!(parse_evidence(None).unwrap().is_empty());
+        assert_eq!(
+            parse_evidence(Some("https://a.example/1, http://b.example/2  ")).unwrap(),
+            vec!["https://a.example/1", "http://b.example/2"]
+        );
+        assert!(parse_evidence(Some("https://a.example/1 not-a-link")).is_err());
+        assert!(parse_evidence(Some("ftp://a.example/1")).is_err());
+    }
+
+    #[test]
+    fn test_case_list_line() {
+        let case = ModerationCase {
+            case_number: 3,
+            action: "tempban".to_string(),
+            actor_id: 1,
+            duration_seconds: Some(3_600),
+            reason: Some("spam".to_string()),
+            ..Default::default()
+        };
+        let line = case_list_line(&case);
+        assert!(line.starts_with("**#3** `tempban` by <@1>"));
+        assert!(line.ends_with("(1h): spam"));
+    }
+}
@@ -0,0 +1,522 @@
+//! Raid detection and lockdown. Every join goes into a per-guild window, and a
+//! burst of joins, new accounts or look-alike members locks the server down:
+//! the verification level goes up, @everyone can't send messages and invites
+//! are paused, until a moderator runs `/lockdown end`.
+use crate::{
+    db::Lockdown,
+    guild::{operations::GuildSettingsOperations, settings::RaidSettings},
+    messaging::messages::{
+        LOCKDOWN_ALERT, LOCKDOWN_ENDED, RAID_DETECTED, RAID_JOIN_BURST, RAID_NEW_ACCOUNTS,
+        RAID_SAME_AVATAR, RAID_SIMILAR_NAMES,
+    },
+    CrackedError, Data,
+};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serenity::all::{
+    ChannelId, Context as SerenityContext, CreateMessage, EditGuild, EditRole, GuildId, Member,
+    Mentionable, Permissions, RoleId, UserId, VerificationLevel,
+};
+use std::{collections::VecDeque, fmt::Display, time::Duration};
+
+/// The guild feature that pauses invites.
+const INVITES_DISABLED: &str = "INVITES_DISABLED";
+/// Most joins remembered per guild.
+const MAX_HISTORY: usize = 200;
+
+/// Why a wave of joins counts as a raid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RaidTrigger {
+    JoinBurst(usize),
+    NewAccounts(usize),
+    SimilarNames { name: String, count: usize },
+    SameAvatar(usize),
+}
+
+impl Display for RaidTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::JoinBurst(n) => write!(f, "{} {}", n, RAID_JOIN_BURST),
+            Self::NewAccounts(n) => write!(f, "{} {}", n, RAID_NEW_ACCOUNTS),
+            Self::SimilarNames { name, count } => {
+                write!(f, "{} {} (`{}`)", count, RAID_SIMILAR_NAMES, name)
+            },
+            Self::SameAvatar(n) => write!(f, "{} {}", n, RAID_SAME_AVATAR),
+        }
+    }
+}
+
+/// The parts of a join raid detection looks at.
+#[derive(Debug, Clone)]
+pub struct RaidJoin {
+    pub at: DateTime<Utc>,
+    pub account_age: Duration,
+    pub name: String,
+    pub avatar: Option<String>,
+}
+
+impl RaidJoin {
+    /// A member's join, as of `now`.
+    pub fn new(member: &Member, now: DateTime<Utc>) -> Self {
+        let created_at = member.user.id.created_at().unix_timestamp();
+        Self {
+            at: now,
+            account_age: Duration::from_secs((now.timestamp() - created_at).max(0) as u64),
+            name: member.user.name.to_string(),
+            avatar: member.user.avatar.map(|hash| hash.to_string()),
+        }
+    }
+}
+
+/// Remembers each guild's recent joins.
+#[derive(Debug, Clone, Default)]
+pub struct RaidDetector {
+    joins: DashMap<GuildId, VecDeque<RaidJoin>>,
+}
+
+impl RaidDetector {
+    /// Record a join and check the guild's recent joins for a raid.
+    pub fn record(
+        &self,
+        settings: &RaidSettings,
+        guild_id: GuildId,
+        join: RaidJoin,
+    ) -> Option<RaidTrigger> {
+        let window = chrono::Duration::seconds(settings.window_seconds as i64);
+        let mut joins = self.joins.entry(guild_id).or_default();
+        while joins.front().is_some_and(|j| join.at - j.at >= window) {
+            joins.pop_front();
+        }
+        joins.push_back(join);
+        if joins.len() > MAX_HISTORY {
+            joins.pop_front();
+        }
+        check_joins(settings, &joins)
+    }
+
+    /// Forget a guild's joins, so one raid only triggers once.
+    pub fn reset(&self, guild_id: GuildId) {
+        self.joins.remove(&guild_id);
+    }
+}
+
+/// Check joins within the window, newest last, against the guild's thresholds.
+fn check_joins(settings: &RaidSettings, joins: &VecDeque<RaidJoin>) -> Option<RaidTrigger> {
+    let over = |threshold: u32, count: usize| threshold > 0 && count >= threshold as usize;
+    if over(settings.join_threshold, joins.len()) {
+        return Some(RaidTrigger::JoinBurst(joins.len()));
+    }
+
+    let new_account = Duration::from_secs(settings.new_account_days as u64 * 24 * 60 * 60);
+    let new_accounts = joins.iter().filter(|j| j.account_age < new_account).count();
+    if over(settings.new_account_threshold, new_accounts) {
+        return Some(RaidTrigger::NewAccounts(new_accounts));
+    }
+
+    let last = joins.back()?;
+    let name = name_key(&last.name);
+    if !name.is_empty() {
+        let count = joins.iter().filter(|j| name_key(&j.name) == name).count();
+        if over(settings.similar_threshold, count) {
+            return Some(RaidTrigger::SimilarNames { name, count });
+        }
+    }
+    if let Some(avatar) = &last.avatar {
+        let count = joins
+            .iter()
+            .filter(|j| j.avatar.as_ref() == Some(avatar))
+            .count();
+        if over(settings.similar_threshold, count) {
+            return Some(RaidTrigger::SameAvatar(count));
+        }
+    }
+    None
+}
+
+/// A name with case, punctuation, look-alike digits and trailing numbers
+/// stripped, so `Raider_01` and `r4ider99` match.
+pub fn name_key(name: &str) -> String {
+    name.to_lowercase()
+        .trim_end_matches(|c: char| c.is_ascii_digit() || !c.is_alphanumeric())
+        .chars()
+        .filter_map(|c| match c {
+            '0' => Some('o'),
+            '1' | '!' => Some('i'),
+            '3' => Some('e'),
+            '4' | '@' => Some('a'),
+            '5' | '$' => Some('s'),
+            '7' => Some('t'),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Check a new member against the guild's raid thresholds and lock the server
+/// down if they're crossed. Returns what triggered the lockdown.
+#[cfg(not(tarpaulin_include))]
+pub async fn check_join(
+    ctx: &SerenityContext,
+    data: &Data,
+    member: &Member,
+) -> Result<Option<RaidTrigger>, CrackedError> {
+    if member.user.bot() {
+        return Ok(None);
+    }
+    let guild_id = member.guild_id;
+    let Some(raid) = data
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.raid_settings)
+        .filter(|raid| raid.enabled)
+    else {
+        return Ok(None);
+    };
+    let Some(trigger) = data
+        .raids
+        .record(&raid, guild_id, RaidJoin::new(member, Utc::now()))
+    else {
+        return Ok(None);
+    };
+    data.raids.reset(guild_id);
+
+    let reason = format!("{} {} in {}s", RAID_DETECTED, trigger, raid.window_seconds);
+    start_lockdown(ctx, data, guild_id, reason, None).await?;
+    Ok(Some(trigger))
+}
+
+/// Lock a guild down: raise the verification level, stop @everyone sending
+/// messages and pause invites, and alert moderators. What it changes is saved
+/// first, so `end_lockdown` can put it back, and forgotten again if Discord
+/// refuses the changes. Returns `None` if the guild is already locked down.
+#[cfg(not(tarpaulin_include))]
+pub async fn start_lockdown(
+    ctx: &SerenityContext,
+    data: &Data,
+    guild_id: GuildId,
+    reason: String,
+    actor: Option<UserId>,
+) -> Result<Option<Lockdown>, CrackedError> {
+    let pool = data
+        .database_pool
+        .as_ref()
+        .ok_or(CrackedError::NoDatabasePool)?;
+    let (level, everyone, features) = {
+        let guild = guild_id
+            .to_guild_cached(&ctx.cache)
+            .ok_or(CrackedError::NoGuildCached)?;
+        let everyone = guild
+            .roles
+            .get(&everyone_role(guild_id))
+            .map(|role| role.permissions)
+            .unwrap_or_default();
+        let features = guild
+            .features
+            .iter()
+            .map(|feature| feature.to_string())
+            .collect::<Vec<_>>();
+        (guild.verification_level, everyone, features)
+    };
+    let invites_disabled = features.iter().any(|f| f == INVITES_DISABLED);
+    let Some(lockdown) = Lockdown::start(
+        pool,
+        guild_id.get() as i64,
+        reason.clone(),
+        actor.map(|id| id.get() as i64),
+        u8::from(level) as i16,
+        everyone.bits() as i64,
+        invites_disabled,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    // The row claims the lockdown, so two can't start at once. If Discord
+    // refuses the changes, drop it again rather than record a lockdown that
+    // never happened.
+    if let Err(err) = lock_guild(
+        ctx,
+        guild_id,
+        level,
+        everyone,
+        features,
+        invites_disabled,
+        &reason,
+    )
+    .await
+    {
+        if let Err(err) = Lockdown::delete(pool, lockdown.id).await {
+            tracing::error!("Failed to drop lockdown {}: {}", lockdown.id, err);
+        }
+        return Err(err);
+    }
+
+    send_alert(
+        ctx,
+        data,
+        guild_id,
+        format!("{} {}", LOCKDOWN_ALERT, reason),
+    )
+    .await;
+    Ok(Some(lockdown))
+}
+
+/// Make the lockdown's changes to the guild. If the @everyone role can't be
+/// changed, the guild settings are put back.
+#[cfg(not(tarpaulin_include))]
+async fn lock_guild(
+    ctx: &SerenityContext,
+    guild_id: GuildId,
+    level: VerificationLevel,
+    everyone: Permissions,
+    features: Vec<String>,
+    invites_disabled: bool,
+    reason: &str,
+) -> Result<(), CrackedError> {
+    let http = &ctx.http;
+    let mut edit = EditGuild::new().audit_log_reason(reason);
+    if u8::from(level) < u8::from(VerificationLevel::High) {
+        edit = edit.verification_level(VerificationLevel::High);
+    }
+    if !invites_disabled {
+        let mut features = features.clone();
+        features.push(INVITES_DISABLED.to_string());
+        edit = edit.features(features);
+    }
+    guild_id.edit(http, edit).await?;
+    let denied = Permissions::SEND_MESSAGES | Permissions::SEND_MESSAGES_IN_THREADS;
+    let res = guild_id
+        .edit_role(
+            http,
+            everyone_role(guild_id),
+            EditRole::new()
+                .permissions(everyone - denied)
+                .audit_log_reason(reason),
+        )
+        .await;
+    if let Err(err) = res {
+        let undo = EditGuild::new()
+            .verification_level(level)
+            .features(features)
+            .audit_log_reason(reason);
+        if let Err(err) = guild_id.edit(http, undo).await {
+            tracing::error!("Failed to undo lockdown of {}: {}", guild_id, err);
+        }
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// End a guild's lockdown, putting back what it changed. Returns `None` if the
+/// guild isn't locked down.
+#[cfg(not(tarpaulin_include))]
+pub async fn end_lockdown(
+    ctx: &SerenityContext,
+    data: &Data,
+    guild_id: GuildId,
+    actor: UserId,
+) -> Result<Option<Lockdown>, CrackedError> {
+    let pool = data
+        .database_pool
+        .as_ref()
+        .ok_or(CrackedError::NoDatabasePool)?;
+    let Some(lockdown) = Lockdown::get_active(pool, guild_id.get() as i64).await? else {
+        return Ok(None);
+    };
+
+    let http = &ctx.http;
+    let reason = format!("{} ({})", LOCKDOWN_ENDED, actor);
+    let mut edit = EditGuild::new()
+        .verification_level(VerificationLevel::from(
+            lockdown.previous_verification_level as u8,
+        ))
+        .audit_log_reason(&reason);
+    if !lockdown.invites_were_disabled {
+        let features = guild_id
+            .to_guild_cached(&ctx.cache)
+            .map(|guild| {
+                guild
+                    .features
+                    .iter()
+                    .map(|feature| feature.to_string())
+                    .filter(|feature| feature != INVITES_DISABLED)
+                    .collect::<Vec<_>>()
+            })
+            .ok_or(CrackedError::NoGuildCached)?;
+        edit = edit.features(features);
+    }
+    guild_id.edit(http, edit).await?;
+    let everyone = Permissions::from_bits_truncate(lockdown.previous_everyone_permissions as u64);
+    guild_id
+        .edit_role(
+            http,
+            everyone_role(guild_id),
+            EditRole::new()
+                .permissions(everyone)
+                .audit_log_reason(&reason),
+        )
+        .await?;
+
+    let lockdown = Lockdown::end(pool, lockdown.id, Utc::now().naive_utc()).await?;
+    data.raids.reset(guild_id);
+    send_alert(
+        ctx,
+        data,
+        guild_id,
+        format!("{} {}", LOCKDOWN_ENDED, actor.mention()),
+    )
+    .await;
+    Ok(Some(lockdown))
+}
+
+/// The @everyone role shares the guild's id.
+fn everyone_role(guild_id: GuildId) -> RoleId {
+    RoleId::new(guild_id.get())
+}
+
+/// Tell moderators, in the alert channel or else the mod log, pinging the alert
+/// role.
+#[cfg(not(tarpaulin_include))]
+async fn send_alert(ctx: &SerenityContext, data: &Data, guild_id: GuildId, content: String) {
+    let Some(settings) = data.get_guild_settings(guild_id).await else {
+        return;
+    };
+    let raid = settings.raid_settings.clone().unwrap_or_default();
+    let Some(channel_id) = raid
+        .alert_channel
+        .map(ChannelId::new)
+        .or_else(|| settings.get_mod_log_channel())
+    else {
+        return;
+    };
+    let content = match raid.alert_role {
+        Some(role_id) => format!("{} {}", RoleId::new(role_id).mention(), content),
+        None => content,
+    };
+    if let Err(e) = channel_id
+        .send_message(&ctx.http, CreateMessage::new().content(content))
+        .await
+    {
+        tracing::warn!("Failed to send lockdown alert in {}: {}", guild_id, e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn settings() -> RaidSettings {
+        RaidSettings {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    fn join(name: &str, secs: i64) -> RaidJoin {
+        RaidJoin {
+            at: DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap(),
+            account_age: Duration::from_secs(365 * 24 * 60 * 60),
+            name: name.to_string(),
+            avatar: None,
+        }
+    }
+
+    #[test]
+    fn test_join_burst() {
+        let detector = RaidDetector::default();
+        let guild = GuildId::new(1);
+        for i in 0..9 {
+            let name = format!("member{}", (b'a' + i as u8) as char);
+            assert_eq!(detector.record(&settings(), guild, join(&name, i)), None);
+        }
+        assert_eq!(
+            detector.record(&settings(), guild, join("last", 9)),
+            Some(RaidTrigger::JoinBurst(10))
+        );
+
+        // Joins spread out are fine.
+        detector.reset(guild);
+        for i in 0..20 {
+            let name = format!("member{}", (b'a' + i as u8) as char);
+            assert_eq!(
+                detector.record(&settings(), guild, join(&name, i * 2)),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_new_accounts() {
+        let detector = RaidDetector::default();
+        let guild = GuildId::new(1);
+        let new = |name: &str, secs| RaidJoin {
+            account_age: Duration::from_secs(60 * 60),
+            ..join(name, secs)
+        };
+        for (i, name) in ["alice", "bob", "carol", "dave"].iter().enumerate() {
+            assert_eq!(
+                detector.record(&settings(), guild, new(name, i as i64)),
+                None
+            );
+        }
+        assert_eq!(
+            detector.record(&settings(), guild, new("erin", 4)),
+            Some(RaidTrigger::NewAccounts(5))
+        );
+
+        // Off when the threshold is 0.
+        let off = RaidSettings {
+            new_account_threshold: 0,
+            ..settings()
+        };
+        detector.reset(guild);
+        for (i, name) in ["alice", "bob", "carol", "dave", "erin"].iter().enumerate() {
+            assert_eq!(detector.record(&off, guild, new(name, i as i64)), None);
+        }
+    }
+
+    #[test]
+    fn test_similar_members() {
+        let detector = RaidDetector::default();
+        let guild = GuildId::new(1);
+        for (i, name) in ["Raider_01", "raider02", "R4IDER"].iter().enumerate() {
+            assert_eq!(
+                detector.record(&settings(), guild, join(name, i as i64)),
+                None
+            );
+        }
+        assert_eq!(
+            detector.record(&settings(), guild, join("raider.99", 3)),
+            Some(RaidTrigger::SimilarNames {
+                name: "raider".to_string(),
+                count: 4
+            })
+        );
+
+        detector.reset(guild);
+        let avatar = |name: &str, secs| RaidJoin {
+            avatar: Some("abc".to_string()),
+            ..join(name, secs)
+        };
+        for (i, name) in ["alice", "bob", "carol"].iter().enumerate() {
+            assert_eq!(
+                detector.record(&settings(), guild, avatar(name, i as i64)),
+                None
+            );
+        }
+        assert_eq!(
+            detector.record(&settings(), guild, avatar("dave", 3)),
+            Some(RaidTrigger::SameAvatar(4))
+        );
+    }
+
+    #[test]
+    fn test_name_key() {
+        assert_eq!(name_key("Raider_01"), "raider");
+        assert_eq!(name_key("r4ider99"), "raider");
+        assert_eq!(name_key("$potify"), "spotify");
+        assert_eq!(name_key("1234"), "");
+        assert_eq!(name_key("__"), "");
+    }
+}
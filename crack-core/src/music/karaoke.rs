@@ -0,0 +1,291 @@
+//! Karaoke: a message showing the current track's synced lyrics, edited as it
+//! plays to highlight the line being sung. Optionally the track is restarted
+//! through a mid/side filter that turns down what's in the centre of the mix,
+//! which is usually the vocals.
+use crate::{
+    errors::CrackedError,
+    http_utils,
+    messaging::messages::{KARAOKE_TRACK_CHANGED, KARAOKE_VOCALS_FAILED},
+    music::lrc::Lrc,
+    sources::rusty_ytdl::RustyYoutubeSearch,
+    utils::TrackData,
+    Data,
+};
+use crack_types::QueryType;
+use serenity::all::{CreateEmbed, EditMessage, Http, Message};
+use songbird::{
+    input::{
+        codecs::{get_codec_registry, get_probe},
+        Input, LiveInput,
+    },
+    tracks::{PlayMode, Track, TrackHandle},
+    Call,
+};
+use std::{sync::Arc, time::Duration};
+use symphonia::core::{
+    audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal},
+    codecs::{CodecDescriptor, CodecParameters, Decoder, DecoderOptions, FinalizeResult},
+    errors::{Error as SymphoniaError, Result as SymphoniaResult},
+    formats::Packet,
+};
+use tokio::sync::Mutex;
+
+/// How often the karaoke message checks for a new line.
+pub const KARAOKE_REFRESH: Duration = Duration::from_secs(2);
+/// Lines shown before and after the one being sung.
+const CONTEXT_LINES: usize = 2;
+/// How much of the centre is kept when turning vocals down, so the bass and
+/// kick, usually centred too, don't go with them.
+const MID_LEVEL: f32 = 0.25;
+
+/// The lines around `current`, with it in bold. Before the first line, the
+/// first few are shown as what's coming.
+pub fn render_lines(lyrics: &Lrc, current: Option<usize>) -> String {
+    let start = current.map_or(0, |i| i.saturating_sub(CONTEXT_LINES));
+    let end = current
+        .map_or(CONTEXT_LINES + 1, |i| i + CONTEXT_LINES + 1)
+        .min(lyrics.lines.len());
+    lyrics.lines[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            // Instrumental breaks are blank lines.
+            let text = if line.text.is_empty() {
+                "♪"
+            } else {
+                line.text.as_str()
+            };
+            if Some(start + i) == current {
+                format!("**▶ {}**", text)
+            } else {
+                text.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn karaoke_embed(title: &str, lyrics: &Lrc, current: Option<usize>) -> CreateEmbed<'static> {
+    CreateEmbed::default()
+        .title(format!("🎤 {}", title))
+        .description(render_lines(lyrics, current))
+}
+
+/// Keep the karaoke message on the line being sung until `track` ends or is
+/// skipped. Only edits when the line changes.
+#[cfg(not(tarpaulin_include))]
+pub async fn follow_track(
+    http: Arc<Http>,
+    mut message: Message,
+    title: String,
+    lyrics: Lrc,
+    track: TrackHandle,
+) {
+    let mut shown = None;
+    let mut interval = tokio::time::interval(KARAOKE_REFRESH);
+    loop {
+        interval.tick().await;
+        let Ok(info) = track.get_info().await else {
+            break;
+        };
+        if matches!(
+            info.playing,
+            PlayMode::Stop | PlayMode::End | PlayMode::Errored(_)
+        ) {
+            break;
+        }
+        let line = lyrics.line_at(info.position);
+        if shown == Some(line) {
+            continue;
+        }
+        shown = Some(line);
+        let edit = EditMessage::new().embed(karaoke_embed(&title, &lyrics, line));
+        if let Err(e) = message.edit(&http, edit).await {
+            tracing::warn!("Error updating karaoke message: {}", e);
+            break;
+        }
+    }
+}
+
+/// Turns the centre of a stereo mix down, keeping the sides.
+pub fn reduce_vocals(left: &mut [f32], right: &mut [f32]) {
+    for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+        let mid = (*l + *r) / 2.0;
+        let side = (*l - *r) / 2.0;
+        *l = mid * MID_LEVEL + side;
+        *r = mid * MID_LEVEL - side;
+    }
+}
+
+/// Wraps a track's decoder, running [`reduce_vocals`] over what it decodes.
+/// Mono tracks pass through, since there's no side to keep.
+pub struct VocalReducer {
+    inner: Box<dyn Decoder>,
+    buf: AudioBuffer<f32>,
+}
+
+impl VocalReducer {
+    pub fn new(inner: Box<dyn Decoder>) -> Self {
+        Self {
+            inner,
+            buf: AudioBuffer::unused(),
+        }
+    }
+}
+
+impl Decoder for VocalReducer {
+    fn try_new(_params: &CodecParameters, _options: &DecoderOptions) -> SymphoniaResult<Self> {
+        Err(SymphoniaError::Unsupported(
+            "the vocal reducer wraps another decoder",
+        ))
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[]
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        self.inner.codec_params()
+    }
+
+    fn decode(&mut self, packet: &Packet) -> SymphoniaResult<AudioBufferRef<'_>> {
+        let decoded = self.inner.decode(packet)?;
+        let spec = *decoded.spec();
+        if *self.buf.spec() != spec || self.buf.capacity() < decoded.capacity() {
+            self.buf = AudioBuffer::new(decoded.capacity() as u64, spec);
+        }
+        decoded.convert(&mut self.buf);
+        if spec.channels.count() == 2 {
+            let (left, right) = self.buf.chan_pair_mut(0, 1);
+            reduce_vocals(left, right);
+        }
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        self.inner.finalize()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+/// `input`, parsed, with its vocals turned down.
+#[cfg(not(tarpaulin_include))]
+pub async fn without_vocals(input: Input) -> Result<Input, CrackedError> {
+    let input = input
+        .make_playable_async(get_codec_registry(), get_probe())
+        .await
+        .map_err(|_| CrackedError::Other(KARAOKE_VOCALS_FAILED))?;
+    let Input::Live(LiveInput::Parsed(mut parsed), rec) = input else {
+        return Err(CrackedError::Other(KARAOKE_VOCALS_FAILED));
+    };
+    parsed.decoder = Box::new(VocalReducer::new(parsed.decoder));
+    Ok(Input::Live(LiveInput::Parsed(parsed), rec))
+}
+
+/// Restart the current track where it is with its vocals turned down,
+/// returning the new handle. It keeps the track's data, so it's still the same
+/// track to the queue and now-playing.
+#[cfg(not(tarpaulin_include))]
+pub async fn restart_without_vocals(
+    data: &Data,
+    call: &Arc<Mutex<Call>>,
+) -> Result<TrackHandle, CrackedError> {
+    let current = call
+        .lock()
+        .await
+        .queue()
+        .current()
+        .ok_or(CrackedError::NothingPlaying)?;
+    let track_data = current.data::<TrackData>();
+    let url = track_data
+        .aux_metadata
+        .read()
+        .await
+        .as_ref()
+        .and_then(|metadata| metadata.source_url.clone())
+        .ok_or(CrackedError::NoMetadata)?;
+    let resolved = data
+        .ct_client
+        .resolve_track(QueryType::VideoLink(url.clone()))
+        .await?;
+    let ytdl = RustyYoutubeSearch::new_with_stuff(
+        http_utils::get_client_old().clone(),
+        QueryType::VideoLink(url),
+        resolved.metadata,
+        resolved.video,
+    )?;
+    let input = without_vocals(ytdl.into()).await?;
+
+    let position = current
+        .get_info()
+        .await
+        .map(|info| info.position)
+        .unwrap_or_default();
+    let mut handler = call.lock().await;
+    // Fetching the track again takes a while, and it may have been skipped.
+    if handler.queue().current().map(|track| track.uuid()) != Some(current.uuid()) {
+        return Err(CrackedError::Other(KARAOKE_TRACK_CHANGED));
+    }
+    let handle = handler
+        .enqueue(Track::new_with_data(input, track_data))
+        .await;
+    // Straight after the current track, which is then skipped.
+    handler.queue().modify_queue(|queue| {
+        if let Some(back) = queue.pop_back() {
+            queue.insert(1, back);
+        }
+    });
+    handler.queue().skip()?;
+    drop(handler);
+    if !position.is_zero() {
+        let _ = handle.seek(position);
+    }
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lyrics() -> Lrc {
+        Lrc::parse("[00:01]one\n[00:02]two\n[00:03]\n[00:04]four\n[00:05]five\n[00:06]six").unwrap()
+    }
+
+    #[test]
+    fn test_render_lines() {
+        let lyrics = lyrics();
+        assert_eq!(render_lines(&lyrics, None), "one\ntwo\n♪");
+        assert_eq!(render_lines(&lyrics, Some(0)), "**▶ one**\ntwo\n♪");
+        assert_eq!(
+            render_lines(&lyrics, Some(3)),
+            "two\n♪\n**▶ four**\nfive\nsix"
+        );
+        assert_eq!(render_lines(&lyrics, Some(5)), "four\nfive\n**▶ six**");
+    }
+
+    #[test]
+    fn test_reduce_vocals() {
+        // Centred, like a lead vocal: turned down.
+        let (mut left, mut right) = ([1.0, -0.5], [1.0, -0.5]);
+        reduce_vocals(&mut left, &mut right);
+        assert_eq!(left, [MID_LEVEL, -0.5 * MID_LEVEL]);
+        assert_eq!(right, left);
+        // Only in the sides: kept as it is.
+        let (mut left, mut right) = ([0.5, 0.0], [-0.5, 0.0]);
+        reduce_vocals(&mut left, &mut right);
+        assert_eq!(left, [0.5, 0.0]);
+        assert_eq!(right, [-0.5, 0.0]);
+        // Panned hard left: the centre part is turned down.
+        let (mut left, mut right) = ([1.0], [0.0]);
+        reduce_vocals(&mut left, &mut right);
+        assert_eq!(left, [0.5 * MID_LEVEL + 0.5]);
+        assert_eq!(right, [0.5 * MID_LEVEL - 0.5]);
+    }
+}
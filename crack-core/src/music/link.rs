@@ -0,0 +1,350 @@
+//! Linked listen-along sessions. A follower guild mirrors its leader's queue:
+//! whenever the leader's track changes, pauses or is seeked (and every
+//! [`SYNC_INTERVAL`] to catch drift), each follower is brought in line with it.
+use crate::{
+    db::SessionLink, errors::CrackedError, http_utils, music::queue::queue_resolved_track_back,
+    utils::TrackData, Data,
+};
+use crack_types::QueryType;
+use serenity::all::GuildId;
+use songbird::{input::AuxMetadata, tracks::PlayMode, Call, Event, EventContext, EventHandler};
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// How far a follower can drift from its leader before it's seeked back.
+pub const SYNC_DRIFT: Duration = Duration::from_secs(2);
+/// How often followers are checked for drift.
+pub const SYNC_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What a session is playing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerState {
+    /// The current track's URL, `None` if nothing's playing.
+    pub track: Option<String>,
+    pub position: Duration,
+    pub playing: bool,
+}
+
+/// One step bringing a follower in line with its leader.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncAction {
+    /// Replace the follower's queue with this track, from this position.
+    Load {
+        url: String,
+        position: Duration,
+    },
+    Seek(Duration),
+    Pause,
+    Play,
+    Stop,
+}
+
+/// The steps to make `follower` play what `leader` is.
+pub fn plan_sync(leader: &PlayerState, follower: &PlayerState) -> Vec<SyncAction> {
+    let Some(url) = &leader.track else {
+        return match follower.track {
+            Some(_) => vec![SyncAction::Stop],
+            None => vec![],
+        };
+    };
+    let mut actions = Vec::new();
+    let mut playing = follower.playing;
+    if follower.track.as_ref() != Some(url) {
+        actions.push(SyncAction::Load {
+            url: url.clone(),
+            position: leader.position,
+        });
+        // A new track starts playing.
+        playing = true;
+    } else if leader.position.abs_diff(follower.position) > SYNC_DRIFT {
+        actions.push(SyncAction::Seek(leader.position));
+    }
+    match (leader.playing, playing) {
+        (true, false) => actions.push(SyncAction::Play),
+        (false, true) => actions.push(SyncAction::Pause),
+        _ => {},
+    }
+    actions
+}
+
+/// A music session that can be read from and mirrored onto.
+pub trait SessionPlayer: Send + Sync {
+    fn state(&self) -> impl Future<Output = PlayerState> + Send;
+    fn apply(&self, action: SyncAction) -> impl Future<Output = Result<(), CrackedError>> + Send;
+}
+
+/// Make each follower play what the leader is. A follower that fails is
+/// logged and skipped, so it doesn't hold up the rest.
+pub async fn mirror<L: SessionPlayer, F: SessionPlayer>(leader: &L, followers: &[F]) {
+    let leader = leader.state().await;
+    for follower in followers {
+        let state = follower.state().await;
+        for action in plan_sync(&leader, &state) {
+            if let Err(e) = follower.apply(action.clone()).await {
+                tracing::warn!("Error syncing linked session ({:?}): {}", action, e);
+                break;
+            }
+        }
+    }
+}
+
+/// A session in a songbird call.
+pub struct CallPlayer {
+    pub data: Arc<Data>,
+    pub call: Arc<Mutex<Call>>,
+}
+
+impl SessionPlayer for CallPlayer {
+    async fn state(&self) -> PlayerState {
+        let current = self.call.lock().await.queue().current();
+        let Some(track) = current else {
+            return PlayerState::default();
+        };
+        let url = track
+            .data::<TrackData>()
+            .aux_metadata
+            .read()
+            .await
+            .as_ref()
+            .and_then(|metadata| metadata.source_url.clone());
+        let (position, playing) = match track.get_info().await {
+            Ok(info) => (info.position, matches!(info.playing, PlayMode::Play)),
+            Err(_) => (Duration::ZERO, false),
+        };
+        PlayerState {
+            track: url,
+            position,
+            playing,
+        }
+    }
+
+    async fn apply(&self, action: SyncAction) -> Result<(), CrackedError> {
+        match action {
+            SyncAction::Load { url, position } => {
+                let resolved = self
+                    .data
+                    .ct_client
+                    .resolve_track(QueryType::VideoLink(url.clone()))
+                    .await?;
+                self.call.lock().await.queue().stop();
+                let queue = queue_resolved_track_back(
+                    &self.call,
+                    resolved,
+                    http_utils::get_client_old().clone(),
+                )
+                .await?;
+                let track = queue.last().ok_or(CrackedError::NothingPlaying)?;
+                // Keep the leader's URL, so the next sync sees the same track.
+                {
+                    let data = track.data::<TrackData>();
+                    let mut metadata = data.aux_metadata.write().await;
+                    metadata.get_or_insert_with(AuxMetadata::default).source_url = Some(url);
+                }
+                if !position.is_zero() {
+                    let _ = track.seek(position);
+                }
+            },
+            SyncAction::Seek(position) => {
+                let current = self.call.lock().await.queue().current();
+                if let Some(track) = current {
+                    let _ = track.seek(position);
+                }
+            },
+            SyncAction::Pause => self.call.lock().await.queue().pause()?,
+            SyncAction::Play => self.call.lock().await.queue().resume()?,
+            SyncAction::Stop => self.call.lock().await.queue().stop(),
+        }
+        Ok(())
+    }
+}
+
+/// The guild a guild is following, if it's in an active link.
+pub async fn leader_of(data: &Data, guild_id: GuildId) -> Option<GuildId> {
+    let pool = data.database_pool.as_ref()?;
+    match SessionLink::leader_of(pool, guild_id.get() as i64).await {
+        Ok(leader) => leader.map(|id| GuildId::new(id as u64)),
+        Err(e) => {
+            tracing::warn!("Error getting leader of {}: {}", guild_id, e);
+            None
+        },
+    }
+}
+
+/// Bring the sessions following `guild_id` in line with `call`, its session.
+/// Followers without a call are skipped until they join voice.
+pub async fn sync_followers(data: Arc<Data>, guild_id: GuildId, call: Arc<Mutex<Call>>) {
+    let Some(pool) = &data.database_pool else {
+        return;
+    };
+    let followers = match SessionLink::followers(pool, guild_id.get() as i64).await {
+        Ok(followers) => followers,
+        Err(e) => {
+            tracing::warn!("Error getting followers of {}: {}", guild_id, e);
+            return;
+        },
+    };
+    if followers.is_empty() {
+        return;
+    }
+    // One sync at a time per leader, so two events don't both load a track.
+    let lock = data.link_sync_locks.entry(guild_id).or_default().clone();
+    let _guard = lock.lock().await;
+    let followers = followers
+        .into_iter()
+        .map(|id| GuildId::new(id as u64))
+        .filter_map(|id| {
+            data.songbirds(id)
+                .into_iter()
+                .find_map(|manager| manager.get(id))
+        })
+        .map(|call| CallPlayer {
+            data: data.clone(),
+            call,
+        })
+        .collect::<Vec<_>>();
+    let leader = CallPlayer { data, call };
+    mirror(&leader, &followers).await;
+}
+
+/// Syncs a leader's followers when its track starts, pauses, and every
+/// [`SYNC_INTERVAL`].
+pub struct LinkSyncHandler {
+    pub guild_id: GuildId,
+    pub data: Arc<Data>,
+    pub call: Arc<Mutex<Call>>,
+}
+
+#[serenity::async_trait]
+impl EventHandler for LinkSyncHandler {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        sync_followers(self.data.clone(), self.guild_id, self.call.clone()).await;
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A call that does what it's told.
+    #[derive(Default)]
+    struct MockCall(std::sync::Mutex<PlayerState>);
+
+    fn playing(url: &str, secs: u64) -> PlayerState {
+        PlayerState {
+            track: Some(url.to_string()),
+            position: Duration::from_secs(secs),
+            playing: true,
+        }
+    }
+
+    impl From<PlayerState> for MockCall {
+        fn from(state: PlayerState) -> Self {
+            Self(std::sync::Mutex::new(state))
+        }
+    }
+
+    impl SessionPlayer for MockCall {
+        async fn state(&self) -> PlayerState {
+            self.0.lock().unwrap().clone()
+        }
+
+        async fn apply(&self, action: SyncAction) -> Result<(), CrackedError> {
+            let mut state = self.0.lock().unwrap();
+            match action {
+                SyncAction::Load { url, position } => {
+                    *state = PlayerState {
+                        track: Some(url),
+                        position,
+                        playing: true,
+                    };
+                },
+                SyncAction::Seek(position) => state.position = position,
+                SyncAction::Pause => state.playing = false,
+                SyncAction::Play => state.playing = true,
+                SyncAction::Stop => *state = PlayerState::default(),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plan_sync() {
+        let leader = playing("a", 30);
+        // In sync, or close enough.
+        let close = playing("a", 31);
+        assert_eq!(plan_sync(&leader, &close), vec![]);
+        let behind = playing("a", 10);
+        assert_eq!(
+            plan_sync(&leader, &behind),
+            vec![SyncAction::Seek(Duration::from_secs(30))]
+        );
+        let other = playing("b", 30);
+        assert_eq!(
+            plan_sync(&leader, &other),
+            vec![SyncAction::Load {
+                url: "a".to_string(),
+                position: Duration::from_secs(30)
+            }]
+        );
+        let paused = PlayerState {
+            playing: false,
+            ..leader.clone()
+        };
+        assert_eq!(plan_sync(&paused, &close), vec![SyncAction::Pause]);
+        assert_eq!(plan_sync(&leader, &paused), vec![SyncAction::Play]);
+        // A paused leader on a new track loads it paused.
+        assert_eq!(
+            plan_sync(&paused, &other),
+            vec![
+                SyncAction::Load {
+                    url: "a".to_string(),
+                    position: Duration::from_secs(30)
+                },
+                SyncAction::Pause
+            ]
+        );
+        assert_eq!(
+            plan_sync(&PlayerState::default(), &close),
+            vec![SyncAction::Stop]
+        );
+        assert_eq!(
+            plan_sync(&PlayerState::default(), &PlayerState::default()),
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mirror() {
+        let leader = MockCall::from(playing("a", 30));
+        let followers = [MockCall::from(playing("b", 5)), MockCall::default()];
+        mirror(&leader, &followers).await;
+        for follower in &followers {
+            assert_eq!(follower.state().await, leader.state().await);
+        }
+
+        // The leader pauses, seeks and moves on.
+        leader.apply(SyncAction::Pause).await.unwrap();
+        mirror(&leader, &followers).await;
+        assert!(!followers[0].state().await.playing);
+        leader
+            .apply(SyncAction::Load {
+                url: "c".to_string(),
+                position: Duration::ZERO,
+            })
+            .await
+            .unwrap();
+        leader
+            .apply(SyncAction::Seek(Duration::from_secs(90)))
+            .await
+            .unwrap();
+        mirror(&leader, &followers).await;
+        for follower in &followers {
+            assert_eq!(follower.state().await, leader.state().await);
+        }
+
+        leader.apply(SyncAction::Stop).await.unwrap();
+        mirror(&leader, &followers).await;
+        assert_eq!(followers[1].state().await, PlayerState::default());
+    }
+}
@@ -0,0 +1,185 @@
+//! Time-synced lyrics in the LRC format: one `[mm:ss.xx]` tag (or several)
+//! before each line, plus header tags like `[ar:Artist]` and `[offset:+500]`.
+use std::time::Duration;
+
+/// One line of lyrics and when it's sung.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LrcLine {
+    pub time: Duration,
+    pub text: String,
+}
+
+/// Parsed LRC lyrics, in the order they're sung.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lrc {
+    pub lines: Vec<LrcLine>,
+}
+
+impl Lrc {
+    /// Parse LRC lyrics. `None` if there isn't a single timed line, e.g. for
+    /// plain lyrics.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut lines = Vec::new();
+        // In milliseconds. Positive makes the lyrics come in sooner.
+        let mut offset = 0i64;
+        for raw in text.lines() {
+            let mut rest = raw.trim();
+            let mut times = Vec::new();
+            while rest.starts_with('[') {
+                let Some(end) = rest.find(']') else {
+                    break;
+                };
+                let tag = &rest[1..end];
+                rest = &rest[end + 1..];
+                if let Some(time) = parse_timestamp(tag) {
+                    times.push(time);
+                } else if let Some(value) = tag.strip_prefix("offset:") {
+                    offset = value.trim().parse().unwrap_or(0);
+                }
+                // Anything else is metadata, like [ar:] or [ti:].
+            }
+            let text = strip_word_times(rest);
+            lines.extend(times.into_iter().map(|time| LrcLine {
+                time,
+                text: text.clone(),
+            }));
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        for line in &mut lines {
+            let millis = (line.time.as_millis() as i64 - offset).max(0);
+            line.time = Duration::from_millis(millis as u64);
+        }
+        // Stable, so lines sharing a time keep their order.
+        lines.sort_by_key(|line| line.time);
+        Some(Self { lines })
+    }
+
+    /// The index of the line being sung at `position`, `None` before the first.
+    pub fn line_at(&self, position: Duration) -> Option<usize> {
+        self.lines
+            .partition_point(|line| line.time <= position)
+            .checked_sub(1)
+    }
+}
+
+/// A `mm:ss`, `mm:ss.xx` or `mm:ss:xx` timestamp.
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (minutes, seconds) = tag.trim().split_once(':')?;
+    let (seconds, fraction) = match seconds.split_once(['.', ':']) {
+        Some((seconds, fraction)) => (seconds, fraction),
+        None => (seconds, ""),
+    };
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if minutes.is_empty() || seconds.is_empty() || !all_digits(minutes) || !all_digits(fraction) {
+        return None;
+    }
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: u64 = seconds.parse().ok().filter(|s| *s < 60)?;
+    // Hundredths usually, but any precision is fine. Past milliseconds is cut.
+    let millis = format!("{:0<3}", fraction)[..3].parse::<u64>().ok()?;
+    Some(Duration::from_millis(
+        (minutes * 60 + seconds) * 1000 + millis,
+    ))
+}
+
+/// Drop the per-word `<mm:ss.xx>` tags of enhanced LRC.
+fn strip_word_times(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(len) if parse_timestamp(&rest[start + 1..start + len]).is_some() => {
+                out.push_str(&rest[..start]);
+                out.push(' ');
+                rest = &rest[start + len + 1..];
+            },
+            _ => {
+                out.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            },
+        }
+    }
+    out.push_str(rest);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn line(millis: u64, text: &str) -> LrcLine {
+        LrcLine {
+            time: Duration::from_millis(millis),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp("01:02.5"),
+            Some(Duration::from_millis(62_500))
+        );
+        assert_eq!(
+            parse_timestamp("00:12.34"),
+            Some(Duration::from_millis(12_340))
+        );
+        assert_eq!(
+            parse_timestamp("00:12:34"),
+            Some(Duration::from_millis(12_340))
+        );
+        assert_eq!(
+            parse_timestamp("10:00.1234"),
+            Some(Duration::from_millis(600_123))
+        );
+        assert_eq!(parse_timestamp("3:05"), Some(Duration::from_secs(185)));
+        assert_eq!(parse_timestamp("ar:Blur"), None);
+        assert_eq!(parse_timestamp("00:75.00"), None);
+        assert_eq!(parse_timestamp("00:-1.00"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = "[ar:Blur]\n\
+            [ti:Song 2]\n\
+            [00:01.00]Woo-hoo\n\
+            [00:10.50][00:30.00]When I feel heavy metal\n\
+            \n\
+            [00:20.00]<00:20.00> I'm <00:20.50> on <00:21.00> my <00:21.50> feet\n\
+            [00:25.00]\n\
+            not a lyric";
+        let lrc = Lrc::parse(text).unwrap();
+        assert_eq!(
+            lrc.lines,
+            vec![
+                line(1_000, "Woo-hoo"),
+                line(10_500, "When I feel heavy metal"),
+                line(20_000, "I'm on my feet"),
+                line(25_000, ""),
+                line(30_000, "When I feel heavy metal"),
+            ]
+        );
+        assert_eq!(Lrc::parse("Just plain lyrics\nwithout times"), None);
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        let lrc = Lrc::parse("[offset:+500]\n[00:00.20]one\n[00:02.00]two").unwrap();
+        assert_eq!(lrc.lines, vec![line(0, "one"), line(1_500, "two")]);
+        let lrc = Lrc::parse("[00:01.00]one\n[offset:-250]").unwrap();
+        assert_eq!(lrc.lines, vec![line(1_250, "one")]);
+    }
+
+    #[test]
+    fn test_line_at() {
+        let lrc = Lrc::parse("[00:05.00]one\n[00:10.00]two\n[00:15.00]three").unwrap();
+        assert_eq!(lrc.line_at(Duration::ZERO), None);
+        assert_eq!(lrc.line_at(Duration::from_secs(5)), Some(0));
+        assert_eq!(lrc.line_at(Duration::from_millis(9_999)), Some(0));
+        assert_eq!(lrc.line_at(Duration::from_secs(12)), Some(1));
+        assert_eq!(lrc.line_at(Duration::from_secs(600)), Some(2));
+        assert_eq!(Lrc::default().line_at(Duration::from_secs(5)), None);
+    }
+}
@@ -1,6 +1,12 @@
 pub mod context;
+pub mod karaoke;
+pub mod link;
+pub mod lrc;
 pub(crate) mod query;
 pub(crate) mod queue;
+pub mod quiz;
+pub mod schedule;
+pub mod sessions;
 
 pub use context::QueryContext;
 pub(crate) use query::*;
@@ -1,6 +1,8 @@
 use crate::{
+    db::TrackOffsets,
     errors::{verify, CrackedError},
     handlers::track_end::update_queue_messages,
+    handlers::track_offset::{TrackEndOffsetHandler, END_OFFSET_POLL},
     http_utils::CacheHttpExt,
     music::NewQueryType,
     sources::rusty_ytdl::RustyYoutubeSearch,
@@ -16,7 +18,7 @@ use serenity::{
 use songbird::{
     input::Input as SongbirdInput,
     tracks::{Queued, Track, TrackHandle},
-    Call,
+    Call, Event,
 };
 use std::str::FromStr;
 use std::{collections::VecDeque, sync::Arc};
@@ -47,6 +49,7 @@ pub async fn queue_resolved_track_back(
     let track_data = Arc::new(TrackData {
         user_id: Arc::new(RwLock::new(Some(resolved_clone.clone().user_id))),
         aux_metadata: Arc::new(RwLock::new(resolved_clone.metadata.clone())),
+        overlay: false,
     });
     let track = Track::new_with_data(ytdl.clone().into(), track_data);
     let _track_handle = handler.enqueue(track).await;
@@ -237,6 +240,22 @@ pub async fn queue_track_back(
     queue
 }
 
+/// Applies start/end offsets to a queued track. The start offset is a seek,
+/// the end offset is a periodic event that stops the track once it's reached.
+pub fn apply_track_offsets(track: &TrackHandle, offsets: TrackOffsets) -> Result<(), CrackedError> {
+    if let Some(start) = offsets.start() {
+        // Don't wait on the seek, the track might not be playing yet.
+        drop(track.seek(start));
+    }
+    if let Some(end) = offsets.end() {
+        track.add_event(
+            Event::Periodic(END_OFFSET_POLL, None),
+            TrackEndOffsetHandler { end },
+        )?;
+    }
+    Ok(())
+}
+
 /// Queue a list of tracks to be played.
 pub async fn queue_ready_track_list(
     call: Arc<Mutex<Call>>,
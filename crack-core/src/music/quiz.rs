@@ -0,0 +1,323 @@
+//! The music quiz: each round plays a snippet of a random track, and members
+//! guess its title or artist in chat. Guesses are matched loosely, so casing,
+//! punctuation, "(Official Video)" and small typos don't matter.
+use serenity::all::UserId;
+use songbird::input::AuxMetadata;
+use std::{collections::HashMap, fmt::Display, time::Duration};
+
+/// How long each snippet plays.
+pub const SNIPPET_LENGTH: Duration = Duration::from_secs(15);
+/// How long members have to guess, from when the snippet starts.
+pub const ROUND_LENGTH: Duration = Duration::from_secs(30);
+pub const DEFAULT_ROUNDS: u32 = 5;
+pub const MAX_ROUNDS: u32 = 20;
+pub const TITLE_POINTS: u32 = 2;
+pub const ARTIST_POINTS: u32 = 1;
+/// How alike a guess and answer have to be, from 0 to 1.
+const MATCH_THRESHOLD: f64 = 0.8;
+/// Words a guess may have besides the answer, so listing every title you can
+/// think of doesn't win.
+const MAX_EXTRA_WORDS: usize = 2;
+
+/// Where a quiz's tracks come from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum QuizSource {
+    /// What's been played in the server.
+    #[default]
+    #[name = "server history"]
+    History,
+    #[name = "playlist"]
+    Playlist,
+    /// A search for the genre.
+    #[name = "genre"]
+    Genre,
+}
+
+/// A track a round can be played from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuizTrack {
+    pub url: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+impl QuizTrack {
+    /// A quiz track from a track's metadata, if it has a URL and a title. A
+    /// video titled "Artist - Title" without an artist is split into both.
+    pub fn from_metadata(metadata: &AuxMetadata) -> Option<Self> {
+        let url = metadata.source_url.clone()?;
+        let title = metadata.track.clone().or(metadata.title.clone())?;
+        let (title, artist) = match (metadata.artist.clone(), title.split_once(" - ")) {
+            (Some(artist), _) => (title, Some(artist)),
+            (None, Some((artist, title))) => {
+                (title.trim().to_string(), Some(artist.trim().to_string()))
+            },
+            (None, None) => (title, None),
+        };
+        Some(Self {
+            url,
+            title,
+            artist: artist.filter(|a| !normalize(a).is_empty()),
+            duration: metadata.duration.filter(|d| !d.is_zero()),
+        })
+    }
+
+    /// Where to start the snippet: somewhere in the middle of the track, past
+    /// the intro and before the outro. `roll` is from 0 to 1.
+    pub fn snippet_start(&self, roll: f64) -> Duration {
+        match self.duration {
+            Some(duration) if duration > SNIPPET_LENGTH * 2 => {
+                let latest = (duration - SNIPPET_LENGTH).mul_f64(0.6);
+                let earliest = duration.mul_f64(0.2).min(latest);
+                earliest + (latest - earliest).mul_f64(roll.clamp(0.0, 1.0))
+            },
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// `s` lowercased, with only letters, digits and single spaces, and without
+/// bracketed extras like "(Official Video)" or a trailing "feat. Someone".
+pub fn normalize(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut depth = 0usize;
+    for c in s.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth > 0 => {},
+            '\'' | '’' => {},
+            '&' => out.push_str(" and "),
+            c if c.is_alphanumeric() => out.extend(c.to_lowercase()),
+            _ => out.push(' '),
+        }
+    }
+    out.split_whitespace()
+        .take_while(|word| !matches!(*word, "feat" | "ft" | "featuring"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `guess` names `answer`: near enough the same, or containing it as
+/// whole words with at most [`MAX_EXTRA_WORDS`] more, e.g. "song 2 by blur"
+/// for "Song 2".
+pub fn is_match(guess: &str, answer: &str) -> bool {
+    let (guess, answer) = (normalize(guess), normalize(answer));
+    if guess.is_empty() || answer.is_empty() {
+        return false;
+    }
+    let extra_words = guess
+        .split(' ')
+        .count()
+        .saturating_sub(answer.split(' ').count());
+    guess == answer
+        || strsim::normalized_levenshtein(&guess, &answer) >= MATCH_THRESHOLD
+        || (extra_words <= MAX_EXTRA_WORDS
+            && format!(" {} ", guess).contains(&format!(" {} ", answer)))
+}
+
+/// What a guess got right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Guess {
+    Title,
+    Artist,
+}
+
+impl Guess {
+    pub fn points(self) -> u32 {
+        match self {
+            Self::Title => TITLE_POINTS,
+            Self::Artist => ARTIST_POINTS,
+        }
+    }
+}
+
+/// One round: the title and artist are each won by the first to guess them.
+#[derive(Debug, Clone)]
+pub struct Round {
+    pub track: QuizTrack,
+    pub title_by: Option<UserId>,
+    pub artist_by: Option<UserId>,
+}
+
+impl Round {
+    pub fn new(track: QuizTrack) -> Self {
+        Self {
+            track,
+            title_by: None,
+            artist_by: None,
+        }
+    }
+
+    /// Check a guess, claiming what it got right that's still up for grabs.
+    pub fn guess(&mut self, user_id: UserId, text: &str) -> Option<Guess> {
+        if self.title_by.is_none() && is_match(text, &self.track.title) {
+            self.title_by = Some(user_id);
+            return Some(Guess::Title);
+        }
+        let artist = self.track.artist.as_deref()?;
+        if self.artist_by.is_none() && is_match(text, artist) {
+            self.artist_by = Some(user_id);
+            return Some(Guess::Artist);
+        }
+        None
+    }
+
+    /// Whether there's nothing left to guess.
+    pub fn is_done(&self) -> bool {
+        self.title_by.is_some() && (self.artist_by.is_some() || self.track.artist.is_none())
+    }
+}
+
+/// Points won so far in a game.
+#[derive(Debug, Clone, Default)]
+pub struct Scoreboard(HashMap<UserId, u32>);
+
+impl Scoreboard {
+    pub fn add(&mut self, user_id: UserId, points: u32) {
+        *self.0.entry(user_id).or_default() += points;
+    }
+
+    /// Everyone who scored, most points first.
+    pub fn ranked(&self) -> Vec<(UserId, u32)> {
+        let mut ranked = self.0.iter().map(|(u, p)| (*u, *p)).collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked
+    }
+}
+
+/// A numbered list of members and their points.
+pub fn render_scores<P: Display>(scores: &[(UserId, P)]) -> String {
+    scores
+        .iter()
+        .enumerate()
+        .map(|(i, (user_id, points))| {
+            let medal = match i {
+                0 => "🥇",
+                1 => "🥈",
+                2 => "🥉",
+                _ => "▫️",
+            };
+            format!("{} <@{}> **{}**", medal, user_id, points)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn song2() -> QuizTrack {
+        QuizTrack {
+            url: "https://www.youtube.com/watch?v=SSbBvKaM6sk".to_string(),
+            title: "Song 2".to_string(),
+            artist: Some("Blur".to_string()),
+            duration: Some(Duration::from_secs(122)),
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Song 2 (Official Music Video) [HD]"), "song 2");
+        assert_eq!(normalize("Don't Stop Me Now"), "dont stop me now");
+        assert_eq!(normalize("Simon & Garfunkel"), "simon and garfunkel");
+        assert_eq!(normalize("Stay ft. Justin Bieber"), "stay");
+        assert_eq!(normalize("Björk"), "björk");
+        assert_eq!(normalize("?!"), "");
+    }
+
+    #[test]
+    fn test_is_match() {
+        assert!(is_match("song 2", "Song 2"));
+        assert!(is_match("SONG 2!!", "Song 2 (Official Video)"));
+        assert!(is_match("bohemian rhapsodie", "Bohemian Rhapsody"));
+        assert!(is_match("song 2 by blur", "Song 2"));
+        assert!(!is_match("it's song 2 by blur", "Song 2"));
+        assert!(!is_match(
+            "parklife beetlebum song 2 tender girls and boys",
+            "Song 2"
+        ));
+        assert!(!is_match("song", "Song 2"));
+        assert!(!is_match("good morning", "Go"));
+        assert!(!is_match("", "Song 2"));
+        assert!(!is_match("anything", "(Official Video)"));
+    }
+
+    #[test]
+    fn test_from_metadata() {
+        let metadata = AuxMetadata {
+            title: Some("Blur - Song 2 (Official Music Video)".to_string()),
+            source_url: Some(song2().url),
+            duration: Some(Duration::from_secs(122)),
+            ..Default::default()
+        };
+        let track = QuizTrack::from_metadata(&metadata).unwrap();
+        assert_eq!(track.title, "Song 2 (Official Music Video)");
+        assert_eq!(track.artist.as_deref(), Some("Blur"));
+        let metadata = AuxMetadata {
+            track: Some("Song 2".to_string()),
+            artist: Some("Blur".to_string()),
+            ..metadata
+        };
+        let track = QuizTrack::from_metadata(&metadata).unwrap();
+        assert_eq!(track, song2());
+        let no_url = AuxMetadata {
+            source_url: None,
+            ..metadata
+        };
+        assert_eq!(QuizTrack::from_metadata(&no_url), None);
+    }
+
+    #[test]
+    fn test_snippet_start() {
+        let near = |a: Duration, secs: f64| {
+            a.abs_diff(Duration::from_secs_f64(secs)) < Duration::from_millis(1)
+        };
+        let track = song2();
+        // From a fifth of the way in, to 60% of the way to the last snippet.
+        assert!(near(track.snippet_start(0.0), 24.4));
+        assert!(near(track.snippet_start(0.5), 44.3));
+        assert!(near(track.snippet_start(1.0), 64.2));
+        assert!(track.snippet_start(1.0) + SNIPPET_LENGTH < Duration::from_secs(122));
+        let short = QuizTrack {
+            duration: Some(Duration::from_secs(20)),
+            ..song2()
+        };
+        assert_eq!(short.snippet_start(0.5), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_round() {
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+        let mut round = Round::new(song2());
+        assert_eq!(round.guess(alice, "parklife"), None);
+        assert_eq!(round.guess(bob, "blur"), Some(Guess::Artist));
+        assert_eq!(round.guess(alice, "blur"), None);
+        assert!(!round.is_done());
+        assert_eq!(round.guess(alice, "song 2"), Some(Guess::Title));
+        assert!(round.is_done());
+
+        let mut round = Round::new(QuizTrack {
+            artist: None,
+            ..song2()
+        });
+        assert_eq!(round.guess(alice, "Song 2"), Some(Guess::Title));
+        assert!(round.is_done());
+    }
+
+    #[test]
+    fn test_scoreboard() {
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+        let mut scores = Scoreboard::default();
+        scores.add(bob, ARTIST_POINTS);
+        scores.add(alice, TITLE_POINTS);
+        scores.add(bob, TITLE_POINTS);
+        assert_eq!(scores.ranked(), vec![(bob, 3), (alice, 2)]);
+        assert_eq!(
+            render_scores(&scores.ranked()),
+            "🥇 <@2> **3**\n🥈 <@1> **2**"
+        );
+    }
+}
@@ -0,0 +1,477 @@
+use crate::{
+    clock::PollJob,
+    commands::join_voice_channel,
+    db::{Playlist, ScheduleAction, ScheduledPlayback, SmartEvalContext},
+    duration::{parse_duration_at, DurationError},
+    errors::CrackedError,
+    guild::operations::GuildSettingsOperations,
+    handlers::sleep_timer::stop_and_leave,
+    http_utils,
+    messaging::message::CrackedMessage,
+    music::queue::{apply_track_offsets, queue_resolved_track_back},
+    Data,
+};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use crack_types::QueryType;
+use serenity::all::{
+    ChannelId, Context as SerenityContext, CreateEmbed, CreateMessage, GuildId, UserId,
+};
+use sqlx::PgPool;
+use std::{future::Future, sync::Arc, time::Duration};
+
+/// How often the scheduler checks for due jobs.
+pub const SCHEDULER_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Parse an IANA timezone name, e.g. `Europe/Berlin`. Defaults to UTC.
+pub fn parse_timezone(timezone: Option<&str>) -> Result<Tz, CrackedError> {
+    match timezone {
+        Some(tz) => tz
+            .trim()
+            .parse::<Tz>()
+            .map_err(|_| CrackedError::Other("Unknown timezone, try e.g. `Europe/Berlin`.")),
+        None => Ok(Tz::UTC),
+    }
+}
+
+/// Parse the time a job should run at. Accepts
+/// - `HH:MM` or `H:MMpm`: the next time the clock shows this in `tz`,
+/// - `<weekday> HH:MM`, e.g. `friday 20:00`: the next such day,
+/// - `YYYY-MM-DD HH:MM` in `tz`,
+/// - an RFC 3339 timestamp, which carries its own offset,
+/// - `in <duration>`, e.g. `in 1h30m`, counted from now.
+///
+/// `now` is passed in so this can be tested against a fixed clock.
+pub fn parse_schedule_time(
+    input: &str,
+    tz: Tz,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>, CrackedError> {
+    let input = input.trim();
+    if let Some(duration) = input.strip_prefix("in ") {
+        let duration = parse_duration_at(duration, now, tz)?;
+        return chrono::Duration::from_std(duration)
+            .ok()
+            .and_then(|d| now.checked_add_signed(d))
+            .ok_or(CrackedError::DurationParseError(DurationError::TooLong));
+    }
+
+    let exact = if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        Some(dt.with_timezone(&Utc))
+    } else if let Ok(naive) = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M") {
+        Some(localize(tz, naive)?)
+    } else {
+        None
+    };
+    if let Some(exact) = exact {
+        return if exact > now {
+            Ok(exact)
+        } else {
+            Err(CrackedError::Other("That time is in the past."))
+        };
+    }
+
+    let (weekday, time) = match input.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [time] => (None, parse_clock_time(time)?),
+        [day, time] => (
+            Some(
+                day.parse::<Weekday>()
+                    .map_err(|_| CrackedError::ParseTimeFail)?,
+            ),
+            parse_clock_time(time)?,
+        ),
+        _ => return Err(CrackedError::ParseTimeFail),
+    };
+
+    let today = now.with_timezone(&tz).date_naive();
+    (0..=7)
+        .filter_map(|days| today.checked_add_days(chrono::Days::new(days)))
+        .filter(|date| weekday.map_or(true, |w| date.weekday() == w))
+        .filter_map(|date: NaiveDate| localize(tz, date.and_time(time)).ok())
+        .find(|at| *at > now)
+        .ok_or(CrackedError::ParseTimeFail)
+}
+
+/// Parse a time of day as `HH:MM` or `H:MMam`/`H:MMpm`.
+fn parse_clock_time(input: &str) -> Result<NaiveTime, CrackedError> {
+    NaiveTime::parse_from_str(input, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(input, "%I:%M%p"))
+        .map_err(|_| CrackedError::ParseTimeFail)
+}
+
+/// Resolve a local time in `tz` to UTC, taking the earlier time when the clocks go back.
+fn localize(tz: Tz, naive: NaiveDateTime) -> Result<DateTime<Utc>, CrackedError> {
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or(CrackedError::Other(
+            "That time doesn't exist in this timezone (daylight saving).",
+        ))
+}
+
+/// Carries out a scheduled job when it's due.
+pub trait ScheduleRunner: Send + Sync + 'static {
+    fn run(&self, job: &ScheduledPlayback)
+        -> impl Future<Output = Result<(), CrackedError>> + Send;
+}
+
+/// Runs due [`ScheduledPlayback`] jobs from the database. Drive it with a
+/// [`Poller`](crate::clock::Poller).
+pub struct ScheduledPlaybacks<R: ScheduleRunner> {
+    pool: PgPool,
+    runner: R,
+}
+
+impl<R: ScheduleRunner> ScheduledPlaybacks<R> {
+    pub fn new(pool: PgPool, runner: R) -> Self {
+        Self { pool, runner }
+    }
+}
+
+impl<R: ScheduleRunner> PollJob for ScheduledPlaybacks<R> {
+    const NAME: &'static str = "Scheduler";
+    const POLL_INTERVAL: Duration = SCHEDULER_POLL_INTERVAL;
+
+    /// Run every job that is due now. Repeating jobs are moved to their next run,
+    /// one-shot jobs are removed. Returns the number of jobs that ran.
+    async fn tick(&self, now: DateTime<Utc>) -> Result<usize, CrackedError> {
+        let due = ScheduledPlayback::get_due(&self.pool, now.naive_utc()).await?;
+        for job in due.iter() {
+            let error = match self.runner.run(job).await {
+                Ok(()) => None,
+                Err(e) => {
+                    tracing::warn!("Scheduled playback {} failed: {}", job.id, e);
+                    Some(e.to_string())
+                },
+            };
+            let res = match job.next_run_after(now) {
+                Some(next) => {
+                    ScheduledPlayback::reschedule(&self.pool, job.id, next.naive_utc(), error).await
+                },
+                None => ScheduledPlayback::delete(&self.pool, job.id).await,
+            };
+            // Keep going so one bad row doesn't hold up the other due jobs.
+            if let Err(e) = res {
+                tracing::error!("Failed to update scheduled playback {}: {}", job.id, e);
+            }
+        }
+        Ok(due.len())
+    }
+}
+
+/// Runs scheduled jobs against Discord: joins voice, queues, stops and posts updates.
+#[derive(Clone)]
+pub struct DiscordScheduleRunner {
+    pub serenity_ctx: Arc<SerenityContext>,
+    pub data: Arc<Data>,
+}
+
+impl ScheduleRunner for DiscordScheduleRunner {
+    async fn run(&self, job: &ScheduledPlayback) -> Result<(), CrackedError> {
+        let res = match job.action()? {
+            ScheduleAction::Play => self.play(job).await,
+            ScheduleAction::Stop => self.stop(job).await,
+        };
+        let (msg, res) = match res {
+            Ok(msg) => (msg, Ok(())),
+            Err(e) => (
+                CrackedMessage::Other(format!("Scheduled {} failed: {}", job.action, e)),
+                Err(e),
+            ),
+        };
+        self.announce(job, msg).await;
+        res
+    }
+}
+
+impl DiscordScheduleRunner {
+    /// Join the job's voice channel (unless already connected) and queue its playlist.
+    async fn play(&self, job: &ScheduledPlayback) -> Result<CrackedMessage, CrackedError> {
+        let pool = self
+            .data
+            .database_pool
+            .as_ref()
+            .ok_or(CrackedError::NoDatabasePool)?;
+        let playlist_id = job.playlist_id.ok_or(CrackedError::Other(
+            "The scheduled playlist no longer exists.",
+        ))?;
+        let playlist = Playlist::get_playlist_by_id(pool, playlist_id).await?;
+
+        let guild_id = GuildId::new(job.guild_id as u64);
+        let channel_id = job
+            .voice_channel_id
+            .map(|id| ChannelId::new(id as u64))
+            .ok_or(CrackedError::NoChannelId)?;
+        // The session already in the channel, or a free identity.
+        let manager = self.data.songbird_for(guild_id, Some(channel_id)).await?;
+        let connected = match manager.get(guild_id) {
+            Some(call) => call.lock().await.current_channel().is_some(),
+            None => false,
+        };
+        let call = match (connected, manager.get(guild_id)) {
+            (true, Some(call)) => call,
+            _ => {
+                join_voice_channel(
+                    &self.serenity_ctx,
+                    self.data.clone(),
+                    &manager,
+                    guild_id,
+                    channel_id,
+                )
+                .await?
+            },
+        };
+
+        let eval_ctx = SmartEvalContext {
+            user_id: job.user_id,
+            guild_id: Some(job.guild_id),
+        };
+        let entries = Playlist::get_queue_entries(pool, playlist_id, &eval_ctx).await?;
+        let user_id = UserId::new(job.user_id as u64);
+        let mut queued = 0;
+        for (metadata, offsets) in entries {
+            let Some(url) = metadata.source_url else {
+                continue;
+            };
+            let resolved = match self
+                .data
+                .ct_client
+                .resolve_track(QueryType::VideoLink(url))
+                .await
+            {
+                Ok(resolved) => resolved.with_user_id(user_id),
+                Err(e) => {
+                    tracing::warn!("Scheduled playback {}: {}", job.id, e);
+                    continue;
+                },
+            };
+            let queue =
+                queue_resolved_track_back(&call, resolved, http_utils::get_client_old().clone())
+                    .await?;
+            if let Some(track) = queue.last() {
+                apply_track_offsets(track, offsets)?;
+            }
+            queued += 1;
+        }
+        if queued == 0 {
+            return Err(CrackedError::PlayListFail);
+        }
+
+        Ok(CrackedMessage::ScheduledPlaybackStarted {
+            playlist: playlist.name,
+        })
+    }
+
+    /// Stop playback, clear the queue and leave: in the job's voice channel if
+    /// it has one, otherwise in every session in the guild.
+    async fn stop(&self, job: &ScheduledPlayback) -> Result<CrackedMessage, CrackedError> {
+        let guild_id = GuildId::new(job.guild_id as u64);
+        let managers = match job.voice_channel_id {
+            Some(id) => {
+                let call = self
+                    .data
+                    .call_in_channel(guild_id, ChannelId::new(id as u64))
+                    .await
+                    .ok_or(CrackedError::NotConnected)?;
+                vec![self.data.songbird_for_call(guild_id, &call)]
+            },
+            None => self.data.songbirds(guild_id),
+        };
+        let mut stopped = false;
+        for manager in managers {
+            stopped |= stop_and_leave(&manager, guild_id).await?;
+        }
+        if !stopped {
+            return Err(CrackedError::NotConnected);
+        }
+        let _ = self.data.set_autoplay(guild_id, false).await;
+        Ok(CrackedMessage::ScheduledPlaybackStopped)
+    }
+
+    /// Post in the guild's music channel, or where the job was scheduled from.
+    async fn announce(&self, job: &ScheduledPlayback, msg: CrackedMessage) {
+        let guild_id = GuildId::new(job.guild_id as u64);
+        let channel_id = match self.data.get_music_channel(guild_id).await {
+            Some(channel_id) => channel_id,
+            None => match job.text_channel_id {
+                Some(id) => ChannelId::new(id as u64),
+                None => return,
+            },
+        };
+        let embed = CreateEmbed::from(msg);
+        if let Err(e) = channel_id
+            .send_message(&self.serenity_ctx.http, CreateMessage::new().embed(embed))
+            .await
+        {
+            tracing::warn!("Failed to announce scheduled playback: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::{Clock, MockClock, Poller};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone(None).unwrap(), Tz::UTC);
+        assert_eq!(
+            parse_timezone(Some("Europe/Berlin")).unwrap(),
+            Tz::Europe__Berlin
+        );
+        assert!(parse_timezone(Some("Mars/Olympus")).is_err());
+    }
+
+    #[test]
+    fn test_parse_schedule_time_clock() {
+        // Wednesday 2024-01-10 12:00 UTC.
+        let now = utc("2024-01-10T12:00:00Z");
+        assert_eq!(
+            parse_schedule_time("20:00", Tz::UTC, now).unwrap(),
+            utc("2024-01-10T20:00:00Z")
+        );
+        // Already passed today, so tomorrow.
+        assert_eq!(
+            parse_schedule_time("09:30", Tz::UTC, now).unwrap(),
+            utc("2024-01-11T09:30:00Z")
+        );
+        assert_eq!(
+            parse_schedule_time("8:15pm", Tz::UTC, now).unwrap(),
+            utc("2024-01-10T20:15:00Z")
+        );
+    }
+
+    #[test]
+    fn test_parse_schedule_time_timezone() {
+        let now = utc("2024-01-10T12:00:00Z");
+        // 20:00 in Berlin in winter is 19:00 UTC.
+        assert_eq!(
+            parse_schedule_time("20:00", Tz::Europe__Berlin, now).unwrap(),
+            utc("2024-01-10T19:00:00Z")
+        );
+        // 20:00 in New York is already tomorrow in UTC.
+        assert_eq!(
+            parse_schedule_time("20:00", Tz::America__New_York, now).unwrap(),
+            utc("2024-01-11T01:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_parse_schedule_time_weekday() {
+        let now = utc("2024-01-10T12:00:00Z");
+        assert_eq!(
+            parse_schedule_time("friday 20:00", Tz::UTC, now).unwrap(),
+            utc("2024-01-12T20:00:00Z")
+        );
+        // Today's slot has passed, so next week.
+        assert_eq!(
+            parse_schedule_time("wed 11:00", Tz::UTC, now).unwrap(),
+            utc("2024-01-17T11:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_parse_schedule_time_absolute() {
+        let now = utc("2024-01-10T12:00:00Z");
+        assert_eq!(
+            parse_schedule_time("2024-02-01 18:00", Tz::Europe__Berlin, now).unwrap(),
+            utc("2024-02-01T17:00:00Z")
+        );
+        assert_eq!(
+            parse_schedule_time("2024-02-01T18:00:00+05:00", Tz::UTC, now).unwrap(),
+            utc("2024-02-01T13:00:00Z")
+        );
+        assert!(parse_schedule_time("2024-01-01 18:00", Tz::UTC, now).is_err());
+        assert!(parse_schedule_time("soon", Tz::UTC, now).is_err());
+        assert!(parse_schedule_time("someday 20:00", Tz::UTC, now).is_err());
+    }
+
+    #[test]
+    fn test_parse_schedule_time_relative() {
+        let now = utc("2024-01-10T12:00:00Z");
+        assert_eq!(
+            parse_schedule_time("in 1h30m", Tz::UTC, now).unwrap(),
+            utc("2024-01-10T13:30:00Z")
+        );
+        assert_eq!(
+            parse_schedule_time("in PT2H", Tz::UTC, now).unwrap(),
+            utc("2024-01-10T14:00:00Z")
+        );
+        assert!(parse_schedule_time("in 5", Tz::UTC, now).is_err());
+    }
+
+    #[test]
+    fn test_parse_schedule_time_dst_gap() {
+        // Clocks in Berlin jump from 02:00 to 03:00 on 2024-03-31.
+        let now = utc("2024-03-30T12:00:00Z");
+        assert!(parse_schedule_time("2024-03-31 02:30", Tz::Europe__Berlin, now).is_err());
+    }
+
+    /// Counts runs instead of touching Discord.
+    #[derive(Clone, Default)]
+    struct CountingRunner {
+        runs: Arc<AtomicUsize>,
+    }
+
+    impl ScheduleRunner for CountingRunner {
+        async fn run(&self, _job: &ScheduledPlayback) -> Result<(), CrackedError> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_scheduler_runs_due_jobs(pool: PgPool) -> Result<(), CrackedError> {
+        let clock = MockClock::new(utc("2024-01-05T19:00:00Z"));
+        let runner = CountingRunner::default();
+        let scheduler = Poller::new(
+            clock.clone(),
+            ScheduledPlaybacks::new(pool.clone(), runner.clone()),
+        )
+        .with_poll_interval(Duration::from_secs(60));
+
+        let base = ScheduledPlayback {
+            guild_id: 1,
+            voice_channel_id: Some(2),
+            user_id: 1,
+            action: ScheduleAction::Stop.to_string(),
+            run_at: utc("2024-01-05T20:00:00Z").naive_utc(),
+            timezone: "UTC".to_string(),
+            ..Default::default()
+        };
+        ScheduledPlayback::create(&pool, &base).await?;
+        let weekly = ScheduledPlayback::create(
+            &pool,
+            &ScheduledPlayback {
+                repeat_days: Some(7),
+                ..base.clone()
+            },
+        )
+        .await?;
+
+        // Not due yet.
+        assert_eq!(scheduler.tick().await?, 0);
+
+        // An hour of polling later, both run once.
+        for _ in 0..60 {
+            clock.sleep(Duration::from_secs(60)).await;
+            scheduler.tick().await?;
+        }
+        assert_eq!(runner.runs.load(Ordering::SeqCst), 2);
+
+        // The one-shot job is gone, the weekly one moved a week ahead.
+        let left = ScheduledPlayback::get_for_guild(&pool, 1).await?;
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].id, weekly.id);
+        assert_eq!(left[0].run_at_utc(), utc("2024-01-12T20:00:00Z"));
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Music sessions across bot identities. Songbird holds one call per guild, so
+//! each extra token ("helper") runs its own voice-only client with its own
+//! voice manager, and a guild gets one session per identity in it. Commands
+//! still come in through the main bot and go to the session in the caller's
+//! channel, or to a free identity if there isn't one.
+use crate::{errors::CrackedError, Data};
+use serenity::all::{Cache, ChannelId, GuildId, Http, UserId};
+use songbird::{Call, Songbird};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// A helper bot identity, which only plays music.
+#[derive(Clone)]
+pub struct HelperBot {
+    pub cache: Arc<Cache>,
+    pub http: Arc<Http>,
+    pub songbird: Arc<Songbird>,
+}
+
+impl std::fmt::Debug for HelperBot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HelperBot").finish_non_exhaustive()
+    }
+}
+
+/// Which session to use for a member in `channel_id`, given the channel each
+/// identity is in: the one already there, otherwise the first free one.
+pub fn pick_session(current: &[Option<ChannelId>], channel_id: ChannelId) -> Option<usize> {
+    current
+        .iter()
+        .position(|c| *c == Some(channel_id))
+        .or_else(|| current.iter().position(Option::is_none))
+}
+
+/// The voice channel of a manager's call in a guild, if it has one.
+async fn current_channel(manager: &Songbird, guild_id: GuildId) -> Option<ChannelId> {
+    let call = manager.get(guild_id)?;
+    let channel = call.lock().await.current_channel();
+    channel.map(|c| ChannelId::new(c.get()))
+}
+
+impl Data {
+    /// The voice managers of the identities in a guild, the main bot's first.
+    pub fn songbirds(&self, guild_id: GuildId) -> Vec<Arc<Songbird>> {
+        std::iter::once(self.songbird.clone())
+            .chain(
+                self.helpers
+                    .iter()
+                    .filter(|helper| helper.cache.guild(guild_id).is_some())
+                    .map(|helper| helper.songbird.clone()),
+            )
+            .collect()
+    }
+
+    /// The voice manager for a member in `channel_id`, or the main bot's if
+    /// the member isn't in voice. Fails if every identity is busy elsewhere,
+    /// rather than taking a session away from another channel.
+    pub async fn songbird_for(
+        &self,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+    ) -> Result<Arc<Songbird>, CrackedError> {
+        let Some(channel_id) = channel_id else {
+            return Ok(self.songbird.clone());
+        };
+        let mut managers = self.songbirds(guild_id);
+        let mut current = Vec::with_capacity(managers.len());
+        for manager in &managers {
+            current.push(current_channel(manager, guild_id).await);
+        }
+        pick_session(&current, channel_id)
+            .map(|index| managers.swap_remove(index))
+            .ok_or(CrackedError::NoFreeSession)
+    }
+
+    /// The voice manager of the identity with `user_id`, if it's the main bot
+    /// (`bot_id`) or one of the helpers.
+    pub fn songbird_of(&self, bot_id: UserId, user_id: UserId) -> Option<Arc<Songbird>> {
+        if user_id == bot_id {
+            return Some(self.songbird.clone());
+        }
+        self.helpers
+            .iter()
+            .find(|helper| helper.cache.current_user().id == user_id)
+            .map(|helper| helper.songbird.clone())
+    }
+
+    /// The call of whichever identity is in `channel_id`.
+    pub async fn call_in_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Option<Arc<Mutex<Call>>> {
+        for manager in self.songbirds(guild_id) {
+            if current_channel(&manager, guild_id).await == Some(channel_id) {
+                return manager.get(guild_id);
+            }
+        }
+        None
+    }
+
+    /// The voice manager that owns `call`.
+    pub fn songbird_for_call(&self, guild_id: GuildId, call: &Arc<Mutex<Call>>) -> Arc<Songbird> {
+        self.songbirds(guild_id)
+            .into_iter()
+            .find(|manager| manager.get(guild_id).is_some_and(|c| Arc::ptr_eq(&c, call)))
+            .unwrap_or_else(|| self.songbird.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pick_session() {
+        let lofi = ChannelId::new(1);
+        let karaoke = ChannelId::new(2);
+        let stage = ChannelId::new(3);
+        // Already there.
+        assert_eq!(pick_session(&[Some(lofi), Some(karaoke)], karaoke), Some(1));
+        // The main bot's free.
+        assert_eq!(pick_session(&[None, Some(lofi)], karaoke), Some(0));
+        // The main bot's busy, a helper isn't.
+        assert_eq!(pick_session(&[Some(lofi), None, None], karaoke), Some(1));
+        // Everyone's busy.
+        assert_eq!(pick_session(&[Some(lofi), Some(karaoke)], stage), None);
+        assert_eq!(pick_session(&[], stage), None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::connection::get_voice_channel_for_user;
 use crate::db::{MetadataMsg, PlayLog};
 use crate::guild::{operations::GuildSettingsOperations, settings::GuildSettings};
 use crate::music::TrackReadyData;
@@ -13,7 +14,7 @@ use poise::serenity_prelude as serenity;
 use poise::{CreateReply, ReplyHandle};
 use serenity::all::{ChannelId, CreateEmbed, GuildId, Message, UserId};
 use songbird::tracks::{PlayMode, TrackQueue};
-use songbird::Call;
+use songbird::{Call, Songbird};
 use std::{future::Future, sync::Arc};
 use tokio::sync::Mutex;
 
@@ -37,6 +38,8 @@ pub trait ContextExt<'ctx> {
 
     /// Gets the log of last played songs on the bot
     fn get_last_played(self) -> impl Future<Output = Result<Vec<String>, CrackedError>>;
+    /// Return the voice manager of the bot identity for the author's voice channel.
+    fn get_songbird(self) -> impl Future<Output = Result<Arc<Songbird>, CrackedError>>;
     /// Return the call that the bot is currently in, if it is in one.
     fn get_call(self) -> impl Future<Output = Result<Arc<Mutex<Call>>, CrackedError>>;
     /// Return the call and the guild id. This is convenience function I found I had many cases for.
@@ -161,17 +164,29 @@ impl<'ctx> ContextExt<'ctx> for crate::Context<'ctx> {
         }
     }
 
+    /// Return the voice manager of the bot identity in the author's voice channel, or a
+    /// free one, so each channel gets its own session.
+    async fn get_songbird(self) -> Result<Arc<Songbird>, CrackedError> {
+        let Some(guild_id) = self.guild_id() else {
+            return Ok(self.data().songbird.clone());
+        };
+        let channel_id = self
+            .guild()
+            .and_then(|guild| get_voice_channel_for_user(&guild, &self.author().id).ok());
+        self.data().songbird_for(guild_id, channel_id).await
+    }
+
     /// Return the call that the bot is currently in, if it is in one.
     async fn get_call(self) -> Result<Arc<Mutex<Call>>, CrackedError> {
         let guild_id = self.guild_id().ok_or(CrackedError::NoGuildId)?;
-        let manager = self.data().songbird.clone();
+        let manager = self.get_songbird().await?;
         manager.get(guild_id).ok_or(CrackedError::NotConnected)
     }
 
     /// Return the call that the bot is currently in, if it is in one.
     async fn get_call_guild_id(self) -> Result<(Arc<Mutex<Call>>, GuildId), CrackedError> {
         let guild_id = self.guild_id().ok_or(CrackedError::NoGuildId)?;
-        let manager = self.data().songbird.clone();
+        let manager = self.get_songbird().await?;
         manager
             .get(guild_id)
             .map(|x| (x, guild_id))
@@ -197,7 +212,7 @@ impl<'ctx> ContextExt<'ctx> for crate::Context<'ctx> {
     /// Gets the channel id that the bot is currently playing in for a given guild.
     async fn get_active_channel_id(self, guild_id: GuildId) -> Option<ChannelId> {
         //let serenity_context = self.serenity_context();
-        let manager = self.data().songbird.clone();
+        let manager = self.get_songbird().await.ok()?;
         let call_lock = manager.get(guild_id)?;
         let call = call_lock.lock().await;
 
@@ -0,0 +1,476 @@
+//! Voice channel recording. Only members who opt in are recorded. Each member's
+//! audio goes to its own WAV file, lined up with a mix of everyone, so the files
+//! share one timeline starting when the recording did.
+pub mod wav;
+
+pub use wav::*;
+
+use crate::{
+    guild::settings::get_recordings_path,
+    messaging::messages::{
+        RECORD_BUTTON_IN, RECORD_BUTTON_OUT, RECORD_NOT_RECORDING, RECORD_OPTED_IN,
+        RECORD_OPTED_OUT,
+    },
+    CrackedError, Data,
+};
+use chrono::{DateTime, Utc};
+use serenity::all::{
+    ButtonStyle, ChannelId, Context as SerenityContext, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, GuildId, Interaction, UserId,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufWriter, Seek, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Discord voice is 48kHz. Recordings are downmixed to mono.
+pub const SAMPLE_RATE: u32 = 48_000;
+/// Samples per voice tick, 20ms.
+pub const FRAME_SAMPLES: usize = SAMPLE_RATE as usize / 50;
+/// Longest recording, after which new audio is dropped.
+pub const MAX_RECORDING: Duration = Duration::from_secs(2 * 60 * 60);
+/// Custom id prefix of the opt in and out buttons.
+pub const RECORD_CUSTOM_ID: &str = "record";
+
+/// The custom id of a guild's opt in or opt out button.
+pub fn record_custom_id(guild_id: GuildId, opt_in: bool) -> String {
+    let action = if opt_in { "in" } else { "out" };
+    format!("{}:{}:{}", RECORD_CUSTOM_ID, guild_id, action)
+}
+
+/// The guild a record button is for and whether it opts in, or `None` if it
+/// isn't one.
+pub fn parse_record_custom_id(custom_id: &str) -> Option<(GuildId, bool)> {
+    let mut parts = custom_id.split(':');
+    if parts.next()? != RECORD_CUSTOM_ID {
+        return None;
+    }
+    let guild_id = parts
+        .next()?
+        .parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .map(GuildId::new)?;
+    let opt_in = match parts.next()? {
+        "in" => true,
+        "out" => false,
+        _ => return None,
+    };
+    parts.next().is_none().then_some((guild_id, opt_in))
+}
+
+/// The opt in and out buttons for a recording announcement.
+pub fn record_buttons(guild_id: GuildId) -> Vec<CreateActionRow<'static>> {
+    let opt_in = CreateButton::new(record_custom_id(guild_id, true))
+        .style(ButtonStyle::Success)
+        .label(RECORD_BUTTON_IN);
+    let opt_out = CreateButton::new(record_custom_id(guild_id, false))
+        .style(ButtonStyle::Secondary)
+        .label(RECORD_BUTTON_OUT);
+    vec![CreateActionRow::Buttons(Cow::Owned(vec![opt_in, opt_out]))]
+}
+
+/// Average interleaved stereo down to mono, padded or cut to one frame.
+pub fn downmix(stereo: &[i16]) -> Vec<i16> {
+    let mut mono = stereo
+        .chunks(2)
+        .map(|pair| (pair.iter().map(|s| *s as i32).sum::<i32>() / pair.len() as i32) as i16)
+        .take(FRAME_SAMPLES)
+        .collect::<Vec<_>>();
+    mono.resize(FRAME_SAMPLES, 0);
+    mono
+}
+
+/// Writes the audio of a call, one voice tick at a time. `open` makes the file
+/// for a member the first time they're heard.
+pub struct Recorder<W: Write + Seek> {
+    ticks: u64,
+    ssrcs: HashMap<u32, UserId>,
+    consented: HashSet<UserId>,
+    mixed: WavWriter<W>,
+    tracks: HashMap<UserId, WavWriter<W>>,
+    open: Box<dyn FnMut(UserId) -> io::Result<W> + Send>,
+}
+
+impl<W: Write + Seek> Recorder<W> {
+    pub fn new(
+        mixed: W,
+        open: impl FnMut(UserId) -> io::Result<W> + Send + 'static,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            ticks: 0,
+            ssrcs: HashMap::new(),
+            consented: HashSet::new(),
+            mixed: WavWriter::new(mixed, 1, SAMPLE_RATE)?,
+            tracks: HashMap::new(),
+            open: Box::new(open),
+        })
+    }
+
+    /// Map an audio source to the member using it, from a speaking update.
+    pub fn speaking(&mut self, ssrc: u32, user_id: UserId) {
+        self.ssrcs.insert(ssrc, user_id);
+    }
+
+    /// The member using an audio source, if we've seen them speak.
+    pub fn user_for(&self, ssrc: u32) -> Option<UserId> {
+        self.ssrcs.get(&ssrc).copied()
+    }
+
+    /// Start recording a member.
+    pub fn opt_in(&mut self, user_id: UserId) {
+        self.consented.insert(user_id);
+    }
+
+    /// Stop recording a member. What was already recorded is kept.
+    pub fn opt_out(&mut self, user_id: UserId) {
+        self.consented.remove(&user_id);
+    }
+
+    pub fn is_consented(&self, user_id: UserId) -> bool {
+        self.consented.contains(&user_id)
+    }
+
+    /// How long the recording is so far.
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.ticks * 20)
+    }
+
+    /// Record one voice tick of decoded stereo audio by source. Audio from
+    /// members who haven't opted in, or whose source isn't known yet, is
+    /// dropped. Returns false once the recording is full.
+    pub fn tick(&mut self, voices: &HashMap<u32, Vec<i16>>) -> io::Result<bool> {
+        if self.duration() >= MAX_RECORDING {
+            return Ok(false);
+        }
+        let mut mix = vec![0i32; FRAME_SAMPLES];
+        let mut heard = HashSet::new();
+        for (ssrc, audio) in voices {
+            let Some(user_id) = self.ssrcs.get(ssrc).copied() else {
+                continue;
+            };
+            if !self.consented.contains(&user_id) || !heard.insert(user_id) {
+                continue;
+            }
+            let mono = downmix(audio);
+            for (mixed, sample) in mix.iter_mut().zip(&mono) {
+                *mixed += *sample as i32;
+            }
+            let track = match self.tracks.entry(user_id) {
+                std::collections::hash_map::Entry::Occupied(track) => track.into_mut(),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    let mut track = WavWriter::new((self.open)(user_id)?, 1, SAMPLE_RATE)?;
+                    // Line the track up with the start of the recording.
+                    track.write_silence(self.ticks as usize * FRAME_SAMPLES)?;
+                    entry.insert(track)
+                },
+            };
+            track.write_samples(&mono)?;
+        }
+        for (user_id, track) in self.tracks.iter_mut() {
+            if !heard.contains(user_id) {
+                track.write_silence(FRAME_SAMPLES)?;
+            }
+        }
+        let mix = mix
+            .into_iter()
+            .map(|s| s.clamp(i16::MIN as i32, i16::MAX as i32) as i16)
+            .collect::<Vec<_>>();
+        self.mixed.write_samples(&mix)?;
+        self.ticks += 1;
+        Ok(true)
+    }
+
+    /// Finish the files, returning the mix and each member's track.
+    pub fn finish(self) -> io::Result<(W, HashMap<UserId, W>)> {
+        let mixed = self.mixed.finish()?;
+        let tracks = self
+            .tracks
+            .into_iter()
+            .map(|(user_id, track)| track.finish().map(|w| (user_id, w)))
+            .collect::<io::Result<_>>()?;
+        Ok((mixed, tracks))
+    }
+}
+
+/// The files of a finished recording.
+#[derive(Debug, Clone)]
+pub struct RecordingFiles {
+    pub mixed: PathBuf,
+    pub tracks: Vec<(UserId, PathBuf)>,
+    pub duration: Duration,
+}
+
+/// A guild's recording in progress, shared with the call's voice handlers.
+pub struct Recording {
+    pub guild_id: GuildId,
+    /// Where the recording was announced.
+    pub channel_id: ChannelId,
+    pub started_by: UserId,
+    pub started_at: DateTime<Utc>,
+    pub dir: PathBuf,
+    recorder: Mutex<Option<Recorder<BufWriter<File>>>>,
+    stopped: AtomicBool,
+}
+
+impl Recording {
+    /// Start a recording in a new directory under the recordings path, named
+    /// for the guild and start time.
+    pub fn start(
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        started_by: UserId,
+        started_at: DateTime<Utc>,
+    ) -> io::Result<Self> {
+        let dir = PathBuf::from(get_recordings_path())
+            .join(guild_id.to_string())
+            .join(started_at.format("%Y%m%dT%H%M%SZ").to_string());
+        Self::start_in(dir, guild_id, channel_id, started_by, started_at)
+    }
+
+    /// Start a recording in `dir`.
+    pub fn start_in(
+        dir: PathBuf,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        started_by: UserId,
+        started_at: DateTime<Utc>,
+    ) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let mixed = BufWriter::new(File::create(dir.join("mixed.wav"))?);
+        let track_dir = dir.clone();
+        let recorder = Recorder::new(mixed, move |user_id| {
+            File::create(track_dir.join(format!("{}.wav", user_id))).map(BufWriter::new)
+        })?;
+        Ok(Self {
+            guild_id,
+            channel_id,
+            started_by,
+            started_at,
+            dir,
+            recorder: Mutex::new(Some(recorder)),
+            stopped: AtomicBool::new(false),
+        })
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    /// Run `f` on the recorder, unless the recording is over.
+    pub fn with_recorder<T>(
+        &self,
+        f: impl FnOnce(&mut Recorder<BufWriter<File>>) -> T,
+    ) -> Option<T> {
+        let mut recorder = self.recorder.lock().unwrap_or_else(|e| e.into_inner());
+        recorder.as_mut().map(f)
+    }
+
+    /// Stop recording and finish the files.
+    pub fn stop(&self) -> io::Result<RecordingFiles> {
+        self.stopped.store(true, Ordering::Release);
+        let recorder = self
+            .recorder
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Recording already stopped"))?;
+        let duration = recorder.duration();
+        let (_, tracks) = recorder.finish()?;
+        let mut tracks = tracks
+            .into_keys()
+            .map(|user_id| (user_id, self.dir.join(format!("{}.wav", user_id))))
+            .collect::<Vec<_>>();
+        tracks.sort();
+        Ok(RecordingFiles {
+            mixed: self.dir.join("mixed.wav"),
+            tracks,
+            duration,
+        })
+    }
+}
+
+/// Handle presses of the opt in and out buttons. Returns whether the
+/// interaction was for recording.
+#[cfg(not(tarpaulin_include))]
+pub async fn handle_record_interaction(
+    ctx: &SerenityContext,
+    data: &Data,
+    interaction: &Interaction,
+) -> Result<bool, CrackedError> {
+    let Interaction::Component(int) = interaction else {
+        return Ok(false);
+    };
+    let Some((guild_id, opt_in)) = parse_record_custom_id(&int.data.custom_id) else {
+        return Ok(false);
+    };
+    let recording = data
+        .recordings
+        .get(&guild_id)
+        .map(|recording| recording.clone())
+        .filter(|recording| !recording.is_stopped());
+    let reply = match recording {
+        None => RECORD_NOT_RECORDING,
+        Some(recording) => {
+            let user_id = int.user.id;
+            recording.with_recorder(|recorder| {
+                if opt_in {
+                    recorder.opt_in(user_id)
+                } else {
+                    recorder.opt_out(user_id)
+                }
+            });
+            if opt_in {
+                RECORD_OPTED_IN
+            } else {
+                RECORD_OPTED_OUT
+            }
+        },
+    };
+    int.create_response(
+        &ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(reply)
+                .ephemeral(true),
+        ),
+    )
+    .await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{
+        io::Cursor,
+        sync::{Arc, Mutex as StdMutex},
+    };
+
+    type Tracks = Arc<StdMutex<Vec<UserId>>>;
+
+    fn recorder() -> (Recorder<Cursor<Vec<u8>>>, Tracks) {
+        let opened = Tracks::default();
+        let opened2 = opened.clone();
+        let recorder = Recorder::new(Cursor::new(Vec::new()), move |user_id| {
+            opened2.lock().unwrap().push(user_id);
+            Ok(Cursor::new(Vec::new()))
+        })
+        .unwrap();
+        (recorder, opened)
+    }
+
+    /// A synthetic voice tick: one frame of constant stereo audio per source.
+    fn tick(voices: &[(u32, i16)]) -> HashMap<u32, Vec<i16>> {
+        voices
+            .iter()
+            .map(|(ssrc, level)| (*ssrc, vec![*level; FRAME_SAMPLES * 2]))
+            .collect()
+    }
+
+    fn samples(wav: &Cursor<Vec<u8>>) -> Vec<i16> {
+        wav.get_ref()[44..]
+            .chunks(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn test_only_consented_users() {
+        let (mut recorder, opened) = recorder();
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+        recorder.speaking(10, alice);
+        recorder.speaking(20, bob);
+        recorder.opt_in(alice);
+
+        assert!(recorder.tick(&tick(&[(10, 100), (20, 50)])).unwrap());
+        assert_eq!(*opened.lock().unwrap(), vec![alice]);
+
+        let (mixed, tracks) = recorder.finish().unwrap();
+        assert_eq!(samples(&mixed), vec![100; FRAME_SAMPLES]);
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(samples(&tracks[&alice]), vec![100; FRAME_SAMPLES]);
+    }
+
+    #[test]
+    fn test_tracks_line_up() {
+        let (mut recorder, _) = recorder();
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+        recorder.opt_in(alice);
+        recorder.opt_in(bob);
+        recorder.speaking(10, alice);
+
+        recorder.tick(&tick(&[(10, 100)])).unwrap();
+        // Audio from a source we can't place yet is dropped.
+        recorder.tick(&tick(&[(20, 7)])).unwrap();
+        recorder.speaking(20, bob);
+        recorder.tick(&tick(&[(10, 100), (20, 50)])).unwrap();
+        assert_eq!(recorder.duration(), Duration::from_millis(60));
+
+        let (mixed, tracks) = recorder.finish().unwrap();
+        let frames = |wav: &Cursor<Vec<u8>>| {
+            samples(wav)
+                .chunks(FRAME_SAMPLES)
+                .map(|frame| frame[0])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(frames(&mixed), vec![100, 0, 150]);
+        assert_eq!(frames(&tracks[&alice]), vec![100, 0, 100]);
+        // Bob's track starts with silence for the time before he spoke.
+        assert_eq!(frames(&tracks[&bob]), vec![0, 0, 50]);
+    }
+
+    #[test]
+    fn test_opt_out_and_clipping() {
+        let (mut recorder, _) = recorder();
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+        for (ssrc, user) in [(10, alice), (20, bob)] {
+            recorder.speaking(ssrc, user);
+            recorder.opt_in(user);
+        }
+        recorder
+            .tick(&tick(&[(10, i16::MAX), (20, i16::MAX)]))
+            .unwrap();
+        recorder.opt_out(bob);
+        assert!(!recorder.is_consented(bob));
+        recorder.tick(&tick(&[(10, 1), (20, 1)])).unwrap();
+
+        let (mixed, tracks) = recorder.finish().unwrap();
+        let mixed = samples(&mixed);
+        assert_eq!(mixed[0], i16::MAX);
+        assert_eq!(mixed[FRAME_SAMPLES], 1);
+        assert_eq!(samples(&tracks[&bob])[FRAME_SAMPLES], 0);
+    }
+
+    #[test]
+    fn test_downmix() {
+        assert_eq!(downmix(&[10, 20, -4, 4])[..2], [15, 0]);
+        assert_eq!(downmix(&[]).len(), FRAME_SAMPLES);
+        assert_eq!(downmix(&vec![1; FRAME_SAMPLES * 4]).len(), FRAME_SAMPLES);
+    }
+
+    #[test]
+    fn test_record_custom_id() {
+        let guild_id = GuildId::new(5);
+        assert_eq!(record_custom_id(guild_id, true), "record:5:in");
+        assert_eq!(
+            parse_record_custom_id("record:5:in"),
+            Some((guild_id, true))
+        );
+        assert_eq!(
+            parse_record_custom_id("record:5:out"),
+            Some((guild_id, false))
+        );
+        assert_eq!(parse_record_custom_id("record:5:maybe"), None);
+        assert_eq!(parse_record_custom_id("record:0:in"), None);
+        assert_eq!(parse_record_custom_id("verify:5"), None);
+    }
+}
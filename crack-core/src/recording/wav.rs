@@ -0,0 +1,119 @@
+//! A minimal streaming WAV writer for 16-bit PCM. The header is written up
+//! front with empty sizes and filled in by `finish`, so recordings go straight
+//! to disk.
+use std::io::{self, Seek, SeekFrom, Write};
+
+const HEADER_LEN: u32 = 44;
+
+/// Writes 16-bit PCM samples to a WAV file.
+#[derive(Debug)]
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    data_len: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Start a WAV file with the given format.
+    pub fn new(mut writer: W, channels: u16, sample_rate: u32) -> io::Result<Self> {
+        write_header(&mut writer, channels, sample_rate, 0)?;
+        Ok(Self {
+            writer,
+            data_len: 0,
+        })
+    }
+
+    /// Append interleaved samples.
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        let bytes = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect::<Vec<_>>();
+        self.writer.write_all(&bytes)?;
+        self.data_len = self.data_len.saturating_add(bytes.len() as u32);
+        Ok(())
+    }
+
+    /// Append `n` samples of silence.
+    pub fn write_silence(&mut self, n: usize) -> io::Result<()> {
+        const CHUNK: [i16; 4096] = [0; 4096];
+        let mut left = n;
+        while left > 0 {
+            let len = left.min(CHUNK.len());
+            self.write_samples(&CHUNK[..len])?;
+            left -= len;
+        }
+        Ok(())
+    }
+
+    /// Bytes of audio written so far.
+    pub fn data_len(&self) -> u32 {
+        self.data_len
+    }
+
+    /// Fill in the sizes and hand back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer
+            .write_all(&(HEADER_LEN - 8 + self.data_len).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(40))?;
+        self.writer.write_all(&self.data_len.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn write_header<W: Write>(
+    writer: &mut W,
+    channels: u16,
+    sample_rate: u32,
+    data_len: u32,
+) -> io::Result<()> {
+    let block_align = channels * 2;
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(HEADER_LEN - 8 + data_len).to_le_bytes())?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    // PCM
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_wav_writer() {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 1, 48_000).unwrap();
+        wav.write_samples(&[1, -1]).unwrap();
+        wav.write_silence(3).unwrap();
+        assert_eq!(wav.data_len(), 10);
+        let bytes = wav.finish().unwrap().into_inner();
+
+        assert_eq!(bytes.len(), 54);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 46);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(u16::from_le_bytes(bytes[22..24].try_into().unwrap()), 1);
+        assert_eq!(
+            u32::from_le_bytes(bytes[24..28].try_into().unwrap()),
+            48_000
+        );
+        assert_eq!(
+            u32::from_le_bytes(bytes[28..32].try_into().unwrap()),
+            96_000
+        );
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 10);
+        assert_eq!(&bytes[44..48], &[1, 0, 0xff, 0xff]);
+        assert!(bytes[48..].iter().all(|b| *b == 0));
+    }
+}
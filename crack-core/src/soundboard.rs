@@ -0,0 +1,230 @@
+//! The soundboard. Members upload short clips, which are checked for size and
+//! length and saved in the guild's soundboard directory. Playing one starts it
+//! as its own track in the call, so it's mixed over the music instead of
+//! going through the queue.
+use crate::guild::settings::get_soundboard_path;
+use serenity::all::GuildId;
+use songbird::input::{File as FileInput, Input};
+use std::{
+    fmt::Display,
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use symphonia::core::{
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+/// Biggest clip upload.
+pub const MAX_CLIP_BYTES: u32 = 1024 * 1024;
+/// Longest clip.
+pub const MAX_CLIP_LENGTH: Duration = Duration::from_secs(10);
+/// Longest clip name.
+pub const MAX_CLIP_NAME: usize = 32;
+/// Audio formats clips can be uploaded in.
+pub const CLIP_EXTENSIONS: [&str; 4] = ["mp3", "ogg", "wav", "flac"];
+
+/// Why a clip can't be added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipError {
+    InvalidName,
+    UnsupportedFormat,
+    TooBig(u32),
+    TooLong(Duration),
+    /// Not audio we can decode, or no way to tell how long it is.
+    Unreadable,
+}
+
+impl Display for ClipError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidName => write!(
+                f,
+                "Clip names are 1 to {MAX_CLIP_NAME} letters, numbers, `-` or `_`."
+            ),
+            Self::UnsupportedFormat => write!(
+                f,
+                "Clips have to be one of: {}.",
+                CLIP_EXTENSIONS.join(", ")
+            ),
+            Self::TooBig(size) => write!(
+                f,
+                "That clip is {} KiB, the most is {} KiB.",
+                size / 1024,
+                MAX_CLIP_BYTES / 1024
+            ),
+            Self::TooLong(length) => write!(
+                f,
+                "That clip is {:.1}s long, the most is {}s.",
+                length.as_secs_f32(),
+                MAX_CLIP_LENGTH.as_secs()
+            ),
+            Self::Unreadable => f.write_str("That file isn't audio I can read."),
+        }
+    }
+}
+
+impl std::error::Error for ClipError {}
+
+/// Check a clip name and lowercase it.
+pub fn parse_clip_name(name: &str) -> Result<String, ClipError> {
+    let name = name.trim().to_lowercase();
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_CLIP_NAME
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(name)
+    } else {
+        Err(ClipError::InvalidName)
+    }
+}
+
+/// The format of an uploaded file, from its extension.
+pub fn clip_extension(file_name: &str) -> Result<&'static str, ClipError> {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .ok_or(ClipError::UnsupportedFormat)?;
+    CLIP_EXTENSIONS
+        .into_iter()
+        .find(|ext| *ext == extension)
+        .ok_or(ClipError::UnsupportedFormat)
+}
+
+/// Check an upload's size before downloading it.
+pub fn check_clip_size(size: u32) -> Result<(), ClipError> {
+    if size > MAX_CLIP_BYTES {
+        Err(ClipError::TooBig(size))
+    } else {
+        Ok(())
+    }
+}
+
+/// How long a clip is. Uses the length in its header, or adds up its packets
+/// if it doesn't have one, like MP3s without a Xing header.
+pub fn probe_duration(audio: Vec<u8>, extension: &str) -> Result<Duration, ClipError> {
+    let source = MediaSourceStream::new(Box::new(Cursor::new(audio)), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(extension);
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|_| ClipError::Unreadable)?;
+    let track = probed.format.default_track().ok_or(ClipError::Unreadable)?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+    let sample_rate = params.sample_rate.ok_or(ClipError::Unreadable)?;
+    let frames = match params.n_frames {
+        Some(frames) => frames,
+        None => {
+            let mut frames = 0;
+            while let Ok(packet) = probed.format.next_packet() {
+                if packet.track_id() == track_id {
+                    frames += packet.dur;
+                }
+            }
+            frames
+        },
+    };
+    if frames == 0 {
+        return Err(ClipError::Unreadable);
+    }
+    Ok(Duration::from_secs_f64(frames as f64 / sample_rate as f64))
+}
+
+/// Check an uploaded clip, returning its format and length.
+pub fn validate_clip(
+    file_name: &str,
+    audio: Vec<u8>,
+) -> Result<(&'static str, Duration), ClipError> {
+    let extension = clip_extension(file_name)?;
+    check_clip_size(audio.len().try_into().unwrap_or(u32::MAX))?;
+    let length = probe_duration(audio, extension)?;
+    if length > MAX_CLIP_LENGTH {
+        return Err(ClipError::TooLong(length));
+    }
+    Ok((extension, length))
+}
+
+/// The directory a guild's clips are saved in.
+pub fn clip_dir(guild_id: GuildId) -> PathBuf {
+    PathBuf::from(get_soundboard_path()).join(guild_id.to_string())
+}
+
+/// The audio of a saved clip, to play in a call.
+pub fn clip_input(guild_id: GuildId, file_name: &str) -> Input {
+    FileInput::new(clip_dir(guild_id).join(file_name)).into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::recording::{WavWriter, SAMPLE_RATE};
+
+    fn wav(length: Duration, sample_rate: u32) -> Vec<u8> {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 1, sample_rate).unwrap();
+        let samples = (length.as_secs_f64() * sample_rate as f64) as usize;
+        wav.write_silence(samples).unwrap();
+        wav.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_parse_clip_name() {
+        assert_eq!(parse_clip_name(" AirHorn "), Ok("airhorn".to_string()));
+        assert_eq!(parse_clip_name("rim_shot-2"), Ok("rim_shot-2".to_string()));
+        assert_eq!(parse_clip_name(""), Err(ClipError::InvalidName));
+        assert_eq!(parse_clip_name("air horn"), Err(ClipError::InvalidName));
+        assert_eq!(parse_clip_name("../etc"), Err(ClipError::InvalidName));
+        assert_eq!(
+            parse_clip_name(&"a".repeat(MAX_CLIP_NAME + 1)),
+            Err(ClipError::InvalidName)
+        );
+    }
+
+    #[test]
+    fn test_clip_extension() {
+        assert_eq!(clip_extension("horn.MP3"), Ok("mp3"));
+        assert_eq!(clip_extension("horn.ogg"), Ok("ogg"));
+        assert_eq!(
+            clip_extension("horn.exe"),
+            Err(ClipError::UnsupportedFormat)
+        );
+        assert_eq!(clip_extension("horn"), Err(ClipError::UnsupportedFormat));
+    }
+
+    #[test]
+    fn test_validate_clip() {
+        let beep = wav(Duration::from_secs(2), SAMPLE_RATE);
+        assert_eq!(
+            validate_clip("beep.wav", beep),
+            Ok(("wav", Duration::from_secs(2)))
+        );
+        // Small enough, but too long.
+        let long = wav(Duration::from_secs(12), 8_000);
+        assert_eq!(
+            validate_clip("long.wav", long),
+            Err(ClipError::TooLong(Duration::from_secs(12)))
+        );
+        let big = wav(Duration::from_secs(11), SAMPLE_RATE);
+        assert!(matches!(
+            validate_clip("big.wav", big),
+            Err(ClipError::TooBig(_))
+        ));
+        assert_eq!(
+            validate_clip("noise.wav", b"not audio".to_vec()),
+            Err(ClipError::Unreadable)
+        );
+        assert_eq!(check_clip_size(MAX_CLIP_BYTES), Ok(()));
+        assert_eq!(
+            check_clip_size(MAX_CLIP_BYTES + 1),
+            Err(ClipError::TooBig(MAX_CLIP_BYTES + 1))
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Synced lyrics from [LRCLIB](https://lrclib.net), a free lyrics database
+//! that needs no API key.
+use crate::errors::CrackedError;
+use serde::Deserialize;
+use std::time::Duration;
+
+const LRCLIB_API: &str = "https://lrclib.net/api";
+
+/// A track's lyrics, as LRCLIB returns them.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LrclibLyrics {
+    #[serde(default)]
+    synced_lyrics: Option<String>,
+}
+
+/// LRC lyrics for a track, if LRCLIB has synced ones. Tries an exact match on
+/// title, artist and length first, then a search on the title.
+pub async fn synced_lyrics(
+    client: &reqwest::Client,
+    title: &str,
+    artist: Option<&str>,
+    duration: Option<Duration>,
+) -> Result<Option<String>, CrackedError> {
+    if let (Some(artist), Some(duration)) = (artist, duration) {
+        let secs = duration.as_secs().to_string();
+        let res = client
+            .get(format!("{LRCLIB_API}/get"))
+            .query(&[
+                ("track_name", title),
+                ("artist_name", artist),
+                ("duration", secs.as_str()),
+            ])
+            .send()
+            .await?;
+        // Not found is a 404, so fall through to searching.
+        if res.status().is_success() {
+            let lyrics = res.json::<LrclibLyrics>().await?;
+            if let Some(lyrics) = first_synced(vec![lyrics]) {
+                return Ok(Some(lyrics));
+            }
+        }
+    }
+    let query = match artist {
+        Some(artist) => format!("{} {}", search_title(title), artist),
+        None => search_title(title),
+    };
+    let results = client
+        .get(format!("{LRCLIB_API}/search"))
+        .query(&[("q", query)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<LrclibLyrics>>()
+        .await?;
+    Ok(first_synced(results))
+}
+
+fn first_synced(results: Vec<LrclibLyrics>) -> Option<String> {
+    results
+        .into_iter()
+        .find_map(|lyrics| lyrics.synced_lyrics.filter(|l| !l.trim().is_empty()))
+}
+
+/// A video title without the bracketed extras, like "(Official Video)", that
+/// keep searches from matching.
+fn search_title(title: &str) -> String {
+    let mut out = String::with_capacity(title.len());
+    let mut depth = 0usize;
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => out.push(c),
+            _ => {},
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_first_synced() {
+        let results: Vec<LrclibLyrics> = serde_json::from_str(
+            r#"[
+                {"id": 1, "trackName": "Song 2", "syncedLyrics": null, "plainLyrics": "Woo-hoo"},
+                {"id": 2, "trackName": "Song 2", "syncedLyrics": " "},
+                {"id": 3, "trackName": "Song 2", "syncedLyrics": "[00:01.00]Woo-hoo"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(first_synced(results), Some("[00:01.00]Woo-hoo".to_string()));
+        let plain: Vec<LrclibLyrics> =
+            serde_json::from_str(r#"[{"id": 1, "plainLyrics": "Woo-hoo"}]"#).unwrap();
+        assert_eq!(first_synced(plain), None);
+    }
+
+    #[test]
+    fn test_search_title() {
+        assert_eq!(
+            search_title("Blur - Song 2 (Official Music Video) [HD]"),
+            "Blur - Song 2"
+        );
+        assert_eq!(search_title("Song 2"), "Song 2");
+    }
+}
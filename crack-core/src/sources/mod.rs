@@ -1,4 +1,6 @@
+pub mod lrclib;
 pub mod rusty_ytdl;
 pub mod spotify;
+pub mod tts;
 pub mod youtube;
 pub mod ytdl;
@@ -0,0 +1,164 @@
+use crate::errors::CrackedError;
+use crate::guild::settings::TtsSettings;
+use crate::utils::TrackData;
+use songbird::input::{AudioStream, Input, LiveInput};
+use songbird::{tracks::Track, Call};
+use std::io::Cursor;
+use std::process::Stdio;
+use symphonia::core::{io::MediaSource, probe::Hint};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+const TTS_COMMAND: &str = "espeak-ng";
+/// Longest announcement, in characters. The rest is cut off.
+pub const MAX_TTS_TEXT: usize = 200;
+/// Longest voice name.
+const MAX_VOICE_NAME: usize = 32;
+
+/// A local text-to-speech engine, run once per announcement.
+///
+/// By default, this uses espeak-ng, which reads the text from stdin and writes
+/// a WAV to stdout.
+#[derive(Clone, Debug)]
+pub struct Tts {
+    program: &'static str,
+    voice: String,
+}
+
+impl Tts {
+    /// Creates a TTS engine speaking with `voice`, using "espeak-ng".
+    #[must_use]
+    pub fn new(voice: String) -> Self {
+        Self::new_tts_like(TTS_COMMAND, voice)
+    }
+
+    /// Creates a TTS engine as in [`new`], using `program`, which has to take
+    /// the same arguments.
+    ///
+    /// [`new`]: Self::new
+    #[must_use]
+    pub fn new_tts_like(program: &'static str, voice: String) -> Self {
+        Self { program, voice }
+    }
+
+    fn args(&self) -> [&str; 4] {
+        ["-v", &self.voice, "--stdin", "--stdout"]
+    }
+
+    /// Speak `text`, returning a WAV.
+    pub async fn synthesize(&self, text: &str) -> Result<Vec<u8>, CrackedError> {
+        let mut child = Command::new(self.program)
+            .args(self.args())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(CrackedError::CommandFailed(
+                self.program,
+                output.status,
+                stderr.into(),
+            ));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Whether a voice name is safe to hand to the engine.
+pub fn valid_voice(voice: &str) -> bool {
+    !voice.is_empty()
+        && voice.len() <= MAX_VOICE_NAME
+        && voice
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '/'))
+        && !voice.starts_with('-')
+}
+
+/// Make text fit to be spoken: links and Discord markup like mentions are
+/// dropped, whitespace is collapsed, and it's cut to [`MAX_TTS_TEXT`]. `None` if
+/// there's nothing left to say.
+pub fn clean_tts_text(text: &str) -> Option<String> {
+    let words = text
+        .split_whitespace()
+        .filter(|word| !word.starts_with("http://") && !word.starts_with("https://"))
+        .filter(|word| !(word.starts_with('<') && word.ends_with('>')))
+        .map(|word| word.trim_matches(|c| matches!(c, '*' | '_' | '~' | '`' | '|')))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    let text = words.join(" ");
+    let text = match text.char_indices().nth(MAX_TTS_TEXT) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// A spoken WAV, as something to play in a call.
+pub fn tts_input(wav: Vec<u8>) -> Input {
+    let mut hint = Hint::new();
+    hint.with_extension("wav");
+    let stream = AudioStream {
+        input: Box::new(Cursor::new(wav)) as Box<dyn MediaSource>,
+        hint: Some(hint),
+    };
+    Input::Live(LiveInput::Raw(stream), None)
+}
+
+/// Speak `text` in a call, over whatever's playing, if TTS is on.
+pub async fn speak(
+    call: &tokio::sync::Mutex<Call>,
+    settings: &TtsSettings,
+    text: &str,
+) -> Result<(), CrackedError> {
+    let Some(text) = clean_tts_text(text).filter(|_| settings.enabled) else {
+        return Ok(());
+    };
+    let wav = Tts::new(settings.voice.clone()).synthesize(&text).await?;
+    let track = Track::new_with_data(tts_input(wav), TrackData::overlay()).volume(settings.volume);
+    let _ = call.lock().await.play(track);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_valid_voice() {
+        assert!(valid_voice("en-us"));
+        assert!(valid_voice("mb/mb-en1"));
+        assert!(valid_voice("en+f3"));
+        assert!(!valid_voice(""));
+        assert!(!valid_voice("--stdout"));
+        assert!(!valid_voice("en us"));
+        assert!(!valid_voice(&"a".repeat(MAX_VOICE_NAME + 1)));
+    }
+
+    #[test]
+    fn test_clean_tts_text() {
+        assert_eq!(
+            clean_tts_text("  **Now   playing** Hello <@123> https://x.y/z "),
+            Some("Now playing Hello".to_string())
+        );
+        assert_eq!(clean_tts_text("<:pog:1> https://a.b"), None);
+        assert_eq!(clean_tts_text(""), None);
+        let long = "é".repeat(MAX_TTS_TEXT + 10);
+        assert_eq!(
+            clean_tts_text(&long).map(|t| t.chars().count()),
+            Some(MAX_TTS_TEXT)
+        );
+    }
+
+    #[test]
+    fn test_args() {
+        let tts = Tts::new("en-gb".to_string());
+        assert_eq!(tts.args(), ["-v", "en-gb", "--stdin", "--stdout"]);
+    }
+}
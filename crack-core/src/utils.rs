@@ -4,13 +4,15 @@ use crate::http_utils::SendMessageParams;
 use crate::metrics::COMMAND_EXECUTIONS;
 use crate::poise_ext::PoiseContextExt;
 use crate::{
-    db::Playlist,
+    db::{BookmarkListing, Playlist, ScheduledPlayback},
     messaging::{
         interface::create_nav_btns,
         message::CrackedMessage,
         messages::{
-            INVITE_LINK_TEXT_SHORT, INVITE_URL, PLAYLISTS, PLAYLIST_EMPTY, PLAYLIST_LIST_EMPTY,
-            QUEUE_PAGE, QUEUE_PAGE_OF, VOTE_TOPGG_LINK_TEXT_SHORT, VOTE_TOPGG_URL,
+            BOOKMARKS, BOOKMARKS_EMPTY, INVITE_LINK_TEXT_SHORT, INVITE_URL, PLAYLISTS,
+            PLAYLIST_EMPTY, PLAYLIST_LIST_EMPTY, PLAYLIST_SERVER_TAG, QUEUE_NO_TITLE, QUEUE_PAGE,
+            QUEUE_PAGE_OF, SCHEDULED_JOBS, SCHEDULE_DAYS, SCHEDULE_LIST_EMPTY, SCHEDULE_REPEATS,
+            VOTE_TOPGG_LINK_TEXT_SHORT, VOTE_TOPGG_URL,
         },
     },
     Context as CrackContext, CrackedError, CrackedResult, Data, Error,
@@ -392,6 +394,8 @@ pub async fn edit_embed_response_poise(
 pub struct TrackData {
     pub user_id: Arc<RwLock<Option<UserId>>>,
     pub aux_metadata: Arc<RwLock<Option<AuxMetadata>>>,
+    /// Played over the queue, like a soundboard clip, rather than from it.
+    pub overlay: bool,
 }
 
 unsafe impl Send for TrackData {}
@@ -402,6 +406,15 @@ impl TrackData {
         Arc::new(Self {
             user_id: Arc::new(RwLock::new(Some(UserId::new(1)))),
             aux_metadata: Arc::new(RwLock::new(None)),
+            overlay: false,
+        })
+    }
+
+    /// Data for a track played over the queue.
+    pub fn overlay() -> Arc<Self> {
+        Arc::new(Self {
+            overlay: true,
+            ..Default::default()
         })
     }
 
@@ -409,6 +422,7 @@ impl TrackData {
         Arc::new(Self {
             user_id: Arc::new(RwLock::new(Some(user_id))),
             aux_metadata: Arc::clone(&self.aux_metadata),
+            overlay: self.overlay,
         })
     }
 
@@ -416,6 +430,7 @@ impl TrackData {
         Arc::new(Self {
             user_id: Arc::clone(&self.user_id),
             aux_metadata: Arc::new(RwLock::new(Some(md))),
+            overlay: self.overlay,
         })
     }
 }
@@ -536,10 +551,15 @@ pub async fn build_playlist_list_embed(playlists: &[Playlist], page: usize) -> C
             let _ = writeln!(
                 description,
                 // "`{}.` [{}]({})",
-                "`{}.` {} ({})",
+                "`{}.` {} ({}){}",
                 i + start_idx + 1,
                 playlist.name,
-                playlist.id
+                playlist.id,
+                if playlist.is_guild_playlist() {
+                    PLAYLIST_SERVER_TAG
+                } else {
+                    ""
+                },
             );
         }
 
@@ -558,6 +578,74 @@ pub async fn build_playlist_list_embed(playlists: &[Playlist], page: usize) -> C
     //     )))
 }
 
+pub fn build_bookmark_list_embed(bookmarks: &[BookmarkListing], page: usize) -> CreateEmbed {
+    let content = if !bookmarks.is_empty() {
+        let start_idx = EMBED_PAGE_SIZE * page;
+        let mut description = String::new();
+
+        for bookmark in bookmarks.iter().skip(start_idx).take(EMBED_PAGE_SIZE) {
+            let title = bookmark.title.as_deref().unwrap_or(QUEUE_NO_TITLE);
+            let position = get_human_readable_timestamp(Some(Duration::from_millis(
+                bookmark.position_ms.max(0) as u64,
+            )));
+            let _ = match &bookmark.source_url {
+                Some(url) => write!(
+                    description,
+                    "`#{}` [{}]({}) @ `{}`",
+                    bookmark.id, title, url, position
+                ),
+                None => write!(description, "`#{}` {} @ `{}`", bookmark.id, title, position),
+            };
+            match &bookmark.note {
+                Some(note) => {
+                    let _ = writeln!(description, " — {}", note);
+                },
+                None => {
+                    let _ = writeln!(description);
+                },
+            }
+        }
+
+        description
+    } else {
+        BOOKMARKS_EMPTY.to_string()
+    };
+
+    CreateEmbed::default().title(BOOKMARKS).description(content)
+}
+
+/// Build the embed listing a guild's scheduled jobs, each with its playlist name if any.
+pub fn build_schedule_list_embed(jobs: &[(ScheduledPlayback, Option<String>)]) -> CreateEmbed {
+    let content = if !jobs.is_empty() {
+        let mut description = String::new();
+
+        for (job, playlist) in jobs.iter() {
+            let run_at = job.run_at_utc().timestamp();
+            let _ = write!(description, "`#{}` **{}**", job.id, job.action);
+            if let Some(playlist) = playlist {
+                let _ = write!(description, " {}", playlist);
+            }
+            let _ = write!(description, " <t:{}:F> (<t:{}:R>)", run_at, run_at);
+            if let Some(days) = job.repeat_days {
+                let _ = write!(
+                    description,
+                    " {} {} {}",
+                    SCHEDULE_REPEATS, days, SCHEDULE_DAYS
+                );
+            }
+            let _ = writeln!(description);
+        }
+
+        description
+    } else {
+        SCHEDULE_LIST_EMPTY.to_string()
+    };
+
+    CreateEmbed::default()
+        .title(SCHEDULED_JOBS)
+        .description(content)
+}
+
 pub async fn build_tracks_embed_metadata(
     playlist_name: String,
     metadata_arr: &[NewAuxMetadata],
@@ -0,0 +1,102 @@
+use crate::guild::settings::VerificationMethod;
+use rand::Rng;
+
+/// Characters used in captchas, leaving out look-alikes such as `0`/`O` and `1`/`I`.
+const CAPTCHA_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+pub const CAPTCHA_LEN: usize = 6;
+/// Wrong answers allowed before a member has to ask a moderator.
+pub const MAX_ATTEMPTS: i32 = 5;
+
+/// Generate a captcha code.
+pub fn generate_captcha<R: Rng + ?Sized>(rng: &mut R) -> String {
+    (0..CAPTCHA_LEN)
+        .map(|_| CAPTCHA_ALPHABET[rng.gen_range(0..CAPTCHA_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Show a captcha code so it can't be copied and pasted as is, e.g. `K·7·P·2·X·9`.
+pub fn render_captcha(code: &str) -> String {
+    code.chars().map(String::from).collect::<Vec<_>>().join("·")
+}
+
+/// The answer to store for a new challenge. Only captchas have one per member.
+pub fn answer_for<R: Rng + ?Sized>(method: VerificationMethod, rng: &mut R) -> Option<String> {
+    match method {
+        VerificationMethod::Captcha => Some(generate_captcha(rng)),
+        VerificationMethod::Password | VerificationMethod::Rules => None,
+    }
+}
+
+/// Check a member's answer. Captchas ignore case, spaces and the separators they're
+/// shown with; passwords only ignore surrounding whitespace.
+pub fn check_answer(
+    method: VerificationMethod,
+    expected: Option<&str>,
+    password: Option<&str>,
+    input: &str,
+) -> bool {
+    match method {
+        VerificationMethod::Captcha => {
+            expected.is_some_and(|code| normalize_captcha(code) == normalize_captcha(input))
+        },
+        VerificationMethod::Password => password.is_some_and(|p| p.trim() == input.trim()),
+        VerificationMethod::Rules => true,
+    }
+}
+
+fn normalize_captcha(input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_generate_captcha() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let code = generate_captcha(&mut rng);
+        assert_eq!(code.len(), CAPTCHA_LEN);
+        assert!(code.bytes().all(|c| CAPTCHA_ALPHABET.contains(&c)));
+        // Seeded generation is repeatable.
+        assert_eq!(generate_captcha(&mut StdRng::seed_from_u64(7)), code);
+        assert_ne!(generate_captcha(&mut rng), code);
+    }
+
+    #[test]
+    fn test_render_captcha() {
+        assert_eq!(render_captcha("K7P2X9"), "K·7·P·2·X·9");
+    }
+
+    #[test]
+    fn test_check_captcha() {
+        let method = VerificationMethod::Captcha;
+        assert!(check_answer(method, Some("K7P2X9"), None, "K7P2X9"));
+        assert!(check_answer(method, Some("K7P2X9"), None, " k7p 2x9 "));
+        assert!(check_answer(method, Some("K7P2X9"), None, "K·7·P·2·X·9"));
+        assert!(!check_answer(method, Some("K7P2X9"), None, "K7P2X8"));
+        assert!(!check_answer(method, None, None, ""));
+    }
+
+    #[test]
+    fn test_check_password_and_rules() {
+        let method = VerificationMethod::Password;
+        assert!(check_answer(method, None, Some("hunter2"), " hunter2 "));
+        assert!(!check_answer(method, None, Some("hunter2"), "Hunter2"));
+        assert!(!check_answer(method, None, None, "hunter2"));
+        assert!(check_answer(VerificationMethod::Rules, None, None, ""));
+    }
+
+    #[test]
+    fn test_answer_for() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!(answer_for(VerificationMethod::Captcha, &mut rng).is_some());
+        assert!(answer_for(VerificationMethod::Password, &mut rng).is_none());
+        assert!(answer_for(VerificationMethod::Rules, &mut rng).is_none());
+    }
+}
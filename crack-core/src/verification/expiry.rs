@@ -0,0 +1,171 @@
+use crate::{
+    clock::PollJob,
+    db::{CaseAction, ModerationCase, VerificationChallenge},
+    messaging::messages::VERIFY_KICK_REASON,
+    moderation::record_case,
+    CrackedError, Data,
+};
+use chrono::{DateTime, Utc};
+use serenity::all::{Context as SerenityContext, GuildId, UserId};
+use sqlx::PgPool;
+use std::{future::Future, sync::Arc, time::Duration};
+
+/// How often to check for members whose verification time ran out.
+pub const VERIFICATION_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Removes a member who didn't pass verification in time.
+pub trait UnverifiedKicker: Send + Sync + 'static {
+    fn kick(
+        &self,
+        challenge: &VerificationChallenge,
+    ) -> impl Future<Output = Result<(), CrackedError>> + Send;
+}
+
+/// Kicks unverified members from the database once their time runs out. Drive
+/// it with a [`Poller`](crate::clock::Poller).
+pub struct VerificationKicks<K: UnverifiedKicker> {
+    pool: PgPool,
+    kicker: K,
+}
+
+impl<K: UnverifiedKicker> VerificationKicks<K> {
+    pub fn new(pool: PgPool, kicker: K) -> Self {
+        Self { pool, kicker }
+    }
+}
+
+impl<K: UnverifiedKicker> PollJob for VerificationKicks<K> {
+    const NAME: &'static str = "Verification kick";
+    const POLL_INTERVAL: Duration = VERIFICATION_POLL_INTERVAL;
+
+    /// Kick every member whose time ran out by now. A member is marked kicked even
+    /// if kicking fails, e.g. because they already left, so it isn't retried
+    /// forever. Returns the number of members handled.
+    async fn tick(&self, now: DateTime<Utc>) -> Result<usize, CrackedError> {
+        let now = now.naive_utc();
+        let expired = VerificationChallenge::get_expired(&self.pool, now).await?;
+        for challenge in expired.iter() {
+            if let Err(e) = self.kicker.kick(challenge).await {
+                tracing::warn!(
+                    "Failed to kick unverified user {} from {}: {}",
+                    challenge.user_id,
+                    challenge.guild_id,
+                    e
+                );
+            }
+            // Keep going so one bad row doesn't hold up the other expired members.
+            if let Err(e) = VerificationChallenge::mark_kicked(&self.pool, challenge.id, now).await
+            {
+                tracing::error!("Failed to mark verification {} kicked: {}", challenge.id, e);
+            }
+        }
+        Ok(expired.len())
+    }
+}
+
+/// Kicks unverified members on Discord and records the kick as a case by the bot.
+#[derive(Clone)]
+pub struct DiscordUnverifiedKicker {
+    pub serenity_ctx: Arc<SerenityContext>,
+    pub data: Arc<Data>,
+}
+
+impl UnverifiedKicker for DiscordUnverifiedKicker {
+    async fn kick(&self, challenge: &VerificationChallenge) -> Result<(), CrackedError> {
+        let http = &self.serenity_ctx.http;
+        let guild_id = GuildId::new(challenge.guild_id as u64);
+        let user_id = UserId::new(challenge.user_id as u64);
+        guild_id
+            .kick(http, user_id, Some(VERIFY_KICK_REASON))
+            .await?;
+
+        let bot_id = self.serenity_ctx.cache.current_user().id;
+        let case = ModerationCase::new(
+            challenge.guild_id,
+            CaseAction::Kick,
+            bot_id.get() as i64,
+            challenge.user_id,
+            Some(VERIFY_KICK_REASON.to_string()),
+            None,
+            chrono::Utc::now(),
+        );
+        record_case(http, &self.data, case).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        clock::{Clock, MockClock, Poller},
+        db::GuildEntity,
+    };
+    use ::serenity::small_fixed_array::FixedString;
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./test_migrations");
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    /// Records kicks instead of touching Discord.
+    #[derive(Clone, Default)]
+    struct RecordingKicker {
+        kicked: Arc<Mutex<Vec<i64>>>,
+    }
+
+    impl UnverifiedKicker for RecordingKicker {
+        async fn kick(&self, challenge: &VerificationChallenge) -> Result<(), CrackedError> {
+            self.kicked.lock().unwrap().push(challenge.user_id);
+            Ok(())
+        }
+    }
+
+    #[sqlx::test(migrator = "MIGRATOR")]
+    async fn test_kicks_unverified_members(pool: PgPool) -> Result<(), CrackedError> {
+        GuildEntity::get_or_create(
+            &pool,
+            1,
+            FixedString::from_str("test").unwrap(),
+            "r!".to_string(),
+        )
+        .await?;
+        let start = utc("2024-01-05T20:00:00Z");
+        let clock = MockClock::new(start);
+        let kicker = RecordingKicker::default();
+        let poll_interval = Duration::from_secs(60 * 60);
+        let scheduler = Poller::new(
+            clock.clone(),
+            VerificationKicks::new(pool.clone(), kicker.clone()),
+        )
+        .with_poll_interval(poll_interval);
+
+        let in_hours = |h: i64| Some((start + chrono::Duration::hours(h)).naive_utc());
+        VerificationChallenge::start(&pool, 1, 10, None, in_hours(1)).await?;
+        let verified = VerificationChallenge::start(&pool, 1, 20, None, in_hours(1)).await?;
+        VerificationChallenge::mark_verified(&pool, verified.id, start.naive_utc()).await?;
+        VerificationChallenge::start(&pool, 1, 30, None, in_hours(3)).await?;
+        // Guilds that never kick don't set a deadline.
+        VerificationChallenge::start(&pool, 1, 40, None, None).await?;
+
+        assert_eq!(scheduler.tick().await?, 0);
+
+        clock.sleep(poll_interval).await;
+        scheduler.tick().await?;
+        assert_eq!(*kicker.kicked.lock().unwrap(), vec![10]);
+
+        clock.sleep(Duration::from_secs(2 * 60 * 60)).await;
+        scheduler.tick().await?;
+        assert_eq!(*kicker.kicked.lock().unwrap(), vec![10, 30]);
+
+        // Nobody is kicked twice.
+        clock.sleep(Duration::from_secs(24 * 60 * 60)).await;
+        assert_eq!(scheduler.tick().await?, 0);
+        Ok(())
+    }
+}
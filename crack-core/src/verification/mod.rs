@@ -0,0 +1,354 @@
+pub mod challenge;
+pub mod expiry;
+
+pub use challenge::*;
+pub use expiry::*;
+
+use crate::{
+    db::VerificationChallenge,
+    guild::{
+        operations::GuildSettingsOperations,
+        settings::{VerificationDelivery, VerificationMethod, VerificationSettings},
+    },
+    messaging::messages::{
+        VERIFY_ALREADY, VERIFY_BUTTON, VERIFY_CAPTCHA_LABEL, VERIFY_FAILED, VERIFY_NOT_PENDING,
+        VERIFY_PASSWORD_LABEL, VERIFY_PROMPT, VERIFY_RULES_PROMPT, VERIFY_SUCCESS,
+        VERIFY_TOO_MANY_ATTEMPTS,
+    },
+    CrackedError, Data,
+};
+use serenity::all::{
+    ActionRowComponent, ButtonStyle, ChannelId, ComponentInteraction, Context as SerenityContext,
+    CreateActionRow, CreateButton, CreateInputText, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateModal, GuildId, InputTextStyle,
+    Interaction, Member, Mentionable, ModalInteraction, RoleId, UserId,
+};
+use std::borrow::Cow;
+
+/// Custom id prefix of the verify button and answer modal. The guild id follows,
+/// so challenges sent by DM know which guild they're for.
+pub const VERIFY_CUSTOM_ID: &str = "verify";
+const VERIFY_INPUT_ID: &str = "verify_answer";
+
+/// The custom id of a guild's verify button and answer modal.
+pub fn verify_custom_id(guild_id: GuildId) -> String {
+    format!("{}:{}", VERIFY_CUSTOM_ID, guild_id)
+}
+
+/// The guild a verify custom id is for, or `None` if it isn't one.
+pub fn parse_verify_custom_id(custom_id: &str) -> Option<GuildId> {
+    let (prefix, guild_id) = custom_id.split_once(':')?;
+    if prefix != VERIFY_CUSTOM_ID {
+        return None;
+    }
+    guild_id
+        .parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .map(GuildId::new)
+}
+
+/// Put a new member through verification: give them the unverified role, store
+/// their challenge and send it. Returns whether the guild verifies members, in
+/// which case the welcome auto role waits until they pass.
+#[cfg(not(tarpaulin_include))]
+pub async fn start_verification(
+    ctx: &SerenityContext,
+    data: &Data,
+    member: &Member,
+) -> Result<bool, CrackedError> {
+    if member.user.bot() {
+        return Ok(false);
+    }
+    let Some(verification) = data
+        .get_guild_settings(member.guild_id)
+        .await
+        .and_then(|settings| settings.verification_settings)
+        .filter(|verification| verification.enabled)
+    else {
+        return Ok(false);
+    };
+    let pool = data
+        .database_pool
+        .as_ref()
+        .ok_or(CrackedError::NoDatabasePool)?;
+
+    if let Some(role_id) = verification.unverified_role {
+        member
+            .add_role(&ctx.http, RoleId::new(role_id), Some(VERIFY_PROMPT))
+            .await?;
+    }
+    let answer = answer_for(verification.method, &mut rand::thread_rng());
+    let expires_at = (verification.kick_after_hours > 0).then(|| {
+        (chrono::Utc::now() + chrono::Duration::hours(verification.kick_after_hours as i64))
+            .naive_utc()
+    });
+    VerificationChallenge::start(
+        pool,
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64,
+        answer,
+        expires_at,
+    )
+    .await?;
+
+    send_challenge(ctx, &verification, member).await?;
+    Ok(true)
+}
+
+/// Send the verify button, by DM or in the verification channel.
+#[cfg(not(tarpaulin_include))]
+async fn send_challenge(
+    ctx: &SerenityContext,
+    verification: &VerificationSettings,
+    member: &Member,
+) -> Result<(), CrackedError> {
+    let mut content = format!("{} {}", member.user.mention(), VERIFY_PROMPT);
+    if let Some(rules) = &verification.rules {
+        content.push_str("\n\n");
+        content.push_str(rules);
+    }
+    if verification.method == VerificationMethod::Rules {
+        content.push_str("\n\n");
+        content.push_str(VERIFY_RULES_PROMPT);
+    }
+    let button = CreateButton::new(verify_custom_id(member.guild_id))
+        .style(ButtonStyle::Primary)
+        .label(VERIFY_BUTTON);
+    let message = CreateMessage::new()
+        .content(content)
+        .components(vec![CreateActionRow::Buttons(Cow::Owned(vec![button]))]);
+
+    if verification.delivery == VerificationDelivery::Dm {
+        match member
+            .user
+            .id
+            .direct_message(&ctx.http, message.clone())
+            .await
+        {
+            Ok(_) => return Ok(()),
+            Err(e) => tracing::info!(
+                "Can't DM {} a verification challenge, using the channel: {}",
+                member.user.id,
+                e
+            ),
+        }
+    }
+    let channel_id = verification
+        .channel_id
+        .map(ChannelId::new)
+        .ok_or(CrackedError::Other("No verification channel set."))?;
+    channel_id.send_message(&ctx.http, message).await?;
+    Ok(())
+}
+
+/// Handle presses of the verify button and submitted answers. Returns whether the
+/// interaction was for verification.
+#[cfg(not(tarpaulin_include))]
+pub async fn handle_verify_interaction(
+    ctx: &SerenityContext,
+    data: &Data,
+    interaction: &Interaction,
+) -> Result<bool, CrackedError> {
+    match interaction {
+        Interaction::Component(int) => match parse_verify_custom_id(&int.data.custom_id) {
+            Some(guild_id) => on_verify_button(ctx, data, int, guild_id)
+                .await
+                .map(|_| true),
+            None => Ok(false),
+        },
+        Interaction::Modal(int) => match parse_verify_custom_id(&int.data.custom_id) {
+            Some(guild_id) => on_verify_answer(ctx, data, int, guild_id)
+                .await
+                .map(|_| true),
+            None => Ok(false),
+        },
+        _ => Ok(false),
+    }
+}
+
+/// Load a member's pending challenge, or the reason they can't answer one.
+#[cfg(not(tarpaulin_include))]
+async fn pending_challenge(
+    data: &Data,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<Result<(VerificationSettings, VerificationChallenge), &'static str>, CrackedError> {
+    let pool = data
+        .database_pool
+        .as_ref()
+        .ok_or(CrackedError::NoDatabasePool)?;
+    let Some(verification) = data
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.verification_settings)
+    else {
+        return Ok(Err(VERIFY_NOT_PENDING));
+    };
+    let challenge =
+        VerificationChallenge::get(pool, guild_id.get() as i64, user_id.get() as i64).await?;
+    Ok(match challenge {
+        None => Err(VERIFY_NOT_PENDING),
+        Some(challenge) if challenge.is_verified() => Err(VERIFY_ALREADY),
+        Some(challenge) if challenge.kicked_at.is_some() => Err(VERIFY_NOT_PENDING),
+        Some(challenge) if challenge.attempts >= MAX_ATTEMPTS => Err(VERIFY_TOO_MANY_ATTEMPTS),
+        Some(challenge) => Ok((verification, challenge)),
+    })
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn on_verify_button(
+    ctx: &SerenityContext,
+    data: &Data,
+    int: &ComponentInteraction,
+    guild_id: GuildId,
+) -> Result<(), CrackedError> {
+    let (verification, challenge) = match pending_challenge(data, guild_id, int.user.id).await? {
+        Ok(pending) => pending,
+        Err(reason) => {
+            return int
+                .create_response(&ctx.http, ephemeral(reason))
+                .await
+                .map_err(Into::into)
+        },
+    };
+
+    let label = match verification.method {
+        VerificationMethod::Rules => {
+            complete_verification(ctx, data, guild_id, int.user.id, &verification, &challenge)
+                .await?;
+            return int
+                .create_response(&ctx.http, ephemeral(VERIFY_SUCCESS))
+                .await
+                .map_err(Into::into);
+        },
+        VerificationMethod::Captcha => format!(
+            "{} {}",
+            VERIFY_CAPTCHA_LABEL,
+            render_captcha(challenge.answer.as_deref().unwrap_or_default())
+        ),
+        VerificationMethod::Password => VERIFY_PASSWORD_LABEL.to_string(),
+    };
+    let input = CreateInputText::new(InputTextStyle::Short, label, VERIFY_INPUT_ID);
+    let modal = CreateModal::new(verify_custom_id(guild_id), VERIFY_BUTTON)
+        .components(vec![CreateActionRow::InputText(input)]);
+    int.create_response(&ctx.http, CreateInteractionResponse::Modal(modal))
+        .await?;
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+async fn on_verify_answer(
+    ctx: &SerenityContext,
+    data: &Data,
+    int: &ModalInteraction,
+    guild_id: GuildId,
+) -> Result<(), CrackedError> {
+    let (verification, challenge) = match pending_challenge(data, guild_id, int.user.id).await? {
+        Ok(pending) => pending,
+        Err(reason) => {
+            return int
+                .create_response(&ctx.http, ephemeral(reason))
+                .await
+                .map_err(Into::into)
+        },
+    };
+    let input = int
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(it) if it.custom_id == VERIFY_INPUT_ID => {
+                it.value.as_ref().map(|v| v.to_string())
+            },
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    if check_answer(
+        verification.method,
+        challenge.answer.as_deref(),
+        verification.password.as_deref(),
+        &input,
+    ) {
+        complete_verification(ctx, data, guild_id, int.user.id, &verification, &challenge).await?;
+        return int
+            .create_response(&ctx.http, ephemeral(VERIFY_SUCCESS))
+            .await
+            .map_err(Into::into);
+    }
+
+    // A fresh captcha for every try, so codes can't be guessed one by one.
+    let answer = answer_for(verification.method, &mut rand::thread_rng());
+    let pool = data
+        .database_pool
+        .as_ref()
+        .ok_or(CrackedError::NoDatabasePool)?;
+    let challenge = VerificationChallenge::record_failure(pool, challenge.id, answer).await?;
+    let reason = if challenge.attempts >= MAX_ATTEMPTS {
+        VERIFY_TOO_MANY_ATTEMPTS
+    } else {
+        VERIFY_FAILED
+    };
+    int.create_response(&ctx.http, ephemeral(reason))
+        .await
+        .map_err(Into::into)
+}
+
+/// Swap the unverified role for the welcome auto role and mark the member verified.
+#[cfg(not(tarpaulin_include))]
+async fn complete_verification(
+    ctx: &SerenityContext,
+    data: &Data,
+    guild_id: GuildId,
+    user_id: UserId,
+    verification: &VerificationSettings,
+    challenge: &VerificationChallenge,
+) -> Result<(), CrackedError> {
+    let http = &ctx.http;
+    if let Some(role_id) = verification.unverified_role {
+        guild_id
+            .remove_member_role(http, user_id, RoleId::new(role_id), Some(VERIFY_SUCCESS))
+            .await?;
+    }
+    let auto_role = data
+        .get_guild_settings(guild_id)
+        .await
+        .and_then(|settings| settings.welcome_settings)
+        .and_then(|welcome| welcome.auto_role);
+    if let Some(role_id) = auto_role {
+        guild_id
+            .add_member_role(http, user_id, RoleId::new(role_id), Some(VERIFY_SUCCESS))
+            .await?;
+    }
+    let pool = data
+        .database_pool
+        .as_ref()
+        .ok_or(CrackedError::NoDatabasePool)?;
+    VerificationChallenge::mark_verified(pool, challenge.id, chrono::Utc::now().naive_utc()).await
+}
+
+fn ephemeral(content: &str) -> CreateInteractionResponse<'_> {
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verify_custom_id() {
+        let guild_id = GuildId::new(123);
+        assert_eq!(verify_custom_id(guild_id), "verify:123");
+        assert_eq!(parse_verify_custom_id("verify:123"), Some(guild_id));
+        assert_eq!(parse_verify_custom_id("verify:0"), None);
+        assert_eq!(parse_verify_custom_id("verify:abc"), None);
+        assert_eq!(parse_verify_custom_id("queue:123"), None);
+        assert_eq!(parse_verify_custom_id("verify"), None);
+    }
+}
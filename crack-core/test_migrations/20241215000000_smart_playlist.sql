@@ -0,0 +1,8 @@
+-- Rule definitions for smart playlists. A smart playlist is a regular playlist
+-- row with a rule attached, evaluated against play_log at play time.
+CREATE TABLE IF NOT EXISTS smart_playlist (
+    playlist_id INTEGER PRIMARY KEY NOT NULL,
+    rule JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_smart_playlist_playlist FOREIGN KEY (playlist_id) REFERENCES playlist(id) ON DELETE CASCADE
+);
@@ -0,0 +1,26 @@
+-- Moderation actions, numbered per guild. Timed actions (tempban, tempmute)
+-- carry an expires_at and are reversed by the bot once it passes.
+ALTER TABLE log_settings ADD COLUMN mod_log_channel BIGINT;
+
+CREATE TABLE IF NOT EXISTS moderation_case (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    case_number BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    actor_id BIGINT NOT NULL,
+    target_id BIGINT NOT NULL,
+    reason TEXT,
+    duration_seconds BIGINT,
+    evidence TEXT[] NOT NULL DEFAULT array[]::TEXT[],
+    expires_at TIMESTAMP,
+    reversed_at TIMESTAMP,
+    log_channel_id BIGINT,
+    log_message_id BIGINT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_moderation_case_guild FOREIGN KEY (guild_id) REFERENCES guild(id),
+    UNIQUE (guild_id, case_number)
+);
+
+CREATE INDEX moderation_case_target_idx ON moderation_case (guild_id, target_id);
+CREATE INDEX moderation_case_expires_at_idx ON moderation_case (expires_at)
+    WHERE reversed_at IS NULL;
@@ -0,0 +1,32 @@
+-- Member verification. New members get a restricted role and a challenge
+-- (captcha, password or rules) and receive the welcome auto role once they pass.
+-- Members who haven't passed by expires_at are kicked.
+CREATE TABLE IF NOT EXISTS verification_settings (
+    guild_id BIGINT PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT false,
+    method TEXT NOT NULL DEFAULT 'captcha',
+    delivery TEXT NOT NULL DEFAULT 'channel',
+    channel_id BIGINT,
+    unverified_role_id BIGINT,
+    password TEXT,
+    rules TEXT,
+    kick_after_hours INT NOT NULL DEFAULT 24,
+    CONSTRAINT fk_verification_settings FOREIGN KEY (guild_id) REFERENCES guild_settings(guild_id)
+);
+
+CREATE TABLE IF NOT EXISTS verification_challenge (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    answer TEXT,
+    attempts INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP,
+    verified_at TIMESTAMP,
+    kicked_at TIMESTAMP,
+    CONSTRAINT fk_verification_challenge_guild FOREIGN KEY (guild_id) REFERENCES guild(id),
+    UNIQUE (guild_id, user_id)
+);
+
+CREATE INDEX verification_challenge_expires_at_idx ON verification_challenge (expires_at)
+    WHERE verified_at IS NULL AND kicked_at IS NULL;
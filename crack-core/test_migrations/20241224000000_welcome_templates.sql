@@ -0,0 +1,4 @@
+-- Leave messages, sent to the welcome channel. Welcome and leave messages are
+-- templates, see `messaging::template`.
+ALTER TABLE welcome_settings
+    ADD COLUMN leave_message TEXT;
@@ -0,0 +1,30 @@
+-- Raid protection: a burst of joins, new accounts or look-alike members within
+-- the window turns on lockdown. A threshold of 0 turns that check off.
+CREATE TABLE IF NOT EXISTS raid_settings (
+    guild_id BIGINT PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT false,
+    join_threshold INT NOT NULL DEFAULT 10,
+    window_seconds INT NOT NULL DEFAULT 10,
+    new_account_days INT NOT NULL DEFAULT 7,
+    new_account_threshold INT NOT NULL DEFAULT 5,
+    similar_threshold INT NOT NULL DEFAULT 4,
+    alert_channel_id BIGINT,
+    alert_role_id BIGINT,
+    CONSTRAINT fk_raid_settings FOREIGN KEY (guild_id) REFERENCES guild_settings(guild_id)
+);
+
+-- Lockdowns, with what they changed so ending one puts it back.
+CREATE TABLE IF NOT EXISTS lockdown (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    actor_id BIGINT,
+    previous_verification_level SMALLINT NOT NULL,
+    previous_everyone_permissions BIGINT NOT NULL,
+    invites_were_disabled BOOLEAN NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    ended_at TIMESTAMP,
+    CONSTRAINT fk_lockdown_guild FOREIGN KEY (guild_id) REFERENCES guild(id)
+);
+
+CREATE UNIQUE INDEX lockdown_active_idx ON lockdown (guild_id) WHERE ended_at IS NULL;
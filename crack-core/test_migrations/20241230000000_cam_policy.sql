@@ -0,0 +1,17 @@
+-- Camera policies: members in a policed voice channel with their camera off
+-- past the grace period are warned by DM, then server muted, then moved to
+-- another channel, then disconnected, one step every step_seconds. Members
+-- with an exempt role are left alone.
+CREATE TABLE IF NOT EXISTS cam_policy (
+    channel_id BIGINT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    grace_seconds INT NOT NULL DEFAULT 60,
+    step_seconds INT NOT NULL DEFAULT 60,
+    move_channel_id BIGINT,
+    exempt_roles BIGINT[] NOT NULL DEFAULT array[]::BIGINT[],
+    warn_message TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_cam_policy_guild FOREIGN KEY (guild_id) REFERENCES guild(id)
+);
+
+CREATE INDEX cam_policy_guild_id_idx ON cam_policy (guild_id);
@@ -0,0 +1,11 @@
+-- Per-guild case counters, so concurrent moderation actions get distinct case
+-- numbers. Bumping a guild's row locks it until the case is inserted.
+CREATE TABLE IF NOT EXISTS moderation_case_counter (
+    guild_id BIGINT PRIMARY KEY,
+    last_case_number BIGINT NOT NULL,
+    CONSTRAINT fk_moderation_case_counter_guild FOREIGN KEY (guild_id) REFERENCES guild(id)
+);
+
+INSERT INTO moderation_case_counter (guild_id, last_case_number)
+SELECT guild_id, MAX(case_number) FROM moderation_case GROUP BY guild_id
+ON CONFLICT (guild_id) DO NOTHING;
@@ -0,0 +1,7 @@
+-- Playlists owned by a guild ("house" playlists) rather than a single user.
+-- user_id is kept as the creator, guild_id marks the owning guild.
+ALTER TABLE playlist
+    ADD COLUMN guild_id BIGINT DEFAULT NULL,
+    ADD CONSTRAINT fk_playlist_guild FOREIGN KEY (guild_id) REFERENCES guild(id);
+
+CREATE INDEX playlist_name_guild_id_idx ON playlist ("name", guild_id);
@@ -0,0 +1,19 @@
+-- Saved moments in a track ("that drop at 2:13").
+CREATE TABLE IF NOT EXISTS bookmark (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    guild_id BIGINT,
+    metadata_id INTEGER NOT NULL,
+    position_ms BIGINT NOT NULL DEFAULT 0,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_bookmark_user FOREIGN KEY (user_id) REFERENCES "user"(id),
+    FOREIGN KEY (metadata_id) REFERENCES metadata(id)
+);
+
+CREATE INDEX bookmark_user_id_idx ON bookmark (user_id);
+
+-- Playlist entries can start and stop partway through a track.
+ALTER TABLE playlist_track
+    ADD COLUMN start_offset_ms BIGINT DEFAULT NULL,
+    ADD COLUMN end_offset_ms BIGINT DEFAULT NULL;
@@ -0,0 +1,21 @@
+-- Playback jobs scheduled ahead of time, e.g. weekly listening parties.
+-- run_at is stored in UTC, timezone is kept so repeats follow local time across DST.
+CREATE TABLE IF NOT EXISTS scheduled_playback (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    voice_channel_id BIGINT,
+    text_channel_id BIGINT,
+    user_id BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    playlist_id INTEGER,
+    run_at TIMESTAMP NOT NULL,
+    timezone TEXT NOT NULL DEFAULT 'UTC',
+    repeat_days INTEGER,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_scheduled_playback_guild FOREIGN KEY (guild_id) REFERENCES guild(id),
+    FOREIGN KEY (user_id) REFERENCES "user"(id),
+    FOREIGN KEY (playlist_id) REFERENCES playlist(id) ON DELETE CASCADE
+);
+
+CREATE INDEX scheduled_playback_run_at_idx ON scheduled_playback (run_at);
@@ -0,0 +1,5 @@
+-- Per-command allow/deny rules. Entries are category names or qualified command
+-- names, e.g. `music` or `playlist create`.
+ALTER TABLE permission_settings
+    ADD COLUMN allowed_commands TEXT[] NOT NULL DEFAULT array[]::TEXT[],
+    ADD COLUMN denied_commands TEXT[] NOT NULL DEFAULT array[]::TEXT[];
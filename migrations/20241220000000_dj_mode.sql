@@ -0,0 +1,5 @@
+-- DJ role and DJ mode. In DJ mode only DJs, admins and the requester of the
+-- current track control playback directly; everyone else votes.
+ALTER TABLE guild_settings
+    ADD COLUMN dj_role_id BIGINT,
+    ADD COLUMN dj_mode BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,15 @@
+-- Automod: per-user rate limits, duplicate messages, mass mentions, invite links
+-- and banned domains in chat. A limit of 0 turns that check off.
+CREATE TABLE IF NOT EXISTS automod_settings (
+    guild_id BIGINT PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT false,
+    rate_limit_messages INT NOT NULL DEFAULT 5,
+    rate_limit_seconds INT NOT NULL DEFAULT 5,
+    max_duplicates INT NOT NULL DEFAULT 3,
+    max_mentions INT NOT NULL DEFAULT 5,
+    block_invites BOOLEAN NOT NULL DEFAULT true,
+    block_banned_domains BOOLEAN NOT NULL DEFAULT true,
+    action TEXT NOT NULL DEFAULT 'delete',
+    timeout_seconds INT NOT NULL DEFAULT 600,
+    CONSTRAINT fk_automod_settings FOREIGN KEY (guild_id) REFERENCES guild_settings(guild_id)
+);
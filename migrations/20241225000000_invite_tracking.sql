@@ -0,0 +1,14 @@
+-- Which invite each member joined with, found by diffing invite uses before
+-- and after the join. Joins that can't be attributed have no code or inviter.
+CREATE TABLE IF NOT EXISTS invite_join (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    invite_code TEXT,
+    inviter_id BIGINT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_invite_join_guild FOREIGN KEY (guild_id) REFERENCES guild(id)
+);
+
+CREATE INDEX invite_join_inviter_idx ON invite_join (guild_id, inviter_id);
+CREATE INDEX invite_join_user_idx ON invite_join (guild_id, user_id);
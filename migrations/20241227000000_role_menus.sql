@@ -0,0 +1,27 @@
+-- Role menus: a message with buttons or a select menu members use to pick their
+-- own roles. The mode decides how picks combine: toggle, unique (one role),
+-- verify (roles are only given, never taken) or max (up to max_roles).
+CREATE TABLE IF NOT EXISTS role_menu (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    message_id BIGINT,
+    title TEXT NOT NULL,
+    mode TEXT NOT NULL DEFAULT 'toggle',
+    style TEXT NOT NULL DEFAULT 'buttons',
+    max_roles INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_role_menu_guild FOREIGN KEY (guild_id) REFERENCES guild(id)
+);
+
+CREATE INDEX role_menu_guild_id_idx ON role_menu (guild_id);
+
+CREATE TABLE IF NOT EXISTS role_menu_option (
+    menu_id BIGINT NOT NULL,
+    role_id BIGINT NOT NULL,
+    label TEXT NOT NULL,
+    emoji TEXT,
+    position INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (menu_id, role_id),
+    CONSTRAINT fk_role_menu_option_menu FOREIGN KEY (menu_id) REFERENCES role_menu(id) ON DELETE CASCADE
+);
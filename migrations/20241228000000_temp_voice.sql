@@ -0,0 +1,24 @@
+-- Temp voice: joining a hub channel creates a voice channel for the member,
+-- named from the hub's template. It's deleted once it's empty.
+CREATE TABLE IF NOT EXISTS temp_voice_hub (
+    channel_id BIGINT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name_template TEXT NOT NULL,
+    user_limit INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_temp_voice_hub_guild FOREIGN KEY (guild_id) REFERENCES guild(id)
+);
+
+CREATE INDEX temp_voice_hub_guild_id_idx ON temp_voice_hub (guild_id);
+
+CREATE TABLE IF NOT EXISTS temp_voice_channel (
+    channel_id BIGINT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    hub_id BIGINT NOT NULL,
+    owner_id BIGINT NOT NULL,
+    locked BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_temp_voice_channel_guild FOREIGN KEY (guild_id) REFERENCES guild(id)
+);
+
+CREATE INDEX temp_voice_channel_guild_id_idx ON temp_voice_channel (guild_id);
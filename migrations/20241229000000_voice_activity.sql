@@ -0,0 +1,22 @@
+-- Voice activity: a member's time in a voice channel, split into intervals
+-- wherever their channel or mute, deafen, stream or camera state changed.
+-- Intervals are sampled by polling, so ended_at is when they were last seen.
+CREATE TABLE IF NOT EXISTS voice_activity (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP NOT NULL,
+    self_mute BOOLEAN NOT NULL DEFAULT FALSE,
+    self_deaf BOOLEAN NOT NULL DEFAULT FALSE,
+    mute BOOLEAN NOT NULL DEFAULT FALSE,
+    deaf BOOLEAN NOT NULL DEFAULT FALSE,
+    streaming BOOLEAN NOT NULL DEFAULT FALSE,
+    video BOOLEAN NOT NULL DEFAULT FALSE,
+    afk BOOLEAN NOT NULL DEFAULT FALSE,
+    CONSTRAINT fk_voice_activity_guild FOREIGN KEY (guild_id) REFERENCES guild(id)
+);
+
+CREATE INDEX voice_activity_guild_ended_idx ON voice_activity (guild_id, ended_at);
+CREATE INDEX voice_activity_guild_user_idx ON voice_activity (guild_id, user_id);
@@ -0,0 +1,15 @@
+-- Soundboard: short clips members upload and play over the music. The audio is
+-- kept on disk; this is what's where.
+CREATE TABLE IF NOT EXISTS soundboard_clip (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    file_name TEXT NOT NULL,
+    duration_ms INT NOT NULL,
+    size_bytes INT NOT NULL,
+    uploaded_by BIGINT NOT NULL,
+    plays BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT fk_soundboard_clip_guild FOREIGN KEY (guild_id) REFERENCES guild(id),
+    CONSTRAINT soundboard_clip_guild_name_key UNIQUE (guild_id, name)
+);
@@ -0,0 +1,10 @@
+-- Text-to-speech announcements in voice, spoken by a local TTS engine.
+CREATE TABLE IF NOT EXISTS tts_settings (
+    guild_id BIGINT PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT false,
+    voice TEXT NOT NULL DEFAULT 'en-us',
+    volume FLOAT NOT NULL DEFAULT 1.0,
+    announce_tracks BOOLEAN NOT NULL DEFAULT true,
+    announce_joins BOOLEAN NOT NULL DEFAULT true,
+    CONSTRAINT fk_tts_settings FOREIGN KEY (guild_id) REFERENCES guild_settings(guild_id)
+);
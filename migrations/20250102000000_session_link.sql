@@ -0,0 +1,12 @@
+-- Linked listen-along sessions: a follower guild mirrors its leader's playback.
+-- Both sides have to agree before a link is active.
+CREATE TABLE IF NOT EXISTS session_link (
+    follower_guild_id BIGINT PRIMARY KEY,
+    leader_guild_id BIGINT NOT NULL,
+    leader_accepted BOOLEAN NOT NULL DEFAULT FALSE,
+    follower_accepted BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT session_link_not_self CHECK (leader_guild_id <> follower_guild_id)
+);
+
+CREATE INDEX IF NOT EXISTS session_link_leader_idx ON session_link (leader_guild_id);
@@ -0,0 +1,11 @@
+-- Music quiz leaderboard: points each member has won in a guild's quizzes.
+CREATE TABLE IF NOT EXISTS quiz_score (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    points BIGINT NOT NULL DEFAULT 0,
+    games_played INT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS quiz_score_points_idx ON quiz_score (guild_id, points DESC);
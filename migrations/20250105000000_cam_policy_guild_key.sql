@@ -0,0 +1,4 @@
+-- Key camera policies by guild and channel, so every lookup and upsert is
+-- scoped to the guild that owns the policy.
+ALTER TABLE cam_policy DROP CONSTRAINT cam_policy_pkey;
+ALTER TABLE cam_policy ADD PRIMARY KEY (guild_id, channel_id);